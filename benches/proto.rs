@@ -0,0 +1,65 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+use flyr::proto;
+use flyr::query::{FlightLeg, Passengers, Seat, TripType};
+
+const ITERATIONS: u32 = 200_000;
+
+fn legs(dest: &str) -> Vec<FlightLeg> {
+    vec![
+        FlightLeg {
+            date: "2026-03-01".into(),
+            from_airport: "HEL".into(),
+            to_airport: dest.into(),
+            max_stops: Some(1),
+            airlines: Some(vec!["AY".into(), "IB".into()]),
+            ..FlightLeg::default()
+        },
+        FlightLeg {
+            date: "2026-03-08".into(),
+            from_airport: dest.into(),
+            to_airport: "HEL".into(),
+            max_stops: Some(1),
+            airlines: None,
+            ..FlightLeg::default()
+        },
+    ]
+}
+
+fn report(name: &str, started: Instant) {
+    let per_op = started.elapsed().as_nanos() / u128::from(ITERATIONS);
+    println!("{name:<24} {per_op:>6} ns/op");
+}
+
+fn main() {
+    let matrix: Vec<Vec<FlightLeg>> = ["BCN", "MAD", "LIS", "FCO", "ATH"]
+        .iter()
+        .map(|d| legs(d))
+        .collect();
+    let pax = Passengers::default();
+
+    let started = Instant::now();
+    for i in 0..ITERATIONS {
+        let legs = &matrix[i as usize % matrix.len()];
+        black_box(proto::encode(legs, &pax, &Seat::Economy, &TripType::RoundTrip));
+    }
+    report("encode", started);
+
+    let mut scratch = Vec::new();
+    let started = Instant::now();
+    for i in 0..ITERATIONS {
+        let legs = &matrix[i as usize % matrix.len()];
+        scratch.clear();
+        proto::encode_into(&mut scratch, legs, &pax, &Seat::Economy, &TripType::RoundTrip);
+        black_box(&scratch);
+    }
+    report("encode_into (reused)", started);
+
+    let started = Instant::now();
+    for i in 0..ITERATIONS {
+        let legs = &matrix[i as usize % matrix.len()];
+        black_box(proto::encoded_len(legs, &pax, &Seat::Economy, &TripType::RoundTrip));
+    }
+    report("encoded_len", started);
+}
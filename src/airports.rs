@@ -0,0 +1,353 @@
+use crate::error::FlightError;
+use crate::metro;
+
+// (IATA code, city, airport name, country)
+const AIRPORTS: [(&str, &str, &str, &str); 249] = [
+    // Western Europe
+    ("LHR", "London", "Heathrow", "United Kingdom"),
+    ("LGW", "London", "Gatwick", "United Kingdom"),
+    ("STN", "London", "Stansted", "United Kingdom"),
+    ("LTN", "London", "Luton", "United Kingdom"),
+    ("LCY", "London", "London City", "United Kingdom"),
+    ("MAN", "Manchester", "Manchester", "United Kingdom"),
+    ("EDI", "Edinburgh", "Edinburgh", "United Kingdom"),
+    ("GLA", "Glasgow", "Glasgow", "United Kingdom"),
+    ("BHX", "Birmingham", "Birmingham", "United Kingdom"),
+    ("BRS", "Bristol", "Bristol", "United Kingdom"),
+    ("DUB", "Dublin", "Dublin", "Ireland"),
+    ("LIS", "Lisbon", "Humberto Delgado", "Portugal"),
+    ("OPO", "Porto", "Francisco Sa Carneiro", "Portugal"),
+    ("FAO", "Faro", "Faro", "Portugal"),
+    ("LPA", "Las Palmas", "Gran Canaria", "Spain"),
+    ("TFS", "Tenerife", "Tenerife South", "Spain"),
+    ("ACE", "Lanzarote", "Cesar Manrique-Lanzarote", "Spain"),
+    ("KEF", "Reykjavik", "Keflavik", "Iceland"),
+    // Central and Southern Europe
+    ("AMS", "Amsterdam", "Schiphol", "Netherlands"),
+    ("BCN", "Barcelona", "El Prat", "Spain"),
+    ("MAD", "Madrid", "Barajas", "Spain"),
+    ("AGP", "Malaga", "Costa del Sol", "Spain"),
+    ("PMI", "Palma de Mallorca", "Palma de Mallorca", "Spain"),
+    ("ALC", "Alicante", "Alicante-Elche", "Spain"),
+    ("IBZ", "Ibiza", "Ibiza", "Spain"),
+    ("VLC", "Valencia", "Valencia", "Spain"),
+    ("SVQ", "Seville", "San Pablo", "Spain"),
+    ("BIO", "Bilbao", "Bilbao", "Spain"),
+    ("CDG", "Paris", "Charles de Gaulle", "France"),
+    ("ORY", "Paris", "Orly", "France"),
+    ("NCE", "Nice", "Cote d'Azur", "France"),
+    ("LYS", "Lyon", "Saint-Exupery", "France"),
+    ("MRS", "Marseille", "Provence", "France"),
+    ("TLS", "Toulouse", "Blagnac", "France"),
+    ("BOD", "Bordeaux", "Merignac", "France"),
+    ("FRA", "Frankfurt", "Frankfurt", "Germany"),
+    ("MUC", "Munich", "Franz Josef Strauss", "Germany"),
+    ("BER", "Berlin", "Brandenburg", "Germany"),
+    ("HAM", "Hamburg", "Hamburg", "Germany"),
+    ("DUS", "Dusseldorf", "Dusseldorf", "Germany"),
+    ("CGN", "Cologne", "Cologne Bonn", "Germany"),
+    ("STR", "Stuttgart", "Stuttgart", "Germany"),
+    ("HAJ", "Hanover", "Hannover", "Germany"),
+    ("NUE", "Nuremberg", "Nuremberg", "Germany"),
+    ("ZRH", "Zurich", "Zurich", "Switzerland"),
+    ("GVA", "Geneva", "Geneva", "Switzerland"),
+    ("BSL", "Basel", "EuroAirport Basel-Mulhouse", "Switzerland"),
+    ("VIE", "Vienna", "Schwechat", "Austria"),
+    ("BRU", "Brussels", "Zaventem", "Belgium"),
+    ("CRL", "Brussels", "Charleroi", "Belgium"),
+    ("LUX", "Luxembourg", "Findel", "Luxembourg"),
+    ("CPH", "Copenhagen", "Kastrup", "Denmark"),
+    ("ARN", "Stockholm", "Arlanda", "Sweden"),
+    ("GOT", "Gothenburg", "Landvetter", "Sweden"),
+    ("OSL", "Oslo", "Gardermoen", "Norway"),
+    ("BGO", "Bergen", "Flesland", "Norway"),
+    ("TRD", "Trondheim", "Vaernes", "Norway"),
+    ("FCO", "Rome", "Fiumicino", "Italy"),
+    ("CIA", "Rome", "Ciampino", "Italy"),
+    ("MXP", "Milan", "Malpensa", "Italy"),
+    ("LIN", "Milan", "Linate", "Italy"),
+    ("BGY", "Milan", "Bergamo Orio al Serio", "Italy"),
+    ("VCE", "Venice", "Marco Polo", "Italy"),
+    ("NAP", "Naples", "Capodichino", "Italy"),
+    ("BLQ", "Bologna", "Guglielmo Marconi", "Italy"),
+    ("FLR", "Florence", "Peretola", "Italy"),
+    ("PSA", "Pisa", "Galileo Galilei", "Italy"),
+    ("CTA", "Catania", "Fontanarossa", "Italy"),
+    ("PMO", "Palermo", "Falcone-Borsellino", "Italy"),
+    ("PRG", "Prague", "Vaclav Havel", "Czech Republic"),
+    ("WAW", "Warsaw", "Chopin", "Poland"),
+    ("KRK", "Krakow", "John Paul II", "Poland"),
+    ("GDN", "Gdansk", "Lech Walesa", "Poland"),
+    ("WRO", "Wroclaw", "Copernicus", "Poland"),
+    ("BUD", "Budapest", "Ferenc Liszt", "Hungary"),
+    ("ZAG", "Zagreb", "Franjo Tudman", "Croatia"),
+    ("SPU", "Split", "Split", "Croatia"),
+    ("DBV", "Dubrovnik", "Dubrovnik", "Croatia"),
+    ("LJU", "Ljubljana", "Joze Pucnik", "Slovenia"),
+    ("BEG", "Belgrade", "Nikola Tesla", "Serbia"),
+    ("MLA", "Malta", "Malta", "Malta"),
+    ("TIA", "Tirana", "Mother Teresa", "Albania"),
+    ("SKP", "Skopje", "Skopje", "North Macedonia"),
+    // Northern and Eastern Europe
+    ("HEL", "Helsinki", "Helsinki-Vantaa", "Finland"),
+    ("TMP", "Tampere", "Tampere-Pirkkala", "Finland"),
+    ("OUL", "Oulu", "Oulu", "Finland"),
+    ("RVN", "Rovaniemi", "Rovaniemi", "Finland"),
+    ("TLL", "Tallinn", "Lennart Meri", "Estonia"),
+    ("RIX", "Riga", "Riga", "Latvia"),
+    ("VNO", "Vilnius", "Vilnius", "Lithuania"),
+    ("ATH", "Athens", "Eleftherios Venizelos", "Greece"),
+    ("HER", "Heraklion", "Nikos Kazantzakis", "Greece"),
+    ("RHO", "Rhodes", "Diagoras", "Greece"),
+    ("JTR", "Santorini", "Santorini", "Greece"),
+    ("CFU", "Corfu", "Ioannis Kapodistrias", "Greece"),
+    ("SKG", "Thessaloniki", "Makedonia", "Greece"),
+    ("OTP", "Bucharest", "Henri Coanda", "Romania"),
+    ("SOF", "Sofia", "Sofia", "Bulgaria"),
+    ("KBP", "Kyiv", "Boryspil", "Ukraine"),
+    ("LCA", "Larnaca", "Larnaca", "Cyprus"),
+    ("PFO", "Paphos", "Paphos", "Cyprus"),
+    // Middle East and North Africa
+    ("TLV", "Tel Aviv", "Ben Gurion", "Israel"),
+    ("CAI", "Cairo", "Cairo", "Egypt"),
+    ("IST", "Istanbul", "Istanbul", "Turkey"),
+    ("SAW", "Istanbul", "Sabiha Gokcen", "Turkey"),
+    ("AYT", "Antalya", "Antalya", "Turkey"),
+    ("ESB", "Ankara", "Esenboga", "Turkey"),
+    ("ADB", "Izmir", "Adnan Menderes", "Turkey"),
+    ("DLM", "Dalaman", "Dalaman", "Turkey"),
+    ("BJV", "Bodrum", "Milas-Bodrum", "Turkey"),
+    ("SVO", "Moscow", "Sheremetyevo", "Russia"),
+    ("DME", "Moscow", "Domodedovo", "Russia"),
+    ("VKO", "Moscow", "Vnukovo", "Russia"),
+    ("LED", "Saint Petersburg", "Pulkovo", "Russia"),
+    ("AMM", "Amman", "Queen Alia", "Jordan"),
+    ("DOH", "Doha", "Hamad", "Qatar"),
+    ("BAH", "Bahrain", "Bahrain", "Bahrain"),
+    ("KWI", "Kuwait City", "Kuwait", "Kuwait"),
+    ("RUH", "Riyadh", "King Khalid", "Saudi Arabia"),
+    ("JED", "Jeddah", "King Abdulaziz", "Saudi Arabia"),
+    ("DXB", "Dubai", "Dubai", "United Arab Emirates"),
+    ("DWC", "Dubai", "Al Maktoum", "United Arab Emirates"),
+    ("AUH", "Abu Dhabi", "Zayed", "United Arab Emirates"),
+    ("MCT", "Muscat", "Muscat", "Oman"),
+    ("CMN", "Casablanca", "Mohammed V", "Morocco"),
+    ("RAK", "Marrakesh", "Menara", "Morocco"),
+    ("TUN", "Tunis", "Carthage", "Tunisia"),
+    ("ALG", "Algiers", "Houari Boumediene", "Algeria"),
+    // Sub-Saharan Africa
+    ("LOS", "Lagos", "Murtala Muhammed", "Nigeria"),
+    ("ACC", "Accra", "Kotoka", "Ghana"),
+    ("DKR", "Dakar", "Blaise Diagne", "Senegal"),
+    ("JNB", "Johannesburg", "O. R. Tambo", "South Africa"),
+    ("CPT", "Cape Town", "Cape Town", "South Africa"),
+    ("NBO", "Nairobi", "Jomo Kenyatta", "Kenya"),
+    ("ADD", "Addis Ababa", "Bole", "Ethiopia"),
+    ("DAR", "Dar es Salaam", "Julius Nyerere", "Tanzania"),
+    ("ZNZ", "Zanzibar", "Abeid Amani Karume", "Tanzania"),
+    ("MRU", "Mauritius", "Sir Seewoosagur Ramgoolam", "Mauritius"),
+    ("SEZ", "Mahe", "Seychelles", "Seychelles"),
+    // South and Central Asia
+    ("DEL", "Delhi", "Indira Gandhi", "India"),
+    ("BOM", "Mumbai", "Chhatrapati Shivaji Maharaj", "India"),
+    ("BLR", "Bengaluru", "Kempegowda", "India"),
+    ("MAA", "Chennai", "Chennai", "India"),
+    ("CCU", "Kolkata", "Netaji Subhas Chandra Bose", "India"),
+    ("HYD", "Hyderabad", "Rajiv Gandhi", "India"),
+    ("COK", "Kochi", "Cochin", "India"),
+    ("GOI", "Goa", "Dabolim", "India"),
+    ("CMB", "Colombo", "Bandaranaike", "Sri Lanka"),
+    ("KTM", "Kathmandu", "Tribhuvan", "Nepal"),
+    ("DAC", "Dhaka", "Hazrat Shahjalal", "Bangladesh"),
+    ("MLE", "Male", "Velana", "Maldives"),
+    ("KHI", "Karachi", "Jinnah", "Pakistan"),
+    ("ISB", "Islamabad", "Islamabad", "Pakistan"),
+    ("LHE", "Lahore", "Allama Iqbal", "Pakistan"),
+    ("TAS", "Tashkent", "Islam Karimov", "Uzbekistan"),
+    ("ALA", "Almaty", "Almaty", "Kazakhstan"),
+    // Southeast Asia
+    ("BKK", "Bangkok", "Suvarnabhumi", "Thailand"),
+    ("DMK", "Bangkok", "Don Mueang", "Thailand"),
+    ("HKT", "Phuket", "Phuket", "Thailand"),
+    ("CNX", "Chiang Mai", "Chiang Mai", "Thailand"),
+    ("USM", "Koh Samui", "Samui", "Thailand"),
+    ("SGN", "Ho Chi Minh City", "Tan Son Nhat", "Vietnam"),
+    ("HAN", "Hanoi", "Noi Bai", "Vietnam"),
+    ("DAD", "Da Nang", "Da Nang", "Vietnam"),
+    ("PNH", "Phnom Penh", "Techo", "Cambodia"),
+    ("RGN", "Yangon", "Yangon", "Myanmar"),
+    ("CGK", "Jakarta", "Soekarno-Hatta", "Indonesia"),
+    ("DPS", "Bali", "Ngurah Rai", "Indonesia"),
+    ("KUL", "Kuala Lumpur", "Kuala Lumpur", "Malaysia"),
+    ("PEN", "Penang", "Penang", "Malaysia"),
+    ("SIN", "Singapore", "Changi", "Singapore"),
+    ("MNL", "Manila", "Ninoy Aquino", "Philippines"),
+    ("CEB", "Cebu", "Mactan-Cebu", "Philippines"),
+    // East Asia
+    ("HKG", "Hong Kong", "Hong Kong", "Hong Kong"),
+    ("MFM", "Macau", "Macau", "Macau"),
+    ("TPE", "Taipei", "Taoyuan", "Taiwan"),
+    ("PEK", "Beijing", "Capital", "China"),
+    ("PKX", "Beijing", "Daxing", "China"),
+    ("PVG", "Shanghai", "Pudong", "China"),
+    ("SHA", "Shanghai", "Hongqiao", "China"),
+    ("CAN", "Guangzhou", "Baiyun", "China"),
+    ("SZX", "Shenzhen", "Bao'an", "China"),
+    ("CTU", "Chengdu", "Tianfu", "China"),
+    ("ICN", "Seoul", "Incheon", "South Korea"),
+    ("GMP", "Seoul", "Gimpo", "South Korea"),
+    ("NRT", "Tokyo", "Narita", "Japan"),
+    ("HND", "Tokyo", "Haneda", "Japan"),
+    ("KIX", "Osaka", "Kansai", "Japan"),
+    ("NGO", "Nagoya", "Chubu Centrair", "Japan"),
+    ("FUK", "Fukuoka", "Fukuoka", "Japan"),
+    ("CTS", "Sapporo", "New Chitose", "Japan"),
+    ("OKA", "Okinawa", "Naha", "Japan"),
+    // Oceania
+    ("SYD", "Sydney", "Kingsford Smith", "Australia"),
+    ("MEL", "Melbourne", "Tullamarine", "Australia"),
+    ("CBR", "Canberra", "Canberra", "Australia"),
+    ("HBA", "Hobart", "Hobart", "Australia"),
+    ("ADL", "Adelaide", "Adelaide", "Australia"),
+    ("BNE", "Brisbane", "Brisbane", "Australia"),
+    ("OOL", "Gold Coast", "Gold Coast", "Australia"),
+    ("CNS", "Cairns", "Cairns", "Australia"),
+    ("DRW", "Darwin", "Darwin", "Australia"),
+    ("PER", "Perth", "Perth", "Australia"),
+    ("AKL", "Auckland", "Auckland", "New Zealand"),
+    ("WLG", "Wellington", "Wellington", "New Zealand"),
+    ("CHC", "Christchurch", "Christchurch", "New Zealand"),
+    ("ZQN", "Queenstown", "Queenstown", "New Zealand"),
+    ("NAN", "Nadi", "Nadi", "Fiji"),
+    ("PPT", "Papeete", "Faa'a", "French Polynesia"),
+    ("HNL", "Honolulu", "Daniel K. Inouye", "United States"),
+    // North America
+    ("JFK", "New York", "John F. Kennedy", "United States"),
+    ("LGA", "New York", "LaGuardia", "United States"),
+    ("EWR", "New York", "Newark Liberty", "United States"),
+    ("BOS", "Boston", "Logan", "United States"),
+    ("IAD", "Washington", "Dulles", "United States"),
+    ("DCA", "Washington", "Reagan National", "United States"),
+    ("PHL", "Philadelphia", "Philadelphia", "United States"),
+    ("ATL", "Atlanta", "Hartsfield-Jackson", "United States"),
+    ("MIA", "Miami", "Miami", "United States"),
+    ("FLL", "Fort Lauderdale", "Fort Lauderdale-Hollywood", "United States"),
+    ("MCO", "Orlando", "Orlando", "United States"),
+    ("CLT", "Charlotte", "Charlotte Douglas", "United States"),
+    ("DTW", "Detroit", "Detroit Metropolitan", "United States"),
+    ("YYZ", "Toronto", "Pearson", "Canada"),
+    ("YUL", "Montreal", "Trudeau", "Canada"),
+    ("ORD", "Chicago", "O'Hare", "United States"),
+    ("DFW", "Dallas", "Dallas/Fort Worth", "United States"),
+    ("IAH", "Houston", "George Bush Intercontinental", "United States"),
+    ("AUS", "Austin", "Austin-Bergstrom", "United States"),
+    ("MSP", "Minneapolis", "Minneapolis-Saint Paul", "United States"),
+    ("DEN", "Denver", "Denver", "United States"),
+    ("SLC", "Salt Lake City", "Salt Lake City", "United States"),
+    ("YYC", "Calgary", "Calgary", "Canada"),
+    ("PHX", "Phoenix", "Sky Harbor", "United States"),
+    ("LAX", "Los Angeles", "Los Angeles", "United States"),
+    ("SFO", "San Francisco", "San Francisco", "United States"),
+    ("SAN", "San Diego", "San Diego", "United States"),
+    ("SEA", "Seattle", "Seattle-Tacoma", "United States"),
+    ("LAS", "Las Vegas", "Harry Reid", "United States"),
+    ("YVR", "Vancouver", "Vancouver", "Canada"),
+    ("ANC", "Anchorage", "Ted Stevens", "United States"),
+    // Latin America
+    ("MEX", "Mexico City", "Benito Juarez", "Mexico"),
+    ("CUN", "Cancun", "Cancun", "Mexico"),
+    ("PTY", "Panama City", "Tocumen", "Panama"),
+    ("BOG", "Bogota", "El Dorado", "Colombia"),
+    ("LIM", "Lima", "Jorge Chavez", "Peru"),
+    ("SCL", "Santiago", "Arturo Merino Benitez", "Chile"),
+    ("EZE", "Buenos Aires", "Ezeiza", "Argentina"),
+    ("GRU", "Sao Paulo", "Guarulhos", "Brazil"),
+    ("GIG", "Rio de Janeiro", "Galeao", "Brazil"),
+];
+
+const MAX_CANDIDATES: usize = 8;
+
+type Airport = (&'static str, &'static str, &'static str, &'static str);
+
+fn is_code(input: &str) -> bool {
+    input.len() == 3 && input.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+fn candidates(input: &str) -> Vec<&'static Airport> {
+    let name = input.to_lowercase();
+    if name.len() < 3 {
+        return Vec::new();
+    }
+    let exact: Vec<&Airport> = AIRPORTS
+        .iter()
+        .filter(|(_, city, ..)| city.to_lowercase() == name)
+        .collect();
+    if !exact.is_empty() {
+        return exact;
+    }
+    AIRPORTS
+        .iter()
+        .filter(|(_, city, airport, _)| {
+            city.to_lowercase().starts_with(&name) || airport.to_lowercase().contains(&name)
+        })
+        .collect()
+}
+
+pub fn is_place(input: &str) -> bool {
+    let input = input.trim();
+    !is_code(input) && !candidates(input).is_empty()
+}
+
+fn describe(&(code, city, airport, country): &Airport) -> String {
+    if city == airport {
+        format!("{code} ({city}, {country})")
+    } else {
+        format!("{code} ({city} {airport}, {country})")
+    }
+}
+
+// Resolves a city or airport name to an IATA code. Anything that already looks like a
+// code is passed through unchanged (uppercased), so unknown airports keep working. A
+// city with several airports resolves to its metro code when one covers them all.
+pub fn resolve(input: &str) -> Result<String, FlightError> {
+    let input = input.trim();
+    if is_code(input) {
+        return Ok(input.to_uppercase());
+    }
+
+    let found = candidates(input);
+    let Some(&&(first_code, first_city, ..)) = found.first() else {
+        return Err(FlightError::Validation(format!(
+            "unknown airport or city \"{input}\" — use its 3-letter IATA code (e.g. HEL, JFK)"
+        )));
+    };
+    if found.len() == 1 {
+        return Ok(first_code.to_string());
+    }
+
+    let codes: Vec<&str> = found.iter().map(|(code, ..)| *code).collect();
+    if found.iter().all(|(_, city, ..)| *city == first_city) {
+        if let Some(metro) = metro::covering(&codes) {
+            return Ok(metro.to_string());
+        }
+    }
+
+    let mut listed: Vec<String> = found.iter().take(MAX_CANDIDATES).map(|a| describe(a)).collect();
+    if found.len() > MAX_CANDIDATES {
+        listed.push(format!("and {} more", found.len() - MAX_CANDIDATES));
+    }
+    Err(FlightError::Validation(format!(
+        "\"{input}\" matches several airports: {} — use one of these codes",
+        listed.join(", ")
+    )))
+}
+
+pub fn resolve_list(input: &str) -> Result<String, FlightError> {
+    let codes = input
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(resolve)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(codes.join(","))
+}
@@ -0,0 +1,130 @@
+use crate::error::FlightError;
+use crate::model::{FlightResult, SearchResult, Warning};
+use crate::query::{SearchQuery, TripType};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bag {
+    CarryOn,
+    Checked,
+}
+
+impl Bag {
+    pub fn from_str_loose(s: &str) -> Result<Self, FlightError> {
+        match s {
+            "carry-on" => Ok(Self::CarryOn),
+            "checked" => Ok(Self::Checked),
+            _ => Err(FlightError::Validation(format!(
+                "invalid bag \"{s}\" — use carry-on or checked"
+            ))),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::CarryOn => "carry-on",
+            Self::Checked => "checked",
+        }
+    }
+}
+
+const FEES: [(&str, i64, i64); 16] = [
+    ("FR", 25, 40),
+    ("U2", 20, 35),
+    ("W6", 30, 40),
+    ("DY", 15, 30),
+    ("D8", 15, 30),
+    ("VY", 15, 30),
+    ("V7", 15, 30),
+    ("HV", 20, 35),
+    ("EW", 15, 35),
+    ("PC", 20, 30),
+    ("BT", 20, 30),
+    ("NK", 55, 50),
+    ("F9", 55, 50),
+    ("G4", 35, 35),
+    ("AK", 0, 20),
+    ("TR", 0, 25),
+];
+
+const DEFAULT_FEES: (i64, i64) = (0, 40);
+
+const EUR_RATES: [(&str, f64); 10] = [
+    ("EUR", 1.0),
+    ("USD", 1.1),
+    ("GBP", 0.85),
+    ("CHF", 0.95),
+    ("SEK", 11.0),
+    ("NOK", 11.5),
+    ("DKK", 7.5),
+    ("PLN", 4.3),
+    ("CAD", 1.5),
+    ("AUD", 1.65),
+];
+
+pub fn fee(airline: &str, bag: Bag) -> i64 {
+    let (carry_on, checked) = FEES
+        .iter()
+        .find(|(code, _, _)| *code == airline)
+        .map_or(DEFAULT_FEES, |&(_, carry_on, checked)| (carry_on, checked));
+    match bag {
+        Bag::CarryOn => carry_on,
+        Bag::Checked => checked,
+    }
+}
+
+fn travel_units(query: Option<&SearchQuery>) -> i64 {
+    let Some(SearchQuery::Structured(q)) = query else {
+        return 1;
+    };
+    let travellers = q.passengers.adults + q.passengers.children + q.passengers.infants_in_seat;
+    let directions = match q.trip {
+        TripType::OneWay => 1,
+        TripType::RoundTrip | TripType::MultiCity => q.legs.len(),
+    };
+    i64::from(travellers.max(1)) * directions.max(1) as i64
+}
+
+pub fn included(flight: &FlightResult, bag: Bag) -> bool {
+    let Some(allowance) = flight.baggage else {
+        return false;
+    };
+    let count = match bag {
+        Bag::CarryOn => allowance.carry_on,
+        Bag::Checked => allowance.checked,
+    };
+    count.is_some_and(|n| n > 0)
+}
+
+pub fn flight_fee(flight: &FlightResult, bag: Bag) -> i64 {
+    if included(flight, bag) {
+        return 0;
+    }
+    let mut carriers: Vec<&str> = flight
+        .segments
+        .iter()
+        .filter_map(|s| s.airline.as_deref())
+        .collect();
+    carriers.dedup();
+    carriers.iter().map(|c| fee(c, bag)).sum()
+}
+
+pub fn annotate(result: &mut SearchResult, bag: Bag, currency: &str) {
+    let currency = result.metadata.currency.as_deref().unwrap_or(currency);
+    let Some(&(_, rate)) = EUR_RATES.iter().find(|(c, _)| c.eq_ignore_ascii_case(currency)) else {
+        let known: Vec<&str> = EUR_RATES.iter().map(|(c, _)| *c).collect();
+        result.warnings.push(Warning::new(
+            "bag_fees",
+            format!(
+                "no bag fee estimates for prices in {currency}; use one of {}",
+                known.join(", ")
+            ),
+        ));
+        return;
+    };
+
+    let units = travel_units(result.query.as_ref());
+    for flight in &mut result.flights {
+        let fee = (flight_fee(flight, bag) as f64 * rate).round() as i64 * units;
+        flight.price_with_bag = flight.price.map(|p| p + fee);
+    }
+}
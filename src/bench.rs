@@ -0,0 +1,121 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::error::{ErrorInfo, FlightError};
+use crate::model::Timings;
+use crate::parse::{self, ParseOptions};
+
+pub const DEFAULT_ITERATIONS: u32 = 100;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FixtureReport {
+    pub fixture: String,
+    pub bytes: usize,
+    pub iterations: u32,
+    pub flights: usize,
+    pub mean_us: f64,
+    pub extract_us: f64,
+    pub json_us: f64,
+    pub build_us: f64,
+    pub mib_per_sec: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allocations_per_iter: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorInfo>,
+}
+
+fn micros(d: Duration, iterations: u32) -> f64 {
+    d.as_secs_f64() * 1e6 / f64::from(iterations.max(1))
+}
+
+pub fn load_fixtures(path: &Path) -> Result<Vec<(String, String)>, FlightError> {
+    let unreadable =
+        |e: std::io::Error| FlightError::Validation(format!("cannot read {}: {e}", path.display()));
+
+    let mut files = if path.is_dir() {
+        std::fs::read_dir(path)
+            .map_err(unreadable)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "html"))
+            .collect::<Vec<_>>()
+    } else {
+        vec![path.to_path_buf()]
+    };
+    files.sort();
+
+    if files.is_empty() {
+        return Err(FlightError::Validation(format!(
+            "no .html fixtures in {}",
+            path.display()
+        )));
+    }
+
+    files
+        .into_iter()
+        .map(|file| {
+            let name = file
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let html = std::fs::read_to_string(&file).map_err(unreadable)?;
+            Ok((name, html))
+        })
+        .collect()
+}
+
+pub fn run_fixture(
+    name: &str,
+    html: &str,
+    iterations: u32,
+    options: &ParseOptions,
+) -> FixtureReport {
+    let iterations = iterations.max(1);
+    let mut report = FixtureReport {
+        fixture: name.to_string(),
+        bytes: html.len(),
+        iterations,
+        flights: 0,
+        mean_us: 0.0,
+        extract_us: 0.0,
+        json_us: 0.0,
+        build_us: 0.0,
+        mib_per_sec: 0.0,
+        allocations_per_iter: None,
+        error: None,
+    };
+
+    let mut phases = Timings::default();
+    let started = Instant::now();
+    for _ in 0..iterations {
+        let parsed = if options.low_memory {
+            parse::parse_html_owned(html.to_string(), options)
+        } else {
+            parse::parse_html_with(html, options)
+        };
+        match parsed {
+            Ok(result) => {
+                report.flights = result.flights.len();
+                if let Some(t) = result.timings {
+                    phases.extract += t.extract;
+                    phases.json += t.json;
+                    phases.build += t.build;
+                }
+            }
+            Err(e) => {
+                report.error = Some(ErrorInfo::from(&e));
+                return report;
+            }
+        }
+    }
+    let elapsed = started.elapsed();
+
+    report.mean_us = micros(elapsed, iterations);
+    report.extract_us = micros(phases.extract, iterations);
+    report.json_us = micros(phases.json, iterations);
+    report.build_us = micros(phases.build, iterations);
+    report.mib_per_sec =
+        (html.len() as f64 * f64::from(iterations)) / (1024.0 * 1024.0) / elapsed.as_secs_f64();
+    report
+}
@@ -0,0 +1,86 @@
+use serde_json::{json, Value};
+
+use crate::calendar::{encode_segment, encode_settings, rpc_payload, rpc_url};
+use crate::error::FlightError;
+use crate::model::{BookingOption, FlightResult};
+use crate::query::{QueryParams, SelectedFlight, TripType};
+
+pub const BOOKING_URL: &str = "https://www.google.com/_/FlightsFrontendUi/data/\
+    travel.frontend.flights.FlightsFrontendService/GetBookingResults";
+
+const GOOGLE: &str = "https://www.google.com";
+
+pub fn request_url(params: &QueryParams) -> String {
+    rpc_url(BOOKING_URL, params)
+}
+
+// The chosen flight, one `[from, date, to, null, airline, number]` per segment, as the
+// booking page sends it.
+fn selected_flights(flight: &FlightResult) -> Result<Vec<Value>, FlightError> {
+    let selected = SelectedFlight::from_flight(flight)?;
+    Ok(selected
+        .iter()
+        .map(|s| json!([s.from_airport, s.date, s.to_airport, null, s.airline, s.flight_number]))
+        .collect())
+}
+
+// A booking request is the search that found `flight`, with the flight selected on its
+// leg (segment index 8). Round trips and multi-city trips need a flight chosen on every
+// leg, which a single result does not carry, so only one-way searches can be booked.
+pub fn encode_filters(params: &QueryParams, flight: &FlightResult) -> Result<Value, FlightError> {
+    let leg = match (&params.trip, params.legs.as_slice()) {
+        (TripType::OneWay, [leg]) => leg,
+        _ => {
+            return Err(FlightError::Validation(
+                "booking options are only available for one-way searches".into(),
+            ))
+        }
+    };
+
+    let mut segment = encode_segment(leg);
+    segment[8] = Value::Array(selected_flights(flight)?);
+    Ok(json!([null, encode_settings(params, vec![segment])]))
+}
+
+pub fn encode_request(params: &QueryParams, flight: &FlightResult) -> Result<String, FlightError> {
+    let filters = encode_filters(params, flight)?.to_string();
+    let envelope = json!([null, filters]).to_string();
+    Ok(format!("f.req={}", urlencoding::encode(&envelope)))
+}
+
+// Each option is `[[code, name, airline_direct], [[null, price], token], url]`; the
+// URL is a Google redirect, usually relative to www.google.com.
+fn parse_option(option: &Value) -> Option<BookingOption> {
+    let provider = option.get(0)?;
+    let provider_code = provider.get(0).and_then(|v| v.as_str()).map(String::from);
+    let name = provider.get(1).and_then(|v| v.as_str()).map(String::from);
+    let url = option.get(2).and_then(|v| v.as_str()).map(|url| {
+        if url.starts_with('/') {
+            format!("{GOOGLE}{url}")
+        } else {
+            url.to_string()
+        }
+    });
+    Some(BookingOption {
+        provider: name.or_else(|| provider_code.clone())?,
+        provider_code,
+        airline_direct: provider.get(2).and_then(|v| v.as_bool()).unwrap_or(false),
+        price: option
+            .get(1)
+            .and_then(|v| v.get(0))
+            .and_then(|v| v.get(1))
+            .and_then(|v| v.as_i64()),
+        url,
+    })
+}
+
+pub fn parse_response(body: &str) -> Result<Vec<BookingOption>, FlightError> {
+    let inner = rpc_payload(body, "booking")?;
+    let mut options: Vec<BookingOption> = inner
+        .get(1)
+        .and_then(|v| v.as_array())
+        .map(|items| items.iter().filter_map(parse_option).collect())
+        .unwrap_or_default();
+    options.sort_by_key(|o| (o.price.is_none(), o.price, !o.airline_direct));
+    Ok(options)
+}
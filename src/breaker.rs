@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
+use crate::error::FlightError;
+use crate::fetch::Fetcher;
+
+pub const DEFAULT_TRIP_AFTER: u32 = 3;
+pub const DEFAULT_RETRY_BUDGET: u32 = 3;
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    trip_after: u32,
+    consecutive: AtomicU32,
+}
+
+impl CircuitBreaker {
+    pub fn new(trip_after: u32) -> Self {
+        Self {
+            trip_after: trip_after.max(1),
+            consecutive: AtomicU32::new(0),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.consecutive.load(Ordering::SeqCst) >= self.trip_after
+    }
+
+    pub fn check(&self) -> Result<(), FlightError> {
+        if self.is_open() {
+            return Err(FlightError::BackingOff {
+                consecutive: self.consecutive.load(Ordering::SeqCst),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn record<T>(&self, result: &Result<T, FlightError>) {
+        match result {
+            Ok(_) => self.consecutive.store(0, Ordering::SeqCst),
+            Err(e) if e.is_pushback() || matches!(e, FlightError::ConsentRequired) => {
+                self.consecutive.fetch_add(1, Ordering::SeqCst);
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+// Retries shared by every request of a fan-out, so N destinations can't each spend
+// their own `FetchOptions::retries`. `with_retries` draws from it.
+#[derive(Debug)]
+pub struct RetryBudget {
+    remaining: AtomicU32,
+}
+
+impl RetryBudget {
+    pub fn new(retries: u32) -> Self {
+        Self {
+            remaining: AtomicU32::new(retries),
+        }
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.remaining.load(Ordering::SeqCst)
+    }
+
+    pub fn try_spend(&self) -> bool {
+        self.remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+    }
+}
+
+#[derive(Debug)]
+pub struct Guarded<F> {
+    inner: F,
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl<F: Clone> Clone for Guarded<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            breaker: self.breaker.clone(),
+        }
+    }
+}
+
+impl<F: Fetcher> Guarded<F> {
+    pub fn new(inner: F, breaker: CircuitBreaker) -> Self {
+        Self {
+            inner,
+            breaker: Arc::new(breaker),
+        }
+    }
+
+    pub fn with_defaults(inner: F) -> Self {
+        Self::new(inner, CircuitBreaker::new(DEFAULT_TRIP_AFTER))
+    }
+
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+}
+
+// Retries belong to the inner fetcher; build it from `FetchOptions::for_fan_out` so it
+// hands 429s and blocks straight back for the breaker to count. A page that arrives
+// only resets the breaker once it has parsed, as it may be an interstitial.
+impl<F: Fetcher> Fetcher for Guarded<F> {
+    async fn fetch_html(&self, params: &[(String, String)]) -> Result<String, FlightError> {
+        self.breaker.check()?;
+        let result = self.inner.fetch_html(params).await;
+        if result.is_err() {
+            self.breaker.record(&result);
+        }
+        result
+    }
+
+    fn record_parsed(&self, result: Result<(), &FlightError>) {
+        self.breaker.record(&result.map_err(FlightError::clone));
+        self.inner.record_parsed(result);
+    }
+}
@@ -0,0 +1,32 @@
+use serde::Serialize;
+
+use crate::model::CalendarDay;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BudgetMatch {
+    pub destination: String,
+    pub date: String,
+    pub return_date: Option<String>,
+    pub price: i64,
+}
+
+pub fn matches(calendars: &[(String, Vec<CalendarDay>)], max_price: i64) -> Vec<BudgetMatch> {
+    let mut found: Vec<BudgetMatch> = calendars
+        .iter()
+        .flat_map(|(destination, days)| {
+            days.iter().filter_map(move |day| {
+                let price = day.price.filter(|&p| p <= max_price)?;
+                Some(BudgetMatch {
+                    destination: destination.clone(),
+                    date: day.date.clone(),
+                    return_date: day.return_date.clone(),
+                    price,
+                })
+            })
+        })
+        .collect();
+    found.sort_by(|a, b| {
+        (a.price, &a.date, &a.destination).cmp(&(b.price, &b.date, &b.destination))
+    });
+    found
+}
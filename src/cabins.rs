@@ -0,0 +1,96 @@
+use serde::Serialize;
+
+use crate::error::{ErrorInfo, FlightError};
+use crate::model::{FlightResult, SearchResult, Warning};
+use crate::query::Seat;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CabinRow {
+    pub seat: Seat,
+    pub flights: usize,
+    pub price: Option<i64>,
+    pub airlines: Vec<String>,
+    pub route: Vec<String>,
+    pub elapsed_minutes: Option<u32>,
+    pub fastest_minutes: Option<u32>,
+    pub price_delta: Option<i64>,
+    pub duration_delta: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorInfo>,
+}
+
+impl CabinRow {
+    fn new(seat: Seat, result: &SearchResult) -> Self {
+        let cheapest = result
+            .flights
+            .iter()
+            .filter(|f| f.price.is_some())
+            .min_by_key(|f| (f.price, f.elapsed_minutes));
+        Self {
+            seat,
+            flights: result.flights.len(),
+            price: cheapest.and_then(|f| f.price),
+            airlines: cheapest.map(|f| f.airlines.clone()).unwrap_or_default(),
+            route: cheapest.map(FlightResult::route).unwrap_or_default(),
+            elapsed_minutes: cheapest.map(|f| f.elapsed_minutes),
+            fastest_minutes: result.flights.iter().map(|f| f.elapsed_minutes).min(),
+            price_delta: None,
+            duration_delta: None,
+            error: None,
+        }
+    }
+
+    fn failed(seat: Seat, err: &FlightError) -> Self {
+        Self {
+            seat,
+            flights: 0,
+            price: None,
+            airlines: Vec::new(),
+            route: Vec::new(),
+            elapsed_minutes: None,
+            fastest_minutes: None,
+            price_delta: None,
+            duration_delta: None,
+            error: Some(ErrorInfo::from(err)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeatComparison {
+    pub baseline: Seat,
+    pub cabins: Vec<CabinRow>,
+    pub warnings: Vec<Warning>,
+}
+
+pub fn compare(outcomes: &[(Seat, Result<SearchResult, FlightError>)]) -> SeatComparison {
+    let mut warnings = Vec::new();
+    let mut cabins: Vec<CabinRow> = outcomes
+        .iter()
+        .map(|(seat, outcome)| match outcome {
+            Ok(result) => {
+                warnings.extend(result.warnings.iter().cloned());
+                CabinRow::new(seat.clone(), result)
+            }
+            Err(e) => CabinRow::failed(seat.clone(), e),
+        })
+        .collect();
+
+    let (base_price, base_minutes) = cabins
+        .first()
+        .map(|b| (b.price, b.elapsed_minutes))
+        .unwrap_or_default();
+    for row in &mut cabins {
+        row.price_delta = row.price.zip(base_price).map(|(p, b)| p - b);
+        row.duration_delta = row
+            .elapsed_minutes
+            .zip(base_minutes)
+            .map(|(m, b)| i64::from(m) - i64::from(b));
+    }
+
+    SeatComparison {
+        baseline: outcomes.first().map(|(s, _)| s.clone()).unwrap_or(Seat::Economy),
+        cabins,
+        warnings,
+    }
+}
@@ -0,0 +1,165 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::error::FlightError;
+use crate::fetch::Fetcher;
+
+pub trait CacheStore: Send + Sync {
+    fn get(&self, key: &str, max_age: Duration) -> Result<Option<String>, FlightError>;
+    fn put(&self, key: &str, value: &str) -> Result<(), FlightError>;
+    fn evict(&self, older_than: Duration) -> Result<usize, FlightError>;
+}
+
+impl<T: CacheStore + ?Sized> CacheStore for Arc<T> {
+    fn get(&self, key: &str, max_age: Duration) -> Result<Option<String>, FlightError> {
+        (**self).get(key, max_age)
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<(), FlightError> {
+        (**self).put(key, value)
+    }
+
+    fn evict(&self, older_than: Duration) -> Result<usize, FlightError> {
+        (**self).evict(older_than)
+    }
+}
+
+// No store caches nothing, so a fetcher can be wrapped whether or not caching is on.
+impl<T: CacheStore> CacheStore for Option<T> {
+    fn get(&self, key: &str, max_age: Duration) -> Result<Option<String>, FlightError> {
+        match self {
+            Some(store) => store.get(key, max_age),
+            None => Ok(None),
+        }
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<(), FlightError> {
+        match self {
+            Some(store) => store.put(key, value),
+            None => Ok(()),
+        }
+    }
+
+    fn evict(&self, older_than: Duration) -> Result<usize, FlightError> {
+        match self {
+            Some(store) => store.evict(older_than),
+            None => Ok(0),
+        }
+    }
+}
+
+pub fn request_key(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+fn fnv1a(s: &str) -> u64 {
+    s.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn io_error(e: std::io::Error) -> FlightError {
+    FlightError::Storage(e.to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct FsCache {
+    dir: PathBuf,
+}
+
+impl FsCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, FlightError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir).map_err(io_error)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{:016x}.html", fnv1a(key)))
+    }
+}
+
+fn age(path: &Path) -> Option<Duration> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(SystemTime::now().duration_since(modified).unwrap_or_default())
+}
+
+impl CacheStore for FsCache {
+    fn get(&self, key: &str, max_age: Duration) -> Result<Option<String>, FlightError> {
+        let path = self.entry_path(key);
+        match age(&path) {
+            Some(a) if a <= max_age => {}
+            _ => return Ok(None),
+        }
+        let body = std::fs::read_to_string(&path).map_err(io_error)?;
+        let (stored_key, value) = body.split_once('\n').unwrap_or(("", ""));
+        Ok((stored_key == key).then(|| value.to_string()))
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<(), FlightError> {
+        let path = self.entry_path(key);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, format!("{key}\n{value}")).map_err(io_error)?;
+        std::fs::rename(&tmp, &path).map_err(io_error)
+    }
+
+    fn evict(&self, older_than: Duration) -> Result<usize, FlightError> {
+        let mut removed = 0;
+        for entry in std::fs::read_dir(&self.dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path.extension().is_some_and(|e| e == "html")
+                && age(&path).is_some_and(|a| a > older_than)
+                && std::fs::remove_file(&path).is_ok()
+            {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cached<F, C> {
+    inner: F,
+    store: C,
+    ttl: Duration,
+}
+
+impl<F: Fetcher, C: CacheStore> Cached<F, C> {
+    pub fn new(inner: F, store: C, ttl: Duration) -> Self {
+        Self { inner, store, ttl }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    pub fn store(&self) -> &C {
+        &self.store
+    }
+}
+
+impl<F: Fetcher, C: CacheStore> Fetcher for Cached<F, C> {
+    async fn fetch_html(&self, params: &[(String, String)]) -> Result<String, FlightError> {
+        let key = request_key(params);
+        if let Ok(Some(html)) = self.store.get(&key, self.ttl) {
+            return Ok(html);
+        }
+        let html = self.inner.fetch_html(params).await?;
+        let _ = self.store.put(&key, &html);
+        Ok(html)
+    }
+
+    fn record_parsed(&self, result: Result<(), &FlightError>) {
+        self.inner.record_parsed(result);
+    }
+}
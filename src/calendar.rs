@@ -0,0 +1,155 @@
+use serde_json::{json, Value};
+
+use crate::error::FlightError;
+use crate::metro;
+use crate::model::CalendarDay;
+use crate::proto::{seat_to_varint, trip_to_varint};
+use crate::query::{self, FlightLeg, QueryParams, TripType};
+
+pub const CALENDAR_URL: &str = "https://www.google.com/_/FlightsFrontendUi/data/\
+    travel.frontend.flights.FlightsFrontendService/GetCalendarGraph";
+
+fn stops_code(max_stops: Option<u32>) -> u32 {
+    max_stops.map_or(0, |n| (n + 1).min(3))
+}
+
+fn trip_days(params: &QueryParams) -> Option<i64> {
+    if !matches!(params.trip, TripType::RoundTrip) {
+        return None;
+    }
+    let depart = query::date_to_days(&params.legs.first()?.date)?;
+    let ret = query::date_to_days(&params.legs.get(1)?.date)?;
+    Some(ret - depart)
+}
+
+pub fn request_url(params: &QueryParams) -> String {
+    rpc_url(CALENDAR_URL, params)
+}
+
+// Google's batch RPC endpoints take the display language, currency and region as
+// query parameters, like the search page.
+pub(crate) fn rpc_url(base: &str, params: &QueryParams) -> String {
+    let mut url = base.to_string();
+    let mut sep = '?';
+    for (k, v) in [
+        ("hl", &params.language),
+        ("curr", &params.currency),
+        ("gl", &params.region),
+    ] {
+        if !v.is_empty() {
+            url.push(sep);
+            url.push_str(&format!("{k}={}", urlencoding::encode(v)));
+            sep = '&';
+        }
+    }
+    url
+}
+
+pub(crate) fn encode_segment(leg: &FlightLeg) -> Value {
+    let airports = |code: &str| -> Vec<Value> {
+        metro::expand(code).iter().map(|a| json!([a, 0])).collect()
+    };
+    json!([
+        [airports(&leg.from_airport)],
+        [airports(&leg.to_airport)],
+        null,
+        stops_code(leg.max_stops),
+        leg.airlines.clone().unwrap_or_default(),
+        null,
+        leg.date,
+        null, null, null, null, null, null, null,
+        3
+    ])
+}
+
+pub(crate) fn encode_settings(params: &QueryParams, segments: Vec<Value>) -> Value {
+    let p = &params.passengers;
+    json!([
+        null, null,
+        trip_to_varint(&params.trip),
+        null,
+        [],
+        seat_to_varint(&params.seat),
+        [p.adults, p.children, p.infants_on_lap, p.infants_in_seat],
+        null, null, null, null, null, null,
+        segments,
+        null, null, null,
+        1
+    ])
+}
+
+pub fn encode_filters(params: &QueryParams, start: &str, end: &str) -> Value {
+    let segments = params.legs.iter().map(encode_segment).collect();
+    let mut filters = json!([null, encode_settings(params, segments), [start, end]]);
+
+    if let (Some(days), Some(arr)) = (trip_days(params), filters.as_array_mut()) {
+        arr.push(json!([days, days]));
+    }
+    filters
+}
+
+pub fn encode_request(params: &QueryParams, start: &str, end: &str) -> String {
+    let filters = encode_filters(params, start, end).to_string();
+    let envelope = json!([null, filters]).to_string();
+    format!("f.req={}", urlencoding::encode(&envelope))
+}
+
+fn find_wrb_payload(value: &Value) -> Option<&str> {
+    let entries = value.as_array()?;
+    entries.iter().find_map(|entry| {
+        let entry = entry.as_array()?;
+        if entry.first()?.as_str()? == "wrb.fr" {
+            entry.get(2)?.as_str()
+        } else {
+            None
+        }
+    })
+}
+
+fn parse_days(inner: &Value) -> Vec<CalendarDay> {
+    let Some(items) = inner.as_array().and_then(|a| a.last()).and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let date = item.get(0)?.as_str()?.to_string();
+            let return_date = item.get(1).and_then(|v| v.as_str()).map(String::from);
+            let price = item
+                .get(2)
+                .and_then(|v| v.get(0))
+                .and_then(|v| v.as_array())
+                .and_then(|a| a.last())
+                .and_then(|v| v.as_i64());
+            Some(CalendarDay {
+                date,
+                return_date,
+                price,
+            })
+        })
+        .collect()
+}
+
+// Batch RPC bodies are either one JSON array or length-prefixed chunks, both after an
+// XSSI guard; the answer is the JSON string inside the "wrb.fr" entry.
+pub(crate) fn rpc_payload(body: &str, what: &str) -> Result<Value, FlightError> {
+    let body = body.trim_start_matches(")]}'").trim_start();
+
+    let whole = std::iter::once(body);
+    let lines = body.lines().filter(|l| l.trim_start().starts_with('['));
+    let payload = whole
+        .chain(lines)
+        .filter_map(|chunk| serde_json::from_str::<Value>(chunk).ok())
+        .find_map(|v| find_wrb_payload(&v).map(String::from))
+        .ok_or_else(|| FlightError::JsParse(format!("{what} response has no wrb.fr payload")))?;
+
+    serde_json::from_str(&payload).map_err(|e| FlightError::JsParse(e.to_string()))
+}
+
+pub fn parse_response(body: &str) -> Result<Vec<CalendarDay>, FlightError> {
+    let inner = rpc_payload(body, "calendar")?;
+    let mut days = parse_days(&inner);
+    days.sort_by(|a, b| a.date.cmp(&b.date));
+    Ok(days)
+}
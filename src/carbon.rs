@@ -0,0 +1,86 @@
+use serde::Serialize;
+
+use crate::model::{CarbonEmission, FlightResult, SearchResult, Warning};
+
+pub fn delta_percent(carbon: &CarbonEmission) -> Option<i64> {
+    let (emitted, typical) = (carbon.emission_grams?, carbon.typical_grams?);
+    if typical <= 0 {
+        return None;
+    }
+    let delta = (emitted - typical) as f64 * 100.0 / typical as f64;
+    Some(delta.round() as i64)
+}
+
+pub fn sort_key(flight: &FlightResult) -> (i64, i64) {
+    (
+        flight.carbon.emission_grams.unwrap_or(i64::MAX),
+        flight.price.unwrap_or(i64::MAX),
+    )
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CarbonRow {
+    pub airlines: Vec<String>,
+    pub route: Vec<String>,
+    pub price: Option<i64>,
+    pub emission_grams: Option<i64>,
+    pub typical_grams: Option<i64>,
+    pub delta_percent: Option<i64>,
+}
+
+impl CarbonRow {
+    fn new(flight: &FlightResult) -> Self {
+        Self {
+            airlines: flight.airlines.clone(),
+            route: flight.route(),
+            price: flight.price,
+            emission_grams: flight.carbon.emission_grams,
+            typical_grams: flight.carbon.typical_grams,
+            delta_percent: flight.carbon.delta_percent,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CarbonReport {
+    pub itineraries: Vec<CarbonRow>,
+    pub greenest: Option<CarbonRow>,
+    pub cheapest: Option<CarbonRow>,
+    pub grams_saved: Option<i64>,
+    pub extra_cost: Option<i64>,
+    pub warnings: Vec<Warning>,
+}
+
+pub fn report(result: &SearchResult) -> CarbonReport {
+    let mut flights: Vec<&FlightResult> = result.flights.iter().collect();
+    flights.sort_by_key(|f| sort_key(f));
+
+    let greenest = flights
+        .iter()
+        .filter(|f| f.carbon.emission_grams.is_some())
+        .min_by_key(|f| sort_key(f));
+    let cheapest = flights
+        .iter()
+        .filter(|f| f.price.is_some())
+        .min_by_key(|f| (f.price, f.carbon.emission_grams.unwrap_or(i64::MAX)));
+
+    let (grams_saved, extra_cost) = match (greenest, cheapest) {
+        (Some(g), Some(c)) => (
+            c.carbon
+                .emission_grams
+                .zip(g.carbon.emission_grams)
+                .map(|(c, g)| c - g),
+            g.price.zip(c.price).map(|(g, c)| g - c),
+        ),
+        _ => (None, None),
+    };
+
+    CarbonReport {
+        itineraries: flights.iter().map(|f| CarbonRow::new(f)).collect(),
+        greenest: greenest.map(|f| CarbonRow::new(f)),
+        cheapest: cheapest.map(|f| CarbonRow::new(f)),
+        grams_saved,
+        extra_cost,
+        warnings: result.warnings.clone(),
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::OnceCell;
+
+use crate::error::FlightError;
+use crate::fetch::Fetcher;
+use crate::model::SearchResult;
+use crate::parse::ParseOptions;
+use crate::query::SearchQuery;
+
+type Shared = Arc<OnceCell<Result<SearchResult, FlightError>>>;
+
+#[derive(Debug, Default)]
+pub struct Coalescer {
+    inflight: Mutex<HashMap<String, Shared>>,
+}
+
+fn coalesce_key(query: &SearchQuery, options: &ParseOptions) -> String {
+    let mut key = String::new();
+    for (k, v) in query.to_url_params() {
+        key.push_str(&k);
+        key.push('=');
+        key.push_str(&v);
+        key.push('&');
+    }
+    key.push_str(&format!("strict={}", options.strict));
+    key
+}
+
+impl Coalescer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.inflight.lock().unwrap().len()
+    }
+
+    pub async fn search<F: Fetcher>(
+        &self,
+        fetcher: &F,
+        query: SearchQuery,
+        parse_options: ParseOptions,
+    ) -> Result<SearchResult, FlightError> {
+        let key = coalesce_key(&query, &parse_options);
+        let cell = self
+            .inflight
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .clone();
+
+        let result = cell
+            .get_or_init(|| crate::search_with_fetcher(fetcher, query, parse_options))
+            .await
+            .clone();
+
+        let mut inflight = self.inflight.lock().unwrap();
+        if inflight.get(&key).is_some_and(|c| Arc::ptr_eq(c, &cell)) {
+            inflight.remove(&key);
+        }
+        drop(inflight);
+        result
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::FlightError;
+use crate::value::Ranker;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub rank: Option<String>,
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("FLYR_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("flyr").join("config.json"))
+}
+
+impl Config {
+    pub fn load_from(path: &Path) -> Result<Self, FlightError> {
+        let invalid = |e: &dyn std::fmt::Display| {
+            FlightError::Validation(format!("invalid config file {}: {e}", path.display()))
+        };
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(invalid(&e)),
+        };
+        let config: Self = serde_json::from_str(&text).map_err(|e| invalid(&e))?;
+        config.ranker().map_err(|e| invalid(&e))?;
+        Ok(config)
+    }
+
+    pub fn load() -> Result<Self, FlightError> {
+        default_path().map_or(Ok(Self::default()), |p| Self::load_from(&p))
+    }
+
+    pub fn ranker(&self) -> Result<Option<Ranker>, FlightError> {
+        self.rank.as_deref().map(Ranker::parse).transpose()
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use crate::export::itinerary_key;
+use crate::model::{Baseline, FlightResult, SearchResult};
+
+pub fn annotate(result: &mut SearchResult, previous: &SearchResult, baseline: Baseline) {
+    let prices: HashMap<String, Option<i64>> = previous
+        .flights
+        .iter()
+        .filter_map(|f| itinerary_key(f).map(|key| (key, f.price)))
+        .collect();
+
+    for flight in &mut result.flights {
+        flight.previous_price = itinerary_key(flight)
+            .and_then(|key| prices.get(&key).copied())
+            .flatten();
+        flight.delta = flight.price.zip(flight.previous_price).map(|(now, then)| now - then);
+    }
+    result.baseline = Some(baseline);
+}
+
+pub fn format_age(secs: u64) -> String {
+    match secs {
+        0..=3599 => format!("{}m ago", (secs / 60).max(1)),
+        3600..=86_399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86_400),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    New,
+    Down(i64),
+    Up(i64),
+    Unchanged,
+}
+
+pub fn change(flight: &FlightResult) -> Change {
+    match (flight.previous_price, flight.delta) {
+        (None, _) => Change::New,
+        (Some(_), Some(d)) if d < 0 => Change::Down(-d),
+        (Some(_), Some(d)) if d > 0 => Change::Up(d),
+        _ => Change::Unchanged,
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use wreq::dns::{Addrs, Name, Resolve, Resolving};
+
+use crate::error::FlightError;
+
+pub const GOOGLE_HOST: &str = "www.google.com";
+pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Default)]
+struct Inner {
+    ttl: Duration,
+    pinned: HashMap<String, Vec<SocketAddr>>,
+    entries: Mutex<HashMap<String, (Instant, Vec<SocketAddr>)>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DnsCache {
+    inner: Arc<Inner>,
+}
+
+impl Default for DnsCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_TTL)
+    }
+}
+
+pub fn parse_override(s: &str) -> Result<(String, IpAddr), FlightError> {
+    let invalid = || {
+        FlightError::Validation(format!(
+            "invalid --resolve \"{s}\" — use HOST=IP, e.g. www.google.com=142.250.74.36"
+        ))
+    };
+    let (host, ip) = s.split_once('=').ok_or_else(invalid)?;
+    let host = host.trim().to_lowercase();
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    let ip = ip.trim().parse::<IpAddr>().map_err(|_| invalid())?;
+    Ok((host, ip))
+}
+
+impl DnsCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                ttl,
+                ..Inner::default()
+            }),
+        }
+    }
+
+    pub fn with_overrides(ttl: Duration, overrides: &[(String, IpAddr)]) -> Self {
+        let mut pinned: HashMap<String, Vec<SocketAddr>> = HashMap::new();
+        for (host, ip) in overrides {
+            pinned
+                .entry(host.to_lowercase())
+                .or_default()
+                .push(SocketAddr::new(*ip, 0));
+        }
+        Self {
+            inner: Arc::new(Inner {
+                ttl,
+                pinned,
+                ..Inner::default()
+            }),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.inner.ttl
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, (Instant, Vec<SocketAddr>)>> {
+        self.inner.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn cached(&self, host: &str) -> Option<Vec<SocketAddr>> {
+        let host = host.to_lowercase();
+        if let Some(addrs) = self.inner.pinned.get(&host) {
+            return Some(addrs.clone());
+        }
+        self.entries()
+            .get(&host)
+            .filter(|(at, _)| at.elapsed() < self.inner.ttl)
+            .map(|(_, addrs)| addrs.clone())
+    }
+
+    pub async fn lookup(&self, host: &str) -> Result<Vec<SocketAddr>, FlightError> {
+        if let Some(addrs) = self.cached(host) {
+            return Ok(addrs);
+        }
+
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(|e| FlightError::DnsResolution(format!("{host} ({e})")))?
+            .collect();
+        if addrs.is_empty() {
+            return Err(FlightError::DnsResolution(format!("{host} (no addresses)")));
+        }
+
+        self.entries()
+            .insert(host.to_lowercase(), (Instant::now(), addrs.clone()));
+        Ok(addrs)
+    }
+}
+
+impl Resolve for DnsCache {
+    fn resolve(&self, name: Name) -> Resolving {
+        let cache = self.clone();
+        Box::pin(async move {
+            let addrs = cache.lookup(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
@@ -1,16 +1,23 @@
 use std::fmt;
+use std::time::Duration;
 
-#[derive(Debug)]
+use serde::Serialize;
+
+#[derive(Debug, Clone)]
 pub enum FlightError {
     Timeout,
     ConnectionFailed(String),
     DnsResolution(String),
     ProxyError(String),
-    RateLimited,
+    RateLimited { retry_after: Option<u64> },
     Blocked(u16),
     HttpStatus(u16),
     TlsError(String),
     ScriptTagNotFound,
+    ConsentRequired,
+    CaptchaRequired(String),
+    BackingOff { consecutive: u32 },
+    Storage(String),
     JsParse(String),
     NoResults,
     InvalidAirport(String),
@@ -38,11 +45,18 @@ impl fmt::Display for FlightError {
                 f,
                 "proxy error — check your --proxy URL is correct ({detail})"
             ),
-            Self::RateLimited => write!(
+            Self::RateLimited { retry_after: None } => write!(
                 f,
                 "rate limited by Google (HTTP 429) — wait a few minutes before retrying, \
                  or use --proxy to route through a different IP"
             ),
+            Self::RateLimited {
+                retry_after: Some(secs),
+            } => write!(
+                f,
+                "rate limited by Google — it asked to wait {secs}s before retrying \
+                 (Retry-After), or use --proxy to route through a different IP"
+            ),
             Self::Blocked(status) => write!(
                 f,
                 "request blocked by Google (HTTP {status}) — this usually means \
@@ -58,9 +72,32 @@ impl fmt::Display for FlightError {
             ),
             Self::ScriptTagNotFound => write!(
                 f,
-                "failed to parse Google Flights response — the page structure may have changed, \
-                 or Google returned a CAPTCHA/consent page. \
-                 Try again, use --proxy, or file an issue if this persists"
+                "failed to find flight data in the Google Flights response — the page \
+                 structure may have changed (consent and CAPTCHA pages are reported as such). \
+                 Try again, or file an issue if this persists"
+            ),
+            Self::ConsentRequired => write!(
+                f,
+                "Google served its cookie consent page instead of flight results — \
+                 automatic consent handling failed or is disabled (--no-auto-consent). \
+                 Try again, drop --no-auto-consent, or use --proxy with a non-EU exit"
+            ),
+            Self::CaptchaRequired(url) => write!(
+                f,
+                "Google is asking for a CAPTCHA (\"unusual traffic\") — this is a hard block, \
+                 not a transient rate limit. Solve it in a browser at {url}, wait before \
+                 retrying, or use --proxy"
+            ),
+            Self::BackingOff { consecutive } => write!(
+                f,
+                "backing off — Google rate-limited or blocked {consecutive} searches in a row, \
+                 so the remaining ones were skipped to avoid making the block worse. \
+                 Wait a while before retrying, or use --proxy"
+            ),
+            Self::Storage(detail) => write!(
+                f,
+                "local cache/history database error ({detail}) — \
+                 check that the cache directory is writable, or set FLYR_DB to another path"
             ),
             Self::JsParse(detail) => write!(
                 f,
@@ -83,6 +120,90 @@ impl fmt::Display for FlightError {
 
 impl std::error::Error for FlightError {}
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorInfo {
+    pub kind: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+impl From<&FlightError> for ErrorInfo {
+    fn from(err: &FlightError) -> Self {
+        Self {
+            kind: err.kind(),
+            message: err.to_string(),
+            retry_after_secs: err.retry_after().map(|d| d.as_secs()),
+        }
+    }
+}
+
+// One row per kind: `kind()` is generated from it and `ERROR_KINDS` lists the kinds in
+// the same order, so the two can't drift apart.
+macro_rules! error_kinds {
+    ($($pattern:pat => $kind:literal,)*) => {
+        pub const ERROR_KINDS: &[&str] = &[$($kind),*];
+
+        impl FlightError {
+            pub fn kind(&self) -> &'static str {
+                match self {
+                    $($pattern => $kind,)*
+                }
+            }
+        }
+    };
+}
+
+error_kinds! {
+    Self::InvalidAirport(_) => "invalid_airport",
+    Self::InvalidDate(_) => "invalid_date",
+    Self::Validation(_) => "validation_error",
+    Self::Timeout => "timeout",
+    Self::ConnectionFailed(_) => "connection_failed",
+    Self::DnsResolution(_) => "dns_error",
+    Self::TlsError(_) => "tls_error",
+    Self::ProxyError(_) => "proxy_error",
+    Self::RateLimited { .. } => "rate_limited",
+    Self::Blocked(_) => "blocked",
+    Self::BackingOff { .. } => "backing_off",
+    Self::HttpStatus(_) => "http_error",
+    Self::ScriptTagNotFound | Self::JsParse(_) => "parse_error",
+    Self::ConsentRequired => "consent_required",
+    Self::CaptchaRequired(_) => "captcha_required",
+    Self::Storage(_) => "storage_error",
+    Self::NoResults => "no_results",
+}
+
+impl FlightError {
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after } => retry_after.map(Duration::from_secs),
+            _ => None,
+        }
+    }
+
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::Timeout | Self::ConnectionFailed(_))
+    }
+
+    // Worth the same request again after a pause: dropped connections, 429s and server
+    // errors. A CAPTCHA or a 403 won't go away by asking again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimited { .. } | Self::Blocked(503) => true,
+            Self::HttpStatus(status) => (500..600).contains(status),
+            other => other.is_transient(),
+        }
+    }
+
+    pub fn is_pushback(&self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited { .. } | Self::Blocked(_) | Self::CaptchaRequired(_)
+        )
+    }
+}
+
 pub fn from_http_error(err: wreq::Error) -> FlightError {
     let msg = err.to_string();
     let lower = msg.to_lowercase();
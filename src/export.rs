@@ -0,0 +1,266 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+use crate::error::FlightError;
+use crate::fetch::unix_now;
+use crate::model::{FlightResult, PriceScope, SearchResult};
+use crate::query::{self, QueryParams};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS searches (
+    id INTEGER PRIMARY KEY,
+    query_key TEXT NOT NULL,
+    searched_at TEXT NOT NULL,
+    origin TEXT NOT NULL,
+    destination TEXT NOT NULL,
+    depart_date TEXT NOT NULL,
+    return_date TEXT,
+    seat TEXT NOT NULL,
+    adults INTEGER NOT NULL,
+    children INTEGER NOT NULL,
+    infants INTEGER NOT NULL,
+    currency TEXT,
+    flight_count INTEGER NOT NULL,
+    UNIQUE (query_key, searched_at)
+);
+CREATE TABLE IF NOT EXISTS flights (
+    id INTEGER PRIMARY KEY,
+    itinerary TEXT NOT NULL UNIQUE,
+    airlines TEXT NOT NULL,
+    origin TEXT NOT NULL,
+    destination TEXT NOT NULL,
+    departure TEXT NOT NULL,
+    arrival TEXT NOT NULL,
+    stops INTEGER NOT NULL,
+    elapsed_minutes INTEGER NOT NULL,
+    air_minutes INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS segments (
+    flight_id INTEGER NOT NULL REFERENCES flights (id),
+    position INTEGER NOT NULL,
+    from_airport TEXT NOT NULL,
+    to_airport TEXT NOT NULL,
+    departure TEXT NOT NULL,
+    arrival TEXT NOT NULL,
+    duration_minutes INTEGER NOT NULL,
+    airline TEXT,
+    flight_number TEXT,
+    aircraft TEXT,
+    PRIMARY KEY (flight_id, position)
+);
+CREATE TABLE IF NOT EXISTS fares (
+    search_id INTEGER NOT NULL REFERENCES searches (id),
+    flight_id INTEGER NOT NULL REFERENCES flights (id),
+    position INTEGER NOT NULL,
+    price INTEGER,
+    price_scope TEXT,
+    emission_grams INTEGER,
+    typical_grams INTEGER,
+    risk TEXT NOT NULL,
+    PRIMARY KEY (search_id, flight_id)
+);
+CREATE TABLE IF NOT EXISTS airlines (
+    code TEXT PRIMARY KEY,
+    name TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS searches_by_route ON searches (origin, destination, depart_date);
+CREATE INDEX IF NOT EXISTS fares_by_flight ON fares (flight_id);
+";
+
+fn storage_error(e: impl std::fmt::Display) -> FlightError {
+    FlightError::Storage(e.to_string())
+}
+
+pub fn itinerary_key(flight: &FlightResult) -> Option<String> {
+    if flight.segments.is_empty() {
+        return None;
+    }
+    let parts: Vec<String> = flight
+        .segments
+        .iter()
+        .map(|s| {
+            format!(
+                "{}{} {}-{} {}",
+                s.airline.as_deref().unwrap_or(""),
+                s.flight_number.as_deref().unwrap_or("?"),
+                s.from_airport.code,
+                s.to_airport.code,
+                s.departure
+            )
+        })
+        .collect();
+    Some(parts.join(" | "))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ExportCounts {
+    pub flights: usize,
+    pub skipped: usize,
+}
+
+pub struct Export {
+    conn: Connection,
+}
+
+impl Export {
+    pub fn open(path: &Path) -> Result<Self, FlightError> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(storage_error)?;
+        }
+        Self::init(Connection::open(path).map_err(storage_error)?)
+    }
+
+    pub fn in_memory() -> Result<Self, FlightError> {
+        Self::init(Connection::open_in_memory().map_err(storage_error)?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, FlightError> {
+        conn.execute_batch(SCHEMA).map_err(storage_error)?;
+        Ok(Self { conn })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn write(
+        &mut self,
+        params: &QueryParams,
+        result: &SearchResult,
+    ) -> Result<ExportCounts, FlightError> {
+        let (Some(first), Some(last)) = (params.legs.first(), params.legs.last()) else {
+            return Ok(ExportCounts::default());
+        };
+        let searched_at = result
+            .searched_at
+            .clone()
+            .unwrap_or_else(|| query::format_timestamp(unix_now()));
+        let currency = result
+            .metadata
+            .currency
+            .clone()
+            .or_else(|| (!params.currency.is_empty()).then(|| params.currency.clone()));
+
+        let tx = self.conn.transaction().map_err(storage_error)?;
+        let search_id: i64 = tx
+            .query_row(
+                "INSERT INTO searches (query_key, searched_at, origin, destination, depart_date,
+                     return_date, seat, adults, children, infants, currency, flight_count)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                 ON CONFLICT (query_key, searched_at) DO UPDATE SET
+                     currency = excluded.currency, flight_count = excluded.flight_count
+                 RETURNING id",
+                params![
+                    crate::store::cache_key(params),
+                    searched_at,
+                    first.from_airport,
+                    first.to_airport,
+                    first.date,
+                    (params.legs.len() > 1).then(|| last.date.clone()),
+                    params.seat.as_str(),
+                    params.passengers.adults,
+                    params.passengers.children,
+                    params.passengers.infants_in_seat + params.passengers.infants_on_lap,
+                    currency,
+                    result.flights.len() as i64,
+                ],
+                |row| row.get(0),
+            )
+            .map_err(storage_error)?;
+
+        for airline in &result.metadata.airlines {
+            tx.execute(
+                "INSERT INTO airlines (code, name) VALUES (?1, ?2)
+                 ON CONFLICT (code) DO UPDATE SET name = excluded.name",
+                params![airline.code, airline.name],
+            )
+            .map_err(storage_error)?;
+        }
+
+        let mut counts = ExportCounts::default();
+        for (position, flight) in result.flights.iter().enumerate() {
+            let (Some(key), Some(dep), Some(arr)) = (
+                itinerary_key(flight),
+                flight.segments.first(),
+                flight.segments.last(),
+            ) else {
+                counts.skipped += 1;
+                continue;
+            };
+
+            let flight_id: i64 = tx
+                .query_row(
+                    "INSERT INTO flights (itinerary, airlines, origin, destination, departure,
+                         arrival, stops, elapsed_minutes, air_minutes)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                     ON CONFLICT (itinerary) DO UPDATE SET
+                         airlines = excluded.airlines,
+                         elapsed_minutes = excluded.elapsed_minutes,
+                         air_minutes = excluded.air_minutes
+                     RETURNING id",
+                    params![
+                        key,
+                        flight.airlines.join(", "),
+                        dep.from_airport.code,
+                        arr.to_airport.code,
+                        dep.departure.to_string(),
+                        arr.arrival.to_string(),
+                        flight.segments.len() as i64 - 1,
+                        flight.elapsed_minutes,
+                        flight.air_minutes,
+                    ],
+                    |row| row.get(0),
+                )
+                .map_err(storage_error)?;
+
+            for (i, s) in flight.segments.iter().enumerate() {
+                tx.execute(
+                    "INSERT OR REPLACE INTO segments (flight_id, position, from_airport,
+                         to_airport, departure, arrival, duration_minutes, airline,
+                         flight_number, aircraft)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        flight_id,
+                        i as i64,
+                        s.from_airport.code,
+                        s.to_airport.code,
+                        s.departure.to_string(),
+                        s.arrival.to_string(),
+                        s.duration_minutes,
+                        s.airline,
+                        s.flight_number,
+                        s.aircraft,
+                    ],
+                )
+                .map_err(storage_error)?;
+            }
+
+            let scope = flight.price_scope.map(|s| match s {
+                PriceScope::Total => "total",
+                PriceScope::Outbound => "outbound",
+            });
+            tx.execute(
+                "INSERT OR REPLACE INTO fares (search_id, flight_id, position, price,
+                     price_scope, emission_grams, typical_grams, risk)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    search_id,
+                    flight_id,
+                    position as i64,
+                    flight.price,
+                    scope,
+                    flight.carbon.emission_grams,
+                    flight.carbon.typical_grams,
+                    flight.risk.to_string(),
+                ],
+            )
+            .map_err(storage_error)?;
+            counts.flights += 1;
+        }
+
+        tx.commit().map_err(storage_error)?;
+        Ok(counts)
+    }
+}
@@ -1,4 +1,8 @@
-use std::sync::Arc;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use scraper::{Html, Selector};
@@ -6,24 +10,82 @@ use wreq::Client;
 use wreq::cookie::Jar;
 use wreq_util::Emulation;
 
+use crate::breaker::{RetryBudget, DEFAULT_RETRY_BUDGET};
+use crate::dns::DnsCache;
 use crate::error::{self, FlightError};
+use crate::model::FlightDateTime;
+use crate::parse;
 
-fn cache_buster() -> String {
+pub fn unix_now() -> u64 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs()
-        .to_string()
+}
+
+fn cache_buster() -> String {
+    unix_now().to_string()
+}
+
+fn month_number(name: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    MONTHS.iter().position(|m| *m == name).map(|i| i as u32 + 1)
+}
+
+pub fn parse_retry_after(value: &str, now_unix: u64) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, time, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    let hms: Vec<u32> = time.split(':').filter_map(|p| p.parse().ok()).collect();
+    let [hour, minute, second] = hms.as_slice() else {
+        return None;
+    };
+    let date = FlightDateTime {
+        year: year.parse().ok()?,
+        month: month_number(month)?,
+        day: day.parse().ok()?,
+        hour: *hour,
+        minute: *minute,
+    };
+    let at = date.minutes_since_epoch() * 60 + *second as i64;
+    Some((at - now_unix as i64).max(0) as u64)
 }
 
 const BASE_URL: &str = "https://www.google.com/travel/flights";
 const MAX_REDIRECTS: u8 = 10;
 
+pub const DEFAULT_RETRIES: u32 = 2;
+pub const DEFAULT_BACKOFF: Duration = Duration::from_millis(500);
+// Longest pause before a retry. A Retry-After beyond it fails the request instead.
+pub const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+// Called before each retry with the attempt that failed (1-based), its error and the
+// pause before the next one.
+pub type RetryHook = Arc<dyn Fn(u32, &FlightError, Duration) + Send + Sync>;
+
 #[derive(Clone)]
 pub struct FetchOptions {
     pub proxy: Option<String>,
     pub timeout: u64,
+    pub auto_consent: bool,
+    pub resolver: Option<DnsCache>,
+    pub connect_timeout: Option<Duration>,
+    pub first_byte_timeout: Option<Duration>,
+    pub body_timeout: Option<Duration>,
+    pub retries: u32,
+    pub backoff: Duration,
+    pub on_retry: Option<RetryHook>,
+    pub retry_budget: Option<Arc<RetryBudget>>,
+    pub retry_pushback: bool,
 }
 
 impl Default for FetchOptions {
@@ -31,14 +93,91 @@ impl Default for FetchOptions {
         Self {
             proxy: None,
             timeout: 30,
+            auto_consent: true,
+            resolver: None,
+            connect_timeout: None,
+            first_byte_timeout: None,
+            body_timeout: None,
+            retries: DEFAULT_RETRIES,
+            backoff: DEFAULT_BACKOFF,
+            on_retry: None,
+            retry_budget: None,
+            retry_pushback: true,
         }
     }
 }
 
+impl FetchOptions {
+    // For the fetcher behind a `Guarded` fan-out: every request draws its retries from
+    // one shared budget, and 429s and blocks are returned at once so the breaker sees
+    // them instead of each request waiting them out.
+    pub fn for_fan_out(self) -> Self {
+        Self {
+            retry_budget: Some(Arc::new(RetryBudget::new(DEFAULT_RETRY_BUDGET))),
+            retry_pushback: false,
+            ..self
+        }
+    }
+
+    fn may_retry(&self, err: &FlightError) -> bool {
+        if err.is_pushback() && !self.retry_pushback {
+            return false;
+        }
+        self.retry_budget.as_ref().is_none_or(|b| b.try_spend())
+    }
+}
+
+// The pause after failed attempt `attempt` (1-based): `base` doubled per attempt, plus
+// up to half again as jitter (`jitter` in 0..1) so parallel searches don't retry in step.
+pub fn backoff_delay(base: Duration, attempt: u32, jitter: f64) -> Duration {
+    let exp = base.saturating_mul(1 << attempt.saturating_sub(1).min(16));
+    exp.saturating_add(exp.mul_f64(jitter.clamp(0.0, 1.0) / 2.0))
+        .min(MAX_BACKOFF)
+}
+
+fn jitter() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// The only retry loop in the crate: at most `options.retries` more attempts after the
+// first, for errors that `is_retryable`, each paid from `options.retry_budget` when
+// there is one. Custom fetchers can wrap their requests in it.
+pub async fn with_retries<T, Fut>(
+    options: &FetchOptions,
+    mut request: impl FnMut() -> Fut,
+) -> Result<T, FlightError>
+where
+    Fut: Future<Output = Result<T, FlightError>>,
+{
+    let mut attempt = 1;
+    loop {
+        let err = match request().await {
+            Err(e) if e.is_retryable() && attempt <= options.retries && options.may_retry(&e) => e,
+            other => return other,
+        };
+        let delay = backoff_delay(options.backoff, attempt, jitter());
+        let delay = match err.retry_after() {
+            Some(wait) if wait > MAX_BACKOFF => return Err(err),
+            Some(wait) => delay.max(wait),
+            None => delay,
+        };
+        if let Some(hook) = &options.on_retry {
+            hook(attempt, &err, delay);
+        }
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
 fn is_redirect(status: u16) -> bool {
     matches!(status, 301 | 302 | 303 | 307 | 308)
 }
 
+fn is_captcha_url(url: &str) -> bool {
+    url.contains("/sorry/")
+}
+
 fn extract_location(response: &wreq::Response) -> Option<String> {
     response
         .headers()
@@ -47,6 +186,18 @@ fn extract_location(response: &wreq::Response) -> Option<String> {
         .map(String::from)
 }
 
+// RPC endpoints answer a blocked session with 200 and the same interstitial pages the
+// search page gets, which would otherwise surface as an unparseable response.
+pub fn interstitial_error(body: &str, url: &str) -> Option<FlightError> {
+    if parse::is_captcha_page(body) {
+        Some(FlightError::CaptchaRequired(url.to_string()))
+    } else if parse::is_consent_page(body) {
+        Some(FlightError::ConsentRequired)
+    } else {
+        None
+    }
+}
+
 fn extract_consent_form(html: &str) -> Option<String> {
     let document = Html::parse_document(html);
     let form_sel = Selector::parse("form[action=\"https://consent.google.com/save\"]").ok()?;
@@ -74,42 +225,79 @@ fn extract_consent_form(html: &str) -> Option<String> {
     )
 }
 
-async fn follow_redirects(client: &Client, start_url: &str) -> Result<String, FlightError> {
+async fn within<T>(
+    limit: Option<Duration>,
+    future: impl Future<Output = wreq::Result<T>>,
+) -> Result<T, FlightError> {
+    let result = match limit {
+        Some(limit) => tokio::time::timeout(limit, future)
+            .await
+            .map_err(|_| FlightError::Timeout)?,
+        None => future.await,
+    };
+    result.map_err(error::from_http_error)
+}
+
+async fn status_error(response: wreq::Response, url: &str) -> FlightError {
+    let status = response.status().as_u16();
+    let retry_after = response
+        .headers()
+        .get("retry-after")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| parse_retry_after(v, unix_now()));
+    let body = response.text().await.unwrap_or_default();
+    if parse::is_captcha_page(&body) {
+        return FlightError::CaptchaRequired(url.to_string());
+    }
+    match (status, retry_after) {
+        (429, retry_after) | (503, retry_after @ Some(_)) => {
+            FlightError::RateLimited { retry_after }
+        }
+        (403 | 503, _) => FlightError::Blocked(status),
+        (s, _) => FlightError::HttpStatus(s),
+    }
+}
+
+async fn follow_redirects(
+    client: &Client,
+    start_url: &str,
+    options: &FetchOptions,
+) -> Result<String, FlightError> {
     let mut url = start_url.to_string();
 
     for _ in 0..MAX_REDIRECTS {
-        let response = client
-            .get(&url)
-            .send()
-            .await
-            .map_err(error::from_http_error)?;
+        let response = within(options.first_byte_timeout, client.get(&url).send()).await?;
 
         let status = response.status().as_u16();
 
         if is_redirect(status) {
             url = extract_location(&response)
                 .ok_or_else(|| FlightError::JsParse("redirect without location".into()))?;
+            if is_captcha_url(&url) {
+                return Err(FlightError::CaptchaRequired(url));
+            }
             continue;
         }
 
-        match status {
-            200 => {}
-            429 => return Err(FlightError::RateLimited),
-            403 | 503 => return Err(FlightError::Blocked(status)),
-            s if s >= 400 => return Err(FlightError::HttpStatus(s)),
-            _ => {}
+        if status >= 400 {
+            return Err(status_error(response, &url).await);
         }
 
-        let html = response.text().await.map_err(error::from_http_error)?;
+        // A 200 page is only checked for a CAPTCHA by the parser, once it turns out to
+        // hold no flights; a redirect to /sorry/ or an error status is caught above.
+        let html = within(options.body_timeout, response.text()).await?;
 
         if let Some(form_body) = extract_consent_form(&html) {
-            let save_resp = client
+            if !options.auto_consent {
+                return Err(FlightError::ConsentRequired);
+            }
+
+            let save = client
                 .post("https://consent.google.com/save")
                 .header("content-type", "application/x-www-form-urlencoded")
                 .body(form_body)
-                .send()
-                .await
-                .map_err(error::from_http_error)?;
+                .send();
+            let save_resp = within(options.first_byte_timeout, save).await?;
 
             if is_redirect(save_resp.status().as_u16()) {
                 url = extract_location(&save_resp)
@@ -117,7 +305,7 @@ async fn follow_redirects(client: &Client, start_url: &str) -> Result<String, Fl
                 continue;
             }
 
-            return Err(FlightError::Blocked(save_resp.status().as_u16()));
+            return Err(FlightError::ConsentRequired);
         }
 
         return Ok(html);
@@ -126,37 +314,236 @@ async fn follow_redirects(client: &Client, start_url: &str) -> Result<String, Fl
     Err(FlightError::Blocked(302))
 }
 
-pub async fn fetch_html(
-    params: &[(String, String)],
-    options: &FetchOptions,
-) -> Result<String, FlightError> {
-    let jar = Arc::new(Jar::default());
+// One browser session: the HTTP client with its emulation, proxy and timeouts, and the
+// cookie jar holding Google's consent. Build it once and reuse it; clones share the
+// connection pool and cookies, so later searches skip the TLS handshake and consent page.
+#[derive(Clone)]
+pub struct FlyrClient {
+    client: Client,
+    options: FetchOptions,
+}
+
+impl FlyrClient {
+    pub fn new(options: FetchOptions) -> Result<Self, FlightError> {
+        let jar = Arc::new(Jar::default());
 
-    let mut builder = Client::builder()
-        .emulation(Emulation::Chrome137)
-        .cookie_provider(jar)
-        .timeout(Duration::from_secs(options.timeout));
+        let mut builder = Client::builder()
+            .emulation(Emulation::Chrome137)
+            .cookie_provider(jar)
+            .timeout(Duration::from_secs(options.timeout));
+
+        if let Some(connect) = options.connect_timeout {
+            builder = builder.connect_timeout(connect);
+        }
 
-    if let Some(ref proxy) = options.proxy {
-        builder = builder.proxy(
-            wreq::Proxy::all(proxy).map_err(error::from_http_error)?,
-        );
+        if let Some(ref resolver) = options.resolver {
+            builder = builder.dns_resolver(resolver.clone());
+        }
+
+        if let Some(ref proxy) = options.proxy {
+            builder = builder.proxy(wreq::Proxy::all(proxy).map_err(error::from_http_error)?);
+        }
+
+        let client = builder.build().map_err(error::from_http_error)?;
+        Ok(Self { client, options })
     }
 
-    let client = builder.build().map_err(error::from_http_error)?;
+    pub fn options(&self) -> &FetchOptions {
+        &self.options
+    }
 
-    let mut params = params.to_vec();
-    params.push(("cx".to_string(), cache_buster()));
+    // The same session (connections and cookies) under other options. `proxy`,
+    // `resolver`, `timeout` and `connect_timeout` were fixed when it was built.
+    pub fn with_options(&self, options: FetchOptions) -> Self {
+        Self {
+            client: self.client.clone(),
+            options,
+        }
+    }
 
-    let mut start_url = format!("{BASE_URL}?");
-    for (i, (k, v)) in params.iter().enumerate() {
-        if i > 0 {
-            start_url.push('&');
+    pub fn for_fan_out(&self) -> Self {
+        self.with_options(self.options.clone().for_fan_out())
+    }
+
+    pub async fn fetch_html(&self, params: &[(String, String)]) -> Result<String, FlightError> {
+        let mut params = params.to_vec();
+        params.push(("cx".to_string(), cache_buster()));
+
+        let mut start_url = format!("{BASE_URL}?");
+        for (i, (k, v)) in params.iter().enumerate() {
+            if i > 0 {
+                start_url.push('&');
+            }
+            start_url.push_str(&urlencoding::encode(k));
+            start_url.push('=');
+            start_url.push_str(&urlencoding::encode(v));
+        }
+
+        with_retries(&self.options, || {
+            follow_redirects(&self.client, &start_url, &self.options)
+        })
+        .await
+    }
+
+    pub async fn post_form(&self, url: &str, body: String) -> Result<String, FlightError> {
+        with_retries(&self.options, || self.post_form_once(url, body.clone())).await
+    }
+
+    async fn post_form_once(&self, url: &str, body: String) -> Result<String, FlightError> {
+        let request = self
+            .client
+            .post(url)
+            .header("content-type", "application/x-www-form-urlencoded;charset=UTF-8")
+            .body(body)
+            .send();
+        let response = within(self.options.first_byte_timeout, request).await?;
+
+        if response.status().as_u16() >= 400 {
+            return Err(status_error(response, url).await);
+        }
+        let body = within(self.options.body_timeout, response.text()).await?;
+        match interstitial_error(&body, url) {
+            Some(err) => Err(err),
+            None => Ok(body),
+        }
+    }
+
+    pub async fn get_text(&self, url: &str) -> Result<String, FlightError> {
+        with_retries(&self.options, || self.get_text_once(url)).await
+    }
+
+    async fn get_text_once(&self, url: &str) -> Result<String, FlightError> {
+        let response = within(self.options.first_byte_timeout, self.client.get(url).send()).await?;
+        if response.status().as_u16() >= 400 {
+            return Err(status_error(response, url).await);
+        }
+        within(self.options.body_timeout, response.text()).await
+    }
+
+    pub async fn warm(&self) -> Result<(), FlightError> {
+        let url = format!("{BASE_URL}?hl=en");
+        follow_redirects(&self.client, &url, &self.options).await.map(|_| ())
+    }
+}
+
+// A few sessions that searches are spread across, so a batch of searches reuses their
+// cookies and connections. Sessions are built on first use; `warm` builds them all and
+// gets their cookies up front.
+#[derive(Clone)]
+pub struct SessionPool {
+    options: FetchOptions,
+    sessions: Arc<Vec<OnceLock<FlyrClient>>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl std::fmt::Debug for SessionPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SessionPool")
+            .field("size", &self.sessions.len())
+            .finish()
+    }
+}
+
+impl SessionPool {
+    pub fn new(size: usize, options: FetchOptions) -> Self {
+        Self {
+            options,
+            sessions: Arc::new((0..size.max(1)).map(|_| OnceLock::new()).collect()),
+            next: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    // The same sessions with one retry budget between them; see `FetchOptions::for_fan_out`.
+    pub fn for_fan_out(&self) -> Self {
+        Self {
+            options: self.options.clone().for_fan_out(),
+            sessions: self.sessions.clone(),
+            next: self.next.clone(),
         }
-        start_url.push_str(&urlencoding::encode(k));
-        start_url.push('=');
-        start_url.push_str(&urlencoding::encode(v));
     }
 
-    follow_redirects(&client, &start_url).await
+    pub fn session(&self) -> Result<FlyrClient, FlightError> {
+        let i = self.next.fetch_add(1, Ordering::Relaxed) % self.sessions.len();
+        self.slot(i)
+    }
+
+    fn slot(&self, i: usize) -> Result<FlyrClient, FlightError> {
+        let slot = &self.sessions[i];
+        let session = match slot.get() {
+            Some(session) => session,
+            None => {
+                let built = FlyrClient::new(self.options.clone())?;
+                slot.get_or_init(|| built)
+            }
+        };
+        Ok(session.with_options(self.options.clone()))
+    }
+
+    // Builds every session and loads Google once in each. Returns how many loaded; only
+    // a session that can't be built (bad proxy, TLS setup) is an error.
+    pub async fn warm(&self) -> Result<usize, FlightError> {
+        let mut join_set = tokio::task::JoinSet::new();
+        for i in 0..self.sessions.len() {
+            let session = self.slot(i)?;
+            join_set.spawn(async move { session.warm().await });
+        }
+
+        let mut warmed = 0;
+        while let Some(result) = join_set.join_next().await {
+            if matches!(result, Ok(Ok(()))) {
+                warmed += 1;
+            }
+        }
+        Ok(warmed)
+    }
+}
+
+pub trait Fetcher: Send + Sync {
+    fn fetch_html(
+        &self,
+        params: &[(String, String)],
+    ) -> impl Future<Output = Result<String, FlightError>> + Send;
+
+    // How a page `fetch_html` returned turned out once parsed. A 200 can still be a
+    // consent or CAPTCHA page, which only the parser recognises; wrappers that react to
+    // blocks (`Guarded`, `Limited`) count it here and pass it on.
+    fn record_parsed(&self, _result: Result<(), &FlightError>) {}
+}
+
+impl Fetcher for FlyrClient {
+    async fn fetch_html(&self, params: &[(String, String)]) -> Result<String, FlightError> {
+        FlyrClient::fetch_html(self, params).await
+    }
+}
+
+impl<F: Fetcher> Fetcher for Arc<F> {
+    async fn fetch_html(&self, params: &[(String, String)]) -> Result<String, FlightError> {
+        F::fetch_html(self, params).await
+    }
+
+    fn record_parsed(&self, result: Result<(), &FlightError>) {
+        F::record_parsed(self, result)
+    }
+}
+
+impl Fetcher for SessionPool {
+    async fn fetch_html(&self, params: &[(String, String)]) -> Result<String, FlightError> {
+        self.session()?.fetch_html(params).await
+    }
+}
+
+// A one-off client per call; hold a `FlyrClient` to reuse connections across searches.
+pub async fn fetch_html(
+    params: &[(String, String)],
+    options: &FetchOptions,
+) -> Result<String, FlightError> {
+    FlyrClient::new(options.clone())?.fetch_html(params).await
 }
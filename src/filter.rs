@@ -0,0 +1,176 @@
+use crate::model::{
+    Category, Codeshare, FareBrand, FlightDateTime, FlightResult, SearchResult, Segment,
+};
+use crate::query::FlightLeg;
+
+fn minute_of_day(dt: &FlightDateTime) -> u32 {
+    dt.hour * 60 + dt.minute
+}
+
+fn within(minute: u32, after: Option<u32>, before: Option<u32>) -> bool {
+    after.is_none_or(|a| minute >= a) && before.is_none_or(|b| minute <= b)
+}
+
+pub const REDEYE_START: u32 = 22 * 60;
+pub const REDEYE_END: u32 = 5 * 60;
+
+pub fn is_redeye(flight: &FlightResult) -> bool {
+    flight.segments.first().is_some_and(|s| {
+        let minute = minute_of_day(&s.departure);
+        !(REDEYE_END..REDEYE_START).contains(&minute)
+    })
+}
+
+pub fn matches(flight: &FlightResult, leg: &FlightLeg) -> bool {
+    if leg.no_redeye && is_redeye(flight) {
+        return false;
+    }
+    if leg
+        .max_duration_minutes
+        .is_some_and(|max| flight.elapsed_minutes > max)
+    {
+        return false;
+    }
+    if let Some(excluded) = &leg.exclude_airlines {
+        let flown_by_excluded = flight
+            .segments
+            .iter()
+            .filter_map(|s| s.airline.as_deref())
+            .any(|code| excluded.iter().any(|e| e.eq_ignore_ascii_case(code)));
+        if flown_by_excluded {
+            return false;
+        }
+    }
+    if !flight
+        .layovers
+        .iter()
+        .all(|l| within(l.minutes, leg.min_layover_minutes, leg.max_layover_minutes))
+    {
+        return false;
+    }
+    let (Some(first), Some(last)) = (flight.segments.first(), flight.segments.last()) else {
+        return true;
+    };
+    within(
+        minute_of_day(&first.departure),
+        leg.depart_after,
+        leg.depart_before,
+    ) && within(
+        minute_of_day(&last.arrival),
+        leg.arrive_after,
+        leg.arrive_before,
+    )
+}
+
+pub fn apply_max_price(result: &mut SearchResult, max_price: i64) -> usize {
+    let before = result.flights.len();
+    result
+        .flights
+        .retain(|f| f.price.is_some_and(|p| p <= max_price));
+    before - result.flights.len()
+}
+
+pub fn is_low_emission(flight: &FlightResult) -> bool {
+    match (flight.carbon.emission_grams, flight.carbon.typical_grams) {
+        (Some(grams), Some(typical)) => grams <= typical,
+        _ => false,
+    }
+}
+
+pub fn apply_low_emissions(result: &mut SearchResult) -> usize {
+    let before = result.flights.len();
+    result.flights.retain(is_low_emission);
+    before - result.flights.len()
+}
+
+pub fn apply_exclude_basic(result: &mut SearchResult) -> usize {
+    let before = result.flights.len();
+    result.flights.retain(|f| f.fare_brand != Some(FareBrand::Basic));
+    before - result.flights.len()
+}
+
+pub fn apply_best_only(result: &mut SearchResult) -> usize {
+    let before = result.flights.len();
+    result.flights.retain(|f| f.category == Some(Category::Best));
+    before - result.flights.len()
+}
+
+// Route and times of every segment: what a codeshare listing has in common with the
+// flight it is sold on.
+fn physical_key(flight: &FlightResult) -> Vec<(&str, &str, i64, i64)> {
+    flight
+        .segments
+        .iter()
+        .map(|s| {
+            (
+                s.from_airport.code.as_str(),
+                s.to_airport.code.as_str(),
+                s.departure.minutes_since_epoch(),
+                s.arrival.minutes_since_epoch(),
+            )
+        })
+        .collect()
+}
+
+fn add_codeshare(segment: &mut Segment, codeshare: Codeshare) {
+    let own = segment.airline.as_deref() == Some(codeshare.airline.as_str())
+        && segment.flight_number.as_deref() == Some(codeshare.flight_number.as_str());
+    let known = segment.codeshares.iter().any(|c| {
+        c.airline == codeshare.airline && c.flight_number == codeshare.flight_number
+    });
+    if !own && !known {
+        segment.codeshares.push(codeshare);
+    }
+}
+
+// Folds `other`'s flight numbers, and the codeshares it already knew, into `kept`.
+fn absorb(kept: &mut FlightResult, other: FlightResult) {
+    for (segment, theirs) in kept.segments.iter_mut().zip(other.segments) {
+        if let (Some(airline), Some(flight_number)) = (theirs.airline, theirs.flight_number) {
+            let codeshare = Codeshare {
+                airline,
+                flight_number,
+                airline_name: theirs.airline_name,
+            };
+            add_codeshare(segment, codeshare);
+        }
+        for codeshare in theirs.codeshares {
+            add_codeshare(segment, codeshare);
+        }
+    }
+}
+
+// The same physical flight sold under several airline codes is listed once per code.
+// Keeps the cheapest listing of each, in the position of the first, with the other
+// codes recorded as its segments' codeshares.
+pub fn dedupe_codeshares(result: &mut SearchResult) -> usize {
+    let before = result.flights.len();
+    let mut groups: Vec<Vec<FlightResult>> = Vec::new();
+    for flight in std::mem::take(&mut result.flights) {
+        let same = groups.iter_mut().find(|group| {
+            !flight.segments.is_empty() && physical_key(&group[0]) == physical_key(&flight)
+        });
+        match same {
+            Some(group) => group.push(flight),
+            None => groups.push(vec![flight]),
+        }
+    }
+
+    for mut group in groups {
+        let cheapest = (0..group.len())
+            .min_by_key(|&i| (group[i].price.is_none(), group[i].price))
+            .unwrap_or(0);
+        let mut kept = group.remove(cheapest);
+        for other in group {
+            absorb(&mut kept, other);
+        }
+        result.flights.push(kept);
+    }
+    before - result.flights.len()
+}
+
+pub fn apply(result: &mut SearchResult, leg: &FlightLeg) -> usize {
+    let before = result.flights.len();
+    result.flights.retain(|f| matches(f, leg));
+    before - result.flights.len()
+}
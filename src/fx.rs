@@ -0,0 +1,161 @@
+use crate::error::FlightError;
+use crate::fetch::FlyrClient;
+use crate::model::{Money, SearchResult, Warning};
+
+pub const ECB_URL: &str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-daily.xml";
+
+// Units of each currency per euro, rounded from ECB reference rates. Good enough to
+// compare fares across markets, not to quote what a card will be charged.
+const EUR_RATES: [(&str, f64); 31] = [
+    ("EUR", 1.0),
+    ("USD", 1.17),
+    ("JPY", 172.0),
+    ("GBP", 0.87),
+    ("CHF", 0.94),
+    ("SEK", 11.0),
+    ("NOK", 11.7),
+    ("DKK", 7.46),
+    ("PLN", 4.26),
+    ("CZK", 24.4),
+    ("HUF", 392.0),
+    ("RON", 5.08),
+    ("BGN", 1.956),
+    ("ISK", 143.0),
+    ("TRY", 48.0),
+    ("CAD", 1.61),
+    ("AUD", 1.77),
+    ("NZD", 1.98),
+    ("CNY", 8.35),
+    ("HKD", 9.1),
+    ("SGD", 1.5),
+    ("KRW", 1620.0),
+    ("INR", 102.0),
+    ("IDR", 19200.0),
+    ("MYR", 4.93),
+    ("PHP", 67.0),
+    ("THB", 37.8),
+    ("BRL", 6.3),
+    ("MXN", 21.7),
+    ("ZAR", 20.4),
+    ("ILS", 3.9),
+];
+
+pub trait RateSource {
+    // Units of `currency` per euro.
+    fn per_eur(&self, currency: &str) -> Option<f64>;
+
+    fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        Some(self.per_eur(to)? / self.per_eur(from)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rates {
+    pub date: Option<String>,
+    per_eur: Vec<(String, f64)>,
+}
+
+impl Rates {
+    pub fn bundled() -> Self {
+        Self {
+            date: None,
+            per_eur: EUR_RATES.iter().map(|&(c, r)| (c.to_string(), r)).collect(),
+        }
+    }
+
+    // Reads the ECB daily reference feed: `<Cube time='...'>` wrapping one
+    // `<Cube currency='USD' rate='1.1697'/>` per currency, all against the euro.
+    pub fn parse_ecb(xml: &str) -> Result<Self, FlightError> {
+        let mut date = None;
+        let mut per_eur = vec![("EUR".to_string(), 1.0)];
+        for tag in xml.split("<Cube").skip(1) {
+            let tag = tag.split('>').next().unwrap_or_default();
+            if let Some(time) = attr(tag, "time") {
+                date = Some(time.to_string());
+            }
+            let (Some(currency), Some(rate)) = (attr(tag, "currency"), attr(tag, "rate")) else {
+                continue;
+            };
+            match rate.parse::<f64>() {
+                Ok(rate) if rate > 0.0 => per_eur.push((currency.to_uppercase(), rate)),
+                _ => {
+                    return Err(FlightError::JsParse(format!(
+                        "ECB rate for {currency} is not a number: {rate}"
+                    )))
+                }
+            }
+        }
+        if per_eur.len() == 1 {
+            return Err(FlightError::JsParse("ECB response has no exchange rates".into()));
+        }
+        Ok(Self { date, per_eur })
+    }
+
+    pub fn currencies(&self) -> Vec<&str> {
+        self.per_eur.iter().map(|(c, _)| c.as_str()).collect()
+    }
+}
+
+impl RateSource for Rates {
+    fn per_eur(&self, currency: &str) -> Option<f64> {
+        self.per_eur
+            .iter()
+            .find(|(c, _)| c.eq_ignore_ascii_case(currency))
+            .map(|&(_, rate)| rate)
+    }
+}
+
+fn attr<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{name}="))? + name.len() + 1;
+    let quote = tag[start..].chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    let value = &tag[start + 1..];
+    value.find(quote).map(|end| &value[..end])
+}
+
+pub async fn fetch_ecb(client: &FlyrClient) -> Result<Rates, FlightError> {
+    let xml = client.get_text(ECB_URL).await?;
+    Rates::parse_ecb(&xml)
+}
+
+// Rewrites every price in `result` into `to`, keeping what Google quoted in
+// `original_price` and `metadata.original_currency`. Prices are in the currency Google
+// returned, falling back to the one requested. `fare` keeps the converted amount down to
+// the target's minor unit; the other prices are rounded to whole units.
+pub fn convert(result: &mut SearchResult, rates: &dyn RateSource, requested: &str, to: &str) {
+    let from = result
+        .metadata
+        .currency
+        .clone()
+        .unwrap_or_else(|| requested.to_uppercase());
+    let to = to.to_uppercase();
+    if from.eq_ignore_ascii_case(&to) {
+        return;
+    }
+    let Some(rate) = rates.rate(&from, &to) else {
+        result.warnings.push(Warning::new(
+            "currency_conversion",
+            format!("no exchange rate from {from} to {to}; prices are left in {from}"),
+        ));
+        return;
+    };
+
+    let scale = |price: i64| (price as f64 * rate).round() as i64;
+    for flight in &mut result.flights {
+        flight.original_price = flight.price;
+        let major = match &flight.fare {
+            Some(fare) => Some(fare.major()),
+            None => flight.price.map(|p| p as f64),
+        };
+        flight.fare = major.map(|m| Money::from_major_f64(m * rate, &to));
+        flight.price = flight.fare.as_ref().map(Money::whole);
+        flight.price_with_bag = flight.price_with_bag.map(scale);
+        flight.previous_price = flight.previous_price.map(scale);
+        flight.delta = flight.delta.map(scale);
+    }
+    if let Some(insights) = &mut result.metadata.price_insights {
+        insights.typical_low = insights.typical_low.map(scale);
+        insights.typical_high = insights.typical_high.map(scale);
+    }
+    result.metadata.original_currency = Some(from);
+    result.metadata.currency = Some(to);
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use crate::error::FlightError;
+use crate::fetch::Fetcher;
+
+#[derive(Debug, Clone)]
+pub struct Hedged<F> {
+    primary: F,
+    backup: Option<F>,
+    after: Duration,
+}
+
+impl<F: Fetcher> Hedged<F> {
+    pub fn new(primary: F, backup: F, after: Duration) -> Self {
+        Self {
+            primary,
+            backup: Some(backup),
+            after,
+        }
+    }
+
+    pub fn disabled(primary: F) -> Self {
+        Self {
+            primary,
+            backup: None,
+            after: Duration::ZERO,
+        }
+    }
+
+    pub fn primary(&self) -> &F {
+        &self.primary
+    }
+
+    pub fn after(&self) -> Option<Duration> {
+        self.backup.as_ref().map(|_| self.after)
+    }
+}
+
+impl<F: Fetcher> Fetcher for Hedged<F> {
+    async fn fetch_html(&self, params: &[(String, String)]) -> Result<String, FlightError> {
+        let Some(backup) = &self.backup else {
+            return self.primary.fetch_html(params).await;
+        };
+
+        let first = self.primary.fetch_html(params);
+        tokio::pin!(first);
+        tokio::select! {
+            result = &mut first => return result,
+            _ = tokio::time::sleep(self.after) => {}
+        }
+
+        let second = backup.fetch_html(params);
+        tokio::pin!(second);
+        let (result, first_done) = tokio::select! {
+            result = &mut first => (result, true),
+            result = &mut second => (result, false),
+        };
+
+        match result {
+            Ok(html) => Ok(html),
+            Err(e) => {
+                let other = if first_done { second.await } else { first.await };
+                other.map_err(|_| e)
+            }
+        }
+    }
+
+    // The page may have come from either request; the primary stands in for both.
+    fn record_parsed(&self, result: Result<(), &FlightError>) {
+        self.primary.record_parsed(result);
+    }
+}
@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use jaq_core::compile::Undefined;
+use jaq_core::load::{self, Arena, File, Loader};
+use jaq_core::{data, unwrap_valr, Compiler, Ctx, Vars};
+use jaq_json::Val;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::error::FlightError;
+
+type Data = data::JustLut<Val>;
+
+fn invalid(message: impl std::fmt::Display) -> FlightError {
+    FlightError::Validation(format!("invalid --jq filter: {message}"))
+}
+
+fn runtime(message: impl std::fmt::Display) -> FlightError {
+    FlightError::Validation(format!("--jq: {message}"))
+}
+
+// jaq reports where it stopped as the rest of the source from that point.
+fn near(rest: &str) -> String {
+    match rest.chars().take(20).collect::<String>() {
+        s if s.is_empty() => "at the end".to_string(),
+        s => format!("near \"{s}\""),
+    }
+}
+
+fn load_error(err: &load::Error<&str>) -> FlightError {
+    let message = match err {
+        load::Error::Io(errs) => errs.first().map(|(_, e)| e.clone()).unwrap_or_default(),
+        load::Error::Lex(errs) => errs
+            .first()
+            .map(|(expect, rest)| format!("expected {} {}", expect.as_str(), near(rest)))
+            .unwrap_or_default(),
+        load::Error::Parse(errs) => errs
+            .first()
+            .map(|(expect, rest)| format!("expected {} {}", expect.as_str(), near(rest)))
+            .unwrap_or_default(),
+    };
+    invalid(message)
+}
+
+fn undefined(name: &str, kind: &Undefined) -> FlightError {
+    invalid(match kind {
+        Undefined::Var => format!("undefined variable ${name}"),
+        Undefined::Filter(arity) => format!("undefined function {name}/{arity}"),
+        _ => format!("undefined {name}"),
+    })
+}
+
+// A jq program compiled with jaq's core, standard and JSON definitions.
+#[derive(Clone)]
+pub struct Filter {
+    filter: Arc<jaq_core::Filter<Data>>,
+}
+
+impl std::fmt::Debug for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Filter").finish_non_exhaustive()
+    }
+}
+
+impl Filter {
+    pub fn parse(src: &str) -> Result<Self, FlightError> {
+        let code = if src.trim().is_empty() { "." } else { src };
+        let defs = jaq_core::defs().chain(jaq_std::defs()).chain(jaq_json::defs());
+        let funs = jaq_core::funs().chain(jaq_std::funs()).chain(jaq_json::funs());
+
+        let arena = Arena::default();
+        let modules = Loader::new(defs)
+            .load(&arena, File { code, path: () })
+            .map_err(|errs| match errs.first() {
+                Some((_, e)) => load_error(e),
+                None => invalid("cannot be parsed"),
+            })?;
+        let filter = Compiler::default()
+            .with_funs(funs)
+            .compile(modules)
+            .map_err(|errs| {
+                let first = errs.first().and_then(|(_, e)| e.first());
+                match first {
+                    Some((name, kind)) => undefined(name, kind),
+                    None => invalid("cannot be compiled"),
+                }
+            })?;
+        Ok(Self {
+            filter: Arc::new(filter),
+        })
+    }
+
+    pub fn run(&self, input: &Value) -> Result<Vec<Value>, FlightError> {
+        let input = Val::deserialize(input).map_err(runtime)?;
+        let ctx = Ctx::<Data>::new(&self.filter.lut, Vars::new([]));
+        self.filter
+            .id
+            .run((ctx, input))
+            .map(unwrap_valr)
+            .map(|output| {
+                let output = output.map_err(runtime)?;
+                serde_json::from_str(&output.to_string()).map_err(runtime)
+            })
+            .collect()
+    }
+}
@@ -1,24 +1,392 @@
+pub mod airports;
+pub mod bags;
+pub mod bench;
+pub mod booking;
+pub mod breaker;
+pub mod budget;
+pub mod cabins;
+pub mod cache;
+pub mod calendar;
+pub mod carbon;
+pub mod coalesce;
+pub mod config;
+pub mod diff;
+pub mod dns;
 pub mod error;
+pub mod export;
 pub mod fetch;
+pub mod fx;
+pub mod filter;
+pub mod hedge;
+pub mod jq;
+pub mod limit;
+pub mod locale;
 pub mod mcp;
+pub mod metro;
 pub mod model;
 pub mod parse;
+pub mod pareto;
 pub mod proto;
 pub mod query;
+pub mod risk;
+pub mod snapshot;
+pub mod stay;
+pub mod store;
 pub mod table;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod tz;
+pub mod value;
+
+pub const SCHEMA_VERSION: u32 = 3;
+
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
 
 use error::FlightError;
-use fetch::FetchOptions;
-use model::SearchResult;
-use query::{QueryParams, SearchQuery};
+use fetch::{FetchOptions, Fetcher};
+pub use fetch::FlyrClient;
+use model::{BookingOption, CalendarDay, FlightResult, PriceScope, SearchResult, Warning};
+use parse::ParseOptions;
+use query::{QueryParams, SearchQuery, TripType};
 
 pub async fn search(
     query: SearchQuery,
     options: FetchOptions,
+) -> Result<SearchResult, FlightError> {
+    search_with(query, options, ParseOptions::default()).await
+}
+
+// Builds a client for this search alone. Callers running more than one search should
+// keep a `FlyrClient` and use `search_with_client`.
+pub async fn search_with(
+    query: SearchQuery,
+    options: FetchOptions,
+    parse_options: ParseOptions,
+) -> Result<SearchResult, FlightError> {
+    let client = FlyrClient::new(options)?;
+    search_with_client(&client, query, parse_options).await
+}
+
+pub async fn search_with_client(
+    client: &FlyrClient,
+    query: SearchQuery,
+    parse_options: ParseOptions,
+) -> Result<SearchResult, FlightError> {
+    search_with_fetcher(client, query, parse_options).await
+}
+
+pub async fn search_with_fetcher<F: Fetcher>(
+    fetcher: &F,
+    query: SearchQuery,
+    parse_options: ParseOptions,
+) -> Result<SearchResult, FlightError> {
+    match query {
+        SearchQuery::Structured(q) if q.has_mixed_cabins() => {
+            search_mixed_cabins(fetcher, q, parse_options).await
+        }
+        query => search_one(fetcher, query, parse_options).await,
+    }
+}
+
+async fn search_one<F: Fetcher>(
+    fetcher: &F,
+    query: SearchQuery,
+    parse_options: ParseOptions,
 ) -> Result<SearchResult, FlightError> {
     let params = query.to_url_params();
-    let html = fetch::fetch_html(&params, &options).await?;
-    parse::parse_html(&html)
+    let started = std::time::Instant::now();
+    let html = fetcher.fetch_html(&params).await?;
+    let fetch = started.elapsed();
+
+    let (query, parsed) = tokio::task::spawn_blocking(move || {
+        let parser = parse::select_parser(&parse_options.parsers, &html, &query);
+        let parsed = parser.parse(html, &query, &parse_options);
+        (query, parsed)
+    })
+    .await
+    .map_err(|e| FlightError::JsParse(format!("parse task failed: {e}")))?;
+    fetcher.record_parsed(parsed.as_ref().map(|_| ()));
+    let mut result = parsed?;
+    if let Some(timings) = &mut result.timings {
+        timings.fetch = fetch;
+    }
+    result.complete_fares(match &query {
+        SearchQuery::Structured(q) => &q.currency,
+        SearchQuery::NaturalLanguage(_) => "",
+    });
+    if let SearchQuery::Structured(q) = &query {
+        // The listed options belong to the first leg without a chosen flight.
+        if let Some(leg) = q.legs.iter().find(|l| l.selected.is_empty()) {
+            filter::apply(&mut result, leg);
+        }
+        if let Some(max_price) = q.max_price {
+            filter::apply_max_price(&mut result, max_price);
+        }
+        if q.low_emissions {
+            filter::apply_low_emissions(&mut result);
+        }
+        result.warnings.extend(currency_warning(&result, &q.currency));
+    }
+    result.query = Some(query);
+    result.searched_at = Some(query::format_timestamp(fetch::unix_now()));
+    Ok(result)
+}
+
+// One search prices every leg in the same cabin, so a trip mixing cabins runs one
+// one-way search per leg. The first leg's options are listed, each priced together
+// with the cheapest fare found for every later leg.
+async fn search_mixed_cabins<F: Fetcher>(
+    fetcher: &F,
+    params: QueryParams,
+    parse_options: ParseOptions,
+) -> Result<SearchResult, FlightError> {
+    let mut legs = Vec::with_capacity(params.legs.len());
+    for i in 0..params.legs.len() {
+        let Some(leg) = params.leg_search(i) else {
+            continue;
+        };
+        let result =
+            search_one(fetcher, SearchQuery::Structured(leg.clone()), parse_options.clone())
+                .await?;
+        legs.push((leg, result));
+    }
+    if legs.is_empty() {
+        return Err(FlightError::Validation("a search needs at least one leg".into()));
+    }
+    let (_, mut result) = legs.remove(0);
+    result.query = Some(SearchQuery::Structured(params.clone()));
+    if legs.is_empty() {
+        if let Some(max_price) = params.max_price {
+            filter::apply_max_price(&mut result, max_price);
+        }
+        return Ok(result);
+    }
+
+    let mut extra = 0;
+    let mut extra_minor = Some(0);
+    let mut chosen = Vec::new();
+    for (leg, leg_result) in &legs {
+        let cheapest = leg_result
+            .flights
+            .iter()
+            .filter(|f| f.price.is_some())
+            .min_by_key(|f| f.price)
+            .ok_or(FlightError::NoResults)?;
+        extra += cheapest.price.unwrap_or_default();
+        extra_minor = extra_minor
+            .zip(cheapest.fare.as_ref())
+            .map(|(sum, fare)| sum + fare.amount_minor);
+        let numbers: Vec<String> = cheapest
+            .segments
+            .iter()
+            .filter_map(|s| {
+                Some(format!("{}{}", s.airline.as_deref()?, s.flight_number.as_deref()?))
+            })
+            .collect();
+        let route = &leg.legs[0];
+        chosen.push(format!(
+            "{}→{} on {} in {} ({}, {})",
+            route.from_airport,
+            route.to_airport,
+            route.date,
+            leg.seat.as_str(),
+            cheapest.price.unwrap_or_default(),
+            numbers.join("/")
+        ));
+        result.warnings.extend(leg_result.warnings.iter().cloned());
+    }
+
+    for flight in &mut result.flights {
+        let Some(price) = flight.price else {
+            continue;
+        };
+        flight.price = Some(price + extra);
+        flight.fare = match (flight.fare.take(), extra_minor) {
+            (Some(fare), Some(minor)) => Some(model::Money {
+                amount_minor: fare.amount_minor + minor,
+                ..fare
+            }),
+            _ => None,
+        };
+        flight.price_scope = Some(PriceScope::Total);
+    }
+    result.complete_fares(&params.currency);
+    if let Some(max_price) = params.max_price {
+        filter::apply_max_price(&mut result, max_price);
+    }
+    result.warnings.push(Warning::new(
+        "mixed_cabins",
+        format!(
+            "legs in different cabins were searched as separate one-way tickets; \
+             prices include the cheapest fare for {}",
+            chosen.join(", ")
+        ),
+    ));
+    Ok(result)
+}
+
+pub async fn fetch_raw_payload<F: Fetcher>(
+    fetcher: &F,
+    query: &SearchQuery,
+) -> Result<serde_json::Value, FlightError> {
+    let html = fetcher.fetch_html(&query.to_url_params()).await?;
+    let payload = parse::extract_payload(&html);
+    fetcher.record_parsed(payload.as_ref().map(|_| ()));
+    payload
+}
+
+fn currency_warning(result: &SearchResult, requested: &str) -> Option<Warning> {
+    let actual = result.metadata.currency.as_deref()?;
+    if requested.is_empty() || actual.eq_ignore_ascii_case(requested) {
+        return None;
+    }
+    Some(Warning::new(
+        "currency_mismatch",
+        format!(
+            "requested prices in {requested}, but Google returned {actual} \
+             (this happens for some markets)"
+        ),
+    ))
+}
+
+pub const MAX_FLEX_DAYS: u32 = 30;
+
+// The day offsets a ±`days` search runs, leaving out departures before `today` (days
+// since the epoch), which Google has no fares for.
+pub fn flex_offsets(params: &QueryParams, days: u32, today: i64) -> Result<Vec<i64>, FlightError> {
+    if days > MAX_FLEX_DAYS {
+        return Err(FlightError::Validation(format!(
+            "--flex {days} would run {} searches (at most {}) — narrow the range",
+            2 * days + 1,
+            2 * MAX_FLEX_DAYS + 1
+        )));
+    }
+    let date = params.legs.first().map(|l| l.date.as_str()).unwrap_or_default();
+    let first = query::date_to_days(date).ok_or_else(|| FlightError::InvalidDate(date.into()))?;
+    let days = i64::from(days);
+    let offsets: Vec<i64> = (-days..=days).filter(|o| first + o >= today).collect();
+    if offsets.is_empty() {
+        return Err(FlightError::Validation(format!(
+            "every departure date of --flex {days} around {date} is in the past"
+        )));
+    }
+    Ok(offsets)
+}
+
+pub async fn search_flexible<F: Fetcher + Clone + 'static>(
+    fetcher: &F,
+    params: &QueryParams,
+    days: u32,
+    parse_options: ParseOptions,
+) -> Result<BTreeMap<String, Result<SearchResult, FlightError>>, FlightError> {
+    let today = (fetch::unix_now() / 86_400) as i64;
+    let mut join_set = tokio::task::JoinSet::new();
+    for offset in flex_offsets(params, days, today)? {
+        let Some(shifted) = params.shift_days(offset) else {
+            continue;
+        };
+        let date = shifted.legs.first().map(|l| l.date.clone()).unwrap_or_default();
+        let fetcher = fetcher.clone();
+        let parse_options = parse_options.clone();
+        join_set.spawn(async move {
+            let result =
+                search_with_fetcher(&fetcher, SearchQuery::Structured(shifted), parse_options)
+                    .await;
+            (date, result)
+        });
+    }
+
+    let mut by_date = BTreeMap::new();
+    while let Some(join_result) = join_set.join_next().await {
+        let (date, result) = join_result.unwrap();
+        by_date.insert(date, result);
+    }
+    Ok(by_date)
+}
+
+// The return flights that go with `outbound`, a result of the round-trip search
+// `params`. Their prices are the round-trip total with that outbound.
+pub async fn search_returns<F: Fetcher>(
+    fetcher: &F,
+    params: &QueryParams,
+    outbound: &FlightResult,
+    parse_options: ParseOptions,
+) -> Result<SearchResult, FlightError> {
+    if !matches!(params.trip, TripType::RoundTrip) || params.legs.len() != 2 {
+        return Err(FlightError::Validation(
+            "return flights are only listed for round-trip searches".into(),
+        ));
+    }
+    if params.has_mixed_cabins() {
+        return Err(FlightError::Validation(
+            "a trip mixing cabins prices each leg on its own; search the return as a one-way trip"
+                .into(),
+        ));
+    }
+    let mut with_outbound = params.clone();
+    with_outbound.legs[0].selected = query::SelectedFlight::from_flight(outbound)?;
+    let query = SearchQuery::Structured(with_outbound);
+    let mut result = search_with_fetcher(fetcher, query, parse_options).await?;
+    for flight in &mut result.flights {
+        flight.price_scope = Some(PriceScope::Total);
+    }
+    Ok(result)
+}
+
+pub type StayResults = BTreeMap<(String, String), Result<SearchResult, FlightError>>;
+
+pub async fn search_stays<F: Fetcher + Clone + 'static>(
+    fetcher: &F,
+    params: &QueryParams,
+    flex_days: u32,
+    nights: RangeInclusive<u32>,
+    parse_options: ParseOptions,
+) -> Result<StayResults, FlightError> {
+    let date = params
+        .legs
+        .first()
+        .map(|l| l.date.clone())
+        .ok_or_else(|| FlightError::Validation("a stay search needs an outbound leg".into()))?;
+
+    let mut join_set = tokio::task::JoinSet::new();
+    for (depart, ret, _) in stay::pairs(&date, flex_days, &nights)? {
+        let query = SearchQuery::Structured(stay::round_trip(params, &depart, &ret));
+        let fetcher = fetcher.clone();
+        let parse_options = parse_options.clone();
+        join_set.spawn(async move {
+            let result = search_with_fetcher(&fetcher, query, parse_options).await;
+            ((depart, ret), result)
+        });
+    }
+
+    let mut by_pair = BTreeMap::new();
+    while let Some(join_result) = join_set.join_next().await {
+        let (pair, result) = join_result.unwrap();
+        by_pair.insert(pair, result);
+    }
+    Ok(by_pair)
+}
+
+pub async fn search_calendar(
+    client: &FlyrClient,
+    params: &QueryParams,
+    start: &str,
+    end: &str,
+) -> Result<Vec<CalendarDay>, FlightError> {
+    let body = calendar::encode_request(params, start, end);
+    let text = client.post_form(&calendar::request_url(params), body).await?;
+    calendar::parse_response(&text)
+}
+
+pub async fn fetch_booking_options(
+    client: &FlyrClient,
+    params: &QueryParams,
+    flight: &FlightResult,
+) -> Result<Vec<BookingOption>, FlightError> {
+    let body = booking::encode_request(params, flight)?;
+    let text = client.post_form(&booking::request_url(params), body).await?;
+    booking::parse_response(&text)
 }
 
 pub fn generate_browser_url(params: &QueryParams) -> String {
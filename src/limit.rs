@@ -0,0 +1,173 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+
+use crate::error::FlightError;
+use crate::fetch::Fetcher;
+
+pub const DEFAULT_CONCURRENCY: u32 = 6;
+
+#[derive(Debug)]
+pub struct AdaptiveLimit {
+    max: u32,
+    limit: AtomicU32,
+    debt: AtomicU32,
+    successes: AtomicU32,
+    permits: Semaphore,
+    spacing: Duration,
+    next_start: Mutex<Option<Instant>>,
+}
+
+impl AdaptiveLimit {
+    pub fn new(max: u32, spacing: Duration) -> Self {
+        let max = max.max(1);
+        Self {
+            max,
+            limit: AtomicU32::new(max),
+            debt: AtomicU32::new(0),
+            successes: AtomicU32::new(0),
+            permits: Semaphore::new(max as usize),
+            spacing,
+            next_start: Mutex::new(None),
+        }
+    }
+
+    pub fn max(&self) -> u32 {
+        self.max
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit.load(Ordering::SeqCst)
+    }
+
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("limit semaphore is never closed");
+        self.pace().await;
+        permit
+    }
+
+    async fn pace(&self) {
+        if self.spacing.is_zero() {
+            return;
+        }
+        let wait = {
+            let mut next = self.next_start.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let start = next.map_or(now, |n| n.max(now));
+            *next = Some(start + self.spacing);
+            start - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    pub fn release(&self, permit: SemaphorePermit<'_>) {
+        if self.take_debt() {
+            permit.forget();
+        }
+    }
+
+    pub fn record<T>(&self, result: &Result<T, FlightError>) {
+        match result {
+            Ok(_) => self.increase(),
+            Err(e) if e.is_pushback() => self.decrease(),
+            Err(_) => {}
+        }
+    }
+
+    fn take_debt(&self) -> bool {
+        self.debt
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |d| d.checked_sub(1))
+            .is_ok()
+    }
+
+    fn decrease(&self) {
+        self.successes.store(0, Ordering::SeqCst);
+        let Ok(old) = self
+            .limit
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |l| {
+                (l > 1).then_some((l / 2).max(1))
+            })
+        else {
+            return;
+        };
+        let cut = old - (old / 2).max(1);
+        let forgotten = self.permits.forget_permits(cut as usize) as u32;
+        self.debt.fetch_add(cut - forgotten, Ordering::SeqCst);
+    }
+
+    fn increase(&self) {
+        let limit = self.limit();
+        if limit >= self.max || self.successes.fetch_add(1, Ordering::SeqCst) + 1 < limit {
+            return;
+        }
+        self.successes.store(0, Ordering::SeqCst);
+        let grown = self
+            .limit
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |l| {
+                (l < self.max).then_some(l + 1)
+            })
+            .is_ok();
+        if grown && !self.take_debt() {
+            self.permits.add_permits(1);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Limited<F> {
+    inner: F,
+    limit: Arc<AdaptiveLimit>,
+}
+
+impl<F: Clone> Clone for Limited<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            limit: self.limit.clone(),
+        }
+    }
+}
+
+impl<F: Fetcher> Limited<F> {
+    pub fn new(inner: F, limit: AdaptiveLimit) -> Self {
+        Self {
+            inner,
+            limit: Arc::new(limit),
+        }
+    }
+
+    pub fn with_defaults(inner: F) -> Self {
+        Self::new(inner, AdaptiveLimit::new(DEFAULT_CONCURRENCY, Duration::ZERO))
+    }
+
+    pub fn limit(&self) -> &AdaptiveLimit {
+        &self.limit
+    }
+}
+
+impl<F: Fetcher> Fetcher for Limited<F> {
+    async fn fetch_html(&self, params: &[(String, String)]) -> Result<String, FlightError> {
+        let permit = self.limit.acquire().await;
+        let result = self.inner.fetch_html(params).await;
+        self.limit.record(&result);
+        self.limit.release(permit);
+        result
+    }
+
+    fn record_parsed(&self, result: Result<(), &FlightError>) {
+        // The fetch already counted as a success; only a block found in the page counts.
+        if let Err(e) = result {
+            self.limit.record(&Err::<(), _>(e.clone()));
+        }
+        self.inner.record_parsed(result);
+    }
+}
@@ -0,0 +1,270 @@
+use crate::error::FlightError;
+use crate::model::{FlightDateTime, Money};
+use crate::tz::{self, Times};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clock {
+    H24,
+    H12,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateOrder {
+    Ymd,
+    Mdy,
+    Dmy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    pub tag: String,
+    pub decimal: char,
+    pub group: Option<char>,
+    pub clock: Clock,
+    pub date_order: DateOrder,
+    pub date_sep: char,
+    pub symbol_after: bool,
+    pub times: Option<Times>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            tag: String::new(),
+            decimal: '.',
+            group: None,
+            clock: Clock::H24,
+            date_order: DateOrder::Ymd,
+            date_sep: '-',
+            symbol_after: false,
+            times: None,
+        }
+    }
+}
+
+const NBSP: char = '\u{a0}';
+
+fn symbol(currency: &str) -> Option<&'static str> {
+    match currency {
+        "USD" => Some("$"),
+        "EUR" => Some("€"),
+        "GBP" => Some("£"),
+        "JPY" | "CNY" => Some("¥"),
+        "KRW" => Some("₩"),
+        "INR" => Some("₹"),
+        "THB" => Some("฿"),
+        _ => None,
+    }
+}
+
+impl Locale {
+    pub fn parse(tag: &str) -> Result<Self, FlightError> {
+        let normalized = tag.trim().replace('_', "-");
+        let (language, region) = match normalized.split_once('-') {
+            Some((l, r)) => (l.to_lowercase(), r.to_uppercase()),
+            None => (normalized.to_lowercase(), String::new()),
+        };
+
+        let base = Self {
+            tag: tag.trim().to_string(),
+            ..Self::default()
+        };
+        let european = |group: char, date_sep: char| Self {
+            decimal: ',',
+            group: Some(group),
+            date_order: DateOrder::Dmy,
+            date_sep,
+            symbol_after: true,
+            ..base.clone()
+        };
+
+        let locale = match (language.as_str(), region.as_str()) {
+            ("en", "" | "US" | "PH") => Self {
+                group: Some(','),
+                clock: Clock::H12,
+                date_order: DateOrder::Mdy,
+                date_sep: '/',
+                ..base
+            },
+            ("en", "AU" | "NZ" | "IN") => Self {
+                group: Some(','),
+                clock: Clock::H12,
+                date_order: DateOrder::Dmy,
+                date_sep: '/',
+                ..base
+            },
+            ("en", _) => Self {
+                group: Some(','),
+                date_order: DateOrder::Dmy,
+                date_sep: '/',
+                ..base
+            },
+            ("de", "CH") => Self {
+                decimal: '.',
+                ..european('\'', '.')
+            },
+            ("de", _) | ("da", _) => european('.', '.'),
+            ("es" | "it", _) => european('.', '/'),
+            ("pt", "BR") => Self {
+                symbol_after: false,
+                ..european('.', '/')
+            },
+            ("pt", _) => european(NBSP, '/'),
+            ("fr", _) => european(NBSP, '/'),
+            ("nl", _) => Self {
+                symbol_after: false,
+                ..european('.', '-')
+            },
+            ("fi" | "nb" | "no" | "pl" | "ru" | "cs", _) => european(NBSP, '.'),
+            ("sv", _) => Self {
+                date_order: DateOrder::Ymd,
+                date_sep: '-',
+                ..european(NBSP, '-')
+            },
+            ("ja" | "zh" | "ko", _) => Self {
+                group: Some(','),
+                date_sep: '/',
+                ..base
+            },
+            _ => {
+                return Err(FlightError::Validation(format!(
+                    "unsupported locale \"{tag}\" — use a language-region tag like en-US, \
+                     de-DE, fr-FR, fi-FI or ja-JP"
+                )))
+            }
+        };
+        Ok(locale)
+    }
+
+    pub fn number(&self, n: i64) -> String {
+        let digits = n.unsigned_abs().to_string();
+        let sign = if n < 0 { "-" } else { "" };
+        let Some(group) = self.group else {
+            return format!("{sign}{digits}");
+        };
+
+        let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push(group);
+            }
+            out.push(c);
+        }
+        format!("{sign}{out}")
+    }
+
+    pub fn decimal(&self, value: f64, places: usize) -> String {
+        let formatted = format!("{value:.places$}");
+        let (whole, frac) = formatted.split_once('.').unwrap_or((&formatted, ""));
+        let whole = whole.parse::<i64>().map(|w| self.number(w)).unwrap_or_default();
+        let whole = if value < 0.0 && !whole.starts_with('-') {
+            format!("-{whole}")
+        } else {
+            whole
+        };
+        if frac.is_empty() {
+            whole
+        } else {
+            format!("{whole}{}{frac}", self.decimal)
+        }
+    }
+
+    pub fn price(&self, price: Option<i64>, currency: &str) -> String {
+        let Some(p) = price else {
+            return "—".to_string();
+        };
+        self.with_symbol(self.number(p), currency)
+    }
+
+    // Whole amounts print like `price`; fractional ones keep every minor digit, so
+    // 12.345 BHD isn't shown as 12 BHD.
+    pub fn money(&self, money: Option<&Money>) -> String {
+        let Some(m) = money else {
+            return "—".to_string();
+        };
+        let amount = if m.is_whole() {
+            self.number(m.whole())
+        } else {
+            self.decimal(m.major(), m.digits() as usize)
+        };
+        self.with_symbol(amount, &m.currency)
+    }
+
+    fn with_symbol(&self, amount: String, currency: &str) -> String {
+        match symbol(currency) {
+            Some(sym) if self.symbol_after => format!("{amount}{NBSP}{sym}"),
+            Some(sym) => format!("{sym}{amount}"),
+            None => format!("{amount} {currency}"),
+        }
+    }
+
+    pub fn date(&self, dt: &FlightDateTime) -> String {
+        let sep = self.date_sep;
+        let (y, m, d) = (dt.year, dt.month, dt.day);
+        match self.date_order {
+            DateOrder::Ymd => format!("{y:04}{sep}{m:02}{sep}{d:02}"),
+            DateOrder::Mdy => format!("{m:02}{sep}{d:02}{sep}{y:04}"),
+            DateOrder::Dmy => format!("{d:02}{sep}{m:02}{sep}{y:04}"),
+        }
+    }
+
+    pub fn iso_date(&self, date: &str) -> String {
+        let mut parts = date.split('-').map(|p| p.parse::<u32>().ok());
+        match (parts.next().flatten(), parts.next().flatten(), parts.next().flatten()) {
+            (Some(year), Some(month), Some(day)) => self.date(&FlightDateTime {
+                year,
+                month,
+                day,
+                hour: 0,
+                minute: 0,
+            }),
+            _ => date.to_string(),
+        }
+    }
+
+    pub fn time(&self, dt: &FlightDateTime) -> String {
+        match self.clock {
+            Clock::H24 => format!("{:02}:{:02}", dt.hour, dt.minute),
+            Clock::H12 => {
+                let suffix = if dt.hour < 12 { "AM" } else { "PM" };
+                let hour = match dt.hour % 12 {
+                    0 => 12,
+                    h => h,
+                };
+                format!("{hour}:{:02} {suffix}", dt.minute)
+            }
+        }
+    }
+
+    pub fn datetime(&self, dt: &FlightDateTime) -> String {
+        format!("{} {}", self.date(dt), self.time(dt))
+    }
+
+    pub fn with_times(self, times: Option<Times>) -> Self {
+        Self { times, ..self }
+    }
+
+    pub fn at(
+        &self,
+        dt: &FlightDateTime,
+        airport: &str,
+        origin: &str,
+    ) -> (FlightDateTime, Option<i32>) {
+        match self.times.and_then(|t| tz::convert(dt, airport, origin, t)) {
+            Some((shifted, offset)) => (shifted, Some(offset)),
+            None => (dt.clone(), None),
+        }
+    }
+
+    pub fn datetime_at(&self, dt: &FlightDateTime, airport: &str, origin: &str) -> String {
+        if self.times.is_none() {
+            return self.datetime(dt);
+        }
+        match self.at(dt, airport, origin) {
+            (shifted, Some(offset)) => {
+                format!("{} {}", self.datetime(&shifted), tz::format_offset(offset))
+            }
+            (_, None) => format!("{} (local)", self.datetime(dt)),
+        }
+    }
+}
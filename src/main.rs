@@ -1,14 +1,39 @@
 use std::collections::BTreeMap;
 use std::process;
+use std::sync::{Arc, Mutex, OnceLock};
 
 use clap::Parser;
+use serde::Serialize;
 use tokio::task::JoinSet;
 
-use flyr::error::FlightError;
-use flyr::fetch::FetchOptions;
-use flyr::model::SearchResult;
-use flyr::query::{FlightLeg, Passengers, QueryParams, Seat, SearchQuery, TripType};
-use flyr::table;
+use flyr::bags::{self, Bag};
+use flyr::breaker::Guarded;
+use flyr::budget::{self, BudgetMatch};
+use flyr::config::Config;
+use flyr::error::{ErrorInfo, FlightError};
+use flyr::export::Export;
+use flyr::cache::Cached;
+use flyr::fetch::{FetchOptions, Fetcher, SessionPool};
+use flyr::filter;
+use flyr::fx;
+use flyr::hedge::Hedged;
+use flyr::jq::Filter;
+use flyr::limit::{AdaptiveLimit, Limited};
+use flyr::locale::Locale;
+use flyr::parse::ParseOptions;
+use flyr::model::{
+    Baseline, BookingOption, CalendarDay, DestinationOutcome, FlightResult, MultiSearch, Risk, SearchResult,
+    Versioned, Warning,
+};
+use flyr::query::{self, FlightLeg, Passengers, QueryParams, Seat, SearchQuery, TripType};
+use flyr::store::{self, Store};
+use flyr::pareto;
+use flyr::risk;
+use flyr::snapshot::{self, SnapshotRow};
+use flyr::stay::{self, StayOption};
+use flyr::value::{self, Ranker};
+use flyr::table::{self, Highlights};
+use flyr::tz::{self, Times};
 
 #[derive(Parser)]
 #[command(
@@ -30,6 +55,19 @@ Agent-optimized:
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "JSON",
+        help = "Override exit codes, e.g. '{\"rate_limited\":75,\"empty\":4}'",
+        long_help = "Override the default exit codes with a JSON object mapping error kinds \
+            (the \"kind\" field of JSON errors, e.g. rate_limited, timeout, invalid_airport), \
+            \"empty\" (--fail-on-empty), \"partial\" (partial multi-destination failure) \
+            and \"alert\" (--alert-below) to codes 0-255. Use @FILE to read the mapping from a \
+            file. Kinds that are not listed keep their default code."
+    )]
+    exit_code_map: Option<String>,
 }
 
 #[derive(clap::Subcommand)]
@@ -51,28 +89,108 @@ Examples:
 Agent-optimized:
   flyr search -f HEL -t BCN,ATH,AYT -d 2026-03-01 --compact --top 3 --currency EUR"
     )]
-    Search(SearchArgs),
+    Search(Box<SearchArgs>),
     #[command(about = "Start MCP server for AI agents (stdio transport)")]
     Mcp,
+    #[command(
+        about = "Show the cheapest fare for every day in a date range",
+        long_about = "Show the lowest price for each departure day in a month or date range, \
+            fetched from Google's price calendar in a single request.",
+        after_help = "\
+Examples:
+  flyr calendar -f HEL -t BCN --month 2026-03
+  flyr calendar -f HEL -t BCN --start 2026-03-01 --end 2026-03-14 --return-days 7"
+    )]
+    Calendar(CalendarArgs),
+    #[command(
+        about = "Benchmark the parser against recorded pages",
+        long_about = "Run the extract -> JSON -> model pipeline over recorded Google Flights \
+            pages and report per-phase timings, throughput and allocations per parse.",
+        after_help = "\
+Examples:
+  flyr bench --fixture tests/fixtures
+  flyr bench --fixture page.html -n 1000 --json"
+    )]
+    Bench(BenchArgs),
+    #[command(about = "Inspect or clean the local search cache")]
+    Cache(CacheArgs),
+    #[command(
+        about = "Append the lowest fares for routes to CSV files",
+        long_about = "Search each route once and append a timestamped row (route, date, lowest \
+            price, lowest nonstop price, currency) to <OUT>/<FROM>-<TO>.csv, writing a header \
+            when the file is new. Meant to be run from cron to build a personal fare dataset.",
+        after_help = "\
+Examples:
+  flyr snapshot -f HEL -t BCN,ATH -d 2026-03-01 --out snapshots/
+  0 7 * * * flyr snapshot -f HEL -t BCN -d 2026-03-01 --return-date 2026-03-08 --currency EUR"
+    )]
+    Snapshot(SnapshotArgs),
+    #[command(
+        about = "Find every destination and date within a budget",
+        long_about = "Sweep the cheapest fare of every day in a month or date range for each \
+            destination, using Google's price calendar (one request per destination), and list \
+            every destination/date combination at or below --max, cheapest first.",
+        after_help = "\
+Examples:
+  flyr budget -f HEL -t BCN,ATH,LIS --max 250 --month 2026-03 --currency EUR
+  flyr budget -f HEL -t BCN,ATH --max 400 --start 2026-03-01 --end 2026-03-20 --return-days 7"
+    )]
+    Budget(BudgetArgs),
+    #[command(
+        about = "Show recorded price history for a route",
+        long_about = "Show the cheapest price of every search recorded for a route \
+            (via --record or --cache-ttl), oldest first."
+    )]
+    History(HistoryArgs),
+    #[command(
+        about = "List where a search result can be bought",
+        long_about = "Re-run a one-way search and fetch the booking options for one of its \
+            results: the airline and agencies selling it, each with its price and a booking \
+            link. N is the result's position in Google's order, as listed by `flyr search` \
+            with the same options and no --sort or --top.",
+        after_help = "\
+Examples:
+  flyr book 1 -f HEL -t BCN -d 2026-03-01
+  flyr book 3 -f HEL -t BCN -d 2026-03-01 --currency EUR --json"
+    )]
+    Book(BookArgs),
+    #[command(
+        about = "Print the JSON Schema of search results",
+        long_about = "Print the JSON Schema (draft 2020-12) describing `flyr search --json` \
+            output for the current schema_version, for validating results in pipelines.",
+        after_help = "\
+Examples:
+  flyr schema --pretty > flyr-search.schema.json"
+    )]
+    Schema(SchemaArgs),
 }
 
+// Shared by `search` and `calendar`.
+const LOCALE_LONG_HELP: &str = "Format prices, dates and times in table and compact output \
+    for a locale (e.g. en-US gives 12-hour times and 1,234; de-DE gives 24-hour times, \
+    01.03.2026 and 1.234 €). Independent of --lang, which only selects the language Google \
+    answers in. Without it, output uses ISO dates and 24-hour times.";
+
 #[derive(clap::Args)]
 struct SearchArgs {
     #[arg(
         short, long,
         value_name = "IATA",
-        help = "Departure airport code",
-        long_help = "Departure airport IATA code (3 letters, e.g. JFK, HEL, LAX). \
-            Required unless using --leg."
+        value_parser = places,
+        help = "Departure airport code (comma-separate for multi-origin)",
+        long_help = "Departure airport IATA code (3 letters, e.g. JFK, HEL, LAX) or city name \
+            (e.g. Helsinki, \"New York\"). Comma-separate to search from several origins (e.g. HEL,ARN,CPH); \
+            every origin is paired with every destination. Required unless using --leg."
     )]
     from: Option<String>,
 
     #[arg(
         short, long,
         value_name = "IATA",
+        value_parser = places,
         help = "Arrival airport code (comma-separate for multi-destination)",
-        long_help = "Arrival airport IATA code (3 letters, e.g. LHR, BCN, NRT). \
-            Comma-separate for multi-destination search (e.g. BCN,ATH,AYT). \
+        long_help = "Arrival airport IATA code (3 letters, e.g. LHR, BCN, NRT) or city name \
+            (e.g. London, Barcelona). Comma-separate for multi-destination search (e.g. BCN,ATH,AYT). \
             Required unless using --leg."
     )]
     to: Option<String>,
@@ -80,8 +198,10 @@ struct SearchArgs {
     #[arg(
         short, long,
         value_name = "YYYY-MM-DD",
-        help = "Departure date",
-        long_help = "Departure date in YYYY-MM-DD format. Required unless using --leg."
+        help = "Departure date (comma-separate for several dates)",
+        long_help = "Departure date in YYYY-MM-DD format. Required unless using --leg. \
+            A comma-separated list (2026-03-01,2026-03-02) searches every date concurrently \
+            and groups the results by date; with --return-date, round trips keep their length."
     )]
     date: Option<String>,
 
@@ -90,8 +210,9 @@ struct SearchArgs {
         value_name = "\"DATE FROM TO\"",
         help = "Flight leg (repeatable, for multi-city)",
         long_help = "Define a flight leg as \"YYYY-MM-DD FROM TO\". Repeat for multi-city \
-            itineraries. Replaces -f/-t/-d when used.\n\
-            Example: --leg \"2026-03-01 LAX NRT\" --leg \"2026-03-10 NRT SEA\"",
+            itineraries. Replaces -f/-t/-d when used. Append seat=CLASS to fly that leg in \
+            another cabin than --seat; each leg is then searched as its own one-way trip.\n\
+            Example: --leg \"2026-03-01 LAX NRT\" --leg \"2026-03-10 NRT SEA seat=business\"",
         num_args = 1,
     )]
     leg: Vec<String>,
@@ -105,6 +226,56 @@ struct SearchArgs {
     )]
     return_date: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "TEXT",
+        conflicts_with_all = ["from", "to", "date", "leg", "return_date", "flex", "nights", "compare_seats"],
+        help = "Free-text search (e.g. \"cheap flights from Helsinki to Tokyo in March\")",
+        long_help = "Search with free text, the way you would type into Google Flights, \
+            instead of -f/-t/-d. Google interprets the query and picks airports and dates; \
+            filters that are encoded in the request (--seat, --max-stops, ...) do not apply."
+    )]
+    query: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Compare the cheapest fare for ±N days around --date",
+        long_help = "Instead of listing flights, search every departure day from N days \
+            before to N days after --date concurrently (keeping the trip length for round \
+            trips) and compare the cheapest fare of each. N is at most 30, and days before \
+            today are skipped. `flyr calendar` answers the same question from Google's price \
+            calendar in a single request."
+    )]
+    flex: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "RANGE",
+        conflicts_with_all = ["return_date", "leg", "compare_seats"],
+        help = "Search round trips of 5-7 nights (or a single number) and list the cheapest",
+        long_help = "Instead of listing flights, search a round trip for every stay length in \
+            RANGE (e.g. 5-7, or 7) departing on --date, or on every day of --flex N around it, \
+            concurrently, and list the cheapest fare of each departure/return pair, cheapest \
+            first. At most 60 pairs per run."
+    )]
+    nights: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "1",
+        requires = "return_date",
+        conflicts_with_all = ["flex", "nights", "compare_seats"],
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Also list the return flights for the Nth outbound result (default 1)",
+        long_help = "After the outbound list of a round trip, select its Nth result (as \
+            listed, default 1) and list the return flights that go with it, priced as the \
+            round-trip total. Costs a second search."
+    )]
+    with_returns: Option<u32>,
+
     #[arg(
         long,
         default_value = "one-way",
@@ -121,6 +292,18 @@ struct SearchArgs {
     )]
     seat: String,
 
+    #[arg(
+        long,
+        value_name = "CLASSES",
+        conflicts_with_all = ["flex", "carbon_report", "plain"],
+        help = "Compare cabins side by side (e.g. economy,business)",
+        long_help = "Search the same route and dates in each listed cabin class concurrently \
+            (economy, premium-economy, business, first) and compare them: the cheapest fare per \
+            cabin, its price and duration relative to the first listed cabin, and the fastest \
+            itinerary. Replaces --seat."
+    )]
+    compare_seats: Option<String>,
+
     #[arg(
         long,
         value_name = "N",
@@ -135,6 +318,92 @@ struct SearchArgs {
     )]
     airlines: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "PRICE",
+        help = "Only fares at or below this price (in --currency)",
+        long_help = "Drop fares above this price (in --currency) from the results. The limit is \
+            applied after parsing, not sent to Google, so it never surfaces fares an unfiltered \
+            search wouldn't list."
+    )]
+    max_price: Option<i64>,
+
+    #[arg(
+        long,
+        help = "Only flights with emissions at or below the route's typical emissions"
+    )]
+    low_emissions: bool,
+
+    #[arg(
+        long,
+        help = "Only the itineraries Google lists as best flights",
+        long_help = "Keep only the itineraries Google recommends (its \"best flights\" list) \
+            and drop the rest. In JSON every flight has a category of best or other."
+    )]
+    best_only: bool,
+
+    #[arg(
+        long,
+        help = "List a flight sold under several airline codes once, at its cheapest",
+        long_help = "Collapse listings of the same physical flight (same airports and times) \
+            sold under different airline codes into the cheapest one. The other flight \
+            numbers are kept in each segment's codeshares."
+    )]
+    dedupe_codeshares: bool,
+
+    #[arg(
+        long,
+        help = "Drop basic fares (no carry-on, or sold as basic economy)",
+        long_help = "Drop itineraries whose fare is basic: sold under a basic brand such as \
+            \"Basic Economy\", or without a full-size carry-on. Hand-baggage-only fares stay; \
+            JSON marks every stripped-down fare with fare_brand."
+    )]
+    exclude_basic: bool,
+
+    #[arg(
+        long,
+        value_name = "FR,W6,...",
+        help = "Drop itineraries flown by these airlines (comma-separated IATA codes)"
+    )]
+    exclude_airlines: Option<String>,
+
+    #[arg(long, help = "Drop red-eyes: flights departing between 22:00 and 05:00")]
+    no_redeye: bool,
+
+    #[arg(long, value_name = "HH:MM", help = "Only flights departing at or after this time")]
+    depart_after: Option<String>,
+
+    #[arg(long, value_name = "HH:MM", help = "Only flights departing at or before this time")]
+    depart_before: Option<String>,
+
+    #[arg(long, value_name = "HH:MM", help = "Only flights arriving at or after this time")]
+    arrive_after: Option<String>,
+
+    #[arg(long, value_name = "HH:MM", help = "Only flights arriving at or before this time")]
+    arrive_before: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        help = "Drop itineraries longer than this door to door (e.g. 14h, 9h30m)"
+    )]
+    max_duration: Option<String>,
+
+    #[arg(long, value_name = "DURATION", help = "Drop connections shorter than this (e.g. 90m)")]
+    min_layover: Option<String>,
+
+    #[arg(long, value_name = "DURATION", help = "Drop connections longer than this (e.g. 4h)")]
+    max_layover: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        help = "Minimum comfortable connection for risk rating (e.g. 90m, 2h)",
+        long_help = "Connections shorter than this are rated as risky, especially when the \
+            carrier changes between segments. Default: 90m."
+    )]
+    min_connection: Option<String>,
+
     #[arg(long, default_value = "1", value_name = "N", help = "Number of adult passengers")]
     adults: u32,
 
@@ -150,21 +419,265 @@ struct SearchArgs {
     #[arg(long, default_value = "en", value_name = "CODE", help = "Language code (e.g. en, de, ja)")]
     lang: String,
 
+    #[arg(
+        long,
+        value_name = "TAG",
+        help = "Format numbers, dates and times for a locale (e.g. en-US, de-DE)",
+        long_help = LOCALE_LONG_HELP
+    )]
+    locale: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "MODE",
+        help = "Show times in each airport's zone, the origin's zone or UTC [local, origin, utc]",
+        long_help = "Convert departure and arrival times in table, plain and compact output and \
+            label them with their UTC offset: local keeps each airport's own time, origin shows \
+            every time in the departure airport's zone, utc shows UTC. Offsets come from a \
+            bundled airport time zone table; airports missing from it are shown in local time. \
+            Without it, times are printed as Google lists them (local to each airport), \
+            unlabeled."
+    )]
+    times: Option<String>,
+
     #[arg(long, default_value = "USD", value_name = "CODE", help = "Currency code (e.g. USD, EUR, JPY)")]
     currency: String,
 
+    #[arg(
+        long,
+        value_name = "CC",
+        help = "Point-of-sale country (e.g. DE, US); prices differ by market",
+        long_help = "Search as if from this country (2-letter code, sent as Google's gl \
+            parameter). Fares often differ by point of sale, so comparing --region DE with \
+            --region US can surface cheaper prices. Independent of --currency and --lang."
+    )]
+    region: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "CODE",
+        help = "Convert prices to this currency after the search (e.g. EUR)",
+        long_help = "Convert every price to CODE once results are parsed, using a bundled \
+            table of ECB reference rates (or today's rates with --live-rates). JSON keeps \
+            what Google quoted as original_price and metadata.original_currency. Useful when \
+            Google will not quote --currency for a market."
+    )]
+    convert_to: Option<String>,
+
+    #[arg(
+        long,
+        requires = "convert_to",
+        help = "Fetch today's ECB exchange rates for --convert-to",
+        long_help = "Download the European Central Bank's daily reference rates for \
+            --convert-to instead of using the bundled table. Falls back to the bundled \
+            rates, with a warning, if the download fails."
+    )]
+    live_rates: bool,
+
     #[arg(long, value_name = "N", help = "Show only the N cheapest results")]
     top: Option<usize>,
 
+    #[arg(
+        long,
+        default_value_t = flyr::SCHEMA_VERSION,
+        value_name = "VERSION",
+        value_parser = clap::value_parser!(u32).range(1..=3),
+        help = "Multi-destination JSON shape [1 = flat, 2 = ok/error map, 3 = versioned]",
+        long_help = "Shape of multi-destination JSON. Version 3 (default) is \
+            {\"schema_version\": 3, \"destinations\": {...}}, where each destination is \
+            {\"ok\": result} or {\"error\": {kind, message}}; version 2 is the bare destination \
+            map; version 1 maps every destination to a result, with failures as empty results \
+            and a warning on stderr."
+    )]
+    schema: u32,
+
+    #[arg(
+        long,
+        conflicts_with = "allow_partial",
+        help = "Stop a multi-destination search at the first failed destination"
+    )]
+    fail_fast: bool,
+
+    #[arg(
+        long,
+        help = "Exit 0 when at least one destination succeeds",
+        long_help = "Exit 0 when at least one destination of a multi-destination search succeeds. \
+            Without this flag a partial success exits 10."
+    )]
+    allow_partial: bool,
+
+    #[arg(
+        long,
+        help = "Exit 11 when the search finds no flights",
+        long_help = "Exit 11 instead of 0 when the search succeeds but finds no flights, so cron \
+            jobs and CI checks can treat \"no availability\" as a signal. For multi-destination \
+            searches, exits 11 when no destination has any flights."
+    )]
+    fail_on_empty: bool,
+
+    #[arg(
+        long,
+        value_name = "PRICE",
+        help = "Mark flights cheaper than PRICE and exit 12 if there are any",
+        long_help = "Highlight itineraries priced below PRICE (in the result currency), print a \
+            summary line, and exit 12 when at least one qualifies. A building block for price \
+            alert scripts: `flyr search ... --alert-below 200 && echo no deal`. In JSON mode the \
+            summary goes to stderr."
+    )]
+    alert_below: Option<i64>,
+
+    #[arg(
+        long,
+        value_name = "KEY",
+        help = "Sort results [price, duration, emissions, value]",
+        long_help = "Sort results by price, by elapsed duration (first departure to last \
+            arrival, including layovers), by CO2 emissions, or by value score (best balance of \
+            price, duration and stops first, see --value-weights). With --top, the first N \
+            results in this order are kept."
+    )]
+    sort: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "WEIGHTS",
+        help = "Weights of the value score (e.g. price=1,duration=0.5,stops=0.25)",
+        long_help = "How much each factor counts in the value score used by --sort value and \
+            the \"best value\" marker. Price and duration are measured against the cheapest and \
+            fastest itinerary in the results, stops per stop. Factors left out of WEIGHTS \
+            count for nothing; without --value-weights the weights are \
+            price=1,duration=1,stops=0.5. The score runs from 100 (cheapest, fastest \
+            and nonstop) down towards 0."
+    )]
+    value_weights: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "WEIGHTS",
+        conflicts_with_all = ["sort", "value_weights"],
+        help = "Rank by value score with these weights (e.g. price=0.6,duration=0.3,stops=0.1)",
+        long_help = "Order results by value score computed with the given weights; shorthand for \
+            --sort value --value-weights WEIGHTS. A default can be set as \"rank\" in the config \
+            file ($XDG_CONFIG_HOME/flyr/config.json, override with FLYR_CONFIG), which applies \
+            whenever --sort is not given. The MCP server ranks with the same weights."
+    )]
+    rank: Option<String>,
+
+    #[arg(
+        long,
+        help = "Compare emissions: greenest first, % vs typical, greenest vs cheapest",
+        long_help = "Sort by CO2 emissions, show each itinerary's emissions relative to the \
+            typical emissions for the route, and summarize the greenest option against the \
+            cheapest one."
+    )]
+    carbon_report: bool,
+
+    #[arg(
+        long,
+        value_name = "BAG",
+        help = "Estimate prices including a bag [carry-on, checked] and sort by them",
+        long_help = "Add an estimated bag fee to every price, from a bundled table of typical \
+            per-airline fees for basic fares (one fee per carrier, direction and seated \
+            passenger), and sort by the resulting price unless --sort is given. Low-cost \
+            fares often look cheapest only without luggage. Estimates are approximate and \
+            available for EUR, USD, GBP, CHF, SEK, NOK, DKK, PLN, CAD and AUD prices."
+    )]
+    with_bag: Option<String>,
+
+    #[arg(
+        long,
+        help = "Keep only itineraries not beaten on both price and duration",
+        long_help = "Drop every itinerary for which another one is at least as cheap and at \
+            least as fast (door-to-door) and better on one of the two. What remains is the \
+            Pareto frontier: the genuine price/duration trade-offs. Applied before --top."
+    )]
+    pareto: bool,
+
+    #[arg(
+        long,
+        requires = "pareto",
+        help = "Also weigh CO2 emissions in --pareto",
+        long_help = "Make --pareto compare CO2 emissions as a third criterion, so a slower or \
+            pricier itinerary survives when it emits less. Itineraries without emissions data \
+            count as the highest emitters."
+    )]
+    pareto_co2: bool,
+
     #[arg(long, help = "One-line-per-flight output (recommended for scripts and AI agents)")]
     compact: bool,
 
+    #[arg(
+        long,
+        requires = "compact",
+        value_name = "FIELDS",
+        help = "Columns of --compact lines, in order (e.g. price,route,duration,co2)",
+        long_help = "Choose which columns --compact prints and in which order, comma-separated: \
+            price, route, duration, stops, airlines, times, risk, co2, flight_numbers, aircraft, \
+            alert, value, change, bags. \
+            Every listed column is always printed, so lines have a fixed shape. Default: \
+            price,route,duration,stops,airlines,times, plus the connection risk when it is not \
+            low and the price change with --diff."
+    )]
+    compact_fields: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["compact", "json", "pretty"],
+        help = "Labeled line-per-field output for screen readers and narrow terminals",
+        long_help = "Replace the table with one line per flight made of labeled fields \
+            (\"Price: $299. Route: HEL to BCN via CDG. Departs ...\"), without box-drawing \
+            characters or column alignment. Easier to follow with a screen reader and on narrow \
+            terminals."
+    )]
+    plain: bool,
+
+    #[arg(
+        long,
+        help = "Add an Operated by column to the table",
+        long_help = "Add a column naming the airline that operates each segment. It differs \
+            from the airline selling the ticket for regional subsidiaries, wet leases and \
+            codeshares. JSON always includes operated_by on segments where it differs."
+    )]
+    operated_by: bool,
+
+    #[arg(
+        long,
+        help = "Fail instead of dropping flights that could not be fully parsed",
+        long_help = "Treat any flight entry or segment that could not be parsed as a hard \
+            parse error (exit 6) instead of silently dropping it. For pipelines that must not \
+            operate on incomplete results."
+    )]
+    strict: bool,
+
+    #[arg(
+        long,
+        help = "Parse with a smaller memory footprint",
+        long_help = "Release the raw HTML and script buffers as soon as the payload is \
+            extracted and convert flights one entry at a time. Slightly slower; meant for \
+            small containers and routers running scheduled fare checks."
+    )]
+    low_memory: bool,
+
     #[arg(long, help = "Output as JSON")]
     json: bool,
 
     #[arg(long, help = "Output as pretty-printed JSON")]
     pretty: bool,
 
+    #[arg(
+        long,
+        value_name = "FILTER",
+        conflicts_with = "compact",
+        help = "Filter the JSON output with a jq expression",
+        long_help = "Run FILTER over the JSON document and print each result on its own line, \
+            without needing jq installed, e.g. --jq '.flights[] | select(.price < 300)'. Implies \
+            --json (combine with --pretty for indented output). The full jq language is \
+            supported, including variables, reduce, string interpolation and regex builtins."
+    )]
+    jq: Option<String>,
+
+    #[arg(skip)]
+    jq_filter: Option<Filter>,
+
     #[arg(long, help = "Open results in Google Flights")]
     open: bool,
 
@@ -176,107 +689,847 @@ struct SearchArgs {
 
     #[arg(long, default_value = "30", value_name = "SECS", help = "Request timeout")]
     timeout: u64,
-}
-
-fn is_json(args: &SearchArgs) -> bool {
-    args.json || args.pretty
-}
 
-fn apply_top(result: &mut SearchResult, n: usize) {
-    result
-        .flights
-        .sort_by_key(|f| f.price.unwrap_or(i64::MAX));
-    result.flights.truncate(n);
-}
+    #[arg(
+        long,
+        help = "Don't accept Google's cookie consent page automatically",
+        long_help = "Fail with a consent_required error (exit 7) instead of submitting \
+            Google's cookie consent form when it is served."
+    )]
+    no_auto_consent: bool,
 
-fn open_browser(query_params: &QueryParams, json_mode: bool) -> ! {
-    let url = flyr::generate_browser_url(query_params);
-    println!("Opening: {url}");
-    if let Err(e) = open::that(&url) {
-        die(
-            &FlightError::Validation(format!("failed to open browser: {e}")),
-            json_mode,
-        );
-    }
-    std::process::exit(0);
-}
+    #[arg(
+        long,
+        value_name = "DELAY",
+        help = "Send a duplicate request if the first is slower than DELAY (e.g. 2s)",
+        long_help = "If Google hasn't answered within DELAY (e.g. 1500ms, 2s), fire a second, \
+            independent request and use whichever answers first."
+    )]
+    hedge: Option<String>,
 
-fn error_code(err: &FlightError) -> i32 {
-    match err {
-        FlightError::InvalidAirport(_)
-        | FlightError::InvalidDate(_)
-        | FlightError::Validation(_) => 2,
-        FlightError::Timeout
-        | FlightError::ConnectionFailed(_)
-        | FlightError::DnsResolution(_)
-        | FlightError::TlsError(_)
-        | FlightError::ProxyError(_) => 3,
-        FlightError::RateLimited | FlightError::Blocked(_) => 4,
-        FlightError::HttpStatus(_) => 5,
-        FlightError::ScriptTagNotFound | FlightError::JsParse(_) => 6,
-        FlightError::NoResults => 0,
-    }
-}
+    #[arg(long, value_name = "URL", requires = "hedge", help = "Proxy for the hedged request")]
+    hedge_proxy: Option<String>,
 
-fn error_kind(err: &FlightError) -> &'static str {
-    match err {
-        FlightError::InvalidAirport(_) => "invalid_airport",
-        FlightError::InvalidDate(_) => "invalid_date",
-        FlightError::Validation(_) => "validation_error",
-        FlightError::Timeout => "timeout",
-        FlightError::ConnectionFailed(_) => "connection_failed",
-        FlightError::DnsResolution(_) => "dns_error",
-        FlightError::TlsError(_) => "tls_error",
-        FlightError::ProxyError(_) => "proxy_error",
-        FlightError::RateLimited => "rate_limited",
-        FlightError::Blocked(_) => "blocked",
-        FlightError::HttpStatus(_) => "http_error",
-        FlightError::ScriptTagNotFound => "parse_error",
-        FlightError::JsParse(_) => "parse_error",
-        FlightError::NoResults => "no_results",
-    }
-}
+    #[arg(long, value_name = "DELAY", help = "Give up if connecting takes longer (e.g. 3s)")]
+    connect_timeout: Option<String>,
 
-fn die(err: &FlightError, json_mode: bool) -> ! {
-    if json_mode {
-        let json = serde_json::json!({
-            "error": {
-                "kind": error_kind(err),
-                "message": err.to_string(),
-            }
-        });
-        println!("{}", serde_json::to_string(&json).unwrap());
-    } else {
-        eprintln!("error: {err}");
-    }
-    process::exit(error_code(err));
-}
+    #[arg(
+        long,
+        value_name = "DELAY",
+        help = "Give up if the response hasn't started within DELAY of sending"
+    )]
+    ttfb_timeout: Option<String>,
 
-fn build_legs(args: &SearchArgs) -> Result<Vec<FlightLeg>, FlightError> {
-    let airlines: Option<Vec<String>> = args
-        .airlines
-        .as_ref()
-        .map(|s| s.split(',').map(|a| a.trim().to_uppercase()).collect());
+    #[arg(long, value_name = "DELAY", help = "Give up if reading the response body takes longer")]
+    body_timeout: Option<String>,
 
-    if !args.leg.is_empty() {
-        let mut legs = Vec::new();
-        for leg_str in &args.leg {
-            let parts: Vec<&str> = leg_str.split_whitespace().collect();
-            if parts.len() != 3 {
-                return Err(FlightError::Validation(format!(
-                    "--leg must be \"DATE FROM TO\", got: \"{leg_str}\""
-                )));
-            }
-            legs.push(FlightLeg {
-                date: parts[0].to_string(),
-                from_airport: parts[1].to_uppercase(),
-                to_airport: parts[2].to_uppercase(),
-                max_stops: args.max_stops,
-                airlines: airlines.clone(),
-            });
-        }
-        return Ok(legs);
-    }
+    #[arg(
+        long,
+        default_value_t = flyr::fetch::DEFAULT_RETRIES,
+        value_name = "N",
+        help = "Retry a request up to N times on timeouts, dropped connections, 429 and 5xx"
+    )]
+    retries: u32,
+
+    #[arg(
+        long,
+        default_value = "500ms",
+        value_name = "DELAY",
+        help = "Pause before the first retry; doubles with each one, plus jitter"
+    )]
+    backoff: String,
+
+    #[arg(short, long, help = "Report retried requests on stderr")]
+    verbose: bool,
+
+    #[arg(
+        long,
+        help = "Print a per-search timing breakdown to stderr",
+        long_help = "Print how long each search spent fetching, extracting the data script, \
+            parsing JSON and building results, to stderr."
+    )]
+    timings: bool,
+
+    #[arg(
+        long,
+        value_name = "HOST=IP",
+        help = "Pin a hostname to an address instead of resolving it (repeatable)"
+    )]
+    resolve: Vec<String>,
+
+    #[arg(
+        long,
+        default_value = "300",
+        value_name = "SECS",
+        help = "How long resolved addresses are reused"
+    )]
+    dns_ttl: u64,
+
+    #[arg(
+        long,
+        default_value_t = flyr::limit::DEFAULT_CONCURRENCY,
+        value_name = "N",
+        help = "Maximum parallel requests for multi-destination searches",
+        long_help = "Start multi-destination searches with up to N requests in flight. The limit \
+            halves whenever Google pushes back (429, blocked, captcha) and recovers by one after \
+            each run of successful responses."
+    )]
+    concurrency: u32,
+
+    #[arg(
+        long,
+        value_name = "DELAY",
+        help = "Minimum gap between request starts in multi-destination searches (e.g. 500ms)"
+    )]
+    delay: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        help = "Reuse a cached result younger than this (e.g. 15m, 2h)",
+        long_help = "Serve identical searches from the local database when a result younger \
+            than DURATION exists, and record fresh results there. The database lives under \
+            $XDG_CACHE_HOME/flyr (override with FLYR_DB) and doubles as price history. \
+            --query, --flex and --nights searches reuse Google's cached responses instead."
+    )]
+    cache_ttl: Option<String>,
+
+    #[arg(
+        long,
+        help = "Record results in the local price history database",
+        long_help = "Store each successful search in the local database (see `flyr history`) \
+            without reusing cached results."
+    )]
+    record: bool,
+
+    #[arg(
+        long,
+        default_value_t = store::DEFAULT_KEEP_DAYS,
+        value_name = "DAYS",
+        help = "Days of cached searches and history to keep"
+    )]
+    keep_days: u64,
+
+    #[arg(
+        long,
+        help = "Show how each itinerary's price changed since the last recorded search",
+        long_help = "Compare every itinerary with the same query's previous search in the local \
+            database and annotate its price change (\"€299 ▼ €31 since 6h ago\"); new \
+            itineraries are marked as new. JSON adds previous_price and delta to each flight and \
+            the compared search as baseline. Implies --record, so repeated runs always compare \
+            against the run before."
+    )]
+    diff: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Upsert results into a normalized SQLite database",
+        long_help = "Write every successful search into the SQLite database at PATH, creating \
+            it if needed. Searches, flights, segments, fares and airlines land in separate \
+            tables; an itinerary seen again is updated in place, so repeated runs accumulate \
+            into one queryable history."
+    )]
+    export_db: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        default_missing_value = "-",
+        conflicts_with_all = ["flex", "nights", "compare_seats", "with_returns", "compact"],
+        help = "Dump Google's unparsed result payload (to FILE, or stdout)",
+        long_help = "Instead of parsing, write the JSON payload extracted from Google's page \
+            to FILE, or to stdout without one (or with -). This is the data flyr's parser \
+            reads: attach it to a bug report when results look wrong, or build your own \
+            extractor on it. It is Google's format, not flyr's, and has no schema_version."
+    )]
+    raw: Option<std::path::PathBuf>,
+}
+
+#[derive(clap::Args)]
+struct BenchArgs {
+    #[arg(long, value_name = "PATH", help = "Directory of .html pages, or a single page")]
+    fixture: std::path::PathBuf,
+
+    #[arg(
+        short = 'n',
+        long,
+        default_value_t = flyr::bench::DEFAULT_ITERATIONS,
+        value_name = "N",
+        help = "Parses per fixture"
+    )]
+    iterations: u32,
+
+    #[arg(long, help = "Benchmark the --low-memory parse path")]
+    low_memory: bool,
+
+    #[arg(long, help = "Output as JSON")]
+    json: bool,
+}
+
+#[derive(clap::Args)]
+struct CalendarArgs {
+    #[arg(short, long, value_name = "IATA", value_parser = places, help = "Departure airport code")]
+    from: String,
+
+    #[arg(short, long, value_name = "IATA", value_parser = places, help = "Arrival airport code")]
+    to: String,
+
+    #[arg(long, value_name = "YYYY-MM", help = "Whole month to show")]
+    month: Option<String>,
+
+    #[arg(long, value_name = "YYYY-MM-DD", help = "First departure date (with --end)")]
+    start: Option<String>,
+
+    #[arg(long, value_name = "YYYY-MM-DD", help = "Last departure date (with --start)")]
+    end: Option<String>,
+
+    #[arg(long, value_name = "N", help = "Price round trips returning N days after departure")]
+    return_days: Option<u32>,
+
+    #[arg(
+        long,
+        default_value = "economy",
+        value_name = "CLASS",
+        help = "Seat class [economy, premium-economy, business, first]"
+    )]
+    seat: String,
+
+    #[arg(long, value_name = "N", help = "Maximum number of stops (0 = nonstop only)")]
+    max_stops: Option<u32>,
+
+    #[arg(long, default_value = "1", value_name = "N", help = "Number of adult passengers")]
+    adults: u32,
+
+    #[arg(long, default_value = "en", value_name = "CODE", help = "Language code (e.g. en, de, ja)")]
+    lang: String,
+
+    #[arg(
+        long,
+        value_name = "TAG",
+        help = "Format numbers, dates and times for a locale (e.g. en-US, de-DE)",
+        long_help = LOCALE_LONG_HELP
+    )]
+    locale: Option<String>,
+
+    #[arg(long, default_value = "USD", value_name = "CODE", help = "Currency code (e.g. USD, EUR, JPY)")]
+    currency: String,
+
+    #[arg(long, help = "One line per day")]
+    compact: bool,
+
+    #[arg(long, help = "Output as JSON")]
+    json: bool,
+
+    #[arg(long, help = "Output as pretty-printed JSON")]
+    pretty: bool,
+
+    #[arg(long, value_name = "URL", help = "HTTP or SOCKS5 proxy")]
+    proxy: Option<String>,
+
+    #[arg(long, default_value = "30", value_name = "SECS", help = "Request timeout")]
+    timeout: u64,
+}
+
+#[derive(clap::Args)]
+struct BookArgs {
+    #[arg(
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Position of the result to book (1 = first)"
+    )]
+    index: u32,
+
+    #[arg(short, long, value_name = "IATA", value_parser = places, help = "Departure airport code")]
+    from: String,
+
+    #[arg(short, long, value_name = "IATA", value_parser = places, help = "Arrival airport code")]
+    to: String,
+
+    #[arg(short, long, value_name = "YYYY-MM-DD", help = "Departure date")]
+    date: String,
+
+    #[arg(
+        long,
+        default_value = "economy",
+        value_name = "CLASS",
+        help = "Seat class [economy, premium-economy, business, first]"
+    )]
+    seat: String,
+
+    #[arg(long, value_name = "N", help = "Maximum number of stops (0 = nonstop only)")]
+    max_stops: Option<u32>,
+
+    #[arg(long, default_value = "1", value_name = "N", help = "Number of adult passengers")]
+    adults: u32,
+
+    #[arg(long, default_value = "en", value_name = "CODE", help = "Language code (e.g. en, de, ja)")]
+    lang: String,
+
+    #[arg(
+        long,
+        value_name = "TAG",
+        help = "Format numbers for a locale (e.g. en-US, de-DE)"
+    )]
+    locale: Option<String>,
+
+    #[arg(long, default_value = "USD", value_name = "CODE", help = "Currency code (e.g. USD, EUR, JPY)")]
+    currency: String,
+
+    #[arg(long, help = "Output as JSON")]
+    json: bool,
+
+    #[arg(long, help = "Output as pretty-printed JSON")]
+    pretty: bool,
+
+    #[arg(long, value_name = "URL", help = "HTTP or SOCKS5 proxy")]
+    proxy: Option<String>,
+
+    #[arg(long, default_value = "30", value_name = "SECS", help = "Request timeout")]
+    timeout: u64,
+}
+
+#[derive(clap::Args)]
+struct BudgetArgs {
+    #[arg(short, long, value_name = "IATA", value_parser = places, help = "Departure airport code")]
+    from: String,
+
+    #[arg(
+        short,
+        long,
+        value_name = "IATA",
+        value_parser = places,
+        help = "Destination airport codes, comma-separated"
+    )]
+    to: String,
+
+    #[arg(long, value_name = "PRICE", help = "Highest acceptable price (in --currency)")]
+    max: i64,
+
+    #[arg(long, value_name = "YYYY-MM", help = "Whole month to sweep")]
+    month: Option<String>,
+
+    #[arg(long, value_name = "YYYY-MM-DD", help = "First departure date (with --end)")]
+    start: Option<String>,
+
+    #[arg(long, value_name = "YYYY-MM-DD", help = "Last departure date (with --start)")]
+    end: Option<String>,
+
+    #[arg(long, value_name = "N", help = "Price round trips returning N days after departure")]
+    return_days: Option<u32>,
+
+    #[arg(
+        long,
+        default_value = "economy",
+        value_name = "CLASS",
+        help = "Seat class [economy, premium-economy, business, first]"
+    )]
+    seat: String,
+
+    #[arg(long, value_name = "N", help = "Maximum number of stops (0 = nonstop only)")]
+    max_stops: Option<u32>,
+
+    #[arg(long, default_value = "1", value_name = "N", help = "Number of adult passengers")]
+    adults: u32,
+
+    #[arg(long, default_value = "en", value_name = "CODE", help = "Language code (e.g. en, de, ja)")]
+    lang: String,
+
+    #[arg(
+        long,
+        value_name = "TAG",
+        help = "Format numbers, dates and times for a locale (e.g. en-US, de-DE)"
+    )]
+    locale: Option<String>,
+
+    #[arg(long, default_value = "USD", value_name = "CODE", help = "Currency code (e.g. USD, EUR, JPY)")]
+    currency: String,
+
+    #[arg(long, value_name = "N", help = "Show only the N cheapest matches")]
+    top: Option<usize>,
+
+    #[arg(long, help = "One line per match")]
+    compact: bool,
+
+    #[arg(long, help = "Output as JSON")]
+    json: bool,
+
+    #[arg(long, help = "Output as pretty-printed JSON")]
+    pretty: bool,
+
+    #[arg(long, value_name = "URL", help = "HTTP or SOCKS5 proxy")]
+    proxy: Option<String>,
+
+    #[arg(long, default_value = "30", value_name = "SECS", help = "Request timeout")]
+    timeout: u64,
+}
+
+#[derive(clap::Args)]
+struct SnapshotArgs {
+    #[arg(short, long, value_name = "IATA", value_parser = places, help = "Departure airport code")]
+    from: String,
+
+    #[arg(
+        short,
+        long,
+        value_name = "IATA",
+        value_parser = places,
+        help = "Arrival airport code(s), comma-separated"
+    )]
+    to: String,
+
+    #[arg(short, long, value_name = "YYYY-MM-DD", help = "Departure date")]
+    date: String,
+
+    #[arg(long, value_name = "YYYY-MM-DD", help = "Return date (round trip)")]
+    return_date: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "snapshots",
+        value_name = "DIR",
+        help = "Directory of the CSV files (created if missing)"
+    )]
+    out: std::path::PathBuf,
+
+    #[arg(
+        long,
+        default_value = "economy",
+        value_name = "CLASS",
+        help = "Seat class [economy, premium-economy, business, first]"
+    )]
+    seat: String,
+
+    #[arg(long, value_name = "N", help = "Maximum number of stops (0 = nonstop only)")]
+    max_stops: Option<u32>,
+
+    #[arg(long, default_value = "1", value_name = "N", help = "Number of adult passengers")]
+    adults: u32,
+
+    #[arg(long, default_value = "en", value_name = "CODE", help = "Language code (e.g. en, de, ja)")]
+    lang: String,
+
+    #[arg(long, default_value = "USD", value_name = "CODE", help = "Currency code (e.g. USD, EUR, JPY)")]
+    currency: String,
+
+    #[arg(long, help = "Print the appended rows as JSON")]
+    json: bool,
+
+    #[arg(long, value_name = "URL", help = "HTTP or SOCKS5 proxy")]
+    proxy: Option<String>,
+
+    #[arg(long, default_value = "30", value_name = "SECS", help = "Request timeout")]
+    timeout: u64,
+}
+
+#[derive(clap::Args)]
+struct CacheArgs {
+    #[command(subcommand)]
+    action: CacheAction,
+
+    #[arg(long, global = true, help = "Output as JSON")]
+    json: bool,
+}
+
+#[derive(clap::Subcommand)]
+enum CacheAction {
+    #[command(about = "Show database location and size")]
+    Stats,
+    #[command(about = "Delete every cached search and history point")]
+    Clear,
+    #[command(about = "Delete searches older than the retention window")]
+    Prune {
+        #[arg(
+            long,
+            default_value_t = store::DEFAULT_KEEP_DAYS,
+            value_name = "DAYS",
+            help = "Days to keep"
+        )]
+        keep_days: u64,
+    },
+}
+
+#[derive(clap::Args)]
+struct HistoryArgs {
+    #[arg(short, long, value_name = "IATA", value_parser = places, help = "Departure airport code")]
+    from: String,
+
+    #[arg(short, long, value_name = "IATA", value_parser = places, help = "Arrival airport code")]
+    to: String,
+
+    #[arg(short, long, value_name = "YYYY-MM-DD", help = "Only this departure date")]
+    date: Option<String>,
+
+    #[arg(long, help = "Output as JSON")]
+    json: bool,
+}
+
+#[derive(clap::Args)]
+struct SchemaArgs {
+    #[arg(long, help = "Pretty-print the schema")]
+    pretty: bool,
+}
+
+fn is_json(args: &SearchArgs) -> bool {
+    args.json || args.pretty || args.jq.is_some()
+}
+
+fn json_output(args: &SearchArgs) -> JsonOutput<'_> {
+    JsonOutput {
+        pretty: args.pretty,
+        jq: args.jq_filter.as_ref(),
+    }
+}
+
+fn display_currency<'a>(result: &'a SearchResult, requested: &'a str) -> &'a str {
+    result.metadata.currency.as_deref().unwrap_or(requested)
+}
+
+fn json_document(args: &SearchArgs) -> bool {
+    is_json(args) && !args.compact
+}
+
+static DEFERRED_WARNINGS: Mutex<Vec<Warning>> = Mutex::new(Vec::new());
+
+fn warn(warning: Warning, json: bool) {
+    if json {
+        DEFERRED_WARNINGS.lock().unwrap().push(warning);
+    } else {
+        eprintln!("warning: {warning}");
+    }
+}
+
+fn take_warnings() -> Vec<Warning> {
+    std::mem::take(&mut *DEFERRED_WARNINGS.lock().unwrap())
+}
+
+fn print_warnings(result: &SearchResult, label: Option<&str>) {
+    let prefix = label.map(|l| format!("{l}: ")).unwrap_or_default();
+    for warning in &result.warnings {
+        eprintln!("warning: {prefix}{warning}");
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SortKey {
+    Price,
+    Duration,
+    Emissions,
+    Value,
+}
+
+impl SortKey {
+    fn from_str_loose(s: &str) -> Result<Self, FlightError> {
+        match s {
+            "price" => Ok(Self::Price),
+            "duration" => Ok(Self::Duration),
+            "emissions" => Ok(Self::Emissions),
+            "value" => Ok(Self::Value),
+            _ => Err(FlightError::Validation(format!("invalid sort key: {s}"))),
+        }
+    }
+}
+
+fn sort_flights(result: &mut SearchResult, key: SortKey) {
+    match key {
+        SortKey::Price => result
+            .flights
+            .sort_by_key(|f| f.effective_price().unwrap_or(i64::MAX)),
+        SortKey::Duration => result
+            .flights
+            .sort_by_key(|f| (f.elapsed_minutes, f.effective_price().unwrap_or(i64::MAX))),
+        SortKey::Emissions => result.flights.sort_by_key(flyr::carbon::sort_key),
+        SortKey::Value => value::sort(&mut result.flights),
+    }
+}
+
+fn order_results(result: &mut SearchResult, sort: Option<SortKey>, top: Option<usize>) {
+    if let Some(key) = sort.or(top.map(|_| SortKey::Price)) {
+        sort_flights(result, key);
+    }
+    if let Some(n) = top {
+        result.flights.truncate(n);
+    }
+}
+
+fn parse_ranker(args: &SearchArgs, config: &Config) -> Result<Ranker, FlightError> {
+    match args.rank.as_deref().or(args.value_weights.as_deref()) {
+        Some(spec) => Ranker::parse(spec),
+        None => Ok(config.ranker()?.unwrap_or_default()),
+    }
+}
+
+fn parse_bag(args: &SearchArgs) -> Result<Option<Bag>, FlightError> {
+    args.with_bag.as_deref().map(Bag::from_str_loose).transpose()
+}
+
+fn parse_min_connection(args: &SearchArgs) -> Result<Option<u32>, FlightError> {
+    args.min_connection
+        .as_deref()
+        .map(query::parse_minutes)
+        .transpose()
+}
+
+fn parse_times(args: &SearchArgs) -> Result<Option<Times>, FlightError> {
+    args.times.as_deref().map(Times::from_str_loose).transpose()
+}
+
+fn validate_locale(tag: &Option<String>) -> Result<(), FlightError> {
+    tag.as_deref().map_or(Ok(()), |t| Locale::parse(t).map(|_| ()))
+}
+
+fn parse_sort(args: &SearchArgs, config: &Config) -> Result<Option<SortKey>, FlightError> {
+    let sort = args.sort.as_deref().map(SortKey::from_str_loose).transpose()?;
+    Ok(sort
+        .or(args.rank.is_some().then_some(SortKey::Value))
+        .or(args.carbon_report.then_some(SortKey::Emissions))
+        .or(args.with_bag.is_some().then_some(SortKey::Price))
+        .or(config.rank.is_some().then_some(SortKey::Value)))
+}
+
+fn open_browser(query_params: &QueryParams, json_mode: bool) -> ! {
+    let url = flyr::generate_browser_url(query_params);
+    println!("Opening: {url}");
+    if let Err(e) = open::that(&url) {
+        die(
+            &FlightError::Validation(format!("failed to open browser: {e}")),
+            json_mode,
+        );
+    }
+    std::process::exit(0);
+}
+
+static EXIT_CODES: OnceLock<BTreeMap<String, i32>> = OnceLock::new();
+
+fn exit_code(kind: &str, default: i32) -> i32 {
+    EXIT_CODES
+        .get()
+        .and_then(|codes| codes.get(kind).copied())
+        .unwrap_or(default)
+}
+
+const OUTCOME_KINDS: [&str; 3] = ["empty", "partial", "alert"];
+
+fn parse_exit_code_map(spec: &str) -> Result<BTreeMap<String, i32>, FlightError> {
+    let json = match spec.strip_prefix('@') {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| {
+            FlightError::Validation(format!("cannot read --exit-code-map file {path}: {e}"))
+        })?,
+        None => spec.to_string(),
+    };
+    let codes: BTreeMap<String, i32> = serde_json::from_str(&json).map_err(|e| {
+        FlightError::Validation(format!(
+            "invalid --exit-code-map: {e} — expected e.g. '{{\"rate_limited\":75}}'"
+        ))
+    })?;
+
+    for (kind, code) in &codes {
+        let known = flyr::error::ERROR_KINDS.contains(&kind.as_str())
+            || OUTCOME_KINDS.contains(&kind.as_str());
+        if !known {
+            return Err(FlightError::Validation(format!(
+                "unknown kind \"{kind}\" in --exit-code-map — use one of: {}, {}",
+                flyr::error::ERROR_KINDS.join(", "),
+                OUTCOME_KINDS.join(", ")
+            )));
+        }
+        if !(0..=255).contains(code) {
+            return Err(FlightError::Validation(format!(
+                "exit code {code} for \"{kind}\" is out of range 0-255"
+            )));
+        }
+    }
+    Ok(codes)
+}
+
+fn error_code(err: &FlightError) -> i32 {
+    let default = match err {
+        FlightError::InvalidAirport(_)
+        | FlightError::InvalidDate(_)
+        | FlightError::Validation(_) => 2,
+        FlightError::Timeout
+        | FlightError::ConnectionFailed(_)
+        | FlightError::DnsResolution(_)
+        | FlightError::TlsError(_)
+        | FlightError::ProxyError(_) => 3,
+        FlightError::RateLimited { .. }
+        | FlightError::Blocked(_)
+        | FlightError::BackingOff { .. } => 4,
+        FlightError::HttpStatus(_) => 5,
+        FlightError::ScriptTagNotFound | FlightError::JsParse(_) => 6,
+        FlightError::ConsentRequired => 7,
+        FlightError::CaptchaRequired(_) => 8,
+        FlightError::Storage(_) => 9,
+        FlightError::NoResults => 0,
+    };
+    exit_code(err.kind(), default)
+}
+
+#[derive(Serialize)]
+struct ErrorDoc {
+    error: ErrorInfo,
+}
+
+#[derive(Serialize)]
+struct CalendarDoc<'a> {
+    days: &'a [CalendarDay],
+}
+
+#[derive(Serialize)]
+struct ReturnsDoc<'a> {
+    outbound: &'a SearchResult,
+    selected: u32,
+    returns: &'a SearchResult,
+}
+
+#[derive(Serialize)]
+struct BookingDoc<'a> {
+    flight: &'a FlightResult,
+    currency: &'a str,
+    options: &'a [BookingOption],
+}
+
+#[derive(Serialize)]
+struct BenchDoc<'a> {
+    fixtures: &'a [flyr::bench::FixtureReport],
+}
+
+#[derive(Serialize)]
+struct HistoryDoc<'a> {
+    points: &'a [store::HistoryPoint],
+}
+
+#[derive(Serialize)]
+struct BudgetDoc<'a> {
+    max_price: i64,
+    currency: &'a str,
+    matches: &'a [BudgetMatch],
+}
+
+#[derive(Serialize)]
+struct StayDoc<'a> {
+    currency: &'a str,
+    stays: &'a [StayOption],
+}
+
+#[derive(Serialize)]
+struct SnapshotDoc<'a> {
+    rows: &'a [SnapshotRow],
+}
+
+#[derive(Serialize)]
+struct RemovedDoc {
+    removed: usize,
+}
+
+// How JSON documents are printed: indented or not, and through the --jq filter if any.
+#[derive(Clone, Copy, Default)]
+struct JsonOutput<'a> {
+    pretty: bool,
+    jq: Option<&'a Filter>,
+}
+
+impl JsonOutput<'_> {
+    fn pretty(pretty: bool) -> Self {
+        Self { pretty, jq: None }
+    }
+}
+
+fn render_json<T: Serialize>(doc: &T, pretty: bool) -> String {
+    if pretty {
+        serde_json::to_string_pretty(doc).unwrap()
+    } else {
+        serde_json::to_string(doc).unwrap()
+    }
+}
+
+fn emit_json<T: Serialize>(doc: &T, out: JsonOutput) {
+    let Some(filter) = out.jq else {
+        println!("{}", render_json(doc, out.pretty));
+        return;
+    };
+    match filter.run(&serde_json::to_value(doc).unwrap()) {
+        Ok(outputs) => {
+            for output in outputs {
+                println!("{}", render_json(&output, out.pretty));
+            }
+        }
+        Err(e) => die(&e, true),
+    }
+}
+
+fn print_json<T: Serialize>(body: T, out: JsonOutput) {
+    emit_json(&Versioned::new(body), out);
+}
+
+fn die(err: &FlightError, json_mode: bool) -> ! {
+    if json_mode {
+        let doc = Versioned::new(ErrorDoc {
+            error: ErrorInfo::from(err),
+        });
+        println!("{}", render_json(&doc, false));
+    } else {
+        eprintln!("error: {err}");
+    }
+    process::exit(error_code(err));
+}
+
+fn leg_template(args: &SearchArgs) -> Result<FlightLeg, FlightError> {
+    let time = |t: &Option<String>| t.as_deref().map(query::parse_time_of_day).transpose();
+    let minutes = |d: &Option<String>| d.as_deref().map(query::parse_minutes).transpose();
+    let codes = |s: &Option<String>| {
+        s.as_ref()
+            .map(|s| s.split(',').map(|a| a.trim().to_uppercase()).collect())
+    };
+    Ok(FlightLeg {
+        max_stops: args.max_stops,
+        airlines: codes(&args.airlines),
+        exclude_airlines: codes(&args.exclude_airlines),
+        depart_after: time(&args.depart_after)?,
+        depart_before: time(&args.depart_before)?,
+        arrive_after: time(&args.arrive_after)?,
+        arrive_before: time(&args.arrive_before)?,
+        max_duration_minutes: minutes(&args.max_duration)?,
+        min_layover_minutes: minutes(&args.min_layover)?,
+        max_layover_minutes: minutes(&args.max_layover)?,
+        no_redeye: args.no_redeye,
+        ..FlightLeg::default()
+    })
+}
+
+fn build_legs(args: &SearchArgs) -> Result<Vec<FlightLeg>, FlightError> {
+    let template = leg_template(args)?;
+
+    if !args.leg.is_empty() {
+        let mut legs = Vec::new();
+        for leg_str in &args.leg {
+            let parts: Vec<&str> = leg_str.split_whitespace().collect();
+            if parts.len() < 3 {
+                return Err(FlightError::Validation(format!(
+                    "--leg must be \"DATE FROM TO [seat=CLASS]\", got: \"{leg_str}\""
+                )));
+            }
+            let mut leg = FlightLeg {
+                date: parts[0].to_string(),
+                from_airport: flyr::airports::resolve(parts[1])?,
+                to_airport: flyr::airports::resolve(parts[2])?,
+                ..template.clone()
+            };
+            for option in &parts[3..] {
+                match option.split_once('=') {
+                    Some(("seat", class)) => leg.seat = Some(Seat::from_str_loose(class)?),
+                    _ => {
+                        return Err(FlightError::Validation(format!(
+                            "unknown --leg option \"{option}\" (expected seat=CLASS)"
+                        )))
+                    }
+                }
+            }
+            legs.push(leg);
+        }
+        return Ok(legs);
+    }
 
     let from = args
         .from
@@ -292,282 +1545,1939 @@ fn build_legs(args: &SearchArgs) -> Result<Vec<FlightLeg>, FlightError> {
         .ok_or_else(|| FlightError::Validation("--date is required (or use --leg)".into()))?;
 
     let mut legs = vec![FlightLeg {
-        date: date.clone(),
-        from_airport: from.to_uppercase(),
-        to_airport: to.to_uppercase(),
+        date: date.clone(),
+        from_airport: from.to_uppercase(),
+        to_airport: to.to_uppercase(),
+        ..template.clone()
+    }];
+
+    if let Some(ref ret_date) = args.return_date {
+        legs.push(FlightLeg {
+            date: ret_date.clone(),
+            from_airport: to.to_uppercase(),
+            to_airport: from.to_uppercase(),
+            ..template.clone()
+        });
+    }
+
+    Ok(legs)
+}
+
+fn determine_trip(args: &SearchArgs) -> String {
+    if args.return_date.is_some() {
+        return "round-trip".to_string();
+    }
+    if args.leg.len() >= 2 && args.trip == "one-way" {
+        return "multi-city".to_string();
+    }
+    args.trip.clone()
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CompactField {
+    Price,
+    Route,
+    Duration,
+    Stops,
+    Airlines,
+    Times,
+    Risk,
+    Co2,
+    FlightNumbers,
+    Aircraft,
+    Alert,
+    Value,
+    Change,
+    Bags,
+}
+
+const COMPACT_FIELDS: [(&str, CompactField); 14] = [
+    ("price", CompactField::Price),
+    ("route", CompactField::Route),
+    ("duration", CompactField::Duration),
+    ("stops", CompactField::Stops),
+    ("airlines", CompactField::Airlines),
+    ("times", CompactField::Times),
+    ("risk", CompactField::Risk),
+    ("co2", CompactField::Co2),
+    ("flight_numbers", CompactField::FlightNumbers),
+    ("aircraft", CompactField::Aircraft),
+    ("alert", CompactField::Alert),
+    ("value", CompactField::Value),
+    ("change", CompactField::Change),
+    ("bags", CompactField::Bags),
+];
+
+const DEFAULT_COMPACT_FIELDS: [CompactField; 6] = [
+    CompactField::Price,
+    CompactField::Route,
+    CompactField::Duration,
+    CompactField::Stops,
+    CompactField::Airlines,
+    CompactField::Times,
+];
+
+fn parse_compact_fields(args: &SearchArgs) -> Result<Option<Vec<CompactField>>, FlightError> {
+    let Some(spec) = &args.compact_fields else {
+        return Ok(None);
+    };
+    spec.split(',')
+        .map(|name| {
+            let name = name.trim().to_lowercase();
+            COMPACT_FIELDS
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, f)| *f)
+                .ok_or_else(|| {
+                    let known: Vec<&str> = COMPACT_FIELDS.iter().map(|(n, _)| *n).collect();
+                    FlightError::Validation(format!(
+                        "unknown compact field \"{name}\" — use any of: {}",
+                        known.join(", ")
+                    ))
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+fn compact_field(
+    flight: &FlightResult,
+    field: CompactField,
+    currency: &str,
+    locale: &Locale,
+    highlights: &Highlights,
+    baseline: Option<&Baseline>,
+) -> String {
+    match field {
+        CompactField::Price => match flight.price_with_bag {
+            Some(p) => format!(
+                "{} ({} with bag)",
+                table::scoped_price(flight, currency, locale),
+                locale.price(Some(p), currency)
+            ),
+            None => table::scoped_price(flight, currency, locale),
+        },
+        CompactField::Route => std::iter::once(
+            flight
+                .segments
+                .first()
+                .map(|s| s.from_airport.code.as_str())
+                .unwrap_or("?"),
+        )
+        .map(String::from)
+        .chain(flight.segments.iter().map(|s| {
+            if s.kind.is_ground() {
+                format!("{}({})", s.to_airport.code, s.kind)
+            } else {
+                s.to_airport.code.clone()
+            }
+        }))
+        .collect::<Vec<_>>()
+        .join(">"),
+        CompactField::Duration if flight.segments.is_empty() => "—".to_string(),
+        CompactField::Duration => {
+            let total = flight.elapsed_minutes;
+            let air = flight.air_minutes;
+            if air < total {
+                format!(
+                    "{}h{:02}m ({}h{:02}m air)",
+                    total / 60,
+                    total % 60,
+                    air / 60,
+                    air % 60
+                )
+            } else {
+                format!("{}h{:02}m", total / 60, total % 60)
+            }
+        }
+        CompactField::Stops if flight.segments.len() <= 1 => "nonstop".to_string(),
+        CompactField::Stops => {
+            let n = flight.segments.len() - 1;
+            let codes: Vec<&str> = flight.segments[..n]
+                .iter()
+                .map(|s| s.to_airport.code.as_str())
+                .collect();
+            format!("{n} stop {}", codes.join(","))
+        }
+        CompactField::Airlines => flight.airlines.join(", "),
+        CompactField::Times => {
+            let (Some(d), Some(a)) = (flight.segments.first(), flight.segments.last()) else {
+                return "—".to_string();
+            };
+            let origin = d.from_airport.code.as_str();
+            let (departure, arrival, day_offset, zone) = match (
+                locale.at(&d.departure, origin, origin),
+                locale.at(&a.arrival, &a.to_airport.code, origin),
+            ) {
+                ((dep, Some(from)), (arr, Some(to))) => {
+                    let days = arr.days_since_epoch() - dep.days_since_epoch();
+                    let zone = if from == to {
+                        format!(" {}", tz::format_offset(from))
+                    } else {
+                        format!(" {}/{}", tz::format_offset(from), tz::format_offset(to))
+                    };
+                    (dep, arr, days as i32, zone)
+                }
+                _ => (
+                    d.departure.clone(),
+                    a.arrival.clone(),
+                    flight.arrival_day_offset,
+                    String::new(),
+                ),
+            };
+            let day_offset = match day_offset {
+                0 => String::new(),
+                n => format!("{n:+}"),
+            };
+            format!(
+                "{}{:02} {}>{}{day_offset}{zone}",
+                month_abbr(departure.month),
+                departure.day,
+                locale.time(&departure),
+                locale.time(&arrival),
+            )
+        }
+        CompactField::Risk => format!("{} connection risk", flight.risk),
+        CompactField::Co2 => table::format_emissions(flight.carbon.emission_grams),
+        CompactField::FlightNumbers => flight
+            .segments
+            .iter()
+            .map(|s| {
+                format!(
+                    "{}{}",
+                    s.airline.as_deref().unwrap_or(""),
+                    s.flight_number.as_deref().unwrap_or("?")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        CompactField::Aircraft => flight
+            .segments
+            .iter()
+            .map(|s| s.aircraft.as_deref().unwrap_or("?"))
+            .collect::<Vec<_>>()
+            .join(","),
+        CompactField::Alert if highlights.is_alert(flight) => "alert".to_string(),
+        CompactField::Alert => "—".to_string(),
+        CompactField::Value => match flight.value_score {
+            Some(score) => format!("value {score}"),
+            None => "—".to_string(),
+        },
+        CompactField::Change => match baseline {
+            Some(baseline) => table::format_change(flight, baseline, currency, locale),
+            None => "—".to_string(),
+        },
+        CompactField::Bags => table::format_baggage(flight.baggage.as_ref()),
+    }
+}
+
+fn print_compact(result: &SearchResult, args: &SearchArgs) {
+    let currency = display_currency(result, &args.currency);
+    let locale = search_locale(args);
+    let custom = parse_compact_fields(args).ok().flatten();
+    let fields = custom.as_deref().unwrap_or(&DEFAULT_COMPACT_FIELDS);
+    let highlights = highlights(args);
+    let baseline = result.baseline.as_ref();
+
+    for flight in &result.flights {
+        let mut line: Vec<String> = fields
+            .iter()
+            .map(|&f| compact_field(flight, f, currency, &locale, &highlights, baseline))
+            .collect();
+        if custom.is_none() {
+            if flight.risk != Risk::Low {
+                line.push(format!("{} connection risk", flight.risk));
+            }
+            if flight.often_delayed() {
+                line.push("⚠ often delayed".to_string());
+            }
+            if let Some(ticketing) = flight.ticketing {
+                line.push(format!("⚠ {ticketing}"));
+            }
+            if let Some(brand) = flight.fare_brand {
+                line.push(brand.to_string());
+            }
+            if highlights.is_alert(flight) {
+                line.push("alert".to_string());
+            }
+            if let Some(baseline) = baseline {
+                line.push(table::format_change(flight, baseline, currency, &locale));
+            }
+        }
+        println!("{}", line.join(" | "));
+    }
+}
+
+fn month_abbr(m: u32) -> &'static str {
+    match m {
+        1 => "Jan",
+        2 => "Feb",
+        3 => "Mar",
+        4 => "Apr",
+        5 => "May",
+        6 => "Jun",
+        7 => "Jul",
+        8 => "Aug",
+        9 => "Sep",
+        10 => "Oct",
+        11 => "Nov",
+        12 => "Dec",
+        _ => "???",
+    }
+}
+
+fn print_carbon(result: &SearchResult, args: &SearchArgs) {
+    let currency = display_currency(result, &args.currency);
+    let locale = display_locale(&args.locale);
+    let report = flyr::carbon::report(result);
+    if is_json(args) && !args.compact {
+        print_json(&report, json_output(args));
+    } else if result.flights.is_empty() {
+        println!("No flights found.");
+    } else if args.compact {
+        for row in &report.itineraries {
+            println!(
+                "{} | {} | {} CO2 ({}) | {}",
+                row.airlines.join(","),
+                row.route.join(">"),
+                table::format_emissions(row.emission_grams),
+                table::format_delta(row.delta_percent),
+                locale.price(row.price, currency)
+            );
+        }
+        println!("{}", table::render_carbon_summary(&report, currency, &locale));
+    } else {
+        println!("{}", table::render_carbon(&report, currency, &locale));
+    }
+}
+
+fn display_locale(tag: &Option<String>) -> Locale {
+    tag.as_deref()
+        .and_then(|t| Locale::parse(t).ok())
+        .unwrap_or_default()
+}
+
+fn search_locale(args: &SearchArgs) -> Locale {
+    display_locale(&args.locale).with_times(parse_times(args).ok().flatten())
+}
+
+fn highlights(args: &SearchArgs) -> Highlights {
+    Highlights {
+        alert_below: args.alert_below,
+        operated_by: args.operated_by,
+    }
+}
+
+fn render_flights(result: &SearchResult, currency: &str, args: &SearchArgs) -> String {
+    let locale = search_locale(args);
+    if args.plain {
+        table::render_plain(result, currency, &locale, &highlights(args))
+    } else {
+        table::render(result, currency, &locale, &highlights(args))
+    }
+}
+
+fn alert_count(result: &SearchResult, args: &SearchArgs) -> usize {
+    let highlights = highlights(args);
+    result.flights.iter().filter(|f| highlights.is_alert(f)).count()
+}
+
+fn print_alert_summary(result: &SearchResult, args: &SearchArgs) {
+    let Some(limit) = args.alert_below else {
+        return;
+    };
+    let summary = table::render_alert_summary(
+        result,
+        limit,
+        display_currency(result, &args.currency),
+        &display_locale(&args.locale),
+    );
+    if json_document(args) {
+        eprintln!("{summary}");
+    } else {
+        println!("{summary}");
+    }
+}
+
+fn print_result(result: &SearchResult, args: &SearchArgs) {
+    let currency = display_currency(result, &args.currency);
+    if args.carbon_report {
+        print_carbon(result, args);
+    } else if args.compact {
+        if result.flights.is_empty() {
+            println!("No flights found.");
+            return;
+        }
+        print_compact(result, args);
+    } else if is_json(args) {
+        print_json(result, json_output(args));
+    } else {
+        if result.flights.is_empty() {
+            println!("No flights found.");
+            return;
+        }
+        println!("{}", render_flights(result, currency, args));
+        print_price_insights(result, currency, args);
+    }
+}
+
+fn print_price_insights(result: &SearchResult, currency: &str, args: &SearchArgs) {
+    let summary = result.metadata.price_insights.as_ref().and_then(|insights| {
+        table::render_price_insights(insights, currency, &search_locale(args))
+    });
+    if let Some(summary) = summary {
+        println!("{summary}");
+    }
+}
+
+fn places(input: &str) -> Result<String, FlightError> {
+    flyr::airports::resolve_list(input)
+}
+
+fn is_multi_dest(args: &SearchArgs) -> bool {
+    [&args.from, &args.to, &args.date]
+        .iter()
+        .any(|codes| codes.as_ref().is_some_and(|c| c.contains(',')))
+}
+
+fn split_codes(codes: &Option<String>) -> Vec<String> {
+    codes
+        .as_ref()
+        .map(|t| {
+            t.split(',')
+                .map(|s| s.trim().to_uppercase())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_destinations(args: &SearchArgs) -> Vec<String> {
+    split_codes(&args.to)
+}
+
+fn parse_origins(args: &SearchArgs) -> Vec<String> {
+    split_codes(&args.from)
+}
+
+fn route_label(origins: &[String], from: &str, dest: &str) -> String {
+    if origins.len() > 1 {
+        store::route_key(from, dest)
+    } else {
+        dest.to_string()
+    }
+}
+
+// With several dates the results are keyed by date alone for a single route, otherwise
+// by date then route, so output is grouped by date either way.
+fn search_label(dates: &[String], single_route: bool, date: &str, route: String) -> String {
+    match (dates.len() > 1, single_route) {
+        (false, _) => route,
+        (true, true) => date.to_string(),
+        (true, false) => format!("{date} {route}"),
+    }
+}
+
+// Round trips keep their length when searched on several departure dates.
+fn return_for(args: &SearchArgs, dates: &[String], date: &str) -> Option<String> {
+    let ret = args.return_date.as_ref()?;
+    let offset = query::date_to_days(date)
+        .zip(dates.first().and_then(|d| query::date_to_days(d)))
+        .map_or(0, |(day, first)| day - first);
+    Some(query::add_days(ret, offset).unwrap_or_else(|| ret.clone()))
+}
+
+type BaseParams = (Passengers, Seat, TripType, FlightLeg);
+
+fn build_base_params(args: &SearchArgs) -> Result<BaseParams, FlightError> {
+    let trip_str = determine_trip(args);
+    let trip = TripType::from_str_loose(&trip_str)?;
+    let seat = Seat::from_str_loose(&args.seat)?;
+    let passengers = Passengers {
+        adults: args.adults,
+        children: args.children,
+        infants_in_seat: args.infants_in_seat,
+        infants_on_lap: args.infants_on_lap,
+    };
+    Ok((passengers, seat, trip, leg_template(args)?))
+}
+
+fn parse_compare_seats(args: &SearchArgs) -> Result<Option<Vec<Seat>>, FlightError> {
+    let Some(list) = args.compare_seats.as_deref() else {
+        return Ok(None);
+    };
+    let mut seats: Vec<Seat> = Vec::new();
+    for name in list.split(',').map(|n| n.trim().to_lowercase()) {
+        let seat = Seat::from_str_loose(&name)?;
+        if seats.contains(&seat) {
+            return Err(FlightError::Validation(format!(
+                "cabin \"{name}\" is listed twice in --compare-seats"
+            )));
+        }
+        seats.push(seat);
+    }
+    if seats.len() < 2 {
+        return Err(FlightError::Validation(
+            "--compare-seats needs at least two cabins, e.g. economy,business".into(),
+        ));
+    }
+    Ok(Some(seats))
+}
+
+fn search_fetch_options(args: &SearchArgs) -> Result<FetchOptions, FlightError> {
+    let overrides = args
+        .resolve
+        .iter()
+        .map(|r| flyr::dns::parse_override(r))
+        .collect::<Result<Vec<_>, _>>()?;
+    let phase = |arg: &Option<String>| arg.as_deref().map(query::parse_delay).transpose();
+    Ok(FetchOptions {
+        proxy: args.proxy.clone(),
+        timeout: args.timeout,
+        auto_consent: !args.no_auto_consent,
+        resolver: Some(flyr::dns::DnsCache::with_overrides(
+            std::time::Duration::from_secs(args.dns_ttl),
+            &overrides,
+        )),
+        connect_timeout: phase(&args.connect_timeout)?,
+        first_byte_timeout: phase(&args.ttfb_timeout)?,
+        body_timeout: phase(&args.body_timeout)?,
+        retries: args.retries,
+        backoff: query::parse_delay(&args.backoff)?,
+        on_retry: args.verbose.then(|| retry_reporter(args.retries)),
+        ..FetchOptions::default()
+    })
+}
+
+fn retry_reporter(retries: u32) -> flyr::fetch::RetryHook {
+    std::sync::Arc::new(move |attempt, err: &FlightError, delay| {
+        eprintln!(
+            "retry: attempt {attempt} of {} failed ({}), retrying in {:.1}s",
+            retries + 1,
+            err.kind(),
+            delay.as_secs_f64()
+        );
+    })
+}
+
+async fn exchange_rates(args: &SearchArgs, client: &flyr::FlyrClient) -> Option<fx::Rates> {
+    args.convert_to.as_ref()?;
+    if !args.live_rates {
+        return Some(fx::Rates::bundled());
+    }
+    Some(fx::fetch_ecb(client).await.unwrap_or_else(|e| {
+        eprintln!("warning: could not fetch ECB rates ({e}); using bundled rates");
+        fx::Rates::bundled()
+    }))
+}
+
+fn convert_prices(result: &mut SearchResult, rates: Option<&fx::Rates>, args: &SearchArgs) {
+    if let (Some(rates), Some(to)) = (rates, args.convert_to.as_deref()) {
+        fx::convert(result, rates, &args.currency, to);
+    }
+}
+
+fn print_timings(label: &str, result: &SearchResult) {
+    let Some(t) = result.timings else {
+        eprintln!("timings {label}: served from cache");
+        return;
+    };
+    let ms = |d: std::time::Duration| format!("{:.1}ms", d.as_secs_f64() * 1000.0);
+    eprintln!(
+        "timings {label}: fetch {} · extract {} · json {} · build {} · total {}",
+        ms(t.fetch),
+        ms(t.extract),
+        ms(t.json),
+        ms(t.build),
+        ms(t.total())
+    );
+}
+
+async fn prime_dns(options: &FetchOptions) -> Result<(), FlightError> {
+    match (&options.proxy, &options.resolver) {
+        (None, Some(resolver)) => resolver.lookup(flyr::dns::GOOGLE_HOST).await.map(drop),
+        _ => Ok(()),
+    }
+}
+
+fn build_fetcher(
+    args: &SearchArgs,
+    options: FetchOptions,
+) -> Result<Hedged<flyr::FlyrClient>, FlightError> {
+    let Some(delay) = args.hedge.as_deref() else {
+        return Ok(Hedged::disabled(flyr::FlyrClient::new(options)?));
+    };
+    let after = query::parse_delay(delay)?;
+    let backup = FetchOptions {
+        proxy: args.hedge_proxy.clone().or_else(|| options.proxy.clone()),
+        ..options.clone()
+    };
+    Ok(Hedged::new(
+        flyr::FlyrClient::new(options)?,
+        flyr::FlyrClient::new(backup)?,
+        after,
+    ))
+}
+
+type ResponseCache<F> = Cached<F, Option<Arc<Store>>>;
+
+// Searches the result store can't key (free text, --flex and --nights) cache Google's
+// responses in the same database instead, keyed by the encoded request, for --cache-ttl.
+fn with_response_cache<F: Fetcher>(
+    args: &SearchArgs,
+    inner: F,
+    store: Option<Arc<Store>>,
+) -> Result<ResponseCache<F>, FlightError> {
+    let Some(minutes) = args.cache_ttl.as_deref().map(query::parse_minutes).transpose()? else {
+        return Ok(Cached::new(inner, None, std::time::Duration::ZERO));
+    };
+    let ttl = std::time::Duration::from_secs(u64::from(minutes) * 60);
+    Ok(Cached::new(inner, store, ttl))
+}
+
+// The database behind --cache-ttl, --record and --diff, opened once per command and
+// shared by the result cache and the response cache.
+fn open_store(args: &SearchArgs) -> Option<Arc<Store>> {
+    if args.cache_ttl.is_none() && !args.record && !args.diff {
+        return None;
+    }
+    match Store::open_default() {
+        Ok(store) => Some(Arc::new(store)),
+        Err(e) => {
+            warn(
+                Warning::new("cache", format!("cache disabled: {e}")),
+                json_document(args),
+            );
+            None
+        }
+    }
+}
+
+struct Cache {
+    store: Arc<Store>,
+    ttl_secs: Option<u64>,
+    keep_days: u64,
+    json: bool,
+}
+
+impl Cache {
+    fn open(args: &SearchArgs, store: Option<Arc<Store>>) -> Result<Option<Self>, FlightError> {
+        let ttl_secs = args
+            .cache_ttl
+            .as_deref()
+            .map(query::parse_minutes)
+            .transpose()?
+            .map(|m| u64::from(m) * 60);
+        Ok(store.map(|store| Self {
+            store,
+            ttl_secs,
+            keep_days: args.keep_days,
+            json: json_document(args),
+        }))
+    }
+
+    fn lookup(&self, params: &QueryParams) -> Option<SearchResult> {
+        let ttl = self.ttl_secs?;
+        match self.store.lookup(params, ttl, flyr::fetch::unix_now()) {
+            Ok(hit) => hit,
+            Err(e) => {
+                warn(Warning::new("cache", e.to_string()), self.json);
+                None
+            }
+        }
+    }
+
+    fn record(&self, params: &QueryParams, result: &SearchResult) {
+        let now = flyr::fetch::unix_now();
+        let outcome = self
+            .store
+            .record(params, result, now)
+            .and_then(|_| self.store.prune(self.keep_days, now));
+        if let Err(e) = outcome {
+            warn(Warning::new("cache", e.to_string()), self.json);
+        }
+    }
+
+    fn compare(&self, params: &QueryParams, result: &mut SearchResult) {
+        let now = flyr::fetch::unix_now();
+        let before = result
+            .searched_at
+            .as_deref()
+            .and_then(query::parse_timestamp)
+            .unwrap_or(now);
+        match self.store.previous(params, before) {
+            Ok(Some((at, previous))) => {
+                let baseline = Baseline {
+                    searched_at: query::format_timestamp(at),
+                    age_secs: now.saturating_sub(at),
+                };
+                flyr::diff::annotate(result, &previous, baseline);
+            }
+            Ok(None) => result.warnings.push(Warning::new(
+                "diff",
+                "no earlier search of this query recorded yet; prices will be compared from \
+                 the next run",
+            )),
+            Err(e) => warn(Warning::new("cache", e.to_string()), self.json),
+        }
+    }
+}
+
+struct ExportDb {
+    export: Export,
+    json: bool,
+}
+
+impl ExportDb {
+    fn open(args: &SearchArgs) -> Option<Self> {
+        let path = args.export_db.as_ref()?;
+        match Export::open(path) {
+            Ok(export) => Some(Self {
+                export,
+                json: json_document(args),
+            }),
+            Err(e) => {
+                warn(
+                    Warning::new("export", format!("export disabled: {e}")),
+                    json_document(args),
+                );
+                None
+            }
+        }
+    }
+
+    fn write(&mut self, params: &QueryParams, result: &SearchResult) {
+        if let Err(e) = self.export.write(params, result) {
+            warn(Warning::new("export", e.to_string()), self.json);
+        }
+    }
+}
+
+fn run_cache(args: &CacheArgs) {
+    let store = match Store::open_default() {
+        Ok(s) => s,
+        Err(e) => die(&e, args.json),
+    };
+    let now = flyr::fetch::unix_now();
+
+    match args.action {
+        CacheAction::Stats => {
+            let stats = match store.stats() {
+                Ok(s) => s,
+                Err(e) => die(&e, args.json),
+            };
+            if args.json {
+                print_json(&stats, JsonOutput::default());
+            } else {
+                println!("Database: {}", stats.path);
+                println!("Searches: {} ({} routes)", stats.searches, stats.routes);
+                if let (Some(oldest), Some(newest)) = (&stats.oldest, &stats.newest) {
+                    println!("Oldest:   {oldest}");
+                    println!("Newest:   {newest}");
+                }
+            }
+        }
+        CacheAction::Clear | CacheAction::Prune { .. } => {
+            let removed = match args.action {
+                CacheAction::Prune { keep_days } => store.prune(keep_days, now),
+                _ => store.clear(),
+            };
+            let removed = match removed {
+                Ok(n) => n,
+                Err(e) => die(&e, args.json),
+            };
+            if args.json {
+                print_json(RemovedDoc { removed }, JsonOutput::default());
+            } else {
+                println!("Removed {removed} cached searches.");
+            }
+        }
+    }
+}
+
+// Counting every allocation costs an atomic add on each one, so only builds with the
+// count-allocations feature pay for it; `flyr bench` reports "—" otherwise.
+#[cfg(feature = "count-allocations")]
+mod counting {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    struct CountingAlloc;
+
+    static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAlloc = CountingAlloc;
+
+    pub fn allocations() -> Option<u64> {
+        Some(ALLOCATIONS.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(not(feature = "count-allocations"))]
+mod counting {
+    pub fn allocations() -> Option<u64> {
+        None
+    }
+}
+
+fn run_bench(args: &BenchArgs) {
+    let fixtures = match flyr::bench::load_fixtures(&args.fixture) {
+        Ok(f) => f,
+        Err(e) => die(&e, args.json),
+    };
+    let options = ParseOptions {
+        low_memory: args.low_memory,
+        ..ParseOptions::default()
+    };
+
+    let reports: Vec<_> = fixtures
+        .iter()
+        .map(|(name, html)| {
+            let before = counting::allocations();
+            let mut report = flyr::bench::run_fixture(name, html, args.iterations, &options);
+            if let (None, Some(before), Some(after)) =
+                (&report.error, before, counting::allocations())
+            {
+                report.allocations_per_iter = Some((after - before) / u64::from(report.iterations));
+            }
+            report
+        })
+        .collect();
+
+    if args.json {
+        print_json(BenchDoc { fixtures: &reports }, JsonOutput::default());
+    } else {
+        println!("{}", table::render_bench(&reports));
+    }
+}
+
+fn run_history(args: &HistoryArgs) {
+    let store = match Store::open_default() {
+        Ok(s) => s,
+        Err(e) => die(&e, args.json),
+    };
+    let route = store::route_key(&args.from, &args.to);
+    let points = match store.history(&route, args.date.as_deref()) {
+        Ok(p) => p,
+        Err(e) => die(&e, args.json),
+    };
+
+    if args.json {
+        print_json(HistoryDoc { points: &points }, JsonOutput::default());
+    } else if points.is_empty() {
+        println!("No history recorded for {route}. Search with --record or --cache-ttl first.");
+    } else {
+        println!("{}", table::render_history(&points));
+    }
+}
+
+fn print_calendar(
+    days: &[CalendarDay],
+    currency: &str,
+    marked: Option<&str>,
+    locale: &Locale,
+    compact: bool,
+    json: Option<JsonOutput>,
+) {
+    if compact {
+        for day in days {
+            let ret = day
+                .return_date
+                .as_deref()
+                .map(|r| format!(" > {r}"))
+                .unwrap_or_default();
+            println!("{}{ret} | {}", day.date, locale.price(day.price, currency));
+        }
+    } else if let Some(out) = json {
+        print_json(CalendarDoc { days }, out);
+    } else if days.is_empty() {
+        println!("No prices found.");
+    } else {
+        println!("{}", table::render_calendar(days, currency, marked, locale));
+    }
+}
+
+fn calendar_range(
+    month: &Option<String>,
+    start: &Option<String>,
+    end: &Option<String>,
+) -> Result<(String, String), FlightError> {
+    match (month, start, end) {
+        (Some(month), None, None) => {
+            let start = format!("{month}-01");
+            let next = query::add_days(&start, 31)
+                .ok_or_else(|| FlightError::InvalidDate(month.clone()))?;
+            let end = query::add_days(&format!("{}-01", &next[..7]), -1)
+                .ok_or_else(|| FlightError::InvalidDate(month.clone()))?;
+            Ok((start, end))
+        }
+        (None, Some(start), Some(end)) => {
+            let (Some(a), Some(b)) = (query::date_to_days(start), query::date_to_days(end)) else {
+                return Err(FlightError::Validation(format!(
+                    "invalid date range {start}..{end} — use YYYY-MM-DD"
+                )));
+            };
+            if b < a {
+                return Err(FlightError::Validation(format!(
+                    "--end {end} is before --start {start}"
+                )));
+            }
+            Ok((start.clone(), end.clone()))
+        }
+        _ => Err(FlightError::Validation(
+            "use either --month YYYY-MM or both --start and --end".into(),
+        )),
+    }
+}
+
+const BATCH_POOL_SIZE: usize = 4;
+
+async fn run_snapshot(args: &SnapshotArgs) {
+    let seat = match Seat::from_str_loose(&args.seat) {
+        Ok(s) => s,
+        Err(e) => die(&e, args.json),
+    };
+    let from = args.from.to_uppercase();
+    let mut routes = Vec::new();
+    for dest in args.to.split(',').map(|d| d.trim().to_uppercase()).filter(|d| !d.is_empty()) {
+        let mut legs = vec![FlightLeg {
+            date: args.date.clone(),
+            from_airport: from.clone(),
+            to_airport: dest.clone(),
+            max_stops: args.max_stops,
+            ..FlightLeg::default()
+        }];
+        if let Some(ret) = &args.return_date {
+            legs.push(FlightLeg {
+                date: ret.clone(),
+                from_airport: dest.clone(),
+                to_airport: from.clone(),
+                max_stops: args.max_stops,
+                ..FlightLeg::default()
+            });
+        }
+        let params = QueryParams {
+            trip: if legs.len() > 1 {
+                TripType::RoundTrip
+            } else {
+                TripType::OneWay
+            },
+            legs,
+            passengers: Passengers {
+                adults: args.adults,
+                ..Passengers::default()
+            },
+            seat: seat.clone(),
+            language: args.lang.clone(),
+            currency: args.currency.clone(),
+            region: String::new(),
+            max_price: None,
+            low_emissions: false,
+        };
+        if let Err(e) = params.validate() {
+            die(&e, args.json);
+        }
+        routes.push(params);
+    }
+
+    let options = FetchOptions {
+        proxy: args.proxy.clone(),
+        timeout: args.timeout,
+        ..FetchOptions::default()
+    };
+    let pool = SessionPool::new(routes.len().min(BATCH_POOL_SIZE), options.for_fan_out());
+    // With more routes than sessions, get every session's cookies first so the routes
+    // queued behind them don't each pay for the consent round trip.
+    let ready = if routes.len() > pool.len() {
+        pool.warm().await.map(|_| ())
+    } else {
+        pool.session().map(|_| ())
+    };
+    if let Err(e) = ready {
+        die(&e, args.json);
+    }
+    let client = Limited::with_defaults(Guarded::with_defaults(pool));
+
+    let mut join_set = JoinSet::new();
+    for (i, params) in routes.iter().enumerate() {
+        let client = client.clone();
+        let query = SearchQuery::Structured(params.clone());
+        join_set.spawn(async move {
+            let result = flyr::search_with_fetcher(&client, query, ParseOptions::default()).await;
+            (i, result)
+        });
+    }
+
+    let mut rows: Vec<(usize, SnapshotRow)> = Vec::new();
+    let mut failures: Vec<FlightError> = Vec::new();
+    while let Some(join_result) = join_set.join_next().await {
+        let (i, result) = join_result.unwrap();
+        let params = &routes[i];
+        match result {
+            Ok(result) => {
+                let row = SnapshotRow::new(params, &result);
+                match snapshot::append(&args.out, &row) {
+                    Ok(path) if !args.json => println!(
+                        "{} {}: {} (nonstop {}) -> {}",
+                        store::route_key(&row.from, &row.to),
+                        row.date,
+                        table::format_price(row.lowest_price, &row.currency),
+                        table::format_price(row.nonstop_lowest, &row.currency),
+                        path.display()
+                    ),
+                    Ok(_) => {}
+                    Err(e) => die(&e, args.json),
+                }
+                rows.push((i, row));
+            }
+            Err(e) => {
+                eprintln!("error: {}: {e}", params.legs[0].to_airport);
+                failures.push(e);
+            }
+        }
+    }
+
+    if args.json {
+        rows.sort_by_key(|(i, _)| *i);
+        let rows: Vec<SnapshotRow> = rows.into_iter().map(|(_, r)| r).collect();
+        print_json(SnapshotDoc { rows: &rows }, JsonOutput::default());
+    }
+    match failures.first() {
+        Some(e) if failures.len() == routes.len() => process::exit(error_code(e)),
+        Some(_) => process::exit(exit_code("partial", EXIT_PARTIAL)),
+        None => {}
+    }
+}
+
+fn flight_label(flight: &FlightResult) -> String {
+    let numbers: Vec<String> = flight
+        .segments
+        .iter()
+        .map(|s| {
+            format!(
+                "{}{}",
+                s.airline.as_deref().unwrap_or_default(),
+                s.flight_number.as_deref().unwrap_or_default()
+            )
+        })
+        .collect();
+    match (flight.segments.first(), flight.segments.last()) {
+        (Some(first), Some(last)) => format!(
+            "{} {} {} → {} {}",
+            numbers.join(" + "),
+            first.from_airport.code,
+            first.departure,
+            last.to_airport.code,
+            last.arrival
+        ),
+        _ => numbers.join(" + "),
+    }
+}
+
+async fn run_book(args: &BookArgs) {
+    let json_mode = args.json || args.pretty;
+    if let Err(e) = validate_locale(&args.locale) {
+        die(&e, json_mode);
+    }
+    let seat = match Seat::from_str_loose(&args.seat) {
+        Ok(s) => s,
+        Err(e) => die(&e, json_mode),
+    };
+    let params = QueryParams {
+        legs: vec![FlightLeg {
+            date: args.date.clone(),
+            from_airport: args.from.to_uppercase(),
+            to_airport: args.to.to_uppercase(),
+            max_stops: args.max_stops,
+            ..FlightLeg::default()
+        }],
+        passengers: Passengers {
+            adults: args.adults,
+            ..Passengers::default()
+        },
+        seat,
+        trip: TripType::OneWay,
+        language: args.lang.clone(),
+        currency: args.currency.clone(),
+        region: String::new(),
+        max_price: None,
+        low_emissions: false,
+    };
+    if let Err(e) = params.validate() {
+        die(&e, json_mode);
+    }
+
+    let options = FetchOptions {
+        proxy: args.proxy.clone(),
+        timeout: args.timeout,
+        ..FetchOptions::default()
+    };
+    let client = match flyr::FlyrClient::new(options) {
+        Ok(c) => c,
+        Err(e) => die(&e, json_mode),
+    };
+    let query = SearchQuery::Structured(params.clone());
+    let result = match flyr::search_with_client(&client, query, ParseOptions::default()).await {
+        Ok(r) => r,
+        Err(e) => die(&e, json_mode),
+    };
+    let Some(flight) = result.flights.get(args.index as usize - 1) else {
+        let e = FlightError::Validation(format!(
+            "there is no result {} — the search returned {} flights",
+            args.index,
+            result.flights.len()
+        ));
+        die(&e, json_mode);
+    };
+    let booking = match flyr::fetch_booking_options(&client, &params, flight).await {
+        Ok(b) => b,
+        Err(e) => die(&e, json_mode),
+    };
+
+    let currency = display_currency(&result, &args.currency);
+    if json_mode {
+        let doc = BookingDoc {
+            flight,
+            currency,
+            options: &booking,
+        };
+        print_json(doc, JsonOutput::pretty(args.pretty));
+    } else if booking.is_empty() {
+        println!("No booking options found for {}.", flight_label(flight));
+    } else {
+        println!("Booking options for {}", flight_label(flight));
+        println!(
+            "{}",
+            table::render_booking(&booking, currency, &display_locale(&args.locale))
+        );
+    }
+}
+
+async fn run_budget(args: &BudgetArgs) {
+    let json_mode = args.json || args.pretty;
+    if let Err(e) = validate_locale(&args.locale) {
+        die(&e, json_mode);
+    }
+    let (start, end) = match calendar_range(&args.month, &args.start, &args.end) {
+        Ok(r) => r,
+        Err(e) => die(&e, json_mode),
+    };
+    let seat = match Seat::from_str_loose(&args.seat) {
+        Ok(s) => s,
+        Err(e) => die(&e, json_mode),
+    };
+
+    let from = args.from.to_uppercase();
+    let mut routes = Vec::new();
+    for dest in args.to.split(',').map(|d| d.trim().to_uppercase()).filter(|d| !d.is_empty()) {
+        let mut legs = vec![FlightLeg {
+            date: start.clone(),
+            from_airport: from.clone(),
+            to_airport: dest.clone(),
+            max_stops: args.max_stops,
+            ..FlightLeg::default()
+        }];
+        if let Some(n) = args.return_days {
+            legs.push(FlightLeg {
+                date: query::add_days(&start, i64::from(n)).unwrap_or_default(),
+                from_airport: dest.clone(),
+                to_airport: from.clone(),
+                max_stops: args.max_stops,
+                ..FlightLeg::default()
+            });
+        }
+        let params = QueryParams {
+            trip: if legs.len() > 1 {
+                TripType::RoundTrip
+            } else {
+                TripType::OneWay
+            },
+            legs,
+            passengers: Passengers {
+                adults: args.adults,
+                ..Passengers::default()
+            },
+            seat: seat.clone(),
+            language: args.lang.clone(),
+            currency: args.currency.clone(),
+            region: String::new(),
+            max_price: None,
+            low_emissions: false,
+        };
+        if let Err(e) = params.validate() {
+            die(&e, json_mode);
+        }
+        routes.push((dest, params));
+    }
+
+    let options = FetchOptions {
+        proxy: args.proxy.clone(),
+        timeout: args.timeout,
+        ..FetchOptions::default()
+    };
+    let client = match flyr::FlyrClient::new(options) {
+        Ok(c) => c,
+        Err(e) => die(&e, json_mode),
+    };
+
+    let mut join_set = JoinSet::new();
+    for (dest, params) in routes.iter().cloned() {
+        let client = client.clone();
+        let (start, end) = (start.clone(), end.clone());
+        join_set.spawn(async move {
+            let days = flyr::search_calendar(&client, &params, &start, &end).await;
+            (dest, days)
+        });
+    }
+
+    let mut calendars: Vec<(String, Vec<CalendarDay>)> = Vec::new();
+    let mut failures: Vec<FlightError> = Vec::new();
+    while let Some(join_result) = join_set.join_next().await {
+        match join_result.unwrap() {
+            (dest, Ok(days)) => calendars.push((dest, days)),
+            (dest, Err(e)) => {
+                eprintln!("error: {dest}: {e}");
+                failures.push(e);
+            }
+        }
+    }
+    if let Some(e) = failures.first().filter(|_| calendars.is_empty()) {
+        die(e, json_mode);
+    }
+
+    let mut matches = budget::matches(&calendars, args.max);
+    if let Some(n) = args.top {
+        matches.truncate(n);
+    }
+    let locale = display_locale(&args.locale);
+    if json_mode {
+        let doc = BudgetDoc {
+            max_price: args.max,
+            currency: &args.currency,
+            matches: &matches,
+        };
+        print_json(doc, JsonOutput::pretty(args.pretty));
+    } else if matches.is_empty() {
+        println!(
+            "Nothing at or below {}.",
+            locale.price(Some(args.max), &args.currency)
+        );
+    } else if args.compact {
+        for m in &matches {
+            let ret = m
+                .return_date
+                .as_deref()
+                .map(|r| format!(" > {r}"))
+                .unwrap_or_default();
+            println!(
+                "{} | {}{ret} | {}",
+                m.destination,
+                m.date,
+                locale.price(Some(m.price), &args.currency)
+            );
+        }
+    } else {
+        println!("{}", table::render_budget(&matches, &args.currency, &locale));
+    }
+
+    if !failures.is_empty() {
+        process::exit(exit_code("partial", EXIT_PARTIAL));
+    }
+}
+
+async fn run_calendar(args: &CalendarArgs) {
+    let json_mode = args.json || args.pretty;
+    if let Err(e) = validate_locale(&args.locale) {
+        die(&e, json_mode);
+    }
+    let (start, end) = match calendar_range(&args.month, &args.start, &args.end) {
+        Ok(r) => r,
+        Err(e) => die(&e, json_mode),
+    };
+    let seat = match Seat::from_str_loose(&args.seat) {
+        Ok(s) => s,
+        Err(e) => die(&e, json_mode),
+    };
+
+    let from = args.from.to_uppercase();
+    let to = args.to.to_uppercase();
+    let mut legs = vec![FlightLeg {
+        date: start.clone(),
+        from_airport: from.clone(),
+        to_airport: to.clone(),
         max_stops: args.max_stops,
-        airlines: airlines.clone(),
+        ..FlightLeg::default()
     }];
+    let trip = match args.return_days {
+        Some(n) => {
+            legs.push(FlightLeg {
+                date: query::add_days(&start, i64::from(n)).unwrap_or_default(),
+                from_airport: to,
+                to_airport: from,
+                max_stops: args.max_stops,
+                ..FlightLeg::default()
+            });
+            TripType::RoundTrip
+        }
+        None => TripType::OneWay,
+    };
 
-    if let Some(ref ret_date) = args.return_date {
-        legs.push(FlightLeg {
-            date: ret_date.clone(),
-            from_airport: to.to_uppercase(),
-            to_airport: from.to_uppercase(),
-            max_stops: args.max_stops,
-            airlines: airlines.clone(),
-        });
+    let params = QueryParams {
+        legs,
+        passengers: Passengers {
+            adults: args.adults,
+            ..Passengers::default()
+        },
+        seat,
+        trip,
+        language: args.lang.clone(),
+        currency: args.currency.clone(),
+        region: String::new(),
+        max_price: None,
+        low_emissions: false,
+    };
+    if let Err(e) = params.validate() {
+        die(&e, json_mode);
     }
 
-    Ok(legs)
+    let options = FetchOptions {
+        proxy: args.proxy.clone(),
+        timeout: args.timeout,
+        ..FetchOptions::default()
+    };
+    let client = match flyr::FlyrClient::new(options) {
+        Ok(c) => c,
+        Err(e) => die(&e, json_mode),
+    };
+    match flyr::search_calendar(&client, &params, &start, &end).await {
+        Ok(days) => print_calendar(
+            &days,
+            &args.currency,
+            None,
+            &display_locale(&args.locale),
+            args.compact,
+            (args.json || args.pretty).then(|| JsonOutput::pretty(args.pretty)),
+        ),
+        Err(e) => die(&e, json_mode),
+    }
+}
+
+async fn run_raw(query: &SearchQuery, path: &std::path::Path, args: &SearchArgs) {
+    let json_mode = is_json(args);
+    let fetcher = match search_fetch_options(args).and_then(|o| build_fetcher(args, o)) {
+        Ok(f) => f,
+        Err(e) => die(&e, json_mode),
+    };
+    let payload = match flyr::fetch_raw_payload(&fetcher, query).await {
+        Ok(p) => p,
+        Err(e) => die(&e, json_mode),
+    };
+    if path.as_os_str() == "-" {
+        emit_json(&payload, json_output(args));
+        return;
+    }
+    if let Err(e) = std::fs::write(path, render_json(&payload, args.pretty) + "\n") {
+        let e = FlightError::Storage(format!("{}: {e}", path.display()));
+        die(&e, json_mode);
+    }
+    eprintln!("Wrote the raw payload to {}", path.display());
+}
+
+async fn run_free_text(text: &str, sort: Option<SortKey>, ranker: &Ranker, args: &SearchArgs) {
+    let json_mode = is_json(args);
+    if text.trim().is_empty() {
+        die(
+            &FlightError::Validation("--query must not be empty".into()),
+            json_mode,
+        );
+    }
+    let fetcher = match search_fetch_options(args)
+        .and_then(|o| build_fetcher(args, o))
+        .and_then(|f| with_response_cache(args, f, open_store(args)))
+    {
+        Ok(f) => f,
+        Err(e) => die(&e, json_mode),
+    };
+    let parse_options = ParseOptions {
+        strict: args.strict,
+        low_memory: args.low_memory,
+        ..ParseOptions::default()
+    };
+
+    let query = SearchQuery::NaturalLanguage(text.trim().to_string());
+    let mut result = match flyr::search_with_fetcher(&fetcher, query, parse_options).await {
+        Ok(r) => r,
+        Err(e) => die(&e, json_mode),
+    };
+    let rates = exchange_rates(args, fetcher.inner().primary()).await;
+    convert_prices(&mut result, rates.as_ref(), args);
+    if args.exclude_basic {
+        filter::apply_exclude_basic(&mut result);
+    }
+    if args.dedupe_codeshares {
+        filter::dedupe_codeshares(&mut result);
+    }
+    if args.best_only {
+        filter::apply_best_only(&mut result);
+    }
+    ranker.score(&mut result);
+    if args.pareto {
+        pareto::retain(&mut result, args.pareto_co2);
+    }
+    order_results(&mut result, sort, args.top);
+    if json_document(args) {
+        result.warnings.extend(take_warnings());
+    } else {
+        print_warnings(&result, None);
+    }
+    print_result(&result, args);
+    print_alert_summary(&result, args);
+    if args.fail_on_empty && result.flights.is_empty() {
+        process::exit(exit_code("empty", EXIT_EMPTY));
+    }
+    if alert_count(&result, args) > 0 {
+        process::exit(exit_code("alert", EXIT_ALERT));
+    }
 }
 
-fn determine_trip(args: &SearchArgs) -> String {
-    if args.return_date.is_some() {
-        return "round-trip".to_string();
+async fn run_flex(
+    params: &QueryParams,
+    days: u32,
+    options: FetchOptions,
+    parse_options: ParseOptions,
+    args: &SearchArgs,
+) {
+    let json_mode = is_json(args);
+    let date = &params.legs[0].date;
+    let spacing = match args.delay.as_deref().map(query::parse_delay).transpose() {
+        Ok(d) => d.unwrap_or_default(),
+        Err(e) => die(&e, json_mode),
+    };
+    let fetcher = match build_fetcher(args, options.for_fan_out())
+        .and_then(|c| with_response_cache(args, c, open_store(args)))
+    {
+        Ok(c) => Limited::new(
+            Guarded::with_defaults(c),
+            AdaptiveLimit::new(args.concurrency, spacing),
+        ),
+        Err(e) => die(&e, json_mode),
+    };
+
+    let by_date = match flyr::search_flexible(&fetcher, params, days, parse_options).await {
+        Ok(by_date) => by_date,
+        Err(e) => die(&e, json_mode),
+    };
+
+    let origin = query::date_to_days(date).unwrap_or_default();
+    let mut cheapest = Vec::new();
+    let mut failures = Vec::new();
+    for (day, outcome) in by_date {
+        match outcome {
+            Ok(result) => {
+                let offset = query::date_to_days(&day).unwrap_or_default() - origin;
+                let return_date = params
+                    .legs
+                    .last()
+                    .filter(|_| params.legs.len() > 1)
+                    .and_then(|l| query::add_days(&l.date, offset));
+                cheapest.push(CalendarDay {
+                    date: day,
+                    return_date,
+                    price: result.flights.iter().filter_map(|f| f.price).min(),
+                });
+            }
+            Err(e) => {
+                eprintln!("error: {day}: {e}");
+                failures.push(e);
+            }
+        }
     }
-    if args.leg.len() >= 2 && args.trip == "one-way" {
-        return "multi-city".to_string();
+    if let Some(e) = failures.first().filter(|_| cheapest.is_empty()) {
+        die(e, json_mode);
+    }
+
+    print_calendar(
+        &cheapest,
+        &args.currency,
+        Some(date),
+        &display_locale(&args.locale),
+        args.compact,
+        is_json(args).then(|| json_output(args)),
+    );
+    if !failures.is_empty() {
+        process::exit(exit_code("partial", EXIT_PARTIAL));
     }
-    args.trip.clone()
 }
 
-fn print_compact(result: &SearchResult, currency: &str) {
-    for flight in &result.flights {
-        let price = table::format_price(flight.price, currency);
+async fn run_stays(
+    params: &QueryParams,
+    nights: &str,
+    days: u32,
+    options: FetchOptions,
+    parse_options: ParseOptions,
+    args: &SearchArgs,
+) {
+    let json_mode = is_json(args);
+    let nights = match stay::parse_nights(nights) {
+        Ok(n) => n,
+        Err(e) => die(&e, json_mode),
+    };
+    let spacing = match args.delay.as_deref().map(query::parse_delay).transpose() {
+        Ok(d) => d.unwrap_or_default(),
+        Err(e) => die(&e, json_mode),
+    };
+    let fetcher = match build_fetcher(args, options.for_fan_out())
+        .and_then(|c| with_response_cache(args, c, open_store(args)))
+    {
+        Ok(c) => Limited::new(
+            Guarded::with_defaults(c),
+            AdaptiveLimit::new(args.concurrency, spacing),
+        ),
+        Err(e) => die(&e, json_mode),
+    };
 
-        let route: Vec<&str> = std::iter::once(
-            flight
-                .segments
-                .first()
-                .map(|s| s.from_airport.code.as_str())
-                .unwrap_or("?"),
-        )
-        .chain(flight.segments.iter().map(|s| s.to_airport.code.as_str()))
-        .collect();
-        let route_str = route.join(">");
+    let by_pair = match flyr::search_stays(&fetcher, params, days, nights, parse_options).await {
+        Ok(r) => r,
+        Err(e) => die(&e, json_mode),
+    };
 
-        let duration = if flight.segments.is_empty() {
-            "—".to_string()
-        } else {
-            let total: u32 = flight.segments.iter().map(|s| s.duration_minutes).sum();
-            format!("{}h{:02}m", total / 60, total % 60)
-        };
+    let mut failures = Vec::new();
+    for ((date, return_date), outcome) in &by_pair {
+        if let Err(e) = outcome {
+            eprintln!("error: {date} > {return_date}: {e}");
+            failures.push(e);
+        }
+    }
+    let mut stays = stay::cheapest(&by_pair);
+    if let Some(e) = failures.first().filter(|_| stays.is_empty()) {
+        die(e, json_mode);
+    }
+    if let Some(n) = args.top {
+        stays.truncate(n);
+    }
 
-        let stops = if flight.segments.len() <= 1 {
-            "nonstop".to_string()
-        } else {
-            let n = flight.segments.len() - 1;
-            let codes: Vec<&str> = flight.segments[..n]
-                .iter()
-                .map(|s| s.to_airport.code.as_str())
-                .collect();
-            format!("{n} stop {}", codes.join(","))
+    let locale = display_locale(&args.locale);
+    if args.compact {
+        for s in &stays {
+            println!(
+                "{} > {} ({} nights) | {}",
+                s.date,
+                s.return_date,
+                s.nights,
+                locale.price(s.price, &args.currency)
+            );
+        }
+    } else if args.json || args.pretty {
+        let doc = StayDoc {
+            currency: &args.currency,
+            stays: &stays,
         };
+        print_json(doc, json_output(args));
+    } else {
+        println!("{}", table::render_stays(&stays, &args.currency, &locale));
+    }
+    if !failures.is_empty() {
+        process::exit(exit_code("partial", EXIT_PARTIAL));
+    }
+}
 
-        let airlines = flight.airlines.join(", ");
-
-        let depart = flight.segments.first();
-        let arrive = flight.segments.last();
-        let time_str = match (depart, arrive) {
-            (Some(d), Some(a)) => format!(
-                "{}{:02} {:02}:{:02}>{:02}:{:02}",
-                month_abbr(d.departure.month),
-                d.departure.day,
-                d.departure.hour,
-                d.departure.minute,
-                a.arrival.hour,
-                a.arrival.minute,
-            ),
-            _ => "—".to_string(),
-        };
+async fn run_seat_comparison(
+    params: &QueryParams,
+    seats: Vec<Seat>,
+    options: FetchOptions,
+    parse_options: ParseOptions,
+    args: &SearchArgs,
+) {
+    let json_mode = is_json(args);
+    let fetcher = match build_fetcher(args, options) {
+        Ok(f) => f,
+        Err(e) => die(&e, json_mode),
+    };
+    let cache = match Cache::open(args, open_store(args)) {
+        Ok(c) => c,
+        Err(e) => die(&e, json_mode),
+    };
 
-        println!("{price} | {route_str} | {duration} | {stops} | {airlines} | {time_str}");
+    let mut join_set = JoinSet::new();
+    for (i, seat) in seats.iter().enumerate() {
+        let query_params = QueryParams {
+            seat: seat.clone(),
+            ..params.clone()
+        };
+        if let Some(hit) = cache.as_ref().and_then(|c| c.lookup(&query_params)) {
+            join_set.spawn(async move { (i, query_params, Ok(hit), true) });
+            continue;
+        }
+        let fetcher = fetcher.clone();
+        let parse_options = parse_options.clone();
+        join_set.spawn(async move {
+            let result = flyr::search_with_fetcher(
+                &fetcher,
+                SearchQuery::Structured(query_params.clone()),
+                parse_options,
+            )
+            .await;
+            (i, query_params, result, false)
+        });
     }
-}
 
-fn month_abbr(m: u32) -> &'static str {
-    match m {
-        1 => "Jan",
-        2 => "Feb",
-        3 => "Mar",
-        4 => "Apr",
-        5 => "May",
-        6 => "Jun",
-        7 => "Jul",
-        8 => "Aug",
-        9 => "Sep",
-        10 => "Oct",
-        11 => "Nov",
-        12 => "Dec",
-        _ => "???",
+    let mut outcomes: Vec<Option<Result<SearchResult, FlightError>>> =
+        seats.iter().map(|_| None).collect();
+    while let Some(join_result) = join_set.join_next().await {
+        let (i, query_params, outcome, from_cache) = join_result.unwrap();
+        if let (Some(cache), Ok(result), false) = (&cache, &outcome, from_cache) {
+            cache.record(&query_params, result);
+        }
+        outcomes[i] = Some(outcome);
+    }
+    let outcomes: Vec<(Seat, Result<SearchResult, FlightError>)> =
+        seats.into_iter().zip(outcomes.into_iter().flatten()).collect();
+
+    // Failures in the order the cabins were listed, so the exit code follows the user's
+    // first choice rather than the alphabetically first cabin.
+    let failures: Vec<&FlightError> = outcomes
+        .iter()
+        .filter_map(|(_, o)| o.as_ref().err())
+        .collect();
+    let with_flights = outcomes
+        .iter()
+        .filter(|(_, o)| o.as_ref().is_ok_and(|r| !r.flights.is_empty()))
+        .count();
+    if failures.len() == outcomes.len() {
+        if let Some(e) = failures.first() {
+            die(e, json_mode);
+        }
     }
-}
 
-fn print_result(result: &SearchResult, args: &SearchArgs) {
-    if args.compact {
-        if result.flights.is_empty() {
-            println!("No flights found.");
-            return;
+    let mut comparison = flyr::cabins::compare(&outcomes);
+    let currency = outcomes
+        .iter()
+        .find_map(|(_, o)| o.as_ref().ok())
+        .map(|r| display_currency(r, &args.currency))
+        .unwrap_or(&args.currency)
+        .to_string();
+    let locale = display_locale(&args.locale);
+
+    if json_document(args) {
+        comparison.warnings.extend(take_warnings());
+        print_json(&comparison, json_output(args));
+    } else {
+        for warning in &comparison.warnings {
+            eprintln!("warning: {warning}");
         }
-        print_compact(result, &args.currency);
-    } else if is_json(args) {
-        let output = if args.pretty {
-            serde_json::to_string_pretty(result).unwrap()
+        if args.compact {
+            for row in &comparison.cabins {
+                match &row.error {
+                    Some(err) => println!("{} | error: {}", row.seat.as_str(), err.message),
+                    None => println!(
+                        "{} | {} ({}) | {} ({}) | {}",
+                        row.seat.as_str(),
+                        locale.price(row.price, &currency),
+                        table::format_price_delta(row.price_delta, &currency, &locale),
+                        row.elapsed_minutes
+                            .map(|m| format!("{}h{:02}m", m / 60, m % 60))
+                            .unwrap_or_else(|| "—".into()),
+                        table::format_duration_delta(row.duration_delta),
+                        row.airlines.join(","),
+                    ),
+                }
+            }
         } else {
-            serde_json::to_string(result).unwrap()
-        };
-        println!("{output}");
-    } else {
-        if result.flights.is_empty() {
-            println!("No flights found.");
-            return;
+            println!(
+                "{}",
+                table::render_seat_comparison(&comparison, &currency, &locale)
+            );
         }
-        println!("{}", table::render(result, &args.currency));
     }
-}
 
-fn is_multi_dest(args: &SearchArgs) -> bool {
-    args.to.as_ref().is_some_and(|t| t.contains(','))
+    let code = multi_exit_code(
+        outcomes.len() - failures.len(),
+        with_flights,
+        failures.first().copied(),
+        args,
+    );
+    if code != 0 {
+        process::exit(code);
+    }
 }
 
-fn parse_destinations(args: &SearchArgs) -> Vec<String> {
-    args.to
-        .as_ref()
-        .map(|t| {
-            t.split(',')
-                .map(|s| s.trim().to_uppercase())
-                .filter(|s| !s.is_empty())
-                .collect()
-        })
-        .unwrap_or_default()
+fn print_dest_section(dest: &str, result: &SearchResult, args: &SearchArgs) {
+    println!("=== {dest} ===");
+    if args.carbon_report {
+        print_carbon(result, args);
+    } else if result.flights.is_empty() {
+        println!("No flights found.");
+    } else if args.compact {
+        print_compact(result, args);
+    } else {
+        let currency = display_currency(result, &args.currency);
+        println!("{}", render_flights(result, currency, args));
+        print_price_insights(result, currency, args);
+    }
+    if !args.compact {
+        println!();
+    }
 }
 
-fn build_base_params(
+const EXIT_PARTIAL: i32 = 10;
+const EXIT_EMPTY: i32 = 11;
+const EXIT_ALERT: i32 = 12;
+
+fn multi_exit_code(
+    succeeded: usize,
+    with_flights: usize,
+    first_failure: Option<&FlightError>,
     args: &SearchArgs,
-) -> Result<(Passengers, Seat, TripType, Option<Vec<String>>), FlightError> {
-    let trip_str = determine_trip(args);
-    let trip = TripType::from_str_loose(&trip_str)?;
-    let seat = Seat::from_str_loose(&args.seat)?;
-    let passengers = Passengers {
-        adults: args.adults,
-        children: args.children,
-        infants_in_seat: args.infants_in_seat,
-        infants_on_lap: args.infants_on_lap,
+) -> i32 {
+    let empty = args.fail_on_empty && succeeded > 0 && with_flights == 0;
+    let Some(first) = first_failure else {
+        return if empty {
+            exit_code("empty", EXIT_EMPTY)
+        } else {
+            0
+        };
     };
-    let airlines: Option<Vec<String>> = args
-        .airlines
-        .as_ref()
-        .map(|s| s.split(',').map(|a| a.trim().to_uppercase()).collect());
-    Ok((passengers, seat, trip, airlines))
+    if args.fail_fast || succeeded == 0 {
+        return error_code(first);
+    }
+    if !args.allow_partial {
+        exit_code("partial", EXIT_PARTIAL)
+    } else if empty {
+        exit_code("empty", EXIT_EMPTY)
+    } else {
+        0
+    }
 }
 
-fn print_multi_result(
-    results: &BTreeMap<String, SearchResult>,
-    args: &SearchArgs,
-) {
-    if args.compact {
-        for (dest, result) in results {
-            println!("=== {dest} ===");
-            if result.flights.is_empty() {
-                println!("No flights found.");
-            } else {
-                print_compact(result, &args.currency);
-            }
-        }
-    } else if is_json(args) {
-        let output = if args.pretty {
-            serde_json::to_string_pretty(results).unwrap()
-        } else {
-            serde_json::to_string(results).unwrap()
+fn print_multi_json(results: &BTreeMap<String, DestinationOutcome>, args: &SearchArgs) {
+    let warnings = take_warnings();
+    if args.schema >= flyr::SCHEMA_VERSION {
+        let doc = MultiSearch {
+            destinations: results,
+            warnings: &warnings,
         };
-        println!("{output}");
+        print_json(doc, json_output(args));
+        return;
+    }
+    for warning in &warnings {
+        eprintln!("warning: {warning}");
+    }
+    let value = if args.schema < 2 {
+        let legacy: BTreeMap<&String, SearchResult> =
+            results.iter().map(|(dest, r)| (dest, r.legacy())).collect();
+        serde_json::to_value(legacy)
     } else {
-        for (dest, result) in results {
-            println!("=== {dest} ===");
-            if result.flights.is_empty() {
-                println!("No flights found.");
-            } else {
-                println!("{}", table::render(result, &args.currency));
-            }
-            println!();
-        }
+        serde_json::to_value(results)
     }
+    .unwrap();
+    emit_json(&value, json_output(args));
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    if let Some(spec) = &cli.exit_code_map {
+        match parse_exit_code_map(spec) {
+            Ok(codes) => {
+                let _ = EXIT_CODES.set(codes);
+            }
+            Err(e) => die(&e, false),
+        }
+    }
 
     match cli.command {
         Commands::Mcp => flyr::mcp::run().await,
-        Commands::Search(args) => {
+        Commands::Calendar(args) => run_calendar(&args).await,
+        Commands::Bench(args) => run_bench(&args),
+        Commands::Cache(args) => run_cache(&args),
+        Commands::History(args) => run_history(&args),
+        Commands::Snapshot(args) => run_snapshot(&args).await,
+        Commands::Budget(args) => run_budget(&args).await,
+        Commands::Book(args) => run_book(&args).await,
+        Commands::Schema(args) => {
+            println!("{}", render_json(&flyr::model::search_result_schema(), args.pretty))
+        }
+        Commands::Search(mut args) => {
             let json_mode = is_json(&args);
+            if let Some(src) = &args.jq {
+                match Filter::parse(src) {
+                    Ok(filter) => args.jq_filter = Some(filter),
+                    Err(e) => die(&e, json_mode),
+                }
+            }
+            let config = match Config::load() {
+                Ok(c) => c,
+                Err(e) => die(&e, json_mode),
+            };
+            let sort = match parse_sort(&args, &config) {
+                Ok(s) => s,
+                Err(e) => die(&e, json_mode),
+            };
+            let min_connection = match parse_min_connection(&args) {
+                Ok(m) => m,
+                Err(e) => die(&e, json_mode),
+            };
+            let ranker = match parse_ranker(&args, &config) {
+                Ok(r) => r,
+                Err(e) => die(&e, json_mode),
+            };
+            let bag = match parse_bag(&args) {
+                Ok(b) => b,
+                Err(e) => die(&e, json_mode),
+            };
+            if let Err(e) = validate_locale(&args.locale) {
+                die(&e, json_mode);
+            }
+            if let Err(e) = parse_times(&args) {
+                die(&e, json_mode);
+            }
+            if let Err(e) = parse_compact_fields(&args) {
+                die(&e, json_mode);
+            }
+            let compare_seats = match parse_compare_seats(&args) {
+                Ok(s) => s,
+                Err(e) => die(&e, json_mode),
+            };
+            if compare_seats.is_some() && is_multi_dest(&args) {
+                die(
+                    &FlightError::Validation(
+                        "--compare-seats needs a single origin, destination and date".into(),
+                    ),
+                    json_mode,
+                );
+            }
+
+            if args.nights.is_some() && is_multi_dest(&args) {
+                die(
+                    &FlightError::Validation(
+                        "--nights needs a single -f/-t route and date, not several".into(),
+                    ),
+                    json_mode,
+                );
+            }
+
+            if args.with_returns.is_some() && is_multi_dest(&args) {
+                die(
+                    &FlightError::Validation(
+                        "--with-returns needs a single -f/-t route and date, not several".into(),
+                    ),
+                    json_mode,
+                );
+            }
+
+            if args.flex.is_some() && (is_multi_dest(&args) || !args.leg.is_empty()) {
+                die(
+                    &FlightError::Validation(
+                        "--flex needs a single -f/-t route and date, not --leg or several \
+                         airports or dates"
+                            .into(),
+                    ),
+                    json_mode,
+                );
+            }
+
+            if args.raw.is_some() && is_multi_dest(&args) {
+                die(
+                    &FlightError::Validation(
+                        "--raw needs a single -f/-t route and date, not several".into(),
+                    ),
+                    json_mode,
+                );
+            }
+
+            if let (Some(text), Some(path)) = (args.query.as_deref(), &args.raw) {
+                let query = SearchQuery::NaturalLanguage(text.trim().to_string());
+                run_raw(&query, path, &args).await;
+                return;
+            }
+
+            if let Some(text) = args.query.as_deref() {
+                run_free_text(text, sort, &ranker, &args).await;
+                return;
+            }
 
             if is_multi_dest(&args) {
                 if !args.leg.is_empty() {
                     die(
                         &FlightError::Validation(
-                            "--leg cannot be used with comma-separated -t destinations".into(),
+                            "--leg cannot be used with comma-separated -f/-t/-d values".into(),
                         ),
                         json_mode,
                     );
                 }
 
-                let from = match args.from.as_ref() {
-                    Some(f) => f.to_uppercase(),
-                    None => die(
+                let origins = parse_origins(&args);
+                if origins.is_empty() {
+                    die(
                         &FlightError::Validation("--from is required (or use --leg)".into()),
                         json_mode,
-                    ),
-                };
-                let date = match args.date.as_ref() {
-                    Some(d) => d.clone(),
-                    None => die(
+                    );
+                }
+                let dates = split_codes(&args.date);
+                if dates.is_empty() {
+                    die(
                         &FlightError::Validation("--date is required (or use --leg)".into()),
                         json_mode,
-                    ),
-                };
+                    );
+                }
 
-                let (passengers, seat, _trip, airlines) = match build_base_params(&args) {
+                let (passengers, seat, _trip, template) = match build_base_params(&args) {
                     Ok(p) => p,
                     Err(e) => die(&e, json_mode),
                 };
 
                 let destinations = parse_destinations(&args);
-                let fetch_options = FetchOptions {
-                    proxy: args.proxy.clone(),
-                    timeout: args.timeout,
+                let single_route = origins.len() == 1 && destinations.len() == 1;
+                let searches: Vec<(&String, &String, &String)> = dates
+                    .iter()
+                    .flat_map(|date| origins.iter().map(move |from| (date, from)))
+                    .flat_map(|(date, from)| {
+                        destinations.iter().map(move |dest| (date, from, dest))
+                    })
+                    .collect();
+                let fetch_options = match search_fetch_options(&args) {
+                    Ok(o) => o,
+                    Err(e) => die(&e, json_mode),
                 };
 
                 if args.open {
-                    let from = match args.from.as_ref() {
-                        Some(f) => f.to_uppercase(),
-                        None => die(
-                            &FlightError::Validation("--from is required (or use --leg)".into()),
-                            json_mode,
-                        ),
-                    };
-                    let date = match args.date.as_ref() {
-                        Some(d) => d.clone(),
-                        None => die(
-                            &FlightError::Validation("--date is required (or use --leg)".into()),
-                            json_mode,
-                        ),
-                    };
-
                     let trip = if args.return_date.is_some() {
                         TripType::RoundTrip
                     } else {
                         TripType::OneWay
                     };
 
-                    for dest in &destinations {
+                    for &(date, from, dest) in &searches {
                         let mut legs = vec![FlightLeg {
                             date: date.clone(),
                             from_airport: from.clone(),
                             to_airport: dest.clone(),
-                            max_stops: args.max_stops,
-                            airlines: airlines.clone(),
+                            ..template.clone()
                         }];
 
-                        if args.return_date.is_some() {
+                        if let Some(ret) = return_for(&args, &dates, date) {
                             legs.push(FlightLeg {
-                                date: args.return_date.clone().unwrap(),
+                                date: ret,
                                 from_airport: dest.clone(),
                                 to_airport: from.clone(),
-                                max_stops: args.max_stops,
-                                airlines: airlines.clone(),
+                                ..template.clone()
                             });
                         }
 
@@ -578,6 +3488,9 @@ async fn main() {
                             trip: trip.clone(),
                             language: args.lang.clone(),
                             currency: args.currency.clone(),
+                            region: args.region.clone().unwrap_or_default(),
+                            max_price: args.max_price,
+                            low_emissions: args.low_emissions,
                         };
 
                         let url = flyr::generate_browser_url(&query_params);
@@ -591,24 +3504,46 @@ async fn main() {
                     return;
                 }
 
+                if let Err(e) = prime_dns(&fetch_options).await {
+                    die(&e, json_mode);
+                }
+                let spacing = match args.delay.as_deref().map(query::parse_delay).transpose() {
+                    Ok(d) => d.unwrap_or_default(),
+                    Err(e) => die(&e, json_mode),
+                };
+                let hedged = match build_fetcher(&args, fetch_options.for_fan_out()) {
+                    Ok(h) => h,
+                    Err(e) => die(&e, json_mode),
+                };
+                let rates = exchange_rates(&args, hedged.primary()).await;
+                let client = Limited::new(
+                    Guarded::with_defaults(hedged),
+                    AdaptiveLimit::new(args.concurrency, spacing),
+                );
+
+                let cache = match Cache::open(&args, open_store(&args)) {
+                    Ok(c) => c,
+                    Err(e) => die(&e, json_mode),
+                };
+                let mut export = ExportDb::open(&args);
+                let mut recorded: BTreeMap<String, QueryParams> = BTreeMap::new();
+
                 let mut join_set = JoinSet::new();
 
-                for dest in &destinations {
+                for &(date, from, dest) in &searches {
                     let mut legs = vec![FlightLeg {
                         date: date.clone(),
                         from_airport: from.clone(),
                         to_airport: dest.clone(),
-                        max_stops: args.max_stops,
-                        airlines: airlines.clone(),
+                        ..template.clone()
                     }];
 
-                    let trip = if args.return_date.is_some() {
+                    let trip = if let Some(ret) = return_for(&args, &dates, date) {
                         legs.push(FlightLeg {
-                            date: args.return_date.clone().unwrap(),
+                            date: ret,
                             from_airport: dest.clone(),
                             to_airport: from.clone(),
-                            max_stops: args.max_stops,
-                            airlines: airlines.clone(),
+                            ..template.clone()
                         });
                         TripType::RoundTrip
                     } else {
@@ -622,6 +3557,9 @@ async fn main() {
                         trip,
                         language: args.lang.clone(),
                         currency: args.currency.clone(),
+                        region: args.region.clone().unwrap_or_default(),
+                        max_price: args.max_price,
+                        low_emissions: args.low_emissions,
                     };
 
                 if args.open {
@@ -638,40 +3576,150 @@ async fn main() {
                         die(&e, json_mode);
                     }
 
-                    let opts = fetch_options.clone();
-                    let dest_code = dest.clone();
+                    let dest_code =
+                        search_label(&dates, single_route, date, route_label(&origins, from, dest));
+                    recorded.insert(dest_code.clone(), query_params.clone());
+                    if let Some(ref cache) = cache {
+                        if let Some(hit) = cache.lookup(&query_params) {
+                            join_set.spawn(async move { (dest_code, Ok(hit), true) });
+                            continue;
+                        }
+                    }
+
+                    let client = client.clone();
+                    let parse_opts = ParseOptions {
+                        strict: args.strict,
+                        low_memory: args.low_memory,
+                        ..ParseOptions::default()
+                    };
                     join_set.spawn(async move {
-                        let result =
-                            flyr::search(SearchQuery::Structured(query_params), opts).await;
-                        (dest_code, result)
+                        let result = flyr::search_with_fetcher(
+                            &client,
+                            SearchQuery::Structured(query_params),
+                            parse_opts,
+                        )
+                        .await;
+                        (dest_code, result, false)
                     });
                 }
 
-                let mut results: BTreeMap<String, SearchResult> = BTreeMap::new();
+                let text = args.compact || !json_mode;
+                let streaming = text && origins.len() == 1 && dates.len() == 1;
+                let mut results: BTreeMap<String, DestinationOutcome> = BTreeMap::new();
+                let mut failures: BTreeMap<String, FlightError> = BTreeMap::new();
+                let mut succeeded = 0;
+                let mut with_flights = 0;
+                let mut alerts = BTreeMap::new();
 
                 while let Some(join_result) = join_set.join_next().await {
-                    let (dest_code, search_result) = join_result.unwrap();
+                    let (dest_code, search_result, from_cache) = join_result.unwrap();
                     match search_result {
                         Ok(mut result) => {
-                            if let Some(n) = args.top {
-                                apply_top(&mut result, n);
+                            if let Some(params) = recorded.get(&dest_code) {
+                                if let (Some(cache), false) = (&cache, from_cache) {
+                                    cache.record(params, &result);
+                                }
+                                if let (Some(cache), true) = (&cache, args.diff) {
+                                    cache.compare(params, &mut result);
+                                }
+                                if let Some(export) = export.as_mut() {
+                                    export.write(params, &result);
+                                }
+                            }
+                            convert_prices(&mut result, rates.as_ref(), &args);
+                            if let Some(mins) = min_connection {
+                                risk::annotate(&mut result, mins);
+                            }
+                            if let Some(bag) = bag {
+                                bags::annotate(&mut result, bag, &args.currency);
+                            }
+                            if args.exclude_basic {
+                                filter::apply_exclude_basic(&mut result);
+                            }
+                            if args.dedupe_codeshares {
+                                filter::dedupe_codeshares(&mut result);
+                            }
+                            if args.best_only {
+                                filter::apply_best_only(&mut result);
+                            }
+                            ranker.score(&mut result);
+                            if args.pareto {
+                                pareto::retain(&mut result, args.pareto_co2);
+                            }
+                            order_results(&mut result, sort, args.top);
+                            if streaming {
+                                print_warnings(&result, Some(&dest_code));
+                            }
+                            if args.timings {
+                                print_timings(&dest_code, &result);
+                            }
+                            succeeded += 1;
+                            with_flights += usize::from(!result.flights.is_empty());
+                            match alert_count(&result, &args) {
+                                0 => {}
+                                n => {
+                                    alerts.insert(dest_code.clone(), n);
+                                }
+                            }
+                            if streaming {
+                                print_dest_section(&dest_code, &result, &args);
+                            } else {
+                                results.insert(dest_code, DestinationOutcome::Ok(Box::new(result)));
                             }
-                            results.insert(dest_code, result);
                         }
                         Err(e) => {
-                            if json_mode {
-                                let mut error_result = SearchResult::default();
-                                error_result.flights = vec![];
-                                results.insert(dest_code.clone(), error_result);
-                                eprintln!("warning: {dest_code}: {e}");
-                            } else {
+                            if !json_mode {
                                 eprintln!("error: {dest_code}: {e}");
+                            } else if args.schema < 2 {
+                                eprintln!("warning: {dest_code}: {e}");
+                            }
+                            if !streaming {
+                                results.insert(
+                                    dest_code.clone(),
+                                    DestinationOutcome::Error(ErrorInfo::from(&e)),
+                                );
+                            }
+                            failures.insert(dest_code, e);
+                            if args.fail_fast {
+                                join_set.abort_all();
+                                break;
                             }
                         }
                     }
                 }
 
-                print_multi_result(&results, &args);
+                if !text {
+                    print_multi_json(&results, &args);
+                } else if !streaming {
+                    for (route, outcome) in &results {
+                        if let DestinationOutcome::Ok(result) = outcome {
+                            print_warnings(result, Some(route));
+                            print_dest_section(route, result, &args);
+                        }
+                    }
+                }
+                if let Some(limit) = args.alert_below {
+                    let limit = display_locale(&args.locale).price(Some(limit), &args.currency);
+                    let summary = if alerts.is_empty() {
+                        format!("No flights below {limit}.")
+                    } else {
+                        let dests: Vec<String> =
+                            alerts.iter().map(|(d, n)| format!("{d} ({n})")).collect();
+                        format!("Alert: flights below {limit} to {}.", dests.join(", "))
+                    };
+                    if text {
+                        println!("{summary}");
+                    } else {
+                        eprintln!("{summary}");
+                    }
+                }
+                let code = multi_exit_code(succeeded, with_flights, failures.values().next(), &args);
+                if code != 0 {
+                    process::exit(code);
+                }
+                if !alerts.is_empty() {
+                    process::exit(exit_code("alert", EXIT_ALERT));
+                }
             } else {
                 let legs = match build_legs(&args) {
                     Ok(l) => l,
@@ -702,6 +3750,9 @@ async fn main() {
                     trip,
                     language: args.lang.clone(),
                     currency: args.currency.clone(),
+                    region: args.region.clone().unwrap_or_default(),
+                    max_price: args.max_price,
+                    low_emissions: args.low_emissions,
                 };
 
                 if args.open {
@@ -718,17 +3769,174 @@ async fn main() {
                     die(&e, json_mode);
                 }
 
-                let fetch_options = FetchOptions {
-                    proxy: args.proxy.clone(),
-                    timeout: args.timeout,
+                let fetch_options = match search_fetch_options(&args) {
+                    Ok(o) => o,
+                    Err(e) => die(&e, json_mode),
+                };
+
+                let parse_options = ParseOptions {
+                    strict: args.strict,
+                    low_memory: args.low_memory,
+                    ..ParseOptions::default()
+                };
+
+                if let Some(path) = &args.raw {
+                    run_raw(&SearchQuery::Structured(query_params.clone()), path, &args).await;
+                    return;
+                }
+
+                if let Some(nights) = args.nights.as_deref() {
+                    let days = args.flex.unwrap_or(0);
+                    run_stays(&query_params, nights, days, fetch_options, parse_options, &args)
+                        .await;
+                    return;
+                }
+
+                if let Some(days) = args.flex {
+                    run_flex(&query_params, days, fetch_options, parse_options, &args).await;
+                    return;
+                }
+
+                if let Some(seats) = compare_seats {
+                    run_seat_comparison(&query_params, seats, fetch_options, parse_options, &args)
+                        .await;
+                    return;
+                }
+
+                let cache = match Cache::open(&args, open_store(&args)) {
+                    Ok(c) => c,
+                    Err(e) => die(&e, json_mode),
+                };
+                let fetcher = match build_fetcher(&args, fetch_options) {
+                    Ok(f) => f,
+                    Err(e) => die(&e, json_mode),
+                };
+                let rates = exchange_rates(&args, fetcher.primary()).await;
+
+                let outcome = match cache.as_ref().and_then(|c| c.lookup(&query_params)) {
+                    Some(hit) => Ok(hit),
+                    None => {
+                        let outcome = flyr::search_with_fetcher(
+                            &fetcher,
+                            SearchQuery::Structured(query_params.clone()),
+                            parse_options.clone(),
+                        )
+                        .await;
+                        if let (Some(cache), Ok(result)) = (&cache, &outcome) {
+                            cache.record(&query_params, result);
+                        }
+                        outcome
+                    }
                 };
 
-                match flyr::search(SearchQuery::Structured(query_params), fetch_options).await {
+                match outcome {
                     Ok(mut result) => {
-                        if let Some(n) = args.top {
-                            apply_top(&mut result, n);
+                        if let Some(mut export) = ExportDb::open(&args) {
+                            export.write(&query_params, &result);
+                        }
+                        if let (Some(cache), true) = (&cache, args.diff) {
+                            cache.compare(&query_params, &mut result);
+                        }
+                        convert_prices(&mut result, rates.as_ref(), &args);
+                        if let Some(mins) = min_connection {
+                            risk::annotate(&mut result, mins);
+                        }
+                        if let Some(bag) = bag {
+                            bags::annotate(&mut result, bag, &args.currency);
+                        }
+                        if args.exclude_basic {
+                            filter::apply_exclude_basic(&mut result);
+                        }
+                        if args.dedupe_codeshares {
+                            filter::dedupe_codeshares(&mut result);
+                        }
+                        if args.best_only {
+                            filter::apply_best_only(&mut result);
+                        }
+                        ranker.score(&mut result);
+                        if args.pareto {
+                            pareto::retain(&mut result, args.pareto_co2);
+                        }
+                        order_results(&mut result, sort, args.top);
+                        let returns = match args.with_returns {
+                            Some(n) => {
+                                let Some(outbound) = result.flights.get(n as usize - 1) else {
+                                    let e = FlightError::Validation(format!(
+                                        "there is no outbound result {n} — the search \
+                                         returned {} flights",
+                                        result.flights.len()
+                                    ));
+                                    die(&e, json_mode);
+                                };
+                                let outcome = flyr::search_returns(
+                                    &fetcher,
+                                    &query_params,
+                                    outbound,
+                                    parse_options,
+                                )
+                                .await;
+                                let mut returns = match outcome {
+                                    Ok(r) => r,
+                                    Err(e) => die(&e, json_mode),
+                                };
+                                convert_prices(&mut returns, rates.as_ref(), &args);
+                                if let Some(mins) = min_connection {
+                                    risk::annotate(&mut returns, mins);
+                                }
+                                if let Some(bag) = bag {
+                                    bags::annotate(&mut returns, bag, &args.currency);
+                                }
+                                if args.exclude_basic {
+                                    filter::apply_exclude_basic(&mut returns);
+                                }
+                                if args.dedupe_codeshares {
+                                    filter::dedupe_codeshares(&mut returns);
+                                }
+                                if args.best_only {
+                                    filter::apply_best_only(&mut returns);
+                                }
+                                ranker.score(&mut returns);
+                                order_results(&mut returns, sort, args.top);
+                                Some((n, flight_label(outbound), returns))
+                            }
+                            None => None,
+                        };
+                        if json_document(&args) {
+                            result.warnings.extend(take_warnings());
+                        } else {
+                            print_warnings(&result, None);
+                        }
+                        if args.timings {
+                            let leg = &query_params.legs[0];
+                            print_timings(
+                                &store::route_key(&leg.from_airport, &leg.to_airport),
+                                &result,
+                            );
+                        }
+                        match &returns {
+                            Some((n, _, returns)) if json_document(&args) => print_json(
+                                ReturnsDoc {
+                                    outbound: &result,
+                                    selected: *n,
+                                    returns,
+                                },
+                                json_output(&args),
+                            ),
+                            Some((n, label, returns)) => {
+                                print_result(&result, &args);
+                                print_warnings(returns, Some("returns"));
+                                println!("\nReturn flights with outbound #{n} ({label}):");
+                                print_result(returns, &args);
+                            }
+                            None => print_result(&result, &args),
+                        }
+                        print_alert_summary(&result, &args);
+                        if args.fail_on_empty && result.flights.is_empty() {
+                            process::exit(exit_code("empty", EXIT_EMPTY));
+                        }
+                        if alert_count(&result, &args) > 0 {
+                            process::exit(exit_code("alert", EXIT_ALERT));
                         }
-                        print_result(&result, &args);
                     }
                     Err(e) => die(&e, json_mode),
                 }
@@ -1,4 +1,5 @@
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 use rmcp::handler::server::tool::ToolRouter;
 use rmcp::handler::server::wrapper::Parameters;
@@ -8,18 +9,28 @@ use rmcp::{tool, tool_handler, tool_router, ErrorData as McpError, ServerHandler
 use serde::Deserialize;
 use tokio::task::JoinSet;
 
-use crate::fetch::FetchOptions;
-use crate::model::SearchResult;
+use crate::airports;
+use crate::breaker::Guarded;
+use crate::coalesce::Coalescer;
+use crate::config::Config;
+use crate::dns::DnsCache;
+use crate::fetch::{FetchOptions, SessionPool};
+use crate::filter;
+use crate::limit::Limited;
+use crate::error::{ErrorInfo, FlightError};
+use crate::model::{DestinationOutcome, MultiSearch, SearchResult, Versioned};
+use crate::parse::ParseOptions;
 use crate::query::{FlightLeg, Passengers, QueryParams, Seat, SearchQuery, TripType};
+use crate::value::Ranker;
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 struct SearchArgs {
     #[schemars(
-        description = "Departure airport IATA code, exactly 3 uppercase letters. Example: HEL, JFK, LAX"
+        description = "Departure airport IATA code or city name. Example: HEL, JFK, Helsinki. An ambiguous name returns an error listing the matching codes"
     )]
     from: String,
     #[schemars(
-        description = "Arrival airport IATA code(s). Comma-separate for multi-destination. Examples: BCN or BCN,ATH,AYT"
+        description = "Arrival airport IATA code(s) or city name(s). Comma-separate for multi-destination. Examples: BCN or BCN,ATH,AYT or Barcelona,Athens"
     )]
     to: String,
     #[schemars(description = "Departure date in YYYY-MM-DD format. Example: 2026-03-01")]
@@ -32,6 +43,10 @@ struct SearchArgs {
         description = "One of: economy, premium-economy, business, first. Default: economy"
     )]
     seat: Option<String>,
+    #[schemars(
+        description = "Cabin for the return flight when it differs from seat. Each direction is then searched as a one-way ticket and prices add the cheapest return fare. One of: economy, premium-economy, business, first"
+    )]
+    return_seat: Option<String>,
     #[schemars(description = "Maximum stops. 0 = nonstop only. Omit for any number of stops")]
     max_stops: Option<u32>,
     #[schemars(description = "Filter airlines by IATA code, comma-separated. Example: AY,IB")]
@@ -46,18 +61,50 @@ struct SearchArgs {
     infants_on_lap: Option<u32>,
     #[schemars(description = "Currency code. Examples: USD, EUR, JPY. Default: USD")]
     currency: Option<String>,
-    #[schemars(description = "Return only N cheapest results")]
+    #[schemars(
+        description = "Point-of-sale country code (Google's gl parameter); fares can differ by market. Example: DE, US. Default: none"
+    )]
+    region: Option<String>,
+    #[schemars(
+        description = "Only return fares at or below this price (in currency). Applied to the results Google returns, so it doesn't bring back more fares than an unfiltered search"
+    )]
+    max_price: Option<i64>,
+    #[schemars(
+        description = "Only return flights whose CO2 emissions are at or below the route's typical emissions. Default: false"
+    )]
+    low_emissions: Option<bool>,
+    #[schemars(
+        description = "Drop red-eye itineraries, i.e. those departing between 22:00 and 05:00 local time. Default: false"
+    )]
+    no_redeye: Option<bool>,
+    #[schemars(
+        description = "Only return the itineraries Google lists as best flights; every flight carries category best or other. Default: false"
+    )]
+    best_only: Option<bool>,
+    #[schemars(
+        description = "List a flight sold under several airline codes once, at its cheapest; the other codes are kept in each segment's codeshares. Default: false"
+    )]
+    dedupe_codeshares: Option<bool>,
+    #[schemars(
+        description = "Drop basic fares (sold as basic economy or without a full-size carry-on); every stripped-down fare carries fare_brand basic or hand_baggage_only. Default: false"
+    )]
+    exclude_basic: Option<bool>,
+    #[schemars(description = "Return only N cheapest results (or N best ranked, with rank)")]
     top: Option<usize>,
+    #[schemars(
+        description = "Rank by value score instead of price, with weights for price, duration and stops; omitted factors count for nothing. Example: price=0.6,duration=0.3,stops=0.1. Default: the rank in the flyr config file, if any"
+    )]
+    rank: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
 struct GetUrlArgs {
     #[schemars(
-        description = "Departure airport IATA code, exactly 3 uppercase letters. Example: HEL, JFK, LAX"
+        description = "Departure airport IATA code or city name. Example: HEL, JFK, Helsinki. An ambiguous name returns an error listing the matching codes"
     )]
     from: String,
     #[schemars(
-        description = "Arrival airport IATA code(s). Comma-separate for multi-destination. Examples: BCN or BCN,ATH,AYT"
+        description = "Arrival airport IATA code(s) or city name(s). Comma-separate for multi-destination. Examples: BCN or BCN,ATH,AYT or Barcelona,Athens"
     )]
     to: String,
     #[schemars(description = "Departure date in YYYY-MM-DD format. Example: 2026-03-01")]
@@ -74,6 +121,24 @@ struct GetUrlArgs {
     adults: Option<u32>,
     #[schemars(description = "Currency code. Examples: USD, EUR, JPY. Default: USD")]
     currency: Option<String>,
+    #[schemars(
+        description = "Point-of-sale country code (Google's gl parameter); fares can differ by market. Example: DE, US. Default: none"
+    )]
+    region: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct SearchTextArgs {
+    #[schemars(
+        description = "Free-text flight search, as typed into Google Flights. Example: cheap flights from Helsinki to Tokyo in March"
+    )]
+    query: String,
+    #[schemars(description = "Return only N cheapest results (or N best ranked, with rank)")]
+    top: Option<usize>,
+    #[schemars(
+        description = "Rank by value score instead of price, with weights for price, duration and stops. Example: price=0.6,duration=0.3,stops=0.1"
+    )]
+    rank: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -99,6 +164,7 @@ fn parse_legs(
         to_airport: to.to_uppercase(),
         max_stops,
         airlines: parsed_airlines.clone(),
+        ..FlightLeg::default()
     }];
 
     let trip = if let Some(ret) = return_date {
@@ -108,6 +174,7 @@ fn parse_legs(
             to_airport: from.to_uppercase(),
             max_stops,
             airlines: parsed_airlines,
+            ..FlightLeg::default()
         });
         TripType::RoundTrip
     } else {
@@ -117,6 +184,12 @@ fn parse_legs(
     (legs, trip)
 }
 
+fn resolve_places(from: &mut String, to: &mut String) -> Result<(), FlightError> {
+    *from = airports::resolve(from)?;
+    *to = airports::resolve_list(to)?;
+    Ok(())
+}
+
 fn tool_error(msg: impl Into<String>) -> Result<CallToolResult, McpError> {
     Ok(CallToolResult::error(vec![Content::text(msg.into())]))
 }
@@ -128,27 +201,55 @@ fn apply_top(result: &mut SearchResult, n: usize) {
     result.flights.truncate(n);
 }
 
+fn order(result: &mut SearchResult, ranker: Option<&Ranker>, top: Option<usize>) {
+    match (ranker, top) {
+        (Some(ranker), top) => {
+            ranker.rank(result);
+            if let Some(n) = top {
+                result.flights.truncate(n);
+            }
+        }
+        (None, Some(n)) => apply_top(result, n),
+        (None, None) => {}
+    }
+}
+
+const MCP_POOL_SIZE: usize = 4;
+
 #[derive(Debug, Clone)]
 struct FlyrMcp {
     tool_router: ToolRouter<Self>,
+    pool: SessionPool,
+    coalescer: Arc<Coalescer>,
+    ranker: Option<Ranker>,
 }
 
 #[tool_router]
 impl FlyrMcp {
-    fn new() -> Self {
+    fn new(pool: SessionPool, ranker: Option<Ranker>) -> Self {
         Self {
             tool_router: Self::tool_router(),
+            pool,
+            coalescer: Arc::new(Coalescer::new()),
+            ranker,
         }
     }
 
     #[tool(
-        description = "Search for flights and return results as JSON. Searches Google Flights for available flights between airports on specific dates. Returns flight options with prices, airlines, duration, stops, and schedule. Comma-separate 'to' for multi-destination comparison. To open results in browser: call flyr_get_url with the same parameters, then call open_url with the returned URL."
+        description = "Search for flights and return results as JSON. Searches Google Flights for available flights between airports on specific dates. Returns flight options with prices, airlines, duration, stops, and schedule. A flight with \"ticketing\" set (\"separate_tickets\" or \"self_transfer\") is sold as separate bookings: a missed connection is not covered, and its \"warnings\" say where; mention this when recommending it. Comma-separate 'to' for multi-destination comparison; the result then maps each destination to {\"ok\": <result>} or {\"error\": {\"kind\", \"message\"}}. To open results in browser: call flyr_get_url with the same parameters, then call open_url with the returned URL."
     )]
     async fn flyr_search(
         &self,
-        Parameters(args): Parameters<SearchArgs>,
+        Parameters(mut args): Parameters<SearchArgs>,
     ) -> Result<CallToolResult, McpError> {
+        if let Err(e) = resolve_places(&mut args.from, &mut args.to) {
+            return tool_error(e.to_string());
+        }
         let is_multi = args.to.contains(',');
+        let ranker = match args.rank.as_deref().map(Ranker::parse).transpose() {
+            Ok(r) => r.or(self.ranker),
+            Err(e) => return tool_error(e.to_string()),
+        };
 
         if is_multi {
             let from = args.from.to_uppercase();
@@ -163,6 +264,15 @@ impl FlyrMcp {
                 Ok(s) => s.unwrap_or(Seat::Economy),
                 Err(e) => return tool_error(e.to_string()),
             };
+            let return_seat = match args
+                .return_seat
+                .as_deref()
+                .map(Seat::from_str_loose)
+                .transpose()
+            {
+                Ok(s) => s,
+                Err(e) => return tool_error(e.to_string()),
+            };
 
             let passengers = Passengers {
                 adults: args.adults.unwrap_or(1),
@@ -185,6 +295,7 @@ impl FlyrMcp {
                 .filter(|s| !s.is_empty())
                 .collect();
 
+            let fetcher = Limited::with_defaults(Guarded::with_defaults(self.pool.for_fan_out()));
             let mut join_set = JoinSet::new();
 
             for dest in &destinations {
@@ -194,6 +305,8 @@ impl FlyrMcp {
                     to_airport: dest.clone(),
                     max_stops: args.max_stops,
                     airlines: airlines.clone(),
+                    no_redeye: args.no_redeye.unwrap_or(false),
+                    ..FlightLeg::default()
                 }];
 
                 let trip = if let Some(ref ret) = args.return_date {
@@ -203,6 +316,9 @@ impl FlyrMcp {
                         to_airport: from.clone(),
                         max_stops: args.max_stops,
                         airlines: airlines.clone(),
+                        seat: return_seat.clone(),
+                        no_redeye: args.no_redeye.unwrap_or(false),
+                        ..FlightLeg::default()
                     });
                     TripType::RoundTrip
                 } else {
@@ -216,43 +332,61 @@ impl FlyrMcp {
                     trip,
                     language: "en".into(),
                     currency: currency.clone(),
+                    region: args.region.clone().unwrap_or_default(),
+                    max_price: args.max_price,
+                    low_emissions: args.low_emissions.unwrap_or(false),
                 };
 
                 if let Err(e) = params.validate() {
                     return tool_error(format!("{dest}: {e}"));
                 }
 
+                let fetcher = fetcher.clone();
+                let coalescer = self.coalescer.clone();
                 let dest_code = dest.clone();
                 let top = args.top;
                 join_set.spawn(async move {
-                    let result =
-                        crate::search(SearchQuery::Structured(params), FetchOptions::default())
-                            .await;
+                    let result = coalescer.search(
+                        &fetcher,
+                        SearchQuery::Structured(params),
+                        ParseOptions::default(),
+                    )
+                    .await;
                     (dest_code, result, top)
                 });
             }
 
-            let mut results: BTreeMap<String, SearchResult> = BTreeMap::new();
+            let mut results: BTreeMap<String, DestinationOutcome> = BTreeMap::new();
             while let Some(join_result) = join_set.join_next().await {
                 let (dest_code, search_result, top) = join_result.unwrap();
                 match search_result {
                     Ok(mut result) => {
-                        if let Some(n) = top {
-                            apply_top(&mut result, n);
+                        if args.exclude_basic.unwrap_or(false) {
+                            filter::apply_exclude_basic(&mut result);
+                        }
+                        if args.dedupe_codeshares.unwrap_or(false) {
+                            filter::dedupe_codeshares(&mut result);
+                        }
+                        if args.best_only.unwrap_or(false) {
+                            filter::apply_best_only(&mut result);
                         }
-                        results.insert(dest_code, result);
+                        order(&mut result, ranker.as_ref(), top);
+                        results.insert(dest_code, DestinationOutcome::Ok(Box::new(result)));
                     }
                     Err(e) => {
-                        results.insert(dest_code.clone(), SearchResult::default());
-                        eprintln!("warning: {dest_code}: {e}");
+                        results.insert(dest_code, DestinationOutcome::Error(ErrorInfo::from(&e)));
                     }
                 }
             }
 
-            let json = serde_json::to_string_pretty(&results).unwrap();
+            let doc = Versioned::new(MultiSearch {
+                destinations: &results,
+                warnings: &[],
+            });
+            let json = serde_json::to_string_pretty(&doc).unwrap();
             Ok(CallToolResult::success(vec![Content::text(json)]))
         } else {
-            let (legs, trip) = parse_legs(
+            let (mut legs, trip) = parse_legs(
                 &args.from,
                 &args.to,
                 &args.date,
@@ -270,6 +404,22 @@ impl FlyrMcp {
                 Ok(s) => s.unwrap_or(Seat::Economy),
                 Err(e) => return tool_error(e.to_string()),
             };
+            match args
+                .return_seat
+                .as_deref()
+                .map(Seat::from_str_loose)
+                .transpose()
+            {
+                Ok(return_seat) => {
+                    if let Some(ret) = legs.get_mut(1) {
+                        ret.seat = return_seat;
+                    }
+                }
+                Err(e) => return tool_error(e.to_string()),
+            }
+            for leg in &mut legs {
+                leg.no_redeye = args.no_redeye.unwrap_or(false);
+            }
 
             let passengers = Passengers {
                 adults: args.adults.unwrap_or(1),
@@ -287,18 +437,34 @@ impl FlyrMcp {
                 trip,
                 language: "en".into(),
                 currency,
+                region: args.region.clone().unwrap_or_default(),
+                max_price: args.max_price,
+                low_emissions: args.low_emissions.unwrap_or(false),
             };
 
             if let Err(e) = params.validate() {
                 return tool_error(e.to_string());
             }
 
-            match crate::search(SearchQuery::Structured(params), FetchOptions::default()).await {
+            match self.coalescer.search(
+                &self.pool,
+                SearchQuery::Structured(params),
+                ParseOptions::default(),
+            )
+            .await
+            {
                 Ok(mut result) => {
-                    if let Some(n) = args.top {
-                        apply_top(&mut result, n);
+                    if args.exclude_basic.unwrap_or(false) {
+                        filter::apply_exclude_basic(&mut result);
                     }
-                    let json = serde_json::to_string_pretty(&result).unwrap();
+                    if args.dedupe_codeshares.unwrap_or(false) {
+                        filter::dedupe_codeshares(&mut result);
+                    }
+                    if args.best_only.unwrap_or(false) {
+                        filter::apply_best_only(&mut result);
+                    }
+                    order(&mut result, ranker.as_ref(), args.top);
+                    let json = serde_json::to_string_pretty(&Versioned::new(&result)).unwrap();
                     Ok(CallToolResult::success(vec![Content::text(json)]))
                 }
                 Err(e) => tool_error(e.to_string()),
@@ -306,13 +472,50 @@ impl FlyrMcp {
         }
     }
 
+    #[tool(
+        description = "Search for flights with a free-text query, the way a person types into Google Flights (e.g. \"cheap flights from Helsinki to Tokyo in March\"). Google interprets the text, so dates and airports may be chosen for you; prefer flyr_search when you know the exact airports and dates. Returns the same JSON as flyr_search."
+    )]
+    async fn flyr_search_text(
+        &self,
+        Parameters(args): Parameters<SearchTextArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        let query = args.query.trim();
+        if query.is_empty() {
+            return tool_error("query must not be empty");
+        }
+        let ranker = match args.rank.as_deref().map(Ranker::parse).transpose() {
+            Ok(r) => r.or(self.ranker),
+            Err(e) => return tool_error(e.to_string()),
+        };
+
+        match self
+            .coalescer
+            .search(
+                &self.pool,
+                SearchQuery::NaturalLanguage(query.to_string()),
+                ParseOptions::default(),
+            )
+            .await
+        {
+            Ok(mut result) => {
+                order(&mut result, ranker.as_ref(), args.top);
+                let json = serde_json::to_string_pretty(&Versioned::new(&result)).unwrap();
+                Ok(CallToolResult::success(vec![Content::text(json)]))
+            }
+            Err(e) => tool_error(e.to_string()),
+        }
+    }
+
     #[tool(
         description = "Generate a Google Flights URL for the given search parameters. This is the ONLY way to get a valid Google Flights URL. Returns an encoded URL that can be opened in a browser with open_url. NEVER construct Google Flights URLs manually -- always use this tool."
     )]
     async fn flyr_get_url(
         &self,
-        Parameters(args): Parameters<GetUrlArgs>,
+        Parameters(mut args): Parameters<GetUrlArgs>,
     ) -> Result<CallToolResult, McpError> {
+        if let Err(e) = resolve_places(&mut args.from, &mut args.to) {
+            return tool_error(e.to_string());
+        }
         let is_multi = args.to.contains(',');
 
         if is_multi {
@@ -346,8 +549,7 @@ impl FlyrMcp {
                     date: args.date.clone(),
                     from_airport: args.from.to_uppercase(),
                     to_airport: dest.clone(),
-                    max_stops: None,
-                    airlines: None,
+                    ..FlightLeg::default()
                 }];
 
                 let trip = if let Some(ref ret) = args.return_date {
@@ -355,8 +557,7 @@ impl FlyrMcp {
                         date: ret.clone(),
                         from_airport: dest.clone(),
                         to_airport: args.from.to_uppercase(),
-                        max_stops: None,
-                        airlines: None,
+                        ..FlightLeg::default()
                     });
                     TripType::RoundTrip
                 } else {
@@ -370,6 +571,9 @@ impl FlyrMcp {
                     trip,
                     language: "en".into(),
                     currency: currency.clone(),
+                    region: args.region.clone().unwrap_or_default(),
+                    max_price: None,
+                    low_emissions: false,
                 };
 
                 if let Err(e) = params.validate() {
@@ -416,6 +620,9 @@ impl FlyrMcp {
                 trip,
                 language: "en".into(),
                 currency,
+                region: args.region.clone().unwrap_or_default(),
+                max_price: None,
+                low_emissions: false,
             };
 
             if let Err(e) = params.validate() {
@@ -458,14 +665,30 @@ impl ServerHandler for FlyrMcp {
                 ..Default::default()
             },
             instructions: Some(
-                "Flight search tool. Workflow: (1) flyr_search to find flights. (2) To open in browser: call flyr_get_url with same params to get URL, then call open_url with that URL. NEVER construct Google Flights URLs yourself -- they require special protobuf encoding.".into(),
+                "Flight search tool. Workflow: (1) flyr_search to find flights (flyr_search_text for free-text queries). (2) To open in browser: call flyr_get_url with same params to get URL, then call open_url with that URL. NEVER construct Google Flights URLs yourself -- they require special protobuf encoding.".into(),
             ),
         }
     }
 }
 
 pub async fn run() {
-    let service = FlyrMcp::new()
+    let pool = SessionPool::new(
+        MCP_POOL_SIZE,
+        FetchOptions {
+            resolver: Some(DnsCache::default()),
+            ..FetchOptions::default()
+        },
+    );
+
+    let ranker = match Config::load().and_then(|c| c.ranker()) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("warning: {e}");
+            None
+        }
+    };
+
+    let service = FlyrMcp::new(pool, ranker)
         .serve(rmcp::transport::stdio())
         .await
         .expect("failed to start MCP server");
@@ -508,4 +731,15 @@ mod tests {
             Some(vec!["AY".to_string(), "IB".to_string()])
         );
     }
+
+    #[test]
+    fn order_ranks_before_truncating() {
+        let html = crate::testing::fixtures::ONE_WAY_HEL_BCN;
+        let mut result = crate::parse::parse_html(html).unwrap();
+        let ranker = Ranker::parse("price=0,duration=1,stops=1").unwrap();
+        order(&mut result, Some(&ranker), Some(1));
+        assert_eq!(result.flights.len(), 1);
+        assert_eq!(result.flights[0].value_score, Some(100));
+        assert_eq!(result.flights[0].airlines, vec!["Norwegian"]);
+    }
 }
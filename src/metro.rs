@@ -0,0 +1,59 @@
+const METROS: [(&str, &[&str]); 19] = [
+    ("NYC", &["JFK", "EWR", "LGA"]),
+    ("WAS", &["IAD", "DCA", "BWI"]),
+    ("CHI", &["ORD", "MDW"]),
+    ("YTO", &["YYZ", "YTZ"]),
+    ("SAO", &["GRU", "CGH", "VCP"]),
+    ("RIO", &["GIG", "SDU"]),
+    ("BUE", &["EZE", "AEP"]),
+    ("LON", &["LHR", "LGW", "STN", "LTN", "LCY", "SEN"]),
+    ("PAR", &["CDG", "ORY", "BVA"]),
+    ("MIL", &["MXP", "LIN", "BGY"]),
+    ("ROM", &["FCO", "CIA"]),
+    ("STO", &["ARN", "BMA", "NYO"]),
+    ("MOW", &["SVO", "DME", "VKO"]),
+    ("REK", &["KEF", "RKV"]),
+    ("TYO", &["HND", "NRT"]),
+    ("OSA", &["KIX", "ITM", "UKB"]),
+    ("SEL", &["ICN", "GMP"]),
+    ("BJS", &["PEK", "PKX"]),
+    ("JKT", &["CGK", "HLP"]),
+];
+
+pub fn airports(code: &str) -> Option<&'static [&'static str]> {
+    METROS
+        .iter()
+        .find(|(metro, _)| metro.eq_ignore_ascii_case(code))
+        .map(|&(_, airports)| airports)
+}
+
+pub fn is_metro(code: &str) -> bool {
+    airports(code).is_some()
+}
+
+pub fn expand(code: &str) -> Vec<&str> {
+    airports(code).map_or_else(|| vec![code], |a| a.to_vec())
+}
+
+pub fn metro_for(airports: &[String]) -> Option<&'static str> {
+    METROS
+        .iter()
+        .find(|(_, members)| {
+            members.len() == airports.len()
+                && members
+                    .iter()
+                    .all(|m| airports.iter().any(|a| a.eq_ignore_ascii_case(m)))
+        })
+        .map(|&(metro, _)| metro)
+}
+
+pub fn covering(airports: &[&str]) -> Option<&'static str> {
+    METROS
+        .iter()
+        .find(|(_, members)| {
+            airports
+                .iter()
+                .all(|a| members.iter().any(|m| m.eq_ignore_ascii_case(a)))
+        })
+        .map(|&(metro, _)| metro)
+}
@@ -1,12 +1,19 @@
-use serde::Serialize;
+use std::collections::BTreeMap;
+use std::time::Duration;
 
-#[derive(Debug, Clone, Serialize)]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ErrorInfo;
+use crate::query::SearchQuery;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Airport {
     pub code: String,
     pub name: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FlightDateTime {
     pub year: u32,
     pub month: u32,
@@ -15,6 +22,43 @@ pub struct FlightDateTime {
     pub minute: u32,
 }
 
+impl FlightDateTime {
+    pub fn days_since_epoch(&self) -> i64 {
+        let (y, m, d) = (self.year as i64, self.month as i64, self.day as i64);
+        let y = if m <= 2 { y - 1 } else { y };
+        let era = y.div_euclid(400);
+        let yoe = y - era * 400;
+        let mp = (m + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + d - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    pub fn minutes_since_epoch(&self) -> i64 {
+        self.days_since_epoch() * 1440 + self.hour as i64 * 60 + self.minute as i64
+    }
+
+    pub fn from_minutes_since_epoch(minutes: i64) -> Self {
+        let z = minutes.div_euclid(1440) + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+        let time = minutes.rem_euclid(1440);
+        Self {
+            year: year as u32,
+            month: month as u32,
+            day: day as u32,
+            hour: (time / 60) as u32,
+            minute: (time % 60) as u32,
+        }
+    }
+}
+
 impl std::fmt::Display for FlightDateTime {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -25,51 +69,499 @@ impl std::fmt::Display for FlightDateTime {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Segment {
     pub from_airport: Airport,
     pub to_airport: Airport,
     pub departure: FlightDateTime,
     pub arrival: FlightDateTime,
     pub duration_minutes: u32,
+    #[serde(default)]
+    pub kind: SegmentKind,
     pub aircraft: Option<String>,
+    pub airline: Option<String>,
+    pub flight_number: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub airline_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operated_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legroom: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub amenities: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub departure_terminal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrival_terminal: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub codeshares: Vec<Codeshare>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub departure_utc_offset_minutes: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrival_utc_offset_minutes: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub departure_iso: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrival_iso: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+// Some itineraries include rail or coach legs, sold by the airline like a flight
+// ("rail & fly") or combined by Google across modes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentKind {
+    #[default]
+    Flight,
+    Train,
+    Bus,
+}
+
+impl SegmentKind {
+    pub fn is_ground(self) -> bool {
+        self != Self::Flight
+    }
+}
+
+impl std::fmt::Display for SegmentKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Flight => write!(f, "flight"),
+            Self::Train => write!(f, "train"),
+            Self::Bus => write!(f, "bus"),
+        }
+    }
+}
+
+// Another airline's flight number for the same physical flight.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Codeshare {
+    pub airline: String,
+    pub flight_number: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub airline_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CarbonEmission {
     pub emission_grams: Option<i64>,
     pub typical_grams: Option<i64>,
+    // Emissions relative to the route's typical itinerary, in whole percent.
+    #[serde(default)]
+    pub delta_percent: Option<i64>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+impl CarbonEmission {
+    pub fn new(emission_grams: Option<i64>, typical_grams: Option<i64>) -> Self {
+        let mut carbon = Self {
+            emission_grams,
+            typical_grams,
+            delta_percent: None,
+        };
+        carbon.delta_percent = crate::carbon::delta_percent(&carbon);
+        carbon
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Risk {
+    #[default]
+    Low,
+    Medium,
+    High,
+}
+
+impl std::fmt::Display for Risk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::Medium => write!(f, "medium"),
+            Self::High => write!(f, "high"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceScope {
+    Total,
+    Outbound,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct FlightResult {
     pub flight_type: String,
     pub airlines: Vec<String>,
     pub segments: Vec<Segment>,
     pub price: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fare: Option<Money>,
     pub carbon: CarbonEmission,
+    pub arrival_day_offset: i32,
+    pub air_minutes: u32,
+    pub elapsed_minutes: u32,
+    pub risk: Risk,
+    pub price_scope: Option<PriceScope>,
+    #[serde(default)]
+    pub value_score: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_with_bag: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_price: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delta: Option<i64>,
+    #[serde(default)]
+    pub layovers: Vec<Layover>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_price: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<Category>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baggage: Option<BagAllowance>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ticketing: Option<Ticketing>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fare_brand: Option<FareBrand>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<Warning>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+// An exact amount in the currency's minor unit (fils for BHD, cents for USD, yen for
+// JPY). `FlightResult.price` stays in whole units for sorting and older consumers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Money {
+    pub amount_minor: i64,
+    pub currency: String,
+}
+
+// ISO 4217 minor-unit exponent; everything not listed has two decimals.
+pub fn minor_digits(currency: &str) -> u32 {
+    match currency.to_uppercase().as_str() {
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        "CLP" | "ISK" | "JPY" | "KRW" | "PYG" | "UGX" | "VND" | "XAF" | "XOF" => 0,
+        _ => 2,
+    }
+}
+
+impl Money {
+    pub fn from_major(amount: i64, currency: &str) -> Self {
+        let currency = currency.to_uppercase();
+        Self {
+            amount_minor: amount * 10i64.pow(minor_digits(&currency)),
+            currency,
+        }
+    }
+
+    pub fn from_major_f64(amount: f64, currency: &str) -> Self {
+        let currency = currency.to_uppercase();
+        let scale = 10f64.powi(minor_digits(&currency) as i32);
+        Self {
+            amount_minor: (amount * scale).round() as i64,
+            currency,
+        }
+    }
+
+    pub fn digits(&self) -> u32 {
+        minor_digits(&self.currency)
+    }
+
+    pub fn major(&self) -> f64 {
+        self.amount_minor as f64 / 10f64.powi(self.digits() as i32)
+    }
+
+    // Rounded to the nearest whole unit, the scale `price` uses.
+    pub fn whole(&self) -> i64 {
+        let scale = 10i64.pow(self.digits());
+        (self.amount_minor + scale / 2).div_euclid(scale)
+    }
+
+    pub fn is_whole(&self) -> bool {
+        self.amount_minor % 10i64.pow(self.digits()) == 0
+    }
+}
+
+// Itineraries Google assembles from more than one booking. With separate tickets no
+// airline rebooks a missed connection; a self-transfer also means collecting bags and
+// checking in again between flights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Ticketing {
+    SeparateTickets,
+    SelfTransfer,
+}
+
+impl std::fmt::Display for Ticketing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SeparateTickets => write!(f, "separate tickets"),
+            Self::SelfTransfer => write!(f, "self-transfer"),
+        }
+    }
+}
+
+// Stripped-down fares. Basic fares leave out a full-size carry-on (and usually seat
+// choice and changes); hand-baggage-only fares include a carry-on but no checked bag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FareBrand {
+    Basic,
+    HandBaggageOnly,
+}
+
+impl std::fmt::Display for FareBrand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Basic => write!(f, "basic fare"),
+            Self::HandBaggageOnly => write!(f, "hand baggage only"),
+        }
+    }
+}
+
+// Bags included in the fare, per passenger; None where Google doesn't say.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct BagAllowance {
+    pub carry_on: Option<u32>,
+    pub checked: Option<u32>,
+}
+
+// Google's split between the itineraries it recommends and the rest of the list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Best,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Layover {
+    pub airport: String,
+    pub minutes: u32,
+}
+
+impl FlightResult {
+    pub fn effective_price(&self) -> Option<i64> {
+        self.price_with_bag.or(self.price)
+    }
+
+    pub fn often_delayed(&self) -> bool {
+        self.warnings.iter().any(|w| w.kind == "often_delayed")
+    }
+
+    pub fn route(&self) -> Vec<String> {
+        self.segments
+            .first()
+            .map(|s| s.from_airport.code.clone())
+            .into_iter()
+            .chain(self.segments.iter().map(|s| s.to_airport.code.clone()))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Airline {
     pub code: String,
     pub name: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Alliance {
     pub code: String,
     pub name: String,
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct SearchMetadata {
     pub airlines: Vec<Airline>,
     pub alliances: Vec<Alliance>,
+    pub currency: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_currency: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_insights: Option<PriceInsights>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceLevel {
+    Low,
+    Typical,
+    High,
+}
+
+impl std::fmt::Display for PriceLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Low => write!(f, "low"),
+            Self::Typical => write!(f, "typical"),
+            Self::High => write!(f, "high"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct PriceInsights {
+    pub current_level: Option<PriceLevel>,
+    pub typical_low: Option<i64>,
+    pub typical_high: Option<i64>,
 }
 
-#[derive(Debug, Clone, Default, Serialize)]
+impl PriceInsights {
+    pub fn level_for(&self, price: i64) -> Option<PriceLevel> {
+        let (low, high) = (self.typical_low?, self.typical_high?);
+        Some(if price < low {
+            PriceLevel::Low
+        } else if price > high {
+            PriceLevel::High
+        } else {
+            PriceLevel::Typical
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Warning {
+    pub kind: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+}
+
+impl Warning {
+    pub fn new(kind: &str, message: impl Into<String>) -> Self {
+        Self {
+            kind: kind.to_string(),
+            message: message.into(),
+            destination: None,
+        }
+    }
+
+    pub fn for_destination(mut self, destination: &str) -> Self {
+        self.destination = Some(destination.to_string());
+        self
+    }
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.destination {
+            Some(dest) => write!(f, "{dest}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<SearchQuery>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub searched_at: Option<String>,
     pub flights: Vec<FlightResult>,
     pub metadata: SearchMetadata,
+    #[serde(default)]
+    pub warnings: Vec<Warning>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline: Option<Baseline>,
+    #[serde(skip)]
+    pub timings: Option<Timings>,
+}
+
+impl SearchResult {
+    // Fills `fare` from `price` where it's missing: freshly parsed flights, and results
+    // saved before fares were stored. Google's currency wins over `fallback`.
+    pub fn complete_fares(&mut self, fallback: &str) {
+        let currency = match self.metadata.currency.as_deref() {
+            Some(c) => c.to_string(),
+            None if !fallback.is_empty() => fallback.to_string(),
+            None => return,
+        };
+        for flight in &mut self.flights {
+            if flight.fare.is_none() {
+                flight.fare = flight.price.map(|p| Money::from_major(p, &currency));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Baseline {
+    pub searched_at: String,
+    pub age_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DestinationOutcome {
+    Ok(Box<SearchResult>),
+    Error(ErrorInfo),
+}
+
+impl DestinationOutcome {
+    pub fn legacy(&self) -> SearchResult {
+        match self {
+            Self::Ok(result) => result.as_ref().clone(),
+            Self::Error(_) => SearchResult::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct Versioned<T> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub body: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn new(body: T) -> Self {
+        Self {
+            schema_version: crate::SCHEMA_VERSION,
+            body,
+        }
+    }
+}
+
+// JSON Schema for `flyr search --json` output, pinned to the current schema_version so
+// a document from another version fails validation instead of half-matching.
+pub fn search_result_schema() -> serde_json::Value {
+    let mut schema = schemars::schema_for!(Versioned<SearchResult>).to_value();
+    schema["title"] = "SearchResult".into();
+    schema["properties"]["schema_version"]["const"] = crate::SCHEMA_VERSION.into();
+    schema
+}
+
+#[derive(Debug, Serialize)]
+pub struct MultiSearch<'a> {
+    pub destinations: &'a BTreeMap<String, DestinationOutcome>,
+    pub warnings: &'a [Warning],
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timings {
+    pub fetch: Duration,
+    pub extract: Duration,
+    pub json: Duration,
+    pub build: Duration,
+}
+
+impl Timings {
+    pub fn total(&self) -> Duration {
+        self.fetch + self.extract + self.json + self.build
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CalendarDay {
+    pub date: String,
+    pub return_date: Option<String>,
+    pub price: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BookingOption {
+    pub provider: String,
+    pub provider_code: Option<String>,
+    pub airline_direct: bool,
+    pub price: Option<i64>,
+    pub url: Option<String>,
 }
@@ -0,0 +1,38 @@
+use crate::model::{FlightResult, SearchResult};
+
+fn costs(flight: &FlightResult, emissions: bool) -> [i64; 3] {
+    [
+        flight.effective_price().unwrap_or(i64::MAX),
+        i64::from(flight.elapsed_minutes),
+        if emissions {
+            flight.carbon.emission_grams.unwrap_or(i64::MAX)
+        } else {
+            0
+        },
+    ]
+}
+
+pub fn dominates(a: &FlightResult, b: &FlightResult, emissions: bool) -> bool {
+    let (a, b) = (costs(a, emissions), costs(b, emissions));
+    a.iter().zip(&b).all(|(x, y)| x <= y) && a != b
+}
+
+pub fn frontier(flights: &[FlightResult], emissions: bool) -> Vec<usize> {
+    (0..flights.len())
+        .filter(|&i| {
+            !flights
+                .iter()
+                .any(|other| dominates(other, &flights[i], emissions))
+        })
+        .collect()
+}
+
+pub fn retain(result: &mut SearchResult, emissions: bool) {
+    let keep = frontier(&result.flights, emissions);
+    let mut index = 0;
+    result.flights.retain(|_| {
+        let kept = keep.binary_search(&index).is_ok();
+        index += 1;
+        kept
+    });
+}
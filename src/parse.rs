@@ -1,8 +1,18 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
 use scraper::{Html, Selector};
+use serde_json::value::RawValue;
 use serde_json::Value;
 
 use crate::error::FlightError;
 use crate::model::*;
+use crate::query::{SearchQuery, TripType};
+use crate::risk;
+use crate::tz;
+use crate::value::{self, ValueWeights};
 
 fn get_val(val: &Value, idx: usize) -> Option<&Value> {
     val.as_array().and_then(|arr| arr.get(idx))
@@ -20,7 +30,128 @@ fn get_u32(val: &Value, idx: usize) -> Option<u32> {
     get_val(val, idx).and_then(|v| v.as_u64()).map(|v| v as u32)
 }
 
-pub fn extract_script(html: &str) -> Result<String, FlightError> {
+fn tag_has_class(tag: &str, class: &str) -> bool {
+    ["class=\"", "class='", "class="].iter().any(|prefix| {
+        tag.match_indices(prefix).any(|(i, _)| {
+            let value = &tag[i + prefix.len()..];
+            value.strip_prefix(class).is_some_and(|after| {
+                after.starts_with(['"', '\'', ' ', '>', '/']) || after.is_empty()
+            })
+        })
+    })
+}
+
+fn scan_script<'a>(html: &'a str, class: &str) -> Option<&'a str> {
+    let mut pos = 0;
+    while let Some(found) = html[pos..].find("<script") {
+        let tag_start = pos + found;
+        let tag_end = tag_start + html[tag_start..].find('>')?;
+        let body_start = tag_end + 1;
+
+        if tag_has_class(&html[tag_start..tag_end], class) {
+            let body_len = html[body_start..].find("</script>")?;
+            return Some(&html[body_start..body_start + body_len]);
+        }
+        pos = body_start;
+    }
+    None
+}
+
+// Every inline script that hands page data over: `<script class="ds:N">` tags and bare
+// `AF_initDataCallback({key: 'ds:N', data: ...})` blocks, labelled by key or class.
+fn data_scripts(html: &str) -> Vec<(String, &str)> {
+    let mut scripts = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = html[pos..].find("<script") {
+        let tag_start = pos + found;
+        let Some(tag_len) = html[tag_start..].find('>') else {
+            break;
+        };
+        let tag = &html[tag_start..tag_start + tag_len];
+        let body_start = tag_start + tag_len + 1;
+        let Some(body_len) = html[body_start..].find("</script>") else {
+            break;
+        };
+        let body = &html[body_start..body_start + body_len];
+        if tag.contains("ds:") || body.contains("AF_initDataCallback") {
+            let label = quoted_after(body, "key:")
+                .or_else(|| quoted_after(tag, "class="))
+                .unwrap_or("AF_initDataCallback");
+            scripts.push((label.to_string(), body));
+        }
+        pos = body_start + body_len;
+    }
+    scripts
+}
+
+fn quoted_after<'a>(text: &'a str, marker: &str) -> Option<&'a str> {
+    let rest = text.split_once(marker)?.1.trim_start();
+    let quote = rest.chars().next().filter(|c| *c == '\'' || *c == '"')?;
+    rest[1..].split(quote).next()
+}
+
+fn flights_shape(payload: &Value) -> bool {
+    has_results_section(payload) || has_flight_list(payload, 2)
+}
+
+// How many itineraries a payload carries in either flight bucket.
+fn flight_count(payload: &Value) -> usize {
+    BUCKETS
+        .iter()
+        .filter_map(|&(idx, _)| get_val(payload, idx).and_then(|v| get_val(v, 0)))
+        .filter_map(|v| v.as_array())
+        .map(|arr| arr.iter().filter(|k| raw_segments(k).is_some()).count())
+        .sum()
+}
+
+// Fallback for when Google renumbers its data scripts: the one whose payload has the
+// flights shape and the most itineraries, with a warning naming where it was found.
+fn best_data_script(
+    html: &str,
+    parse: fn(&str) -> Result<Value, FlightError>,
+) -> Option<(Value, Warning)> {
+    let (label, payload) = data_scripts(html)
+        .into_iter()
+        .filter_map(|(label, js)| Some((label, parse(js).ok()?)))
+        .filter(|(_, payload)| flights_shape(payload))
+        .enumerate()
+        .max_by_key(|(i, (_, payload))| (flight_count(payload), std::cmp::Reverse(*i)))?
+        .1;
+    let warning = Warning::new(
+        "script_fallback",
+        format!(
+            "flight data was not in the ds:1 script; using {label} instead \
+             (Google may have changed its page layout)"
+        ),
+    );
+    Some((payload, warning))
+}
+
+// The search payload from ds:1, or from the best-matching data script when ds:1 is
+// missing or holds no flights. An empty ds:1 still wins over scripts without flights:
+// that's a route with no results, not a renumbered page.
+fn locate_payload(
+    html: &str,
+    script: Result<String, FlightError>,
+    parse: fn(&str) -> Result<Value, FlightError>,
+) -> Result<(Value, Option<Warning>), FlightError> {
+    match script.and_then(|js| parse(&js)) {
+        Ok(payload) if flight_count(&payload) > 0 => Ok((payload, None)),
+        primary => match best_data_script(html, parse) {
+            Some((payload, warning))
+                if flight_count(&payload) > 0
+                    || !matches!(&primary, Ok(p) if flights_shape(p)) =>
+            {
+                Ok((payload, Some(warning)))
+            }
+            _ => primary
+                .map(|payload| (payload, None))
+                .map_err(|e| blocked_page_error(html).unwrap_or(e)),
+        },
+    }
+}
+
+fn dom_extract_script(html: &str) -> Option<String> {
     let document = Html::parse_document(html);
     let selector =
         Selector::parse(r#"script[class="ds:1"]"#).expect("valid selector");
@@ -29,21 +160,44 @@ pub fn extract_script(html: &str) -> Result<String, FlightError> {
         .select(&selector)
         .next()
         .map(|el| el.inner_html())
+}
+
+pub fn extract_script(html: &str) -> Result<String, FlightError> {
+    scan_script(html, "ds:1")
+        .map(String::from)
+        .or_else(|| dom_extract_script(html))
         .ok_or(FlightError::ScriptTagNotFound)
 }
 
-pub fn parse_js(js: &str) -> Result<Value, FlightError> {
+fn data_slice(js: &str) -> Result<&str, FlightError> {
     let data = js
         .split_once("data:")
         .map(|(_, rest)| rest)
         .ok_or_else(|| FlightError::JsParse("no 'data:' marker found".into()))?;
 
-    let data = data
-        .rsplit_once(',')
+    data.rsplit_once(',')
         .map(|(left, _)| left)
-        .ok_or_else(|| FlightError::JsParse("no trailing comma found".into()))?;
+        .ok_or_else(|| FlightError::JsParse("no trailing comma found".into()))
+}
 
-    serde_json::from_str(data).map_err(|e| FlightError::JsParse(e.to_string()))
+pub fn parse_js(js: &str) -> Result<Value, FlightError> {
+    serde_json::from_str(data_slice(js)?).map_err(|e| FlightError::JsParse(e.to_string()))
+}
+
+const PAYLOAD_INDICES: &[usize] = &[2, 3, 5, 7];
+
+pub fn parse_js_sparse(js: &str) -> Result<Value, FlightError> {
+    let top: Vec<&RawValue> = serde_json::from_str(data_slice(js)?)
+        .map_err(|e| FlightError::JsParse(e.to_string()))?;
+
+    let mut payload = vec![Value::Null; top.len()];
+    for &idx in PAYLOAD_INDICES {
+        if let Some(raw) = top.get(idx) {
+            payload[idx] =
+                serde_json::from_str(raw.get()).map_err(|e| FlightError::JsParse(e.to_string()))?;
+        }
+    }
+    Ok(Value::Array(payload))
 }
 
 fn parse_datetime(date_val: &Value, time_val: &Value) -> Option<FlightDateTime> {
@@ -76,18 +230,288 @@ fn parse_segment(sf: &Value) -> Option<Segment> {
     let arrival = parse_datetime(arrival_date, arrival_time)?;
 
     let duration_minutes = get_u32(sf, 11).unwrap_or(0);
-    let aircraft = get_str(sf, 17);
+    let vehicle = get_str(sf, 17);
+    let kind = segment_kind(vehicle.as_deref());
+    let aircraft = vehicle.filter(|_| !kind.is_ground());
+    let legroom = get_str(sf, 14)
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    let amenities = parse_amenities(sf);
+    let codeshares = parse_codeshares(sf);
+    let departure_terminal = terminal(sf, 24);
+    let arrival_terminal = terminal(sf, 25);
+
+    let flight_info = get_val(sf, 22);
+    let airline = flight_info.and_then(|v| get_str(v, 0));
+    let flight_number = flight_info.and_then(|v| get_str(v, 1));
+    let airline_name = flight_info.and_then(|v| get_str(v, 3));
+    let operated_by = get_str(sf, 2)
+        .and_then(|text| operator(&text))
+        .filter(|op| !airline_name.as_ref().is_some_and(|m| m.eq_ignore_ascii_case(op)));
+
+    let (departure_offset, arrival_offset) = tz::segment_offsets(
+        (&from_airport.code, &departure),
+        (&to_airport.code, &arrival),
+        duration_minutes,
+    );
 
     Some(Segment {
+        departure_iso: departure_offset.map(|o| tz::iso8601(&departure, o)),
+        arrival_iso: arrival_offset.map(|o| tz::iso8601(&arrival, o)),
+        departure_utc_offset_minutes: departure_offset,
+        arrival_utc_offset_minutes: arrival_offset,
         from_airport,
         to_airport,
         departure,
         arrival,
         duration_minutes,
+        kind,
         aircraft,
+        airline,
+        flight_number,
+        airline_name,
+        operated_by,
+        legroom,
+        amenities,
+        departure_terminal,
+        arrival_terminal,
+        codeshares,
     })
 }
 
+// sf[17] names the vehicle: an aircraft type for flights, and the mode itself ("Train",
+// "High-speed train", "Bus", "Coach") for ground legs.
+fn segment_kind(vehicle: Option<&str>) -> SegmentKind {
+    let vehicle = vehicle.unwrap_or_default().to_lowercase();
+    let has = |words: &[&str]| {
+        vehicle
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| words.contains(&word))
+    };
+    if has(&["train", "rail", "railway", "tgv", "ice"]) {
+        SegmentKind::Train
+    } else if has(&["bus", "coach"]) {
+        SegmentKind::Bus
+    } else {
+        SegmentKind::Flight
+    }
+}
+
+// sf[15] lists the other airlines selling the segment under their own number, each
+// shaped like sf[22]: [code, number, null, name].
+fn parse_codeshares(sf: &Value) -> Vec<Codeshare> {
+    let Some(list) = get_val(sf, 15).and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    list.iter()
+        .filter_map(|item| {
+            Some(Codeshare {
+                airline: get_str(item, 0)?,
+                flight_number: get_str(item, 1)?,
+                airline_name: get_str(item, 3),
+            })
+        })
+        .collect()
+}
+
+// sf[24] and sf[25] name the departure and arrival terminals ("2", "T5", "Terminal 1"),
+// kept as the bare designator.
+fn terminal(sf: &Value, idx: usize) -> Option<String> {
+    let text = get_str(sf, idx)?;
+    let text = text.trim();
+    let name = match text.get(..9) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("terminal ") => &text[9..],
+        _ => text,
+    };
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// sf[12] lists what is on board ("Wi-Fi for a fee", "In-seat power & USB outlets",
+// "On-demand video"), each entry either the text itself or an array led by it.
+fn parse_amenities(sf: &Value) -> Vec<String> {
+    let Some(list) = get_val(sf, 12).and_then(|v| v.as_array()) else {
+        return Vec::new();
+    };
+    list.iter()
+        .filter_map(|item| item.as_str().map(String::from).or_else(|| get_str(item, 0)))
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+// Google words it "Operated by Air Nostrum as Iberia Regional"; keep the airline that
+// flies the plane.
+fn operator(text: &str) -> Option<String> {
+    let text = text.trim();
+    let name = match text.get(..12) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("operated by ") => &text[12..],
+        _ => text,
+    };
+    let name = name.split(" as ").next().unwrap_or(name).trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn arrival_day_offset(segments: &[Segment]) -> i32 {
+    match (segments.first(), segments.last()) {
+        (Some(first), Some(last)) => {
+            (last.arrival.days_since_epoch() - first.departure.days_since_epoch()) as i32
+        }
+        _ => 0,
+    }
+}
+
+// Time in the air: rail and coach legs count towards the trip, not the flying.
+fn air_minutes(segments: &[Segment]) -> u32 {
+    segments
+        .iter()
+        .filter(|s| !s.kind.is_ground())
+        .map(|s| s.duration_minutes)
+        .sum()
+}
+
+fn utc_minutes(local: &FlightDateTime, offset: Option<i32>) -> Option<i64> {
+    offset.map(|o| local.minutes_since_epoch() - i64::from(o))
+}
+
+// Connections are timed in UTC when both offsets are known, so a change of airport
+// across zones or a connection spanning a DST switch comes out right.
+fn layovers(segments: &[Segment]) -> Vec<Layover> {
+    segments
+        .windows(2)
+        .map(|w| {
+            let arrive = (&w[0].arrival, w[0].arrival_utc_offset_minutes);
+            let depart = (&w[1].departure, w[1].departure_utc_offset_minutes);
+            let minutes = match (utc_minutes(arrive.0, arrive.1), utc_minutes(depart.0, depart.1)) {
+                (Some(a), Some(d)) => d - a,
+                _ => depart.0.minutes_since_epoch() - arrive.0.minutes_since_epoch(),
+            };
+            Layover {
+                airport: w[0].to_airport.code.clone(),
+                minutes: minutes.max(0) as u32,
+            }
+        })
+        .collect()
+}
+
+fn elapsed_minutes(segments: &[Segment], layovers: &[Layover]) -> u32 {
+    let travel: u32 = segments.iter().map(|s| s.duration_minutes).sum();
+    travel + layovers.iter().map(|l| l.minutes).sum::<u32>()
+}
+
+const ELAPSED_TOLERANCE_MINUTES: i64 = 5;
+
+// Travel plus connection time must equal the span between the first departure and the
+// last arrival in UTC. They disagree when Google's durations or the zone table are off
+// for this itinerary, and consumers are told rather than handed a wrong total.
+fn elapsed_warning(segments: &[Segment], elapsed: u32) -> Option<Warning> {
+    let (first, last) = (segments.first()?, segments.last()?);
+    let span = utc_minutes(&last.arrival, last.arrival_utc_offset_minutes)?
+        - utc_minutes(&first.departure, first.departure_utc_offset_minutes)?;
+    if (span - i64::from(elapsed)).abs() <= ELAPSED_TOLERANCE_MINUTES {
+        return None;
+    }
+    let hm = |m: i64| format!("{}h{:02}m", m / 60, m.abs() % 60);
+    Some(Warning::new(
+        "elapsed_mismatch",
+        format!(
+            "{}→{}: flights and connections add up to {}, but departure and arrival are {} apart",
+            first.from_airport.code,
+            last.to_airport.code,
+            hm(i64::from(elapsed)),
+            hm(span)
+        ),
+    ))
+}
+
+fn raw_segments(k: &Value) -> Option<&Vec<Value>> {
+    get_val(k, 0)
+        .and_then(|flight| get_val(flight, 2))
+        .and_then(|v| v.as_array())
+}
+
+// flight[19] is [carry-on bags, checked bags] included in the fare, each null when the
+// fare rules are unknown.
+fn parse_baggage(flight: &Value) -> Option<BagAllowance> {
+    let bags = get_val(flight, 19)?;
+    let allowance = BagAllowance {
+        carry_on: get_u32(bags, 0),
+        checked: get_u32(bags, 1),
+    };
+    (allowance != BagAllowance::default()).then_some(allowance)
+}
+
+// sf[13] carries Google's punctuality notice ("Often delayed by 30+ min") on segments
+// with a poor on-time record.
+fn delay_warnings(raw: &[Value]) -> Vec<Warning> {
+    raw.iter()
+        .filter_map(|sf| {
+            let notice = get_str(sf, 13).filter(|s| !s.trim().is_empty())?;
+            let info = get_val(sf, 22);
+            let flight = format!(
+                "{}{}",
+                info.and_then(|v| get_str(v, 0)).unwrap_or_default(),
+                info.and_then(|v| get_str(v, 1)).unwrap_or_default()
+            );
+            let message = if flight.is_empty() {
+                notice.trim().to_string()
+            } else {
+                format!("{flight}: {}", notice.trim())
+            };
+            Some(Warning::new("often_delayed", message))
+        })
+        .collect()
+}
+
+// flight[24] is the notice on itineraries sold as more than one booking: "Separate
+// tickets booked together", or "Self transfer" when bags are re-checked in between.
+fn parse_ticketing(flight: &Value) -> Option<Ticketing> {
+    let notice = get_str(flight, 24)?.trim().to_lowercase();
+    if notice.contains("self transfer") || notice.contains("self-transfer") {
+        Some(Ticketing::SelfTransfer)
+    } else if notice.is_empty() {
+        None
+    } else {
+        Some(Ticketing::SeparateTickets)
+    }
+}
+
+// flight[23] is the fare's brand label where the airline sells branded fares ("Basic
+// Economy", "Economy Light", "Hand baggage only"). A fare without a carry-on is basic
+// whatever it's called.
+fn parse_fare_brand(flight: &Value, baggage: Option<&BagAllowance>) -> Option<FareBrand> {
+    let label = get_str(flight, 23).unwrap_or_default().to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| label.contains(w));
+    if has(&["basic"]) || baggage.is_some_and(|b| b.carry_on == Some(0)) {
+        Some(FareBrand::Basic)
+    } else if has(&["light", "hand baggage", "hand luggage", "carry-on only", "cabin bag only"]) {
+        Some(FareBrand::HandBaggageOnly)
+    } else {
+        None
+    }
+}
+
+fn ticketing_warning(ticketing: Ticketing, layovers: &[Layover]) -> Warning {
+    let airports: Vec<&str> = layovers.iter().map(|l| l.airport.as_str()).collect();
+    let at = if airports.is_empty() {
+        String::new()
+    } else {
+        format!(" at {}", airports.join(", "))
+    };
+    match ticketing {
+        Ticketing::SeparateTickets => Warning::new(
+            "separate_tickets",
+            format!("Separate tickets{at}: a missed connection is not covered"),
+        ),
+        Ticketing::SelfTransfer => Warning::new(
+            "self_transfer",
+            format!(
+                "Self-transfer{at}: collect and re-check bags; a missed connection is not covered"
+            ),
+        ),
+    }
+}
+
 fn parse_flight(k: &Value) -> Option<FlightResult> {
     let flight = get_val(k, 0)?;
 
@@ -98,7 +522,7 @@ fn parse_flight(k: &Value) -> Option<FlightResult> {
         .map(|arr| arr.iter().filter_map(|v| v.as_str().map(String::from)).collect())
         .unwrap_or_default();
 
-    let segments_arr = get_val(flight, 2).and_then(|v| v.as_array());
+    let segments_arr = raw_segments(k);
     let segments: Vec<Segment> = segments_arr
         .map(|arr| arr.iter().filter_map(parse_segment).collect())
         .unwrap_or_default();
@@ -108,17 +532,46 @@ fn parse_flight(k: &Value) -> Option<FlightResult> {
         .and_then(|v| get_i64(v, 1));
 
     let extras = get_val(flight, 22);
-    let carbon = CarbonEmission {
-        emission_grams: extras.and_then(|e| get_i64(e, 7)),
-        typical_grams: extras.and_then(|e| get_i64(e, 8)),
-    };
+    let carbon = CarbonEmission::new(
+        extras.and_then(|e| get_i64(e, 7)),
+        extras.and_then(|e| get_i64(e, 8)),
+    );
+
+    let arrival_day_offset = arrival_day_offset(&segments);
+    let air_minutes = air_minutes(&segments);
+    let layovers = layovers(&segments);
+    let elapsed_minutes = elapsed_minutes(&segments, &layovers);
+    let ticketing = parse_ticketing(flight);
+    let risk = risk::assess(&segments, ticketing, risk::DEFAULT_MIN_CONNECTION);
+    let baggage = parse_baggage(flight);
+    let fare_brand = parse_fare_brand(flight, baggage.as_ref());
+    let mut warnings = segments_arr.map(|arr| delay_warnings(arr)).unwrap_or_default();
+    warnings.extend(elapsed_warning(&segments, elapsed_minutes));
+    warnings.extend(ticketing.map(|t| ticketing_warning(t, &layovers)));
 
     Some(FlightResult {
         flight_type,
         airlines,
         segments,
         price,
+        fare: None,
         carbon,
+        arrival_day_offset,
+        air_minutes,
+        elapsed_minutes,
+        risk,
+        price_scope: None,
+        value_score: None,
+        price_with_bag: None,
+        previous_price: None,
+        delta: None,
+        layovers,
+        original_price: None,
+        category: None,
+        baggage,
+        ticketing,
+        fare_brand,
+        warnings,
     })
 }
 
@@ -149,29 +602,457 @@ fn parse_metadata(payload: &Value) -> SearchMetadata {
     SearchMetadata {
         airlines,
         alliances,
+        currency: None,
+        original_currency: None,
+        price_insights: parse_price_insights(payload),
+    }
+}
+
+// payload[5] holds Google's price verdict for the route: [level, [[currency, typical
+// low], [currency, typical high]]], with level 1 = low, 2 = typical, 3 = high.
+fn parse_price_insights(payload: &Value) -> Option<PriceInsights> {
+    let root = get_val(payload, 5)?;
+    let range = get_val(root, 1);
+    let insights = PriceInsights {
+        current_level: match get_i64(root, 0) {
+            Some(1) => Some(PriceLevel::Low),
+            Some(2) => Some(PriceLevel::Typical),
+            Some(3) => Some(PriceLevel::High),
+            _ => None,
+        },
+        typical_low: range.and_then(|r| get_val(r, 0)).and_then(|p| get_i64(p, 1)),
+        typical_high: range.and_then(|r| get_val(r, 1)).and_then(|p| get_i64(p, 1)),
+    };
+    let empty = insights.current_level.is_none()
+        && insights.typical_low.is_none()
+        && insights.typical_high.is_none();
+    (!empty).then_some(insights)
+}
+
+// Google leaves the level out on some pages; the cheapest fare against the typical
+// range gives the same verdict.
+fn complete_price_level(result: &mut SearchResult) {
+    let cheapest = result.flights.iter().filter_map(|f| f.price).min();
+    if let (Some(insights), Some(cheapest)) = (&mut result.metadata.price_insights, cheapest) {
+        if insights.current_level.is_none() {
+            insights.current_level = insights.level_for(cheapest);
+        }
+    }
+}
+
+fn is_currency_code(s: &str) -> bool {
+    s.len() == 3 && s.chars().all(|c| c.is_ascii_uppercase())
+}
+
+fn parse_currency(entries: &[Value]) -> Option<String> {
+    entries
+        .iter()
+        .filter_map(|k| {
+            get_val(k, 1)
+                .and_then(|v| get_val(v, 0))
+                .and_then(|v| get_str(v, 0))
+        })
+        .find(|code| is_currency_code(code))
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub strict: bool,
+    pub low_memory: bool,
+    // Tried in order before the built-in Google payload parser.
+    pub parsers: Vec<Arc<dyn ResultParser>>,
+}
+
+// Turns a fetched page into results. A search uses the first registered parser that
+// accepts the page and falls back to `GooglePayloadParser`, so another page variant
+// (a mobile page, a future layout) can be handled without changing this module.
+pub trait ResultParser: fmt::Debug + Send + Sync {
+    fn name(&self) -> &str;
+
+    fn accepts(&self, html: &str, query: &SearchQuery) -> bool;
+
+    fn parse(
+        &self,
+        html: String,
+        query: &SearchQuery,
+        options: &ParseOptions,
+    ) -> Result<SearchResult, FlightError>;
+}
+
+// The regular results page and the free-text "top flights" page, both read from the
+// `AF_initDataCallback` payload.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GooglePayloadParser;
+
+impl ResultParser for GooglePayloadParser {
+    fn name(&self) -> &str {
+        "google-payload"
     }
+
+    fn accepts(&self, _html: &str, _query: &SearchQuery) -> bool {
+        true
+    }
+
+    fn parse(
+        &self,
+        html: String,
+        query: &SearchQuery,
+        options: &ParseOptions,
+    ) -> Result<SearchResult, FlightError> {
+        // The scope is only recorded when the page states it. The hint matches English
+        // wording, so other languages leave it unset rather than guessed.
+        let (scope, mut result) = match query {
+            SearchQuery::Structured(q) if matches!(q.trip, TripType::RoundTrip) => {
+                (price_scope_hint(&html), parse_html_owned(html, options)?)
+            }
+            SearchQuery::Structured(_) => (None, parse_html_owned(html, options)?),
+            SearchQuery::NaturalLanguage(_) => {
+                (price_scope_hint(&html), parse_free_text_html(&html, options)?)
+            }
+        };
+        if let Some(scope) = scope {
+            for flight in &mut result.flights {
+                flight.price_scope = Some(scope);
+            }
+        }
+        Ok(result)
+    }
+}
+
+pub fn select_parser<'a>(
+    parsers: &'a [Arc<dyn ResultParser>],
+    html: &str,
+    query: &SearchQuery,
+) -> &'a dyn ResultParser {
+    static DEFAULT: GooglePayloadParser = GooglePayloadParser;
+    parsers
+        .iter()
+        .find(|p| p.accepts(html, query))
+        .map_or(&DEFAULT, |p| p.as_ref())
+}
+
+fn check_strict(index: usize, k: &Value, parsed: Option<&FlightResult>) -> Result<(), FlightError> {
+    let flight = parsed.ok_or_else(|| {
+        FlightError::JsParse(format!("flight entry {index} could not be parsed (strict mode)"))
+    })?;
+
+    let raw = raw_segments(k).map_or(0, |arr| arr.len());
+    if flight.segments.is_empty() {
+        return Err(FlightError::JsParse(format!(
+            "flight entry {index} has no parseable segments (strict mode)"
+        )));
+    }
+    if flight.segments.len() < raw {
+        return Err(FlightError::JsParse(format!(
+            "flight entry {index}: {} of {raw} segments could not be parsed (strict mode)",
+            raw - flight.segments.len()
+        )));
+    }
+    Ok(())
 }
 
 pub fn parse_payload(payload: &Value) -> Result<SearchResult, FlightError> {
-    let metadata = parse_metadata(payload);
+    parse_payload_with(payload, &ParseOptions::default())
+}
+
+// Google lists the itineraries it recommends ("best flights") under payload[2][0] and
+// everything else under payload[3][0].
+const BUCKETS: [(usize, Category); 2] = [(2, Category::Best), (3, Category::Other)];
+
+fn push_flight(
+    flights: &mut Vec<FlightResult>,
+    incomplete: &mut usize,
+    index: usize,
+    k: &Value,
+    category: Category,
+    options: &ParseOptions,
+) -> Result<(), FlightError> {
+    let mut parsed = parse_flight(k);
+    if let Err(e) = check_strict(index, k, parsed.as_ref()) {
+        if options.strict {
+            return Err(e);
+        }
+        *incomplete += 1;
+    }
+    if let Some(flight) = &mut parsed {
+        flight.category = Some(category);
+    }
+    flights.extend(parsed);
+    Ok(())
+}
+
+fn incomplete_warnings(incomplete: usize, total: usize) -> Vec<Warning> {
+    if incomplete == 0 {
+        return Vec::new();
+    }
+    vec![Warning::new(
+        "incomplete_flights",
+        format!(
+            "{incomplete} of {total} flight entries could not be fully parsed and were dropped \
+             or are missing segments (--strict fails instead)"
+        ),
+    )]
+}
+
+// One bucket's flight entries, either borrowed from the payload or taken out of it so
+// that each entry is freed as soon as it has been parsed.
+enum Entries<'a> {
+    Borrowed(std::slice::Iter<'a, Value>),
+    Owned(std::vec::IntoIter<Value>),
+}
+
+impl<'a> Entries<'a> {
+    fn new(idx: usize, root: Option<Cow<'a, Value>>) -> Result<Option<Self>, FlightError> {
+        match root {
+            None | Some(Cow::Borrowed(Value::Null)) | Some(Cow::Owned(Value::Null)) => Ok(None),
+            Some(Cow::Borrowed(Value::Array(arr))) => Ok(Some(Self::Borrowed(arr.iter()))),
+            Some(Cow::Owned(Value::Array(arr))) => Ok(Some(Self::Owned(arr.into_iter()))),
+            Some(_) => Err(FlightError::JsParse(format!("payload[{idx}][0] is not an array"))),
+        }
+    }
+
+    fn as_slice(&self) -> &[Value] {
+        match self {
+            Self::Borrowed(entries) => entries.as_slice(),
+            Self::Owned(entries) => entries.as_slice(),
+        }
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Cow<'a, Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Self::Borrowed(entries) => entries.next().map(Cow::Borrowed),
+            Self::Owned(entries) => entries.next().map(Cow::Owned),
+        }
+    }
+}
 
-    let flights_root = get_val(payload, 3).and_then(|v| get_val(v, 0));
+fn parse_buckets<'a>(
+    mut metadata: SearchMetadata,
+    roots: impl IntoIterator<Item = (usize, Category, Option<Cow<'a, Value>>)>,
+    options: &ParseOptions,
+) -> Result<SearchResult, FlightError> {
+    let (mut incomplete, mut total) = (0, 0);
 
-    let flights = match flights_root {
-        Some(root) if !root.is_null() => {
-            let arr = root
-                .as_array()
-                .ok_or_else(|| FlightError::JsParse("payload[3][0] is not an array".into()))?;
-            arr.iter().filter_map(parse_flight).collect()
+    let mut flights = Vec::new();
+    for (idx, category, root) in roots {
+        let Some(entries) = Entries::new(idx, root)? else {
+            continue;
+        };
+        metadata.currency = metadata.currency.or_else(|| parse_currency(entries.as_slice()));
+        flights.reserve(entries.as_slice().len());
+        for k in entries {
+            push_flight(&mut flights, &mut incomplete, total, &k, category, options)?;
+            total += 1;
         }
-        _ => Vec::new(),
+    }
+
+    let mut result = SearchResult {
+        flights,
+        metadata,
+        warnings: incomplete_warnings(incomplete, total),
+        ..SearchResult::default()
     };
+    complete_price_level(&mut result);
+    value::annotate(&mut result, &ValueWeights::default());
+    Ok(result)
+}
 
-    Ok(SearchResult { flights, metadata })
+pub fn parse_payload_with(
+    payload: &Value,
+    options: &ParseOptions,
+) -> Result<SearchResult, FlightError> {
+    let roots = BUCKETS.map(|(idx, category)| {
+        let root = get_val(payload, idx).and_then(|v| get_val(v, 0));
+        (idx, category, root.map(Cow::Borrowed))
+    });
+    parse_buckets(parse_metadata(payload), roots, options)
+}
+
+pub fn parse_payload_owned(
+    mut payload: Value,
+    options: &ParseOptions,
+) -> Result<SearchResult, FlightError> {
+    let metadata = parse_metadata(&payload);
+    let roots = BUCKETS.map(|(idx, category)| {
+        let root = payload
+            .get_mut(idx)
+            .and_then(|v| v.get_mut(0))
+            .map(Value::take);
+        (idx, category, root.map(Cow::Owned))
+    });
+    drop(payload);
+    parse_buckets(metadata, roots, options)
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .any(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+pub fn price_scope_hint(html: &str) -> Option<PriceScope> {
+    let has = |needle| contains_ignore_case(html, needle);
+    if has("round trip total") || has("round-trip total") {
+        Some(PriceScope::Total)
+    } else if has("outbound price") || has("price per direction") {
+        Some(PriceScope::Outbound)
+    } else {
+        None
+    }
 }
 
 pub fn parse_html(html: &str) -> Result<SearchResult, FlightError> {
-    let js = extract_script(html)?;
-    let payload = parse_js(&js)?;
-    parse_payload(&payload)
+    parse_html_with(html, &ParseOptions::default())
+}
+
+fn has_results_section(payload: &Value) -> bool {
+    get_val(payload, 3).is_some_and(|v| v.is_array())
+}
+
+pub fn is_consent_page(html: &str) -> bool {
+    const MARKERS: &[&str] = &[
+        "action=\"https://consent.google.com/save\"",
+        "consent.google.com",
+        "<title>Before you continue",
+    ];
+    MARKERS.iter().any(|m| html.contains(m))
+}
+
+pub const CAPTCHA_URL: &str = "https://www.google.com/sorry/index";
+
+// The sorry page is identified by its challenge form. Its wording ("unusual traffic")
+// and a reCAPTCHA widget can also turn up in ordinary pages and their scripts.
+pub fn is_captcha_page(html: &str) -> bool {
+    if !html.contains("captcha-form") {
+        return false;
+    }
+    let Ok(form) = Selector::parse("form#captcha-form") else {
+        return false;
+    };
+    Html::parse_document(html).select(&form).next().is_some()
+}
+
+fn blocked_page_error(html: &str) -> Option<FlightError> {
+    if is_consent_page(html) {
+        Some(FlightError::ConsentRequired)
+    } else if is_captcha_page(html) {
+        Some(FlightError::CaptchaRequired(CAPTCHA_URL.into()))
+    } else {
+        None
+    }
+}
+
+fn empty_shell_error() -> FlightError {
+    FlightError::JsParse(
+        "response has no results section — Google may have served an empty page shell".into(),
+    )
+}
+
+pub fn parse_html_with(html: &str, options: &ParseOptions) -> Result<SearchResult, FlightError> {
+    let started = Instant::now();
+    let script = extract_script(html);
+    let extracted = Instant::now();
+    let (payload, fallback) = locate_payload(html, script, parse_js_sparse)?;
+    let parsed = Instant::now();
+    let mut result = parse_payload_with(&payload, options)?;
+
+    if result.flights.is_empty() && !has_results_section(&payload) {
+        return Err(blocked_page_error(html).unwrap_or_else(empty_shell_error));
+    }
+    result.warnings.extend(fallback);
+
+    result.timings = Some(Timings {
+        extract: extracted - started,
+        json: parsed - extracted,
+        build: parsed.elapsed(),
+        ..Timings::default()
+    });
+    Ok(result)
+}
+
+// A free-text (`q=`) search lands on Google's "top flights" page rather than the
+// regular results list: the itineraries sit only under payload[2][0] (the best-flights
+// bucket), and the data is sometimes emitted as ds:0 rather than ds:1.
+const FREE_TEXT_SCRIPTS: &[&str] = &["ds:1", "ds:0"];
+
+fn has_flight_list(payload: &Value, idx: usize) -> bool {
+    get_val(payload, idx)
+        .and_then(|v| get_val(v, 0))
+        .is_some_and(|v| v.is_array())
+}
+
+pub fn parse_free_text_html(
+    html: &str,
+    options: &ParseOptions,
+) -> Result<SearchResult, FlightError> {
+    let mut shaped = None;
+    for class in FREE_TEXT_SCRIPTS {
+        let Some(js) = scan_script(html, class) else {
+            continue;
+        };
+        let Ok(payload) = parse_js(js) else {
+            continue;
+        };
+        if flight_count(&payload) > 0 {
+            return parse_payload_with(&payload, options);
+        }
+        if shaped.is_none() && flights_shape(&payload) {
+            shaped = Some(payload);
+        }
+    }
+    let (payload, fallback) = match best_data_script(html, parse_js) {
+        Some((payload, warning)) if flight_count(&payload) > 0 || shaped.is_none() => {
+            (payload, Some(warning))
+        }
+        _ => (
+            shaped.ok_or_else(|| blocked_page_error(html).unwrap_or_else(empty_shell_error))?,
+            None,
+        ),
+    };
+    let mut result = parse_payload_with(&payload, options)?;
+    result.warnings.extend(fallback);
+    Ok(result)
+}
+
+// The whole payload, before `parse_payload` picks it apart: what `--raw` dumps so a
+// changed layout can be inspected without flyr's assumptions about it.
+pub fn extract_payload(html: &str) -> Result<Value, FlightError> {
+    let js = extract_script(html)
+        .or_else(|e| scan_script(html, "ds:0").map(String::from).ok_or(e));
+    locate_payload(html, js, parse_js).map(|(payload, _)| payload)
+}
+
+pub fn parse_html_owned(html: String, options: &ParseOptions) -> Result<SearchResult, FlightError> {
+    if !options.low_memory {
+        return parse_html_with(&html, options);
+    }
+
+    let started = Instant::now();
+    let script = extract_script(&html);
+    let extracted = Instant::now();
+    let (payload, fallback) = locate_payload(&html, script, parse_js_sparse)?;
+    let blocked = blocked_page_error(&html);
+    drop(html);
+    let parsed = Instant::now();
+
+    let has_results = has_results_section(&payload);
+    let mut result = parse_payload_owned(payload, options)?;
+
+    if result.flights.is_empty() && !has_results {
+        return Err(blocked.unwrap_or_else(empty_shell_error));
+    }
+    result.warnings.extend(fallback);
+
+    result.timings = Some(Timings {
+        extract: extracted - started,
+        json: parsed - extracted,
+        build: parsed.elapsed(),
+        ..Timings::default()
+    });
+    Ok(result)
 }
@@ -1,4 +1,6 @@
-use crate::query::{FlightLeg, Passengers, Seat, TripType};
+use crate::error::FlightError;
+use crate::metro;
+use crate::query::{FlightLeg, Passengers, QueryParams, Seat, SelectedFlight, TripType};
 
 fn encode_varint(mut value: u64, buf: &mut Vec<u8>) {
     loop {
@@ -12,6 +14,18 @@ fn encode_varint(mut value: u64, buf: &mut Vec<u8>) {
     }
 }
 
+fn varint_len(value: u64) -> usize {
+    (64 - value.max(1).leading_zeros() as usize).div_ceil(7)
+}
+
+fn tag_len(field: u32) -> usize {
+    varint_len((field as u64) << 3)
+}
+
+fn field_len(field: u32, len: usize) -> usize {
+    tag_len(field) + varint_len(len as u64) + len
+}
+
 fn encode_tag(field: u32, wire_type: u8, buf: &mut Vec<u8>) {
     encode_varint(((field as u64) << 3) | wire_type as u64, buf);
 }
@@ -22,44 +36,90 @@ fn encode_string(field: u32, s: &str, buf: &mut Vec<u8>) {
     buf.extend_from_slice(s.as_bytes());
 }
 
-fn encode_submessage(field: u32, inner: &[u8], buf: &mut Vec<u8>) {
-    encode_tag(field, 2, buf);
-    encode_varint(inner.len() as u64, buf);
-    buf.extend_from_slice(inner);
+fn airport_len(code: &str) -> usize {
+    field_len(2, code.len())
 }
 
-fn encode_airport(code: &str) -> Vec<u8> {
-    let mut buf = Vec::new();
-    encode_string(2, code, &mut buf);
-    buf
+// A metro code (NYC, LON, ...) becomes one entry per airport, which Google treats as
+// "any of these", so a single search covers the whole metro area.
+fn airports_len(field: u32, code: &str) -> usize {
+    metro::expand(code)
+        .iter()
+        .map(|a| field_len(field, airport_len(a)))
+        .sum()
+}
+
+fn encode_airport(field: u32, code: &str, buf: &mut Vec<u8>) {
+    for airport in metro::expand(code) {
+        encode_tag(field, 2, buf);
+        encode_varint(airport_len(airport) as u64, buf);
+        encode_string(2, airport, buf);
+    }
+}
+
+fn selected_fields(flight: &SelectedFlight) -> [(u32, &str); 5] {
+    [
+        (1, &flight.from_airport),
+        (2, &flight.date),
+        (3, &flight.to_airport),
+        (5, &flight.airline),
+        (6, &flight.flight_number),
+    ]
+}
+
+fn selected_len(flight: &SelectedFlight) -> usize {
+    selected_fields(flight)
+        .iter()
+        .map(|(field, value)| field_len(*field, value.len()))
+        .sum()
+}
+
+fn flight_data_len(leg: &FlightLeg) -> usize {
+    let mut len = field_len(2, leg.date.len());
+    len += leg.selected.iter().map(|f| field_len(4, selected_len(f))).sum::<usize>();
+    if let Some(max_stops) = leg.max_stops {
+        len += tag_len(5) + varint_len(max_stops as u64);
+    }
+    if let Some(ref airlines) = leg.airlines {
+        len += airlines.iter().map(|a| field_len(6, a.len())).sum::<usize>();
+    }
+    len + airports_len(13, &leg.from_airport) + airports_len(14, &leg.to_airport)
 }
 
-fn encode_flight_data(leg: &FlightLeg) -> Vec<u8> {
-    let mut buf = Vec::new();
+fn encode_flight_data(leg: &FlightLeg, buf: &mut Vec<u8>) {
+    encode_string(2, &leg.date, buf);
 
-    encode_string(2, &leg.date, &mut buf);
+    // A flight already chosen on this leg, so Google lists the next leg's options.
+    for flight in &leg.selected {
+        encode_tag(4, 2, buf);
+        encode_varint(selected_len(flight) as u64, buf);
+        for (field, value) in selected_fields(flight) {
+            encode_string(field, value, buf);
+        }
+    }
 
     if let Some(max_stops) = leg.max_stops {
-        encode_tag(5, 0, &mut buf);
-        encode_varint(max_stops as u64, &mut buf);
+        encode_tag(5, 0, buf);
+        encode_varint(max_stops as u64, buf);
     }
 
     if let Some(ref airlines) = leg.airlines {
         for airline in airlines {
-            encode_string(6, airline, &mut buf);
+            encode_string(6, airline, buf);
         }
     }
 
-    let from = encode_airport(&leg.from_airport);
-    encode_submessage(13, &from, &mut buf);
-
-    let to = encode_airport(&leg.to_airport);
-    encode_submessage(14, &to, &mut buf);
+    // `exclude_airlines` is not encoded: the airline field is an allow-list, and an
+    // unknown code there narrows the search instead of widening it. Exclusion is
+    // applied after parsing (see `filter::apply`), as are the departure and arrival
+    // time windows, the maximum duration and the layover bounds, whose tfs fields
+    // have never been checked against a real URL.
 
-    buf
+    encode_airport(13, &leg.from_airport, buf);
+    encode_airport(14, &leg.to_airport, buf);
 }
 
-fn seat_to_varint(seat: &Seat) -> u64 {
+pub(crate) fn seat_to_varint(seat: &Seat) -> u64 {
     match seat {
         Seat::Economy => 1,
         Seat::PremiumEconomy => 2,
@@ -68,7 +128,7 @@ fn seat_to_varint(seat: &Seat) -> u64 {
     }
 }
 
-fn trip_to_varint(trip: &TripType) -> u64 {
+pub(crate) fn trip_to_varint(trip: &TripType) -> u64 {
     match trip {
         TripType::RoundTrip => 1,
         TripType::OneWay => 2,
@@ -76,13 +136,62 @@ fn trip_to_varint(trip: &TripType) -> u64 {
     }
 }
 
-fn passengers_to_enums(p: &Passengers) -> Vec<u64> {
-    let mut vals = Vec::new();
-    vals.extend(std::iter::repeat_n(1, p.adults as usize));
-    vals.extend(std::iter::repeat_n(2, p.children as usize));
-    vals.extend(std::iter::repeat_n(3, p.infants_in_seat as usize));
-    vals.extend(std::iter::repeat_n(4, p.infants_on_lap as usize));
-    vals
+fn passengers_to_enums(p: &Passengers) -> impl Iterator<Item = u8> {
+    std::iter::repeat_n(1, p.adults as usize)
+        .chain(std::iter::repeat_n(2, p.children as usize))
+        .chain(std::iter::repeat_n(3, p.infants_in_seat as usize))
+        .chain(std::iter::repeat_n(4, p.infants_on_lap as usize))
+}
+
+fn passenger_count(p: &Passengers) -> usize {
+    (p.adults + p.children + p.infants_in_seat + p.infants_on_lap) as usize
+}
+
+pub fn encoded_len(
+    legs: &[FlightLeg],
+    passengers: &Passengers,
+    seat: &Seat,
+    trip: &TripType,
+) -> usize {
+    let legs_len: usize = legs.iter().map(|leg| field_len(3, flight_data_len(leg))).sum();
+    let pax = passenger_count(passengers);
+    let pax_len = if pax > 0 { field_len(8, pax) } else { 0 };
+
+    legs_len
+        + pax_len
+        + tag_len(9)
+        + varint_len(seat_to_varint(seat))
+        + tag_len(19)
+        + varint_len(trip_to_varint(trip))
+}
+
+pub fn encode_into(
+    buf: &mut Vec<u8>,
+    legs: &[FlightLeg],
+    passengers: &Passengers,
+    seat: &Seat,
+    trip: &TripType,
+) {
+    buf.reserve(encoded_len(legs, passengers, seat, trip));
+
+    for leg in legs {
+        encode_tag(3, 2, buf);
+        encode_varint(flight_data_len(leg) as u64, buf);
+        encode_flight_data(leg, buf);
+    }
+
+    let pax = passenger_count(passengers);
+    if pax > 0 {
+        encode_tag(8, 2, buf);
+        encode_varint(pax as u64, buf);
+        buf.extend(passengers_to_enums(passengers));
+    }
+
+    encode_tag(9, 0, buf);
+    encode_varint(seat_to_varint(seat), buf);
+
+    encode_tag(19, 0, buf);
+    encode_varint(trip_to_varint(trip), buf);
 }
 
 pub fn encode(
@@ -91,29 +200,219 @@ pub fn encode(
     seat: &Seat,
     trip: &TripType,
 ) -> Vec<u8> {
-    let mut buf = Vec::new();
+    let mut buf = Vec::with_capacity(encoded_len(legs, passengers, seat, trip));
+    encode_into(&mut buf, legs, passengers, seat, trip);
+    buf
+}
 
-    for leg in legs {
-        let fd = encode_flight_data(leg);
-        encode_submessage(3, &fd, &mut buf);
+fn decode_error(msg: impl Into<String>) -> FlightError {
+    FlightError::Validation(format!("invalid tfs parameter: {}", msg.into()))
+}
+
+enum Wire<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn varint(&mut self) -> Result<u64, FlightError> {
+        let mut value = 0u64;
+        for (i, &byte) in self.buf.iter().enumerate().take(10) {
+            value |= u64::from(byte & 0x7F) << (7 * i);
+            if byte & 0x80 == 0 {
+                self.buf = &self.buf[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err(decode_error("truncated varint"))
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], FlightError> {
+        if len > self.buf.len() {
+            return Err(decode_error("field runs past the end of the message"));
+        }
+        let (head, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(head)
     }
 
-    let pax = passengers_to_enums(passengers);
-    if !pax.is_empty() {
-        let mut packed = Vec::new();
-        for v in &pax {
-            encode_varint(*v, &mut packed);
+    fn field(&mut self) -> Result<Option<(u32, Wire<'a>)>, FlightError> {
+        if self.buf.is_empty() {
+            return Ok(None);
         }
-        encode_tag(8, 2, &mut buf);
-        encode_varint(packed.len() as u64, &mut buf);
-        buf.extend_from_slice(&packed);
+        let tag = self.varint()?;
+        let field = (tag >> 3) as u32;
+        let wire = match tag & 7 {
+            0 => Wire::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Wire::Fixed
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                Wire::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Wire::Fixed
+            }
+            other => return Err(decode_error(format!("unsupported wire type {other}"))),
+        };
+        Ok(Some((field, wire)))
     }
+}
 
-    encode_tag(9, 0, &mut buf);
-    encode_varint(seat_to_varint(seat), &mut buf);
+fn utf8(bytes: &[u8]) -> Result<String, FlightError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| decode_error("string is not UTF-8"))
+}
 
-    encode_tag(19, 0, &mut buf);
-    encode_varint(trip_to_varint(trip), &mut buf);
+fn seat_from_varint(value: u64) -> Result<Seat, FlightError> {
+    match value {
+        1 => Ok(Seat::Economy),
+        2 => Ok(Seat::PremiumEconomy),
+        3 => Ok(Seat::Business),
+        4 => Ok(Seat::First),
+        other => Err(decode_error(format!("unknown seat class {other}"))),
+    }
+}
 
-    buf
+fn trip_from_varint(value: u64) -> Result<TripType, FlightError> {
+    match value {
+        1 => Ok(TripType::RoundTrip),
+        2 => Ok(TripType::OneWay),
+        3 => Ok(TripType::MultiCity),
+        other => Err(decode_error(format!("unknown trip type {other}"))),
+    }
+}
+
+fn decode_airport(bytes: &[u8]) -> Result<String, FlightError> {
+    let mut reader = Reader { buf: bytes };
+    let mut code = None;
+    while let Some((field, wire)) = reader.field()? {
+        if let (2, Wire::Bytes(b)) = (field, wire) {
+            code = Some(utf8(b)?);
+        }
+    }
+    code.ok_or_else(|| decode_error("airport without a code"))
+}
+
+// The inverse of `encode_airport`: several entries are folded back into their metro
+// code; any other combination can't be represented by a single `FlightLeg` airport.
+fn fold_airports(airports: Vec<String>, what: &str) -> Result<String, FlightError> {
+    match airports.len() {
+        0 => Err(decode_error(format!("leg without a {what} airport"))),
+        1 => Ok(airports.into_iter().next().unwrap_or_default()),
+        _ => metro::metro_for(&airports).map(String::from).ok_or_else(|| {
+            decode_error(format!(
+                "several {what} airports ({}) are not supported",
+                airports.join(", ")
+            ))
+        }),
+    }
+}
+
+fn decode_selected(bytes: &[u8]) -> Result<SelectedFlight, FlightError> {
+    let mut selected = SelectedFlight {
+        from_airport: String::new(),
+        date: String::new(),
+        to_airport: String::new(),
+        airline: String::new(),
+        flight_number: String::new(),
+    };
+    let mut reader = Reader { buf: bytes };
+    while let Some((field, wire)) = reader.field()? {
+        let Wire::Bytes(b) = wire else { continue };
+        match field {
+            1 => selected.from_airport = utf8(b)?,
+            2 => selected.date = utf8(b)?,
+            3 => selected.to_airport = utf8(b)?,
+            5 => selected.airline = utf8(b)?,
+            6 => selected.flight_number = utf8(b)?,
+            _ => {}
+        }
+    }
+    Ok(selected)
+}
+
+fn decode_leg(bytes: &[u8]) -> Result<FlightLeg, FlightError> {
+    let mut leg = FlightLeg::default();
+    let (mut from, mut to) = (Vec::new(), Vec::new());
+    let mut reader = Reader { buf: bytes };
+    while let Some((field, wire)) = reader.field()? {
+        match (field, wire) {
+            (2, Wire::Bytes(b)) => leg.date = utf8(b)?,
+            (4, Wire::Bytes(b)) => leg.selected.push(decode_selected(b)?),
+            (5, Wire::Varint(v)) => leg.max_stops = Some(v as u32),
+            (6, Wire::Bytes(b)) => leg.airlines.get_or_insert_with(Vec::new).push(utf8(b)?),
+            (13, Wire::Bytes(b)) => from.push(decode_airport(b)?),
+            (14, Wire::Bytes(b)) => to.push(decode_airport(b)?),
+            _ => {}
+        }
+    }
+    leg.from_airport = fold_airports(from, "departure")?;
+    leg.to_airport = fold_airports(to, "arrival")?;
+    Ok(leg)
+}
+
+fn decode_passengers(bytes: &[u8], passengers: &mut Passengers) -> Result<(), FlightError> {
+    let mut reader = Reader { buf: bytes };
+    while !reader.buf.is_empty() {
+        add_passenger(reader.varint()?, passengers)?;
+    }
+    Ok(())
+}
+
+fn add_passenger(kind: u64, passengers: &mut Passengers) -> Result<(), FlightError> {
+    match kind {
+        1 => passengers.adults += 1,
+        2 => passengers.children += 1,
+        3 => passengers.infants_in_seat += 1,
+        4 => passengers.infants_on_lap += 1,
+        other => return Err(decode_error(format!("unknown passenger type {other}"))),
+    }
+    Ok(())
+}
+
+// Decodes what `encode_into` writes. `language`, `currency` and `region` are not
+// part of tfs and are left empty.
+pub fn decode(bytes: &[u8]) -> Result<QueryParams, FlightError> {
+    let mut legs = Vec::new();
+    let mut passengers = Passengers {
+        adults: 0,
+        ..Passengers::default()
+    };
+    let mut seat = Seat::Economy;
+    let mut trip = TripType::OneWay;
+
+    let mut reader = Reader { buf: bytes };
+    while let Some((field, wire)) = reader.field()? {
+        match (field, wire) {
+            (3, Wire::Bytes(b)) => legs.push(decode_leg(b)?),
+            (8, Wire::Bytes(b)) => decode_passengers(b, &mut passengers)?,
+            (8, Wire::Varint(v)) => add_passenger(v, &mut passengers)?,
+            (9, Wire::Varint(v)) => seat = seat_from_varint(v)?,
+            (19, Wire::Varint(v)) => trip = trip_from_varint(v)?,
+            _ => {}
+        }
+    }
+    if legs.is_empty() {
+        return Err(decode_error("no flight legs"));
+    }
+
+    Ok(QueryParams {
+        legs,
+        passengers,
+        seat,
+        trip,
+        language: String::new(),
+        currency: String::new(),
+        region: String::new(),
+        max_price: None,
+        low_emissions: false,
+    })
 }
@@ -1,19 +1,92 @@
-use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE_NO_PAD};
 use base64::Engine;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
+use crate::airports;
 use crate::error::FlightError;
+use crate::model::{FlightDateTime, FlightResult};
 use crate::proto;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct FlightLeg {
     pub date: String,
     pub from_airport: String,
     pub to_airport: String,
     pub max_stops: Option<u32>,
     pub airlines: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depart_after: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depart_before: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrive_after: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arrive_before: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration_minutes: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_layover_minutes: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_layover_minutes: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exclude_airlines: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seat: Option<Seat>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub no_redeye: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub selected: Vec<SelectedFlight>,
 }
 
-#[derive(Debug, Clone)]
+// One segment of a flight already chosen on a leg. Once every earlier leg has a choice,
+// Google lists the next leg's options priced together with it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SelectedFlight {
+    pub from_airport: String,
+    pub date: String,
+    pub to_airport: String,
+    pub airline: String,
+    pub flight_number: String,
+}
+
+impl SelectedFlight {
+    pub fn from_flight(flight: &FlightResult) -> Result<Vec<Self>, FlightError> {
+        if flight.segments.is_empty() {
+            return Err(FlightError::Validation("the selected result has no segments".into()));
+        }
+        flight
+            .segments
+            .iter()
+            .map(|seg| {
+                let (Some(airline), Some(number)) = (&seg.airline, &seg.flight_number) else {
+                    return Err(FlightError::Validation(format!(
+                        "the {} → {} segment has no flight number to select",
+                        seg.from_airport.code, seg.to_airport.code
+                    )));
+                };
+                Ok(Self {
+                    from_airport: seg.from_airport.code.clone(),
+                    date: days_to_date(seg.departure.days_since_epoch()),
+                    to_airport: seg.to_airport.code.clone(),
+                    airline: airline.clone(),
+                    flight_number: number.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl FlightLeg {
+    pub fn has_time_windows(&self) -> bool {
+        self.depart_after.is_some()
+            || self.depart_before.is_some()
+            || self.arrive_after.is_some()
+            || self.arrive_before.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Passengers {
     pub adults: u32,
     pub children: u32,
@@ -21,6 +94,45 @@ pub struct Passengers {
     pub infants_on_lap: u32,
 }
 
+impl Passengers {
+    pub fn validate(&self) -> Result<(), FlightError> {
+        let seated = self.adults + self.children + self.infants_in_seat;
+        let total = seated + self.infants_on_lap;
+
+        if total > 9 {
+            return Err(FlightError::Validation(format!(
+                "total passengers ({total}) exceeds maximum of 9 \
+                 ({seated} needing seats, {} on lap)",
+                self.infants_on_lap
+            )));
+        }
+
+        if total == 0 {
+            return Err(FlightError::Validation(
+                "at least one passenger required".into(),
+            ));
+        }
+
+        if self.infants_on_lap > self.adults {
+            return Err(FlightError::Validation(format!(
+                "infants on lap cannot exceed number of adults ({} infants, {} adults) \
+                 — each lap infant must sit with its own adult",
+                self.infants_on_lap, self.adults
+            )));
+        }
+
+        if self.infants_in_seat > self.adults {
+            return Err(FlightError::Validation(format!(
+                "infants in seat cannot exceed number of adults ({} infants, {} adults) \
+                 — airlines require an adult next to each seated infant",
+                self.infants_in_seat, self.adults
+            )));
+        }
+
+        Ok(())
+    }
+}
+
 impl Default for Passengers {
     fn default() -> Self {
         Self {
@@ -32,7 +144,8 @@ impl Default for Passengers {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
 pub enum Seat {
     Economy,
     PremiumEconomy,
@@ -50,9 +163,19 @@ impl Seat {
             _ => Err(FlightError::Validation(format!("invalid seat class: {s}"))),
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Economy => "economy",
+            Self::PremiumEconomy => "premium-economy",
+            Self::Business => "business",
+            Self::First => "first",
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
 pub enum TripType {
     RoundTrip,
     OneWay,
@@ -70,7 +193,7 @@ impl TripType {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct QueryParams {
     pub legs: Vec<FlightLeg>,
     pub passengers: Passengers,
@@ -78,11 +201,44 @@ pub struct QueryParams {
     pub trip: TripType,
     pub language: String,
     pub currency: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub region: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_price: Option<i64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub low_emissions: bool,
 }
 
 fn validate_airport(code: &str) -> Result<(), FlightError> {
-    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_uppercase()) {
-        return Err(FlightError::InvalidAirport(code.to_string()));
+    if code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase()) {
+        return Ok(());
+    }
+    if airports::is_place(code) {
+        let resolved = airports::resolve(code)?;
+        return Err(FlightError::Validation(format!(
+            "\"{code}\" is a place name, not an airport code — use {resolved} \
+             (flyr::airports::resolve converts names)"
+        )));
+    }
+    Err(FlightError::InvalidAirport(code.to_string()))
+}
+
+fn validate_window(
+    after: Option<u32>,
+    before: Option<u32>,
+    what: &str,
+) -> Result<(), FlightError> {
+    if after.into_iter().chain(before).any(|m| m > 1440) {
+        return Err(FlightError::Validation(format!(
+            "{what} time window must lie within one day"
+        )));
+    }
+    if let (Some(a), Some(b)) = (after, before) {
+        if a > b {
+            return Err(FlightError::Validation(format!(
+                "{what} window is empty: \"after\" is later than \"before\""
+            )));
+        }
     }
     Ok(())
 }
@@ -128,6 +284,110 @@ fn validate_date(date: &str) -> Result<(), FlightError> {
     Ok(())
 }
 
+pub fn date_to_days(date: &str) -> Option<i64> {
+    validate_date(date).ok()?;
+    let mut parts = date.split('-').map(|p| p.parse::<u32>().ok());
+    let dt = FlightDateTime {
+        year: parts.next()??,
+        month: parts.next()??,
+        day: parts.next()??,
+        hour: 0,
+        minute: 0,
+    };
+    Some(dt.days_since_epoch())
+}
+
+pub fn days_to_date(days: i64) -> String {
+    let dt = FlightDateTime::from_minutes_since_epoch(days * 1440);
+    format!("{:04}-{:02}-{:02}", dt.year, dt.month, dt.day)
+}
+
+pub fn format_timestamp(unix: u64) -> String {
+    let secs = unix % 86_400;
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        days_to_date((unix / 86_400) as i64),
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+pub fn parse_timestamp(s: &str) -> Option<u64> {
+    let (date, time) = s.strip_suffix('Z')?.split_once('T')?;
+    let days = u64::try_from(date_to_days(date)?).ok()?;
+    let mut parts = time.split(':').map(|p| p.parse::<u64>().ok());
+    let (h, m, sec) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(days * 86_400 + h * 3600 + m * 60 + sec)
+}
+
+pub fn add_days(date: &str, days: i64) -> Option<String> {
+    date_to_days(date).map(|d| days_to_date(d + days))
+}
+
+pub fn parse_minutes(s: &str) -> Result<u32, FlightError> {
+    let invalid = || {
+        FlightError::Validation(format!(
+            "invalid duration \"{s}\" — use minutes or hours, e.g. 90m, 2h, 1h30m"
+        ))
+    };
+
+    let lower = s.trim().to_lowercase();
+    if !lower.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let (hours, rest) = match lower.split_once('h') {
+        Some((h, rest)) => (h.parse::<u32>().map_err(|_| invalid())?, rest),
+        None => (0, lower.as_str()),
+    };
+    let rest = rest.strip_suffix('m').unwrap_or(rest);
+    let mins = if rest.is_empty() {
+        0
+    } else {
+        rest.parse::<u32>().map_err(|_| invalid())?
+    };
+
+    hours
+        .checked_mul(60)
+        .and_then(|m| m.checked_add(mins))
+        .ok_or_else(invalid)
+}
+
+pub fn parse_time_of_day(s: &str) -> Result<u32, FlightError> {
+    let invalid = || {
+        FlightError::Validation(format!("invalid time \"{s}\" — use HH:MM, e.g. 08:00, 21:30"))
+    };
+
+    let (h, m) = s.trim().split_once(':').ok_or_else(invalid)?;
+    let hours = h.parse::<u32>().map_err(|_| invalid())?;
+    let mins = m.parse::<u32>().map_err(|_| invalid())?;
+    if m.len() != 2 || hours > 24 || mins > 59 || (hours == 24 && mins > 0) {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + mins)
+}
+
+pub fn parse_delay(s: &str) -> Result<std::time::Duration, FlightError> {
+    let invalid = || {
+        FlightError::Validation(format!(
+            "invalid delay \"{s}\" — use milliseconds or seconds, e.g. 500ms, 2s"
+        ))
+    };
+
+    let lower = s.trim().to_lowercase();
+    let (value, millis_per_unit) = if let Some(ms) = lower.strip_suffix("ms") {
+        (ms, 1)
+    } else if let Some(secs) = lower.strip_suffix('s') {
+        (secs, 1000)
+    } else {
+        (lower.as_str(), 1000)
+    };
+    let n = value.parse::<u64>().map_err(|_| invalid())?;
+    let millis = n.checked_mul(millis_per_unit).ok_or_else(invalid)?;
+    Ok(std::time::Duration::from_millis(millis))
+}
+
 impl QueryParams {
     pub fn validate(&self) -> Result<(), FlightError> {
         if self.legs.is_empty() {
@@ -140,37 +400,121 @@ impl QueryParams {
             validate_airport(&leg.from_airport)?;
             validate_airport(&leg.to_airport)?;
             validate_date(&leg.date)?;
+            validate_window(leg.depart_after, leg.depart_before, "departure")?;
+            validate_window(leg.arrive_after, leg.arrive_before, "arrival")?;
+            if let (Some(min), Some(max)) = (leg.min_layover_minutes, leg.max_layover_minutes) {
+                if min > max {
+                    return Err(FlightError::Validation(format!(
+                        "minimum layover ({min}m) is longer than the maximum ({max}m)"
+                    )));
+                }
+            }
+            if let (Some(only), Some(excluded)) = (&leg.airlines, &leg.exclude_airlines) {
+                if let Some(code) = only.iter().find(|a| excluded.contains(a)) {
+                    return Err(FlightError::Validation(format!(
+                        "airline {code} is both required and excluded"
+                    )));
+                }
+            }
+            if leg.max_duration_minutes == Some(0) {
+                return Err(FlightError::Validation(
+                    "maximum trip duration must be longer than 0 minutes".into(),
+                ));
+            }
         }
 
-        let total = self.passengers.adults
-            + self.passengers.children
-            + self.passengers.infants_in_seat
-            + self.passengers.infants_on_lap;
-
-        if total > 9 {
+        if !self.region.is_empty()
+            && (self.region.len() != 2 || !self.region.chars().all(|c| c.is_ascii_alphabetic()))
+        {
             return Err(FlightError::Validation(format!(
-                "total passengers ({total}) exceeds maximum of 9"
+                "region must be a 2-letter country code (e.g. DE, US), got: {}",
+                self.region
             )));
         }
 
-        if total == 0 {
+        if self.max_price.is_some_and(|p| p <= 0) {
             return Err(FlightError::Validation(
-                "at least one passenger required".into(),
+                "maximum price must be greater than 0".into(),
             ));
         }
 
-        if self.passengers.infants_on_lap > self.passengers.adults {
-            return Err(FlightError::Validation(
-                "infants on lap cannot exceed number of adults".into(),
-            ));
+        self.passengers.validate()
+    }
+
+    // Google's query has one cabin for the whole trip, so a leg in another cabin is
+    // searched on its own (see `leg_search`).
+    pub fn has_mixed_cabins(&self) -> bool {
+        self.legs
+            .iter()
+            .any(|l| l.seat.as_ref().is_some_and(|s| *s != self.seat))
+    }
+
+    // Leg `i` as a one-way search in that leg's cabin. `max_price` is left to the
+    // combined trip.
+    pub fn leg_search(&self, i: usize) -> Option<QueryParams> {
+        let mut leg = self.legs.get(i)?.clone();
+        let seat = leg.seat.take().unwrap_or_else(|| self.seat.clone());
+        Some(QueryParams {
+            legs: vec![leg],
+            seat,
+            trip: TripType::OneWay,
+            max_price: None,
+            ..self.clone()
+        })
+    }
+
+    pub fn shift_days(&self, days: i64) -> Option<QueryParams> {
+        let mut shifted = self.clone();
+        for leg in &mut shifted.legs {
+            leg.date = add_days(&leg.date, days)?;
         }
+        Some(shifted)
+    }
 
-        Ok(())
+    pub fn tfs(&self) -> String {
+        STANDARD.encode(proto::encode(&self.legs, &self.passengers, &self.seat, &self.trip))
+    }
+
+    pub fn from_tfs(tfs: &str) -> Result<QueryParams, FlightError> {
+        let tfs = tfs.trim().trim_end_matches('=');
+        let bytes = URL_SAFE_NO_PAD
+            .decode(tfs)
+            .or_else(|_| STANDARD_NO_PAD.decode(tfs))
+            .map_err(|e| FlightError::Validation(format!("invalid tfs parameter: {e}")))?;
+        proto::decode(&bytes)
+    }
+
+    pub fn from_google_flights_url(url: &str) -> Result<QueryParams, FlightError> {
+        let query = url.split_once('?').map_or(url, |(_, q)| q);
+        let query = query.split('#').next().unwrap_or_default();
+        let mut tfs = None;
+        let (mut language, mut currency, mut region) = (String::new(), String::new(), String::new());
+        for pair in query.split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = urlencoding::decode(value)
+                .map_err(|e| FlightError::Validation(format!("invalid URL: {e}")))?
+                .into_owned();
+            match key {
+                "tfs" => tfs = Some(value),
+                "hl" => language = value,
+                "curr" => currency = value,
+                "gl" => region = value,
+                _ => {}
+            }
+        }
+        let tfs = tfs.ok_or_else(|| {
+            FlightError::Validation("URL has no tfs parameter (not a Google Flights search?)".into())
+        })?;
+
+        let mut params = Self::from_tfs(&tfs)?;
+        params.language = language;
+        params.currency = currency;
+        params.region = region;
+        Ok(params)
     }
 
     pub fn to_url_params(&self) -> Vec<(String, String)> {
-        let encoded = proto::encode(&self.legs, &self.passengers, &self.seat, &self.trip);
-        let b64 = STANDARD.encode(&encoded);
+        let b64 = self.tfs();
 
         let mut params = vec![("tfs".to_string(), b64)];
 
@@ -180,11 +524,16 @@ impl QueryParams {
         if !self.currency.is_empty() {
             params.push(("curr".to_string(), self.currency.clone()));
         }
+        if !self.region.is_empty() {
+            params.push(("gl".to_string(), self.region.clone()));
+        }
 
         params
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
 pub enum SearchQuery {
     Structured(QueryParams),
     NaturalLanguage(String),
@@ -213,6 +562,9 @@ pub fn to_google_flights_url(params: &QueryParams) -> String {
     if !params.language.is_empty() {
         url.push_str(&format!("&hl={}", params.language));
     }
+    if !params.region.is_empty() {
+        url.push_str(&format!("&gl={}", params.region));
+    }
 
     url
 }
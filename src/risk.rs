@@ -0,0 +1,39 @@
+use crate::model::{Risk, SearchResult, Segment, Ticketing};
+
+pub const DEFAULT_MIN_CONNECTION: u32 = 90;
+
+fn connection_minutes(arriving: &Segment, departing: &Segment) -> i64 {
+    departing.departure.minutes_since_epoch() - arriving.arrival.minutes_since_epoch()
+}
+
+fn changes_carrier(arriving: &Segment, departing: &Segment) -> bool {
+    match (&arriving.airline, &departing.airline) {
+        (Some(a), Some(b)) => a != b,
+        _ => false,
+    }
+}
+
+// On separate tickets no airline rebooks a missed connection, so they are never low
+// risk, and a short connection on them is as bad as a tight change of carrier.
+pub fn assess(segments: &[Segment], ticketing: Option<Ticketing>, min_connection: u32) -> Risk {
+    let floor = if ticketing.is_some() { Risk::Medium } else { Risk::Low };
+    segments
+        .windows(2)
+        .map(|w| {
+            let short = connection_minutes(&w[0], &w[1]) < min_connection as i64;
+            // A carrier change only matters when the connection is already tight.
+            let unprotected = ticketing.is_some() || changes_carrier(&w[0], &w[1]);
+            match (short, unprotected) {
+                (true, true) => Risk::High,
+                (true, false) => Risk::Medium,
+                (false, _) => Risk::Low,
+            }
+        })
+        .fold(floor, Risk::max)
+}
+
+pub fn annotate(result: &mut SearchResult, min_connection: u32) {
+    for flight in &mut result.flights {
+        flight.risk = assess(&flight.segments, flight.ticketing, min_connection);
+    }
+}
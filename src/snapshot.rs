@@ -0,0 +1,99 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::error::FlightError;
+use crate::fetch::unix_now;
+use crate::model::SearchResult;
+use crate::query::{self, QueryParams};
+use crate::store;
+
+pub const CSV_HEADER: &str =
+    "searched_at,from,to,date,return_date,lowest_price,nonstop_lowest,currency,flights";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotRow {
+    pub searched_at: String,
+    pub from: String,
+    pub to: String,
+    pub date: String,
+    pub return_date: Option<String>,
+    pub lowest_price: Option<i64>,
+    pub nonstop_lowest: Option<i64>,
+    pub currency: String,
+    pub flights: usize,
+}
+
+impl SnapshotRow {
+    pub fn new(params: &QueryParams, result: &SearchResult) -> Self {
+        let outbound = &params.legs[0];
+        let prices = |nonstop: bool| {
+            result
+                .flights
+                .iter()
+                .filter(|f| !nonstop || f.segments.len() == 1)
+                .filter_map(|f| f.price)
+                .min()
+        };
+        Self {
+            searched_at: result
+                .searched_at
+                .clone()
+                .unwrap_or_else(|| query::format_timestamp(unix_now())),
+            from: outbound.from_airport.clone(),
+            to: outbound.to_airport.clone(),
+            date: outbound.date.clone(),
+            return_date: params.legs.get(1).map(|l| l.date.clone()),
+            lowest_price: prices(false),
+            nonstop_lowest: prices(true),
+            currency: result
+                .metadata
+                .currency
+                .clone()
+                .unwrap_or_else(|| params.currency.clone()),
+            flights: result.flights.len(),
+        }
+    }
+
+    pub fn csv_line(&self) -> String {
+        let opt = |v: Option<i64>| v.map(|n| n.to_string()).unwrap_or_default();
+        [
+            self.searched_at.clone(),
+            self.from.clone(),
+            self.to.clone(),
+            self.date.clone(),
+            self.return_date.clone().unwrap_or_default(),
+            opt(self.lowest_price),
+            opt(self.nonstop_lowest),
+            self.currency.clone(),
+            self.flights.to_string(),
+        ]
+        .join(",")
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{}.csv", store::route_key(&self.from, &self.to))
+    }
+}
+
+pub fn append(dir: &Path, row: &SnapshotRow) -> Result<PathBuf, FlightError> {
+    let storage = |e: std::io::Error| FlightError::Storage(format!("{}: {e}", dir.display()));
+    std::fs::create_dir_all(dir).map_err(storage)?;
+
+    let path = dir.join(row.file_name());
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(storage)?;
+    let mut lines = String::new();
+    if file.metadata().map_err(storage)?.len() == 0 {
+        lines.push_str(CSV_HEADER);
+        lines.push('\n');
+    }
+    lines.push_str(&row.csv_line());
+    lines.push('\n');
+    file.write_all(lines.as_bytes()).map_err(storage)?;
+    Ok(path)
+}
@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+use serde::Serialize;
+
+use crate::error::FlightError;
+use crate::model::SearchResult;
+use crate::query::{self, QueryParams, TripType};
+
+pub const MAX_PAIRS: usize = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StayOption {
+    pub date: String,
+    pub return_date: String,
+    pub nights: u32,
+    pub price: Option<i64>,
+}
+
+pub fn parse_nights(s: &str) -> Result<RangeInclusive<u32>, FlightError> {
+    let invalid = || {
+        FlightError::Validation(format!(
+            "invalid --nights \"{s}\" — use a number or a range, e.g. 7 or 5-7"
+        ))
+    };
+    let (min, max) = s.trim().split_once('-').unwrap_or((s.trim(), s.trim()));
+    let min: u32 = min.trim().parse().map_err(|_| invalid())?;
+    let max: u32 = max.trim().parse().map_err(|_| invalid())?;
+    if min == 0 || min > max {
+        return Err(invalid());
+    }
+    Ok(min..=max)
+}
+
+// Every (departure, return) pair for departures within ±flex_days of `date` and stays
+// of `nights`, in date order.
+pub fn pairs(
+    date: &str,
+    flex_days: u32,
+    nights: &RangeInclusive<u32>,
+) -> Result<Vec<(String, String, u32)>, FlightError> {
+    let flex = i64::from(flex_days);
+    let mut pairs = Vec::new();
+    for offset in -flex..=flex {
+        let depart =
+            query::add_days(date, offset).ok_or_else(|| FlightError::InvalidDate(date.into()))?;
+        for n in nights.clone() {
+            let ret = query::add_days(&depart, i64::from(n))
+                .ok_or_else(|| FlightError::InvalidDate(depart.clone()))?;
+            pairs.push((depart.clone(), ret, n));
+        }
+    }
+    if pairs.len() > MAX_PAIRS {
+        return Err(FlightError::Validation(format!(
+            "--nights with --flex would run {} searches (at most {MAX_PAIRS}) — narrow the range",
+            pairs.len()
+        )));
+    }
+    Ok(pairs)
+}
+
+// Turns a one-way query into the round trip for one stay: the outbound leg moves to
+// `date` and a mirrored return leg with the same filters is added on `return_date`.
+pub fn round_trip(params: &QueryParams, date: &str, return_date: &str) -> QueryParams {
+    let mut stay = params.clone();
+    stay.legs.truncate(1);
+    if let Some(outbound) = stay.legs.first_mut() {
+        outbound.date = date.to_string();
+        let mut inbound = outbound.clone();
+        inbound.date = return_date.to_string();
+        std::mem::swap(&mut inbound.from_airport, &mut inbound.to_airport);
+        stay.legs.push(inbound);
+    }
+    stay.trip = TripType::RoundTrip;
+    stay
+}
+
+pub fn cheapest(
+    results: &BTreeMap<(String, String), Result<SearchResult, FlightError>>,
+) -> Vec<StayOption> {
+    let mut options: Vec<StayOption> = results
+        .iter()
+        .filter_map(|((date, return_date), result)| {
+            let result = result.as_ref().ok()?;
+            let nights = query::date_to_days(return_date)? - query::date_to_days(date)?;
+            Some(StayOption {
+                date: date.clone(),
+                return_date: return_date.clone(),
+                nights: nights as u32,
+                price: result.flights.iter().filter_map(|f| f.price).min(),
+            })
+        })
+        .collect();
+    options.sort_by(|a, b| {
+        (a.price.is_none(), a.price, &a.date, a.nights)
+            .cmp(&(b.price.is_none(), b.price, &b.date, b.nights))
+    });
+    options
+}
@@ -0,0 +1,339 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+use crate::cache::CacheStore;
+use crate::error::FlightError;
+use crate::model::{CarbonEmission, SearchResult};
+use crate::query::{QueryParams, Seat};
+
+pub const DEFAULT_KEEP_DAYS: u64 = 90;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS searches (
+    id INTEGER PRIMARY KEY,
+    cache_key TEXT NOT NULL,
+    route TEXT NOT NULL,
+    depart_date TEXT NOT NULL,
+    return_date TEXT,
+    seat TEXT NOT NULL,
+    currency TEXT,
+    searched_at INTEGER NOT NULL,
+    min_price INTEGER,
+    flight_count INTEGER NOT NULL,
+    result_json TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS searches_by_key ON searches (cache_key, searched_at);
+CREATE INDEX IF NOT EXISTS searches_by_route ON searches (route, depart_date, searched_at);
+CREATE TABLE IF NOT EXISTS responses (
+    key TEXT PRIMARY KEY,
+    stored_at INTEGER NOT NULL,
+    body TEXT NOT NULL
+);
+";
+
+fn storage_error(e: impl std::fmt::Display) -> FlightError {
+    FlightError::Storage(e.to_string())
+}
+
+// Results saved by older versions carry only whole-unit prices and raw emission grams.
+fn load_result(json: &str, params: &QueryParams) -> Result<SearchResult, FlightError> {
+    let mut result: SearchResult = serde_json::from_str(json).map_err(storage_error)?;
+    result.complete_fares(&params.currency);
+    for flight in &mut result.flights {
+        let carbon = &flight.carbon;
+        flight.carbon = CarbonEmission::new(carbon.emission_grams, carbon.typical_grams);
+    }
+    Ok(result)
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("FLYR_DB") {
+        return Some(PathBuf::from(path));
+    }
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".cache")))?;
+    Some(base.join("flyr").join("flyr.db"))
+}
+
+pub fn cache_key(params: &QueryParams) -> String {
+    params
+        .to_url_params()
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+pub fn route_key(from: &str, to: &str) -> String {
+    format!("{}-{}", from.to_uppercase(), to.to_uppercase())
+}
+
+fn seat_name(seat: &Seat) -> &'static str {
+    match seat {
+        Seat::Economy => "economy",
+        Seat::PremiumEconomy => "premium-economy",
+        Seat::Business => "business",
+        Seat::First => "first",
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPoint {
+    pub searched_at: String,
+    pub route: String,
+    pub depart_date: String,
+    pub return_date: Option<String>,
+    pub seat: String,
+    pub currency: Option<String>,
+    pub min_price: Option<i64>,
+    pub flight_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreStats {
+    pub path: String,
+    pub searches: i64,
+    pub routes: i64,
+    pub oldest: Option<String>,
+    pub newest: Option<String>,
+}
+
+pub struct Store {
+    conn: Mutex<Connection>,
+    path: PathBuf,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self, FlightError> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir).map_err(storage_error)?;
+        }
+        let conn = Connection::open(path).map_err(storage_error)?;
+        Self::init(conn, path.to_path_buf())
+    }
+
+    pub fn open_default() -> Result<Self, FlightError> {
+        let path = default_path().ok_or_else(|| {
+            FlightError::Storage("cannot locate a cache directory (HOME is not set)".into())
+        })?;
+        Self::open(&path)
+    }
+
+    pub fn in_memory() -> Result<Self, FlightError> {
+        let conn = Connection::open_in_memory().map_err(storage_error)?;
+        Self::init(conn, PathBuf::from(":memory:"))
+    }
+
+    fn init(conn: Connection, path: PathBuf) -> Result<Self, FlightError> {
+        conn.execute_batch(SCHEMA).map_err(storage_error)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            path,
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn lookup(
+        &self,
+        params: &QueryParams,
+        max_age_secs: u64,
+        now: u64,
+    ) -> Result<Option<SearchResult>, FlightError> {
+        let since = now.saturating_sub(max_age_secs) as i64;
+        let json: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT result_json FROM searches
+                 WHERE cache_key = ?1 AND searched_at >= ?2
+                 ORDER BY searched_at DESC, id DESC LIMIT 1",
+                params![cache_key(params), since],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(storage_error)?;
+
+        json.map(|j| load_result(&j, params)).transpose()
+    }
+
+    pub fn previous(
+        &self,
+        params: &QueryParams,
+        before: u64,
+    ) -> Result<Option<(u64, SearchResult)>, FlightError> {
+        let row: Option<(i64, String)> = self
+            .conn()
+            .query_row(
+                "SELECT searched_at, result_json FROM searches
+                 WHERE cache_key = ?1 AND searched_at < ?2
+                 ORDER BY searched_at DESC, id DESC LIMIT 1",
+                params![cache_key(params), before as i64],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(storage_error)?;
+
+        row.map(|(at, json)| {
+            load_result(&json, params).map(|result| (at as u64, result))
+        })
+        .transpose()
+    }
+
+    pub fn record(
+        &self,
+        params: &QueryParams,
+        result: &SearchResult,
+        now: u64,
+    ) -> Result<(), FlightError> {
+        let (Some(first), Some(last)) = (params.legs.first(), params.legs.last()) else {
+            return Ok(());
+        };
+        let route = route_key(&first.from_airport, &first.to_airport);
+        let return_date = (params.legs.len() > 1).then(|| last.date.clone());
+        let currency = result
+            .metadata
+            .currency
+            .clone()
+            .or_else(|| (!params.currency.is_empty()).then(|| params.currency.clone()));
+        let min_price = result.flights.iter().filter_map(|f| f.price).min();
+        let json = serde_json::to_string(result).map_err(storage_error)?;
+
+        self.conn()
+            .execute(
+                "INSERT INTO searches (cache_key, route, depart_date, return_date, seat,
+                     currency, searched_at, min_price, flight_count, result_json)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    cache_key(params),
+                    route,
+                    first.date,
+                    return_date,
+                    seat_name(&params.seat),
+                    currency,
+                    now as i64,
+                    min_price,
+                    result.flights.len() as i64,
+                    json,
+                ],
+            )
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    pub fn history(
+        &self,
+        route: &str,
+        depart_date: Option<&str>,
+    ) -> Result<Vec<HistoryPoint>, FlightError> {
+        let conn = self.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT strftime('%Y-%m-%dT%H:%M:%SZ', searched_at, 'unixepoch'), route,
+                     depart_date, return_date, seat, currency, min_price, flight_count
+                 FROM searches
+                 WHERE route = ?1 AND (?2 IS NULL OR depart_date = ?2)
+                 ORDER BY depart_date, searched_at, id",
+            )
+            .map_err(storage_error)?;
+
+        let rows = stmt
+            .query_map(params![route, depart_date], |row| {
+                Ok(HistoryPoint {
+                    searched_at: row.get(0)?,
+                    route: row.get(1)?,
+                    depart_date: row.get(2)?,
+                    return_date: row.get(3)?,
+                    seat: row.get(4)?,
+                    currency: row.get(5)?,
+                    min_price: row.get(6)?,
+                    flight_count: row.get(7)?,
+                })
+            })
+            .map_err(storage_error)?;
+
+        rows.collect::<Result<Vec<_>, _>>().map_err(storage_error)
+    }
+
+    pub fn prune(&self, keep_days: u64, now: u64) -> Result<usize, FlightError> {
+        let cutoff = now.saturating_sub(keep_days * 86_400) as i64;
+        let conn = self.conn();
+        let removed = conn
+            .execute("DELETE FROM searches WHERE searched_at < ?1", params![cutoff])
+            .map_err(storage_error)?;
+        conn.execute("DELETE FROM responses WHERE stored_at < ?1", params![cutoff])
+            .map_err(storage_error)?;
+        Ok(removed)
+    }
+
+    pub fn clear(&self) -> Result<usize, FlightError> {
+        let conn = self.conn();
+        conn.execute("DELETE FROM responses", [])
+            .map_err(storage_error)?;
+        conn.execute("DELETE FROM searches", [])
+            .map_err(storage_error)
+    }
+
+    pub fn stats(&self) -> Result<StoreStats, FlightError> {
+        self.conn()
+            .query_row(
+                "SELECT COUNT(*), COUNT(DISTINCT route),
+                     strftime('%Y-%m-%dT%H:%M:%SZ', MIN(searched_at), 'unixepoch'),
+                     strftime('%Y-%m-%dT%H:%M:%SZ', MAX(searched_at), 'unixepoch')
+                 FROM searches",
+                [],
+                |row| {
+                    Ok(StoreStats {
+                        path: self.path.display().to_string(),
+                        searches: row.get(0)?,
+                        routes: row.get(1)?,
+                        oldest: row.get(2)?,
+                        newest: row.get(3)?,
+                    })
+                },
+            )
+            .map_err(storage_error)
+    }
+}
+
+impl CacheStore for Store {
+    fn get(&self, key: &str, max_age: Duration) -> Result<Option<String>, FlightError> {
+        let since = crate::fetch::unix_now().saturating_sub(max_age.as_secs()) as i64;
+        self.conn()
+            .query_row(
+                "SELECT body FROM responses WHERE key = ?1 AND stored_at >= ?2",
+                params![key, since],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(storage_error)
+    }
+
+    fn put(&self, key: &str, value: &str) -> Result<(), FlightError> {
+        self.conn()
+            .execute(
+                "INSERT OR REPLACE INTO responses (key, stored_at, body) VALUES (?1, ?2, ?3)",
+                params![key, crate::fetch::unix_now() as i64, value],
+            )
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn evict(&self, older_than: Duration) -> Result<usize, FlightError> {
+        let cutoff = crate::fetch::unix_now().saturating_sub(older_than.as_secs()) as i64;
+        self.conn()
+            .execute("DELETE FROM responses WHERE stored_at < ?1", params![cutoff])
+            .map_err(storage_error)
+    }
+}
@@ -1,62 +1,223 @@
-use comfy_table::{Table, ContentArrangement, presets::UTF8_FULL};
+use comfy_table::{Cell, Color, Table, ContentArrangement, presets::UTF8_FULL};
 
-use crate::model::SearchResult;
+use crate::bench::FixtureReport;
+use crate::budget::BudgetMatch;
+use crate::cabins::SeatComparison;
+use crate::carbon::{CarbonReport, CarbonRow};
+use crate::diff::{self, Change};
+use crate::locale::Locale;
+use crate::model::{
+    BagAllowance, Baseline, BookingOption, CalendarDay, FlightResult, Money, PriceInsights,
+    PriceScope, Risk, SearchResult,
+};
+use crate::stay::StayOption;
+use crate::store::HistoryPoint;
+use crate::value;
 
 pub fn format_price(price: Option<i64>, currency: &str) -> String {
-    let p = match price {
-        Some(p) => p,
-        None => return "—".to_string(),
+    format_money(price.map(|p| Money::from_major(p, currency)).as_ref())
+}
+
+pub fn format_money(money: Option<&Money>) -> String {
+    Locale::default().money(money)
+}
+
+// The exact fare where there is one, otherwise the whole-unit price in `currency`.
+pub fn flight_price(flight: &FlightResult, currency: &str, locale: &Locale) -> String {
+    match &flight.fare {
+        Some(fare) => locale.money(Some(fare)),
+        None => locale.price(flight.price, currency),
+    }
+}
+
+// The price followed by what it covers: "€589 round trip" or "€320 outbound".
+pub fn scoped_price(flight: &FlightResult, currency: &str, locale: &Locale) -> String {
+    let price = flight_price(flight, currency, locale);
+    match flight.price_scope {
+        Some(PriceScope::Total) => format!("{price} round trip"),
+        Some(PriceScope::Outbound) => format!("{price} outbound"),
+        None => price,
+    }
+}
+
+fn format_duration(minutes: u32) -> String {
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Highlights {
+    pub alert_below: Option<i64>,
+    pub operated_by: bool,
+}
+
+impl Highlights {
+    pub fn is_alert(&self, flight: &FlightResult) -> bool {
+        matches!((self.alert_below, flight.price), (Some(limit), Some(p)) if p < limit)
+    }
+}
+
+pub fn render_alert_summary(
+    result: &SearchResult,
+    limit: i64,
+    currency: &str,
+    locale: &Locale,
+) -> String {
+    let limit_str = locale.price(Some(limit), currency);
+    let below: Vec<&FlightResult> =
+        result.flights.iter().filter(|f| f.price.is_some_and(|p| p < limit)).collect();
+    let cheapest = result
+        .flights
+        .iter()
+        .filter(|f| f.price.is_some())
+        .min_by_key(|f| f.price);
+    let describe = |f: &FlightResult| {
+        format!("{} ({})", locale.price(f.price, currency), f.airlines.join(", "))
+    };
+
+    match (below.len(), cheapest) {
+        (0, Some(c)) => format!("No flights below {limit_str}; cheapest is {}.", describe(c)),
+        (0, None) => format!("No flights below {limit_str}."),
+        (n, c) => format!(
+            "Alert: {n} of {} flights below {limit_str}; cheapest is {}.",
+            result.flights.len(),
+            c.map(describe).unwrap_or_default()
+        ),
+    }
+}
+
+pub fn render_price_insights(
+    insights: &PriceInsights,
+    currency: &str,
+    locale: &Locale,
+) -> Option<String> {
+    let level = insights.current_level?;
+    let range = match (insights.typical_low, insights.typical_high) {
+        (Some(low), Some(high)) => format!(
+            " (typically {}–{} for this route)",
+            locale.price(Some(low), currency),
+            locale.price(Some(high), currency)
+        ),
+        _ => String::new(),
     };
-    match currency {
-        "USD" => format!("${p}"),
-        "EUR" => format!("€{p}"),
-        "GBP" => format!("£{p}"),
-        "JPY" | "CNY" => format!("¥{p}"),
-        "KRW" => format!("₩{p}"),
-        "INR" => format!("₹{p}"),
-        "THB" => format!("฿{p}"),
-        _ => format!("{p} {currency}"),
+    Some(format!(
+        "Prices are currently {}{range}.",
+        level.to_string().to_uppercase()
+    ))
+}
+
+pub fn format_change(
+    flight: &FlightResult,
+    baseline: &Baseline,
+    currency: &str,
+    locale: &Locale,
+) -> String {
+    let since = diff::format_age(baseline.age_secs);
+    match diff::change(flight) {
+        Change::New => "new".to_string(),
+        Change::Down(d) => format!("▼ {} since {since}", locale.price(Some(d), currency)),
+        Change::Up(d) => format!("▲ {} since {since}", locale.price(Some(d), currency)),
+        Change::Unchanged => format!("unchanged since {since}"),
     }
 }
 
-pub fn render(result: &SearchResult, currency: &str) -> String {
+fn change_color(flight: &FlightResult) -> Option<Color> {
+    match diff::change(flight) {
+        Change::Down(_) => Some(Color::Green),
+        Change::Up(_) => Some(Color::Red),
+        Change::New | Change::Unchanged => None,
+    }
+}
+
+pub fn render(
+    result: &SearchResult,
+    currency: &str,
+    locale: &Locale,
+    highlights: &Highlights,
+) -> String {
+    let mut header = vec!["Airlines"];
+    if highlights.operated_by {
+        header.push("Operated by");
+    }
+    header.extend([
+        "Route", "Depart", "Arrive", "Duration", "Air time", "Stops", "Aircraft",
+    ]);
+    let with_legroom = result
+        .flights
+        .iter()
+        .any(|f| f.segments.iter().any(|s| s.legroom.is_some()));
+    if with_legroom {
+        header.push("Legroom");
+    }
+    let with_emissions = result.flights.iter().any(|f| f.carbon.emission_grams.is_some());
+    if with_emissions {
+        header.push("CO2");
+    }
+    header.push("Price");
+
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL)
         .set_content_arrangement(ContentArrangement::Dynamic)
-        .set_header(vec![
-            "Airlines", "Route", "Depart", "Arrive", "Duration", "Stops", "Aircraft", "Price",
-        ]);
+        .set_header(header);
 
-    for flight in &result.flights {
-        let airlines = flight.airlines.join(", ");
+    let best = value::best(&result.flights);
+    for (i, flight) in result.flights.iter().enumerate() {
+        let mut airlines = flight.airlines.join(", ");
+        if flight.often_delayed() {
+            airlines.push_str("\n⚠ often delayed");
+        }
+        if let Some(ticketing) = flight.ticketing {
+            airlines.push_str(&format!("\n⚠ {ticketing}"));
+        }
+        if let Some(brand) = flight.fare_brand {
+            airlines.push_str(&format!("\n{brand}"));
+        }
 
         let route: Vec<String> = flight
             .segments
             .iter()
-            .map(|s| format!("{} → {}", s.from_airport.code, s.to_airport.code))
+            .map(|s| {
+                let mode = if s.kind.is_ground() {
+                    format!(" ({})", s.kind)
+                } else {
+                    String::new()
+                };
+                format!(
+                    "{}{} → {}{}{mode}",
+                    s.from_airport.code,
+                    terminal_suffix(s.departure_terminal.as_deref()),
+                    s.to_airport.code,
+                    terminal_suffix(s.arrival_terminal.as_deref())
+                )
+            })
             .collect();
         let route_str = route.join("\n");
 
+        let origin = flight
+            .segments
+            .first()
+            .map(|s| s.from_airport.code.as_str())
+            .unwrap_or("");
+
         let depart = flight
             .segments
             .first()
-            .map(|s| s.departure.to_string())
+            .map(|s| locale.datetime_at(&s.departure, &s.from_airport.code, origin))
             .unwrap_or_else(|| "—".to_string());
 
         let arrive = flight
             .segments
             .last()
-            .map(|s| s.arrival.to_string())
+            .map(|s| locale.datetime_at(&s.arrival, &s.to_airport.code, origin))
             .unwrap_or_else(|| "—".to_string());
 
-        let duration = if flight.segments.is_empty() {
-            "—".to_string()
+        let (duration, air_time) = if flight.segments.is_empty() {
+            ("—".to_string(), "—".to_string())
         } else {
-            let total_duration: u32 = flight.segments.iter().map(|s| s.duration_minutes).sum();
-            let hours = total_duration / 60;
-            let mins = total_duration % 60;
-            format!("{hours}h {mins:02}m")
+            (
+                format_duration(flight.elapsed_minutes),
+                format_duration(flight.air_minutes),
+            )
         };
 
         let stops = if flight.segments.is_empty() {
@@ -72,6 +233,11 @@ pub fn render(result: &SearchResult, currency: &str) -> String {
             format!("{n} ({})", stopovers.join(", "))
         };
 
+        let stops = match flight.risk {
+            Risk::Low => stops,
+            r => format!("{stops}\n⚠ {r} risk"),
+        };
+
         let aircraft: Vec<String> = flight
             .segments
             .iter()
@@ -79,19 +245,656 @@ pub fn render(result: &SearchResult, currency: &str) -> String {
             .collect();
         let aircraft_str = aircraft.join(", ");
 
-        let price = format_price(flight.price, currency);
+        let price = flight_price(flight, currency, locale);
+        let price = match flight.price_scope {
+            Some(PriceScope::Total) => format!("{price}\n(round trip)"),
+            Some(PriceScope::Outbound) => format!("{price}\n(outbound)"),
+            None => price,
+        };
+        let price = match flight.price_with_bag {
+            Some(p) => format!("{price}\n{} with bag", locale.price(Some(p), currency)),
+            None => price,
+        };
+        let price = if highlights.is_alert(flight) {
+            format!("{price}\n▼ alert")
+        } else {
+            price
+        };
+        let price = if best == Some(i) {
+            format!("{price}\n★ best value")
+        } else {
+            price
+        };
+        let (price, color) = match &result.baseline {
+            Some(baseline) => (
+                format!("{price}\n{}", format_change(flight, baseline, currency, locale)),
+                change_color(flight),
+            ),
+            None => (price, None),
+        };
+        let price = match color {
+            Some(color) => Cell::new(price).fg(color),
+            None => Cell::new(price),
+        };
+
+        let mut row = vec![Cell::new(airlines)];
+        if highlights.operated_by {
+            row.push(Cell::new(operators(flight)));
+        }
+        row.extend([
+            Cell::new(route_str),
+            Cell::new(depart),
+            Cell::new(arrive),
+            Cell::new(duration),
+            Cell::new(air_time),
+            Cell::new(stops),
+            Cell::new(aircraft_str),
+        ]);
+        if with_legroom {
+            let legroom: Vec<&str> = flight
+                .segments
+                .iter()
+                .map(|s| s.legroom.as_deref().unwrap_or("—"))
+                .collect();
+            row.push(Cell::new(legroom.join("\n")));
+        }
+        if with_emissions {
+            let mut co2 = format_emissions(flight.carbon.emission_grams);
+            if let Some(delta) = flight.carbon.delta_percent {
+                co2.push_str(&format!("\n{}", format_emissions_delta(delta)));
+            }
+            row.push(Cell::new(co2));
+        }
+        row.push(price);
+        table.add_row(row);
+    }
+
+    table.to_string()
+}
+
+// " T2" for numbered terminals, " International" and the like as given.
+fn terminal_suffix(terminal: Option<&str>) -> String {
+    match terminal {
+        Some(t) if t.starts_with(|c: char| c.is_ascii_digit()) => format!(" T{t}"),
+        Some(t) => format!(" {t}"),
+        None => String::new(),
+    }
+}
+
+// One line per segment, so it lines up with the Route column; segments flown by the
+// airline that sells them show that airline.
+fn operators(flight: &FlightResult) -> String {
+    flight
+        .segments
+        .iter()
+        .map(|s| {
+            s.operated_by
+                .as_deref()
+                .or(s.airline_name.as_deref())
+                .or(s.airline.as_deref())
+                .unwrap_or("—")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn spoken_duration(minutes: u32) -> String {
+    let unit = |n: u32, word: &str| match n {
+        1 => format!("1 {word}"),
+        n => format!("{n} {word}s"),
+    };
+    match (minutes / 60, minutes % 60) {
+        (0, m) => unit(m, "minute"),
+        (h, 0) => unit(h, "hour"),
+        (h, m) => format!("{} {}", unit(h, "hour"), unit(m, "minute")),
+    }
+}
+
+pub fn render_plain(
+    result: &SearchResult,
+    currency: &str,
+    locale: &Locale,
+    highlights: &Highlights,
+) -> String {
+    let mut lines = Vec::with_capacity(result.flights.len());
+    let best = value::best(&result.flights);
 
+    for (i, flight) in result.flights.iter().enumerate() {
+        let mut fields = vec![format!("Option {} of {}", i + 1, result.flights.len())];
+
+        fields.push(format!("Price: {}", scoped_price(flight, currency, locale)));
+        if let Some(p) = flight.price_with_bag {
+            fields.push(format!("Price with bag: {}", locale.price(Some(p), currency)));
+        }
+        if let Some(brand) = flight.fare_brand {
+            fields.push(format!("Fare: {brand}"));
+        }
+        if flight.baggage.is_some() {
+            fields.push(format!(
+                "Included bags: {}",
+                format_baggage(flight.baggage.as_ref())
+            ));
+        }
+        if let Some(baseline) = &result.baseline {
+            let since = diff::format_age(baseline.age_secs);
+            fields.push(match diff::change(flight) {
+                Change::New => "New since the last search".to_string(),
+                Change::Down(d) => {
+                    format!("Down {} since {since}", locale.price(Some(d), currency))
+                }
+                Change::Up(d) => format!("Up {} since {since}", locale.price(Some(d), currency)),
+                Change::Unchanged => format!("Unchanged since {since}"),
+            });
+        }
+
+        if highlights.is_alert(flight) {
+            fields.push("Below alert price".to_string());
+        }
+
+        if best == Some(i) {
+            fields.push("Best value".to_string());
+        }
+
+        if !flight.airlines.is_empty() {
+            fields.push(format!("Airlines: {}", flight.airlines.join(", ")));
+        }
+
+        if let (Some(first), Some(last)) = (flight.segments.first(), flight.segments.last()) {
+            let via: Vec<&str> = flight.segments[..flight.segments.len() - 1]
+                .iter()
+                .map(|s| s.to_airport.code.as_str())
+                .collect();
+            let route = format!("Route: {} to {}", first.from_airport.code, last.to_airport.code);
+            fields.push(if via.is_empty() {
+                format!("{route}, nonstop")
+            } else {
+                format!("{route} via {}", via.join(" and "))
+            });
+            let origin = first.from_airport.code.as_str();
+            fields.push(format!(
+                "Departs {}",
+                locale.datetime_at(&first.departure, origin, origin)
+            ));
+            fields.push(format!(
+                "Arrives {}",
+                locale.datetime_at(&last.arrival, &last.to_airport.code, origin)
+            ));
+            fields.push(format!("Duration: {}", spoken_duration(flight.elapsed_minutes)));
+        }
+
+        for s in flight.segments.iter().filter(|s| s.kind.is_ground()) {
+            let mode = s.kind.to_string();
+            let mode = mode[..1].to_uppercase() + &mode[1..];
+            fields.push(format!(
+                "{mode} from {} to {}",
+                s.from_airport.code, s.to_airport.code
+            ));
+        }
+
+        if flight.risk != Risk::Low {
+            fields.push(format!("Connection risk: {}", flight.risk));
+        }
+
+        for pair in flight.segments.windows(2) {
+            if let (Some(arrive), Some(depart)) =
+                (&pair[0].arrival_terminal, &pair[1].departure_terminal)
+            {
+                if arrive != depart {
+                    fields.push(format!(
+                        "Terminal change at {}: {} to {}",
+                        pair[0].to_airport.code,
+                        terminal_suffix(Some(arrive)).trim(),
+                        terminal_suffix(Some(depart)).trim()
+                    ));
+                }
+            }
+        }
+
+        for warning in &flight.warnings {
+            match warning.kind.as_str() {
+                "often_delayed" => fields.push(format!("Punctuality: {}", warning.message)),
+                "separate_tickets" | "self_transfer" => {
+                    fields.push(format!("Ticketing: {}", warning.message))
+                }
+                _ => {}
+            }
+        }
+
+        let aircraft: Vec<&str> = flight
+            .segments
+            .iter()
+            .filter_map(|s| s.aircraft.as_deref())
+            .collect();
+        if !aircraft.is_empty() {
+            fields.push(format!("Aircraft: {}", aircraft.join(", ")));
+        }
+
+        if let Some(grams) = flight.carbon.emission_grams {
+            let delta = match flight.carbon.delta_percent {
+                Some(0) => ", typical for the route".to_string(),
+                Some(d) if d < 0 => format!(", {}% below typical", -d),
+                Some(d) => format!(", {d}% above typical"),
+                None => String::new(),
+            };
+            fields.push(format!("Emissions: {}{delta}", format_emissions(Some(grams))));
+        }
+
+        let legroom: Vec<&str> = flight
+            .segments
+            .iter()
+            .filter_map(|s| s.legroom.as_deref())
+            .collect();
+        if !legroom.is_empty() {
+            fields.push(format!("Legroom: {}", legroom.join(", ")));
+        }
+
+        let mut amenities: Vec<&str> = Vec::new();
+        for amenity in flight.segments.iter().flat_map(|s| &s.amenities) {
+            if !amenities.contains(&amenity.as_str()) {
+                amenities.push(amenity);
+            }
+        }
+        if !amenities.is_empty() {
+            fields.push(format!("On board: {}", amenities.join(", ")));
+        }
+
+        let mut operated_by: Vec<&str> = flight
+            .segments
+            .iter()
+            .filter_map(|s| s.operated_by.as_deref())
+            .collect();
+        operated_by.dedup();
+        if !operated_by.is_empty() {
+            fields.push(format!("Operated by {}", operated_by.join(" and ")));
+        }
+
+        let codeshares: Vec<String> = flight
+            .segments
+            .iter()
+            .flat_map(|s| &s.codeshares)
+            .map(|c| format!("{}{}", c.airline, c.flight_number))
+            .collect();
+        if !codeshares.is_empty() {
+            fields.push(format!("Also sold as {}", codeshares.join(", ")));
+        }
+
+        lines.push(format!("{}.", fields.join(". ")));
+    }
+
+    lines.join("\n")
+}
+
+pub fn render_history(points: &[HistoryPoint]) -> String {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Searched", "Depart", "Return", "Seat", "Flights", "Cheapest"]);
+
+    for point in points {
+        let currency = point.currency.as_deref().unwrap_or("");
         table.add_row(vec![
-            &airlines,
-            &route_str,
-            &depart,
-            &arrive,
-            &duration,
-            &stops,
-            &aircraft_str,
-            &price,
+            point.searched_at.replace('T', " ").trim_end_matches('Z').to_string(),
+            point.depart_date.clone(),
+            point.return_date.clone().unwrap_or_else(|| "—".to_string()),
+            point.seat.clone(),
+            point.flight_count.to_string(),
+            format_price(point.min_price, currency),
         ]);
     }
 
     table.to_string()
 }
+
+fn weekday(date: &str) -> &'static str {
+    const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    crate::query::date_to_days(date)
+        .map(|d| DAYS[(d + 4).rem_euclid(7) as usize])
+        .unwrap_or("")
+}
+
+pub fn render_calendar(
+    days: &[CalendarDay],
+    currency: &str,
+    marked: Option<&str>,
+    locale: &Locale,
+) -> String {
+    let with_return = days.iter().any(|d| d.return_date.is_some());
+    let cheapest = days.iter().filter_map(|d| d.price).min();
+
+    let mut header = vec!["Date", "Day"];
+    if with_return {
+        header.push("Return");
+    }
+    header.push("Cheapest");
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(header);
+
+    for day in days {
+        let mut date = locale.iso_date(&day.date);
+        if marked == Some(day.date.as_str()) {
+            date.push_str(" ◀");
+        }
+        let mut price = locale.price(day.price, currency);
+        if day.price.is_some() && day.price == cheapest {
+            price.push_str(" ★");
+        }
+
+        let mut row = vec![date, weekday(&day.date).to_string()];
+        if with_return {
+            row.push(
+                day.return_date
+                    .as_deref()
+                    .map(|r| locale.iso_date(r))
+                    .unwrap_or_else(|| "—".to_string()),
+            );
+        }
+        row.push(price);
+        table.add_row(row);
+    }
+
+    table.to_string()
+}
+
+pub fn render_booking(options: &[BookingOption], currency: &str, locale: &Locale) -> String {
+    let cheapest = options.iter().filter_map(|o| o.price).min();
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Provider", "Sells as", "Price", "Link"]);
+
+    for option in options {
+        let mut price = locale.price(option.price, currency);
+        if option.price.is_some() && option.price == cheapest {
+            price.push_str(" ★");
+        }
+        let kind = if option.airline_direct { "Airline" } else { "Agency" };
+        table.add_row(vec![
+            option.provider.clone(),
+            kind.to_string(),
+            price,
+            option.url.clone().unwrap_or_else(|| "—".to_string()),
+        ]);
+    }
+
+    table.to_string()
+}
+
+pub fn render_budget(matches: &[BudgetMatch], currency: &str, locale: &Locale) -> String {
+    let with_return = matches.iter().any(|m| m.return_date.is_some());
+
+    let mut header = vec!["Destination", "Date", "Day"];
+    if with_return {
+        header.push("Return");
+    }
+    header.push("Price");
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(header);
+
+    for m in matches {
+        let mut row = vec![
+            m.destination.clone(),
+            locale.iso_date(&m.date),
+            weekday(&m.date).to_string(),
+        ];
+        if with_return {
+            row.push(
+                m.return_date
+                    .as_deref()
+                    .map(|r| locale.iso_date(r))
+                    .unwrap_or_else(|| "—".to_string()),
+            );
+        }
+        row.push(locale.price(Some(m.price), currency));
+        table.add_row(row);
+    }
+
+    table.to_string()
+}
+
+pub fn render_stays(stays: &[StayOption], currency: &str, locale: &Locale) -> String {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Depart", "Day", "Return", "Day", "Nights", "Price"]);
+
+    for s in stays {
+        table.add_row(vec![
+            locale.iso_date(&s.date),
+            weekday(&s.date).to_string(),
+            locale.iso_date(&s.return_date),
+            weekday(&s.return_date).to_string(),
+            s.nights.to_string(),
+            locale.price(s.price, currency),
+        ]);
+    }
+
+    table.to_string()
+}
+
+pub fn render_bench(reports: &[FixtureReport]) -> String {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "Fixture", "Size", "Flights", "Mean", "Extract", "JSON", "Build", "MiB/s", "Allocs",
+        ]);
+
+    for r in reports {
+        let size = format!("{:.1} KiB", r.bytes as f64 / 1024.0);
+        if let Some(ref error) = r.error {
+            table.add_row(vec![r.fixture.clone(), size, format!("error: {}", error.kind)]);
+            continue;
+        }
+        let us = |v: f64| format!("{v:.1}µs");
+        table.add_row(vec![
+            r.fixture.clone(),
+            size,
+            r.flights.to_string(),
+            us(r.mean_us),
+            us(r.extract_us),
+            us(r.json_us),
+            us(r.build_us),
+            format!("{:.1}", r.mib_per_sec),
+            r.allocations_per_iter
+                .map(|a| a.to_string())
+                .unwrap_or_else(|| "—".to_string()),
+        ]);
+    }
+
+    table.to_string()
+}
+
+pub fn format_emissions(grams: Option<i64>) -> String {
+    match grams {
+        Some(g) => format!("{} kg", (g + 500) / 1000),
+        None => "—".to_string(),
+    }
+}
+
+// "−18% emissions" against the route's typical itinerary.
+pub fn format_emissions_delta(delta: i64) -> String {
+    match delta {
+        0 => "typical emissions".to_string(),
+        d if d < 0 => format!("−{}% emissions", -d),
+        d => format!("+{d}% emissions"),
+    }
+}
+
+pub fn format_baggage(allowance: Option<&BagAllowance>) -> String {
+    let Some(allowance) = allowance else {
+        return "—".to_string();
+    };
+    let mut parts = Vec::new();
+    match allowance.carry_on {
+        Some(0) => parts.push("no carry-on".to_string()),
+        Some(n) => parts.push(format!("{n} carry-on")),
+        None => {}
+    }
+    match allowance.checked {
+        Some(0) => parts.push("no checked bag".to_string()),
+        Some(1) => parts.push("1 checked bag".to_string()),
+        Some(n) => parts.push(format!("{n} checked bags")),
+        None => {}
+    }
+    if parts.is_empty() {
+        return "—".to_string();
+    }
+    parts.join(", ")
+}
+
+pub fn format_delta(delta: Option<i64>) -> String {
+    match delta {
+        Some(0) => "typical".to_string(),
+        Some(d) => format!("{d:+}%"),
+        None => "—".to_string(),
+    }
+}
+
+fn describe(row: &CarbonRow, currency: &str, locale: &Locale) -> String {
+    format!(
+        "{} {} — {} CO2 ({}), {}",
+        row.airlines.join(", "),
+        row.route.join(">"),
+        format_emissions(row.emission_grams),
+        format_delta(row.delta_percent),
+        locale.price(row.price, currency)
+    )
+}
+
+pub fn render_carbon_summary(report: &CarbonReport, currency: &str, locale: &Locale) -> String {
+    let (Some(green), Some(cheap)) = (&report.greenest, &report.cheapest) else {
+        return "No emissions data for these itineraries.".to_string();
+    };
+
+    let mut lines = vec![
+        format!("Greenest: {}", describe(green, currency, locale)),
+        format!("Cheapest: {}", describe(cheap, currency, locale)),
+    ];
+    match (report.grams_saved, report.extra_cost) {
+        (Some(0), _) | (None, _) => {
+            lines.push("The cheapest option is also the greenest.".to_string())
+        }
+        (Some(saved), cost) => {
+            let pct = cheap
+                .emission_grams
+                .filter(|&g| g > 0)
+                .map(|g| format!(" ({}%)", saved * 100 / g))
+                .unwrap_or_default();
+            let cost = match cost {
+                Some(c) if c > 0 => format!("{} more", locale.price(Some(c), currency)),
+                Some(c) if c < 0 => format!("{} less", locale.price(Some(-c), currency)),
+                _ => "the same price".to_string(),
+            };
+            lines.push(format!(
+                "Choosing the greenest saves {}{pct} CO2 for {cost}.",
+                format_emissions(Some(saved))
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+pub fn render_carbon(report: &CarbonReport, currency: &str, locale: &Locale) -> String {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec!["Airlines", "Route", "CO2", "vs typical", "Price"]);
+
+    for row in &report.itineraries {
+        table.add_row(vec![
+            row.airlines.join(", "),
+            row.route.join(" → "),
+            format_emissions(row.emission_grams),
+            format_delta(row.delta_percent),
+            locale.price(row.price, currency),
+        ]);
+    }
+
+    format!("{table}\n{}", render_carbon_summary(report, currency, locale))
+}
+
+pub fn format_price_delta(delta: Option<i64>, currency: &str, locale: &Locale) -> String {
+    match delta {
+        Some(0) => "same".to_string(),
+        Some(d) if d > 0 => format!("+{}", locale.price(Some(d), currency)),
+        Some(d) => format!("-{}", locale.price(Some(-d), currency)),
+        None => "—".to_string(),
+    }
+}
+
+pub fn format_duration_delta(delta: Option<i64>) -> String {
+    match delta {
+        Some(0) => "same".to_string(),
+        Some(d) => {
+            let sign = if d > 0 { '+' } else { '-' };
+            format!("{sign}{}", format_duration(d.unsigned_abs() as u32))
+        }
+        None => "—".to_string(),
+    }
+}
+
+pub fn render_seat_comparison(
+    comparison: &SeatComparison,
+    currency: &str,
+    locale: &Locale,
+) -> String {
+    let baseline = comparison.baseline.as_str();
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(vec![
+            "Cabin".to_string(),
+            "Flights".to_string(),
+            "Cheapest".to_string(),
+            format!("vs {baseline}"),
+            "Duration".to_string(),
+            format!("vs {baseline}"),
+            "Fastest".to_string(),
+            "Airlines".to_string(),
+        ]);
+
+    let mut errors = Vec::new();
+    for (i, row) in comparison.cabins.iter().enumerate() {
+        if let Some(err) = &row.error {
+            errors.push(format!("{}: {}", row.seat.as_str(), err.message));
+        }
+        let (price_delta, duration_delta) = if i == 0 {
+            ("baseline".to_string(), "baseline".to_string())
+        } else {
+            (
+                format_price_delta(row.price_delta, currency, locale),
+                format_duration_delta(row.duration_delta),
+            )
+        };
+        table.add_row(vec![
+            row.seat.as_str().to_string(),
+            row.flights.to_string(),
+            locale.price(row.price, currency),
+            price_delta,
+            row.elapsed_minutes.map(format_duration).unwrap_or_else(|| "—".into()),
+            duration_delta,
+            row.fastest_minutes.map(format_duration).unwrap_or_else(|| "—".into()),
+            row.airlines.join(", "),
+        ]);
+    }
+
+    if errors.is_empty() {
+        table.to_string()
+    } else {
+        format!("{table}\n{}", errors.join("\n"))
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::error::FlightError;
+use crate::fetch::Fetcher;
+use crate::query::{FlightLeg, Passengers, QueryParams, Seat, TripType};
+
+pub mod fixtures {
+    pub const ONE_WAY_HEL_BCN: &str = include_str!("../tests/fixtures/one_way_hel_bcn.html");
+    pub const BEST_FLIGHTS_HEL_BCN: &str =
+        include_str!("../tests/fixtures/best_flights_hel_bcn.html");
+    pub const NO_RESULTS: &str = include_str!("../tests/fixtures/no_results.html");
+    pub const CONSENT: &str = include_str!("../tests/fixtures/consent.html");
+    pub const CAPTCHA: &str = include_str!("../tests/fixtures/captcha.html");
+}
+
+pub fn hel_bcn_query() -> QueryParams {
+    QueryParams {
+        legs: vec![FlightLeg {
+            date: "2026-03-01".into(),
+            from_airport: "HEL".into(),
+            to_airport: "BCN".into(),
+            ..FlightLeg::default()
+        }],
+        passengers: Passengers::default(),
+        seat: Seat::Economy,
+        trip: TripType::OneWay,
+        language: "en".into(),
+        currency: "EUR".into(),
+        region: String::new(),
+        max_price: None,
+        low_emissions: false,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MockFetcher {
+    queue: Mutex<VecDeque<Result<String, FlightError>>>,
+    fallback: Option<String>,
+    requests: Mutex<Vec<Vec<(String, String)>>>,
+}
+
+impl MockFetcher {
+    pub fn new(html: impl Into<String>) -> Self {
+        Self {
+            fallback: Some(html.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn queued() -> Self {
+        Self::default()
+    }
+
+    pub fn push_html(self, html: impl Into<String>) -> Self {
+        self.queue.lock().unwrap().push_back(Ok(html.into()));
+        self
+    }
+
+    pub fn push_error(self, error: FlightError) -> Self {
+        self.queue.lock().unwrap().push_back(Err(error));
+        self
+    }
+
+    pub fn requests(&self) -> Vec<Vec<(String, String)>> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Fetcher for MockFetcher {
+    async fn fetch_html(&self, params: &[(String, String)]) -> Result<String, FlightError> {
+        self.requests.lock().unwrap().push(params.to_vec());
+
+        if let Some(next) = self.queue.lock().unwrap().pop_front() {
+            return next;
+        }
+        self.fallback.clone().ok_or_else(|| {
+            FlightError::ConnectionFailed("mock fetcher has no response queued".into())
+        })
+    }
+}
@@ -0,0 +1,381 @@
+use chrono::{Days, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::{Africa, America, Asia, Atlantic, Australia, Europe, Indian, Pacific, Tz};
+
+use crate::error::FlightError;
+use crate::model::FlightDateTime;
+
+// Each airport's IANA zone; offsets and daylight saving come from the tz database.
+const AIRPORTS: [(&str, Tz); 249] = [
+    // Western Europe
+    ("LHR", Europe::London),
+    ("LGW", Europe::London),
+    ("STN", Europe::London),
+    ("LTN", Europe::London),
+    ("LCY", Europe::London),
+    ("MAN", Europe::London),
+    ("EDI", Europe::London),
+    ("GLA", Europe::London),
+    ("BHX", Europe::London),
+    ("BRS", Europe::London),
+    ("DUB", Europe::Dublin),
+    ("LIS", Europe::Lisbon),
+    ("OPO", Europe::Lisbon),
+    ("FAO", Europe::Lisbon),
+    ("LPA", Atlantic::Canary),
+    ("TFS", Atlantic::Canary),
+    ("ACE", Atlantic::Canary),
+    ("KEF", Atlantic::Reykjavik),
+    // Central Europe
+    ("AMS", Europe::Amsterdam),
+    ("BCN", Europe::Madrid),
+    ("MAD", Europe::Madrid),
+    ("AGP", Europe::Madrid),
+    ("PMI", Europe::Madrid),
+    ("ALC", Europe::Madrid),
+    ("IBZ", Europe::Madrid),
+    ("VLC", Europe::Madrid),
+    ("SVQ", Europe::Madrid),
+    ("BIO", Europe::Madrid),
+    ("CDG", Europe::Paris),
+    ("ORY", Europe::Paris),
+    ("NCE", Europe::Paris),
+    ("LYS", Europe::Paris),
+    ("MRS", Europe::Paris),
+    ("TLS", Europe::Paris),
+    ("BOD", Europe::Paris),
+    ("FRA", Europe::Berlin),
+    ("MUC", Europe::Berlin),
+    ("BER", Europe::Berlin),
+    ("HAM", Europe::Berlin),
+    ("DUS", Europe::Berlin),
+    ("CGN", Europe::Berlin),
+    ("STR", Europe::Berlin),
+    ("HAJ", Europe::Berlin),
+    ("NUE", Europe::Berlin),
+    ("ZRH", Europe::Zurich),
+    ("GVA", Europe::Zurich),
+    ("BSL", Europe::Paris),
+    ("VIE", Europe::Vienna),
+    ("BRU", Europe::Brussels),
+    ("CRL", Europe::Brussels),
+    ("LUX", Europe::Luxembourg),
+    ("CPH", Europe::Copenhagen),
+    ("ARN", Europe::Stockholm),
+    ("GOT", Europe::Stockholm),
+    ("OSL", Europe::Oslo),
+    ("BGO", Europe::Oslo),
+    ("TRD", Europe::Oslo),
+    ("FCO", Europe::Rome),
+    ("CIA", Europe::Rome),
+    ("MXP", Europe::Rome),
+    ("LIN", Europe::Rome),
+    ("BGY", Europe::Rome),
+    ("VCE", Europe::Rome),
+    ("NAP", Europe::Rome),
+    ("BLQ", Europe::Rome),
+    ("FLR", Europe::Rome),
+    ("PSA", Europe::Rome),
+    ("CTA", Europe::Rome),
+    ("PMO", Europe::Rome),
+    ("PRG", Europe::Prague),
+    ("WAW", Europe::Warsaw),
+    ("KRK", Europe::Warsaw),
+    ("GDN", Europe::Warsaw),
+    ("WRO", Europe::Warsaw),
+    ("BUD", Europe::Budapest),
+    ("ZAG", Europe::Zagreb),
+    ("SPU", Europe::Zagreb),
+    ("DBV", Europe::Zagreb),
+    ("LJU", Europe::Ljubljana),
+    ("BEG", Europe::Belgrade),
+    ("MLA", Europe::Malta),
+    ("TIA", Europe::Tirane),
+    ("SKP", Europe::Skopje),
+    // Eastern Europe
+    ("HEL", Europe::Helsinki),
+    ("TMP", Europe::Helsinki),
+    ("OUL", Europe::Helsinki),
+    ("RVN", Europe::Helsinki),
+    ("TLL", Europe::Tallinn),
+    ("RIX", Europe::Riga),
+    ("VNO", Europe::Vilnius),
+    ("ATH", Europe::Athens),
+    ("HER", Europe::Athens),
+    ("RHO", Europe::Athens),
+    ("JTR", Europe::Athens),
+    ("CFU", Europe::Athens),
+    ("SKG", Europe::Athens),
+    ("OTP", Europe::Bucharest),
+    ("SOF", Europe::Sofia),
+    ("KBP", Europe::Kyiv),
+    ("LCA", Asia::Nicosia),
+    ("PFO", Asia::Nicosia),
+    ("TLV", Asia::Jerusalem),
+    ("CAI", Africa::Cairo),
+    // Turkey, Russia, Middle East
+    ("IST", Europe::Istanbul),
+    ("SAW", Europe::Istanbul),
+    ("AYT", Europe::Istanbul),
+    ("ESB", Europe::Istanbul),
+    ("ADB", Europe::Istanbul),
+    ("DLM", Europe::Istanbul),
+    ("BJV", Europe::Istanbul),
+    ("SVO", Europe::Moscow),
+    ("DME", Europe::Moscow),
+    ("VKO", Europe::Moscow),
+    ("LED", Europe::Moscow),
+    ("AMM", Asia::Amman),
+    ("DOH", Asia::Qatar),
+    ("BAH", Asia::Bahrain),
+    ("KWI", Asia::Kuwait),
+    ("RUH", Asia::Riyadh),
+    ("JED", Asia::Riyadh),
+    ("DXB", Asia::Dubai),
+    ("DWC", Asia::Dubai),
+    ("AUH", Asia::Dubai),
+    ("MCT", Asia::Muscat),
+    // Africa
+    ("CMN", Africa::Casablanca),
+    ("RAK", Africa::Casablanca),
+    ("TUN", Africa::Tunis),
+    ("ALG", Africa::Algiers),
+    ("LOS", Africa::Lagos),
+    ("ACC", Africa::Accra),
+    ("DKR", Africa::Dakar),
+    ("JNB", Africa::Johannesburg),
+    ("CPT", Africa::Johannesburg),
+    ("NBO", Africa::Nairobi),
+    ("ADD", Africa::Addis_Ababa),
+    ("DAR", Africa::Dar_es_Salaam),
+    ("ZNZ", Africa::Dar_es_Salaam),
+    ("MRU", Indian::Mauritius),
+    ("SEZ", Indian::Mahe),
+    // South and Central Asia
+    ("DEL", Asia::Kolkata),
+    ("BOM", Asia::Kolkata),
+    ("BLR", Asia::Kolkata),
+    ("MAA", Asia::Kolkata),
+    ("CCU", Asia::Kolkata),
+    ("HYD", Asia::Kolkata),
+    ("COK", Asia::Kolkata),
+    ("GOI", Asia::Kolkata),
+    ("CMB", Asia::Colombo),
+    ("KTM", Asia::Kathmandu),
+    ("DAC", Asia::Dhaka),
+    ("MLE", Indian::Maldives),
+    ("KHI", Asia::Karachi),
+    ("ISB", Asia::Karachi),
+    ("LHE", Asia::Karachi),
+    ("TAS", Asia::Tashkent),
+    ("ALA", Asia::Almaty),
+    // East and Southeast Asia
+    ("BKK", Asia::Bangkok),
+    ("DMK", Asia::Bangkok),
+    ("HKT", Asia::Bangkok),
+    ("CNX", Asia::Bangkok),
+    ("USM", Asia::Bangkok),
+    ("SGN", Asia::Ho_Chi_Minh),
+    ("HAN", Asia::Ho_Chi_Minh),
+    ("DAD", Asia::Ho_Chi_Minh),
+    ("PNH", Asia::Phnom_Penh),
+    ("RGN", Asia::Yangon),
+    ("CGK", Asia::Jakarta),
+    ("DPS", Asia::Makassar),
+    ("KUL", Asia::Kuala_Lumpur),
+    ("PEN", Asia::Kuala_Lumpur),
+    ("SIN", Asia::Singapore),
+    ("MNL", Asia::Manila),
+    ("CEB", Asia::Manila),
+    ("HKG", Asia::Hong_Kong),
+    ("MFM", Asia::Macau),
+    ("TPE", Asia::Taipei),
+    ("PEK", Asia::Shanghai),
+    ("PKX", Asia::Shanghai),
+    ("PVG", Asia::Shanghai),
+    ("SHA", Asia::Shanghai),
+    ("CAN", Asia::Shanghai),
+    ("SZX", Asia::Shanghai),
+    ("CTU", Asia::Shanghai),
+    ("ICN", Asia::Seoul),
+    ("GMP", Asia::Seoul),
+    ("NRT", Asia::Tokyo),
+    ("HND", Asia::Tokyo),
+    ("KIX", Asia::Tokyo),
+    ("NGO", Asia::Tokyo),
+    ("FUK", Asia::Tokyo),
+    ("CTS", Asia::Tokyo),
+    ("OKA", Asia::Tokyo),
+    // Oceania
+    ("SYD", Australia::Sydney),
+    ("MEL", Australia::Melbourne),
+    ("CBR", Australia::Sydney),
+    ("HBA", Australia::Hobart),
+    ("ADL", Australia::Adelaide),
+    ("BNE", Australia::Brisbane),
+    ("OOL", Australia::Brisbane),
+    ("CNS", Australia::Brisbane),
+    ("DRW", Australia::Darwin),
+    ("PER", Australia::Perth),
+    ("AKL", Pacific::Auckland),
+    ("WLG", Pacific::Auckland),
+    ("CHC", Pacific::Auckland),
+    ("ZQN", Pacific::Auckland),
+    ("NAN", Pacific::Fiji),
+    ("PPT", Pacific::Tahiti),
+    ("HNL", Pacific::Honolulu),
+    // North America
+    ("JFK", America::New_York),
+    ("LGA", America::New_York),
+    ("EWR", America::New_York),
+    ("BOS", America::New_York),
+    ("IAD", America::New_York),
+    ("DCA", America::New_York),
+    ("PHL", America::New_York),
+    ("ATL", America::New_York),
+    ("MIA", America::New_York),
+    ("FLL", America::New_York),
+    ("MCO", America::New_York),
+    ("CLT", America::New_York),
+    ("DTW", America::Detroit),
+    ("YYZ", America::Toronto),
+    ("YUL", America::Toronto),
+    ("ORD", America::Chicago),
+    ("DFW", America::Chicago),
+    ("IAH", America::Chicago),
+    ("AUS", America::Chicago),
+    ("MSP", America::Chicago),
+    ("DEN", America::Denver),
+    ("SLC", America::Denver),
+    ("YYC", America::Edmonton),
+    ("PHX", America::Phoenix),
+    ("LAX", America::Los_Angeles),
+    ("SFO", America::Los_Angeles),
+    ("SAN", America::Los_Angeles),
+    ("SEA", America::Los_Angeles),
+    ("LAS", America::Los_Angeles),
+    ("YVR", America::Vancouver),
+    ("ANC", America::Anchorage),
+    ("MEX", America::Mexico_City),
+    ("CUN", America::Cancun),
+    // Latin America
+    ("PTY", America::Panama),
+    ("BOG", America::Bogota),
+    ("LIM", America::Lima),
+    ("SCL", America::Santiago),
+    ("EZE", America::Argentina::Buenos_Aires),
+    ("GRU", America::Sao_Paulo),
+    ("GIG", America::Sao_Paulo),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Times {
+    Local,
+    Origin,
+    Utc,
+}
+
+impl Times {
+    pub fn from_str_loose(s: &str) -> Result<Self, FlightError> {
+        match s {
+            "local" => Ok(Self::Local),
+            "origin" => Ok(Self::Origin),
+            "utc" => Ok(Self::Utc),
+            _ => Err(FlightError::Validation(format!(
+                "invalid time display \"{s}\" — use local, origin or utc"
+            ))),
+        }
+    }
+}
+
+fn zone(airport: &str) -> Option<Tz> {
+    AIRPORTS
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(airport))
+        .map(|&(_, tz)| tz)
+}
+
+fn naive(dt: &FlightDateTime) -> Option<NaiveDateTime> {
+    NaiveDate::from_ymd_opt(dt.year as i32, dt.month, dt.day)?.and_hms_opt(dt.hour, dt.minute, 0)
+}
+
+fn minutes(offset: impl Offset) -> i32 {
+    offset.fix().local_minus_utc() / 60
+}
+
+// When clocks go back the repeated hour takes its first (summer) reading; a time
+// skipped when they go forward keeps the offset from before the change.
+pub fn utc_offset(airport: &str, local: &FlightDateTime) -> Option<i32> {
+    let tz = zone(airport)?;
+    let local = naive(local)?;
+    let offset = match tz.offset_from_local_datetime(&local) {
+        LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => offset,
+        LocalResult::None => tz.offset_from_utc_datetime(&local.checked_sub_days(Days::new(1))?),
+    };
+    Some(minutes(offset))
+}
+
+pub fn offset_at(airport: &str, utc_minutes: i64) -> Option<i32> {
+    let tz = zone(airport)?;
+    let utc = naive(&FlightDateTime::from_minutes_since_epoch(utc_minutes))?;
+    Some(minutes(tz.offset_from_utc_datetime(&utc)))
+}
+
+pub fn to_utc_minutes(airport: &str, local: &FlightDateTime) -> Option<i64> {
+    utc_offset(airport, local).map(|offset| local.minutes_since_epoch() - i64::from(offset))
+}
+
+pub fn convert(
+    local: &FlightDateTime,
+    airport: &str,
+    origin: &str,
+    times: Times,
+) -> Option<(FlightDateTime, i32)> {
+    let offset = utc_offset(airport, local)?;
+    let utc = local.minutes_since_epoch() - i64::from(offset);
+    let target = match times {
+        Times::Local => offset,
+        Times::Utc => 0,
+        Times::Origin => offset_at(origin, utc)?,
+    };
+    Some((
+        FlightDateTime::from_minutes_since_epoch(utc + i64::from(target)),
+        target,
+    ))
+}
+
+pub fn segment_offsets(
+    (from, departure): (&str, &FlightDateTime),
+    (to, arrival): (&str, &FlightDateTime),
+    duration_minutes: u32,
+) -> (Option<i32>, Option<i32>) {
+    let local_gap = arrival.minutes_since_epoch() - departure.minutes_since_epoch();
+    let shift = (local_gap - i64::from(duration_minutes)) as i32;
+    match (utc_offset(from, departure), utc_offset(to, arrival)) {
+        (Some(dep), None) if duration_minutes > 0 => (Some(dep), Some(dep + shift)),
+        (None, Some(arr)) if duration_minutes > 0 => (Some(arr - shift), Some(arr)),
+        offsets => offsets,
+    }
+}
+
+pub fn iso8601(local: &FlightDateTime, offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:00{sign}{:02}:{:02}",
+        local.year,
+        local.month,
+        local.day,
+        local.hour,
+        local.minute,
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+pub fn format_offset(minutes: i32) -> String {
+    let sign = if minutes < 0 { '-' } else { '+' };
+    match (minutes.abs() / 60, minutes.abs() % 60) {
+        (0, 0) => "UTC".to_string(),
+        (h, 0) => format!("UTC{sign}{h}"),
+        (h, m) => format!("UTC{sign}{h}:{m:02}"),
+    }
+}
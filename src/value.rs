@@ -0,0 +1,127 @@
+use crate::error::FlightError;
+use crate::model::{FlightResult, SearchResult};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueWeights {
+    pub price: f64,
+    pub duration: f64,
+    pub stops: f64,
+}
+
+impl Default for ValueWeights {
+    fn default() -> Self {
+        Self {
+            price: 1.0,
+            duration: 1.0,
+            stops: 0.5,
+        }
+    }
+}
+
+impl ValueWeights {
+    // Factors the spec leaves out count for nothing: "price=1" ranks by price alone.
+    pub fn parse(spec: &str) -> Result<Self, FlightError> {
+        let mut weights = Self {
+            price: 0.0,
+            duration: 0.0,
+            stops: 0.0,
+        };
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let invalid = || {
+                FlightError::Validation(format!(
+                    "invalid value weight \"{pair}\" — use e.g. price=1,duration=0.5,stops=0.25"
+                ))
+            };
+            let (key, weight) = pair.split_once('=').ok_or_else(invalid)?;
+            let weight: f64 = weight.trim().parse().map_err(|_| invalid())?;
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(invalid());
+            }
+            match key.trim() {
+                "price" => weights.price = weight,
+                "duration" => weights.duration = weight,
+                "stops" => weights.stops = weight,
+                _ => return Err(invalid()),
+            }
+        }
+        if weights.price + weights.duration + weights.stops == 0.0 {
+            return Err(FlightError::Validation(
+                "at least one value weight must be above zero".into(),
+            ));
+        }
+        Ok(weights)
+    }
+}
+
+fn stops(flight: &FlightResult) -> usize {
+    flight.segments.len().saturating_sub(1)
+}
+
+pub fn score(
+    flight: &FlightResult,
+    cheapest: i64,
+    fastest: u32,
+    weights: &ValueWeights,
+) -> Option<u32> {
+    let price = flight.effective_price()?;
+    let relative = |value: f64, best: f64| if best > 0.0 { value / best - 1.0 } else { 0.0 };
+    let penalty = weights.price * relative(price as f64, cheapest as f64)
+        + weights.duration * relative(f64::from(flight.elapsed_minutes), f64::from(fastest))
+        + weights.stops * stops(flight) as f64;
+    Some((100.0 / (1.0 + penalty.max(0.0))).round() as u32)
+}
+
+pub fn annotate(result: &mut SearchResult, weights: &ValueWeights) {
+    let cheapest = result.flights.iter().filter_map(|f| f.effective_price()).min();
+    let fastest = result.flights.iter().map(|f| f.elapsed_minutes).min();
+    for flight in &mut result.flights {
+        flight.value_score = cheapest
+            .zip(fastest)
+            .and_then(|(c, f)| score(flight, c, f, weights));
+    }
+}
+
+pub fn sort(flights: &mut [FlightResult]) {
+    flights.sort_by_key(|f| {
+        (
+            std::cmp::Reverse(f.value_score),
+            f.effective_price().unwrap_or(i64::MAX),
+        )
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Ranker {
+    pub weights: ValueWeights,
+}
+
+impl Ranker {
+    pub fn new(weights: ValueWeights) -> Self {
+        Self { weights }
+    }
+
+    pub fn parse(spec: &str) -> Result<Self, FlightError> {
+        ValueWeights::parse(spec).map(Self::new)
+    }
+
+    pub fn score(&self, result: &mut SearchResult) {
+        annotate(result, &self.weights);
+    }
+
+    pub fn rank(&self, result: &mut SearchResult) {
+        self.score(result);
+        sort(&mut result.flights);
+    }
+}
+
+pub fn best(flights: &[FlightResult]) -> Option<usize> {
+    if flights.len() < 2 {
+        return None;
+    }
+    flights
+        .iter()
+        .enumerate()
+        .filter_map(|(i, f)| f.value_score.map(|s| (i, s, f.effective_price())))
+        .min_by_key(|&(i, s, price)| (std::cmp::Reverse(s), price, i))
+        .map(|(i, _, _)| i)
+}
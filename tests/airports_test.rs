@@ -0,0 +1,54 @@
+use flyr::airports::{is_place, resolve, resolve_list};
+use flyr::testing::hel_bcn_query;
+
+#[test]
+fn codes_pass_through_uppercased() {
+    assert_eq!(resolve("hel").unwrap(), "HEL");
+    assert_eq!(resolve(" JFK ").unwrap(), "JFK");
+    assert_eq!(resolve("ZZZ").unwrap(), "ZZZ");
+}
+
+#[test]
+fn city_names_resolve_to_their_airport() {
+    assert_eq!(resolve("Helsinki").unwrap(), "HEL");
+    assert_eq!(resolve("barcelona").unwrap(), "BCN");
+    assert_eq!(resolve("Heathrow").unwrap(), "LHR");
+}
+
+#[test]
+fn multi_airport_cities_resolve_to_their_metro() {
+    assert_eq!(resolve("New York").unwrap(), "NYC");
+    assert_eq!(resolve("London").unwrap(), "LON");
+    assert_eq!(resolve("Tokyo").unwrap(), "TYO");
+}
+
+#[test]
+fn ambiguous_names_list_candidates() {
+    let err = resolve("Shanghai").unwrap_err().to_string();
+    assert!(err.contains("PVG") && err.contains("SHA"), "{err}");
+
+    let err = resolve("Sant").unwrap_err().to_string();
+    assert!(err.contains("JTR") && err.contains("SCL"), "{err}");
+}
+
+#[test]
+fn unknown_names_are_rejected() {
+    let err = resolve("Atlantis").unwrap_err().to_string();
+    assert!(err.contains("unknown airport or city"), "{err}");
+    assert!(!is_place("Atlantis"));
+    assert!(!is_place("HEL"));
+}
+
+#[test]
+fn lists_resolve_each_entry() {
+    assert_eq!(resolve_list("Helsinki, ARN,Copenhagen").unwrap(), "HEL,ARN,CPH");
+    assert!(resolve_list("HEL,Atlantis").is_err());
+}
+
+#[test]
+fn validation_suggests_the_code_for_a_place_name() {
+    let mut params = hel_bcn_query();
+    params.legs[0].to_airport = "Barcelona".into();
+    let err = params.validate().unwrap_err().to_string();
+    assert!(err.contains("use BCN"), "{err}");
+}
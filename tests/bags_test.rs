@@ -0,0 +1,95 @@
+use flyr::bags::{annotate, fee, included, Bag};
+use flyr::model::{BagAllowance, SearchResult};
+use flyr::parse::parse_html;
+use flyr::query::{FlightLeg, Passengers, QueryParams, SearchQuery, Seat, TripType};
+use flyr::testing::fixtures;
+
+fn prices_with_bag(result: &SearchResult) -> Vec<(String, Option<i64>)> {
+    result
+        .flights
+        .iter()
+        .map(|f| (f.airlines.join(","), f.price_with_bag))
+        .collect()
+}
+
+fn leg(from: &str, to: &str) -> FlightLeg {
+    FlightLeg {
+        date: "2026-03-01".into(),
+        from_airport: from.into(),
+        to_airport: to.into(),
+        ..FlightLeg::default()
+    }
+}
+
+#[test]
+fn fee_table_falls_back_to_a_default() {
+    assert_eq!(fee("FR", Bag::CarryOn), 25);
+    assert_eq!(fee("FR", Bag::Checked), 40);
+    assert_eq!(fee("AY", Bag::CarryOn), 0);
+    assert_eq!(fee("AY", Bag::Checked), 40);
+    assert!(Bag::from_str_loose("duffel").is_err());
+}
+
+#[test]
+fn checked_bag_is_added_once_per_carrier() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    annotate(&mut result, Bag::Checked, "EUR");
+
+    assert_eq!(
+        prices_with_bag(&result),
+        vec![
+            ("AY".to_string(), Some(229)),
+            ("Lufthansa".to_string(), Some(182)),
+            ("Norwegian".to_string(), Some(151)),
+        ]
+    );
+    assert_eq!(result.flights[2].effective_price(), Some(151));
+}
+
+#[test]
+fn bags_included_in_the_fare_cost_nothing_extra() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.flights[2].baggage = Some(BagAllowance {
+        carry_on: Some(1),
+        checked: Some(0),
+    });
+    assert!(included(&result.flights[2], Bag::CarryOn));
+    assert!(!included(&result.flights[2], Bag::Checked));
+
+    annotate(&mut result, Bag::CarryOn, "EUR");
+    assert_eq!(result.flights[2].price_with_bag, result.flights[2].price);
+}
+
+#[test]
+fn fees_scale_with_passengers_and_directions() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.query = Some(SearchQuery::Structured(QueryParams {
+        legs: vec![leg("HEL", "BCN"), leg("BCN", "HEL")],
+        passengers: Passengers {
+            adults: 2,
+            infants_on_lap: 1,
+            ..Passengers::default()
+        },
+        seat: Seat::Economy,
+        trip: TripType::RoundTrip,
+        language: "en".into(),
+        currency: "EUR".into(),
+        region: String::new(),
+        max_price: None,
+        low_emissions: false,
+    }));
+    annotate(&mut result, Bag::CarryOn, "EUR");
+
+    assert_eq!(result.flights[0].price_with_bag, Some(189));
+    assert_eq!(result.flights[2].price_with_bag, Some(121 + 15 * 4));
+}
+
+#[test]
+fn unsupported_currency_warns_instead_of_guessing() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.metadata.currency = Some("JPY".into());
+    annotate(&mut result, Bag::Checked, "JPY");
+
+    assert!(result.flights.iter().all(|f| f.price_with_bag.is_none()));
+    assert_eq!(result.warnings.last().unwrap().kind, "bag_fees");
+}
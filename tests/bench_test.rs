@@ -0,0 +1,31 @@
+use flyr::bench::{load_fixtures, run_fixture};
+use flyr::parse::ParseOptions;
+use flyr::testing::fixtures;
+
+#[test]
+fn reports_flights_and_phase_timings() {
+    let report = run_fixture("hel_bcn", fixtures::ONE_WAY_HEL_BCN, 3, &ParseOptions::default());
+    assert!(report.error.is_none());
+    assert_eq!(report.flights, 3);
+    assert_eq!(report.iterations, 3);
+    assert_eq!(report.bytes, fixtures::ONE_WAY_HEL_BCN.len());
+    assert!(report.mean_us > 0.0);
+    assert!(report.mean_us >= report.json_us);
+}
+
+#[test]
+fn failing_fixture_reports_its_error() {
+    let report = run_fixture("consent", fixtures::CONSENT, 10, &ParseOptions::default());
+    assert_eq!(report.error.map(|e| e.kind), Some("consent_required"));
+}
+
+#[test]
+fn loads_html_fixtures_sorted() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+    let loaded = load_fixtures(dir.as_ref()).unwrap();
+    let names: Vec<&str> = loaded.iter().map(|(n, _)| n.as_str()).collect();
+    assert!(names.contains(&"one_way_hel_bcn.html"));
+    assert!(names.windows(2).all(|w| w[0] <= w[1]));
+
+    assert!(load_fixtures("/nonexistent/flyr".as_ref()).is_err());
+}
@@ -0,0 +1,88 @@
+use flyr::booking::{encode_filters, encode_request, parse_response, request_url};
+use flyr::parse::parse_html;
+use flyr::query::{FlightLeg, TripType};
+use flyr::testing::{fixtures, hel_bcn_query};
+
+const INNER: &str = r#"[null,[[["EDREAMS","eDreams",false],[[null,131],"t1"],"/travel/clk/f?u=1"],[["AY","Finnair",true],[[null,142],"t2"],"https://www.finnair.com/book"],[["VY","Vueling",true],[[null,131],"t3"],null],[[null,"Kiwi.com"],[],null]]]"#;
+
+fn wrapped(inner: &str) -> String {
+    serde_json::json!([["wrb.fr", null, inner, null, null, null, "generic"]]).to_string()
+}
+
+#[test]
+fn filters_select_every_segment_of_the_flight() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let flight = &result.flights[0];
+    let filters = encode_filters(&hel_bcn_query(), flight).unwrap();
+
+    let selected = filters[1][13][0][8].as_array().unwrap();
+    assert_eq!(selected.len(), flight.segments.len());
+    let first = &flight.segments[0];
+    assert_eq!(selected[0][0], first.from_airport.code.as_str());
+    assert_eq!(selected[0][1], "2026-03-01");
+    assert_eq!(selected[0][4], first.airline.as_deref().unwrap());
+    assert_eq!(selected[0][5], first.flight_number.as_deref().unwrap());
+}
+
+#[test]
+fn round_trips_cannot_be_booked_from_one_result() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let mut params = hel_bcn_query();
+    params.trip = TripType::RoundTrip;
+    params.legs.push(FlightLeg {
+        date: "2026-03-08".into(),
+        from_airport: "BCN".into(),
+        to_airport: "HEL".into(),
+        ..FlightLeg::default()
+    });
+    assert!(encode_filters(&params, &result.flights[0]).is_err());
+}
+
+#[test]
+fn segments_without_flight_numbers_are_rejected() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let mut flight = result.flights[0].clone();
+    flight.segments[0].flight_number = None;
+    assert!(encode_filters(&hel_bcn_query(), &flight).is_err());
+}
+
+#[test]
+fn request_is_form_encoded() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let body = encode_request(&hel_bcn_query(), &result.flights[0]).unwrap();
+    assert!(body.starts_with("f.req="));
+    assert!(!body.contains(' '));
+    assert!(request_url(&hel_bcn_query()).contains("GetBookingResults?hl=en&curr=EUR"));
+}
+
+#[test]
+fn parses_options_cheapest_first() {
+    let body = format!(")]}}'\n\n{}", wrapped(INNER));
+    let options = parse_response(&body).unwrap();
+
+    assert_eq!(options.len(), 4);
+    assert_eq!(options[0].provider, "Vueling");
+    assert!(options[0].airline_direct);
+    assert_eq!(options[0].price, Some(131));
+    assert_eq!(options[0].url, None);
+    assert_eq!(options[1].provider, "eDreams");
+    assert_eq!(options[1].provider_code.as_deref(), Some("EDREAMS"));
+    assert_eq!(
+        options[1].url.as_deref(),
+        Some("https://www.google.com/travel/clk/f?u=1")
+    );
+    assert_eq!(options[2].url.as_deref(), Some("https://www.finnair.com/book"));
+    assert_eq!(options[3].provider, "Kiwi.com");
+    assert_eq!(options[3].price, None);
+}
+
+#[test]
+fn missing_payload_is_an_error() {
+    assert!(parse_response(")]}'\n[[\"e\",4]]").is_err());
+}
+
+#[test]
+fn empty_option_list_is_not_an_error() {
+    let body = format!(")]}}'\n{}", wrapped("[null,null]"));
+    assert!(parse_response(&body).unwrap().is_empty());
+}
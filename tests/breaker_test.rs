@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use flyr::breaker::{CircuitBreaker, Guarded, RetryBudget};
+use flyr::error::FlightError;
+use flyr::fetch::Fetcher;
+use flyr::model::SearchResult;
+use flyr::parse::ParseOptions;
+use flyr::query::SearchQuery;
+use flyr::testing::{fixtures, hel_bcn_query, MockFetcher};
+
+#[tokio::test]
+async fn breaker_trips_after_consecutive_pushback() {
+    let mock = MockFetcher::queued()
+        .push_error(FlightError::RateLimited { retry_after: None })
+        .push_error(FlightError::Blocked(403))
+        .push_html(fixtures::NO_RESULTS);
+    let fetcher = Guarded::new(mock, CircuitBreaker::new(2));
+
+    assert!(matches!(
+        fetcher.fetch_html(&[]).await,
+        Err(FlightError::RateLimited { .. })
+    ));
+    assert!(matches!(fetcher.fetch_html(&[]).await, Err(FlightError::Blocked(403))));
+    assert!(fetcher.breaker().is_open());
+
+    match fetcher.fetch_html(&[]).await {
+        Err(FlightError::BackingOff { consecutive }) => assert_eq!(consecutive, 2),
+        other => panic!("expected BackingOff, got {other:?}"),
+    }
+}
+
+async fn search<F: Fetcher>(fetcher: &F) -> Result<SearchResult, FlightError> {
+    flyr::search_with_fetcher(
+        fetcher,
+        SearchQuery::Structured(hel_bcn_query()),
+        ParseOptions::default(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn success_resets_the_breaker() {
+    let mock = MockFetcher::queued()
+        .push_error(FlightError::RateLimited { retry_after: None })
+        .push_html(fixtures::NO_RESULTS)
+        .push_error(FlightError::RateLimited { retry_after: None })
+        .push_html(fixtures::NO_RESULTS);
+    let fetcher = Guarded::new(mock, CircuitBreaker::new(2));
+
+    for _ in 0..4 {
+        let _ = search(&fetcher).await;
+    }
+    assert!(!fetcher.breaker().is_open());
+}
+
+#[tokio::test]
+async fn interstitials_found_by_the_parser_trip_the_breaker() {
+    let mock = MockFetcher::queued()
+        .push_html(fixtures::CAPTCHA)
+        .push_html(fixtures::CONSENT)
+        .push_html(fixtures::NO_RESULTS);
+    let fetcher = Guarded::new(mock, CircuitBreaker::new(2));
+
+    assert!(matches!(search(&fetcher).await, Err(FlightError::CaptchaRequired(_))));
+    assert!(!fetcher.breaker().is_open());
+    assert!(matches!(search(&fetcher).await, Err(FlightError::ConsentRequired)));
+    assert!(matches!(
+        search(&fetcher).await,
+        Err(FlightError::BackingOff { consecutive: 2 })
+    ));
+}
+
+#[tokio::test]
+async fn transient_errors_are_not_retried_again() {
+    let mock = Arc::new(
+        MockFetcher::queued()
+            .push_error(FlightError::Timeout)
+            .push_html(fixtures::NO_RESULTS),
+    );
+    let fetcher = Guarded::new(mock.clone(), CircuitBreaker::new(3));
+
+    assert!(matches!(fetcher.fetch_html(&[]).await, Err(FlightError::Timeout)));
+    assert_eq!(mock.requests().len(), 1);
+}
+
+#[test]
+fn budget_never_goes_negative() {
+    let budget = RetryBudget::new(1);
+    assert!(budget.try_spend());
+    assert!(!budget.try_spend());
+    assert_eq!(budget.remaining(), 0);
+}
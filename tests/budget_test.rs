@@ -0,0 +1,78 @@
+use flyr::budget::{matches, BudgetMatch};
+use flyr::model::CalendarDay;
+
+fn day(date: &str, price: Option<i64>) -> CalendarDay {
+    CalendarDay {
+        date: date.into(),
+        return_date: None,
+        price,
+    }
+}
+
+#[test]
+fn keeps_only_fares_within_budget_cheapest_first() {
+    let calendars = vec![
+        (
+            "BCN".to_string(),
+            vec![
+                day("2026-03-01", Some(180)),
+                day("2026-03-02", Some(260)),
+                day("2026-03-03", None),
+            ],
+        ),
+        (
+            "ATH".to_string(),
+            vec![day("2026-03-01", Some(120)), day("2026-03-02", Some(250))],
+        ),
+    ];
+
+    let found = matches(&calendars, 250);
+    let summary: Vec<(&str, &str, i64)> = found
+        .iter()
+        .map(|m| (m.destination.as_str(), m.date.as_str(), m.price))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("ATH", "2026-03-01", 120),
+            ("BCN", "2026-03-01", 180),
+            ("ATH", "2026-03-02", 250),
+        ]
+    );
+}
+
+#[test]
+fn ties_are_ordered_by_date_then_destination() {
+    let calendars = vec![
+        ("LIS".to_string(), vec![day("2026-03-05", Some(99))]),
+        ("BCN".to_string(), vec![day("2026-03-05", Some(99))]),
+        ("ATH".to_string(), vec![day("2026-03-04", Some(99))]),
+    ];
+    let order: Vec<String> = matches(&calendars, 100)
+        .into_iter()
+        .map(|m| m.destination)
+        .collect();
+    assert_eq!(order, vec!["ATH", "BCN", "LIS"]);
+}
+
+#[test]
+fn carries_return_dates_through() {
+    let calendars = vec![(
+        "BCN".to_string(),
+        vec![CalendarDay {
+            date: "2026-03-01".into(),
+            return_date: Some("2026-03-08".into()),
+            price: Some(210),
+        }],
+    )];
+    assert_eq!(
+        matches(&calendars, 300),
+        vec![BudgetMatch {
+            destination: "BCN".into(),
+            date: "2026-03-01".into(),
+            return_date: Some("2026-03-08".into()),
+            price: 210,
+        }]
+    );
+    assert!(matches(&calendars, 200).is_empty());
+}
@@ -0,0 +1,66 @@
+use flyr::cabins::compare;
+use flyr::error::FlightError;
+use flyr::locale::Locale;
+use flyr::model::SearchResult;
+use flyr::parse::parse_html;
+use flyr::query::Seat;
+use flyr::table::render_seat_comparison;
+use flyr::testing::fixtures;
+
+fn business() -> SearchResult {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    for flight in &mut result.flights {
+        flight.price = flight.price.map(|p| p * 4);
+        flight.elapsed_minutes -= 15;
+    }
+    result
+}
+
+#[test]
+fn compare_measures_cabins_against_the_first() {
+    let economy = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let comparison = compare(&[(Seat::Economy, Ok(economy)), (Seat::Business, Ok(business()))]);
+
+    assert_eq!(comparison.baseline, Seat::Economy);
+    let [economy, business] = comparison.cabins.as_slice() else {
+        panic!("expected two cabins");
+    };
+    assert_eq!(economy.price, Some(121));
+    assert_eq!(economy.airlines, vec!["Norwegian"]);
+    assert_eq!(economy.price_delta, Some(0));
+    assert_eq!(economy.fastest_minutes, Some(255));
+    assert_eq!(business.price, Some(484));
+    assert_eq!(business.price_delta, Some(363));
+    assert_eq!(business.duration_delta, Some(-15));
+    assert_eq!(business.route, vec!["HEL", "BCN"]);
+}
+
+#[test]
+fn compare_keeps_failed_cabins() {
+    let economy = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let comparison = compare(&[
+        (Seat::Economy, Ok(economy)),
+        (Seat::First, Err(FlightError::HttpStatus(500))),
+    ]);
+
+    let first = &comparison.cabins[1];
+    assert_eq!(first.error.as_ref().unwrap().kind, "http_error");
+    assert_eq!(first.price, None);
+    assert_eq!(first.price_delta, None);
+
+    let json = serde_json::to_string(&comparison).unwrap();
+    assert!(json.contains("\"baseline\":\"economy\""));
+    assert!(json.contains("\"seat\":\"first\""));
+}
+
+#[test]
+fn comparison_table_shows_deltas() {
+    let economy = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let comparison = compare(&[(Seat::Economy, Ok(economy)), (Seat::Business, Ok(business()))]);
+    let table = render_seat_comparison(&comparison, "EUR", &Locale::default());
+
+    assert!(table.contains("vs economy"));
+    assert!(table.contains("baseline"));
+    assert!(table.contains("+€363"));
+    assert!(table.contains("-0h 15m"));
+}
@@ -0,0 +1,77 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use flyr::cache::{CacheStore, Cached, FsCache};
+use flyr::fetch::Fetcher;
+use flyr::store::Store;
+use flyr::testing::{fixtures, MockFetcher};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("flyr-cache-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn params(currency: &str) -> Vec<(String, String)> {
+    vec![
+        ("tfs".into(), "CBwQAhoe".into()),
+        ("curr".into(), currency.into()),
+    ]
+}
+
+#[test]
+fn fs_cache_round_trip_and_eviction() {
+    let cache = FsCache::new(temp_dir("fs")).unwrap();
+    cache.put("a=1", "<html>one</html>").unwrap();
+
+    assert_eq!(cache.get("a=1", HOUR).unwrap().as_deref(), Some("<html>one</html>"));
+    assert_eq!(cache.get("a=2", HOUR).unwrap(), None);
+    assert_eq!(cache.evict(HOUR).unwrap(), 0);
+
+    for entry in std::fs::read_dir(cache.dir()).unwrap() {
+        let file = std::fs::File::options()
+            .write(true)
+            .open(entry.unwrap().path())
+            .unwrap();
+        file.set_modified(SystemTime::now() - 2 * HOUR).unwrap();
+    }
+    assert_eq!(cache.get("a=1", HOUR).unwrap(), None);
+    assert_eq!(cache.evict(HOUR).unwrap(), 1);
+}
+
+#[test]
+fn sqlite_store_is_a_cache_store() {
+    let store = Store::in_memory().unwrap();
+    store.put("k", "v1").unwrap();
+    store.put("k", "v2").unwrap();
+
+    assert_eq!(store.get("k", HOUR).unwrap().as_deref(), Some("v2"));
+    assert_eq!(store.get("missing", HOUR).unwrap(), None);
+    assert_eq!(store.evict(HOUR).unwrap(), 0);
+}
+
+#[tokio::test]
+async fn missing_store_caches_nothing() {
+    let fetcher = Cached::new(MockFetcher::new(fixtures::NO_RESULTS), None::<FsCache>, HOUR);
+
+    fetcher.fetch_html(&params("EUR")).await.unwrap();
+    fetcher.fetch_html(&params("EUR")).await.unwrap();
+
+    assert_eq!(fetcher.inner().requests().len(), 2);
+    assert_eq!(fetcher.store().get("tfs=CBwQAhoe&curr=EUR", HOUR).unwrap(), None);
+}
+
+#[tokio::test]
+async fn cached_fetcher_skips_repeat_requests() {
+    let store: Arc<dyn CacheStore> = Arc::new(Store::in_memory().unwrap());
+    let fetcher = Cached::new(MockFetcher::new(fixtures::NO_RESULTS), store, HOUR);
+
+    fetcher.fetch_html(&params("EUR")).await.unwrap();
+    fetcher.fetch_html(&params("EUR")).await.unwrap();
+    fetcher.fetch_html(&params("USD")).await.unwrap();
+
+    assert_eq!(fetcher.inner().requests().len(), 2);
+    assert!(fetcher.store().get("tfs=CBwQAhoe&curr=EUR", HOUR).unwrap().is_some());
+}
@@ -0,0 +1,80 @@
+use flyr::calendar::{encode_filters, encode_request, parse_response, request_url};
+use flyr::query::{self, FlightLeg, TripType};
+use flyr::testing::hel_bcn_query;
+
+const INNER: &str = r#"[null,[["2026-03-02",null,[[null,188]]],["2026-03-01",null,[[null,142]]],["2026-03-03",null,[]]]]"#;
+
+fn wrapped(inner: &str) -> String {
+    serde_json::json!([["wrb.fr", null, inner, null, null, null, "generic"]]).to_string()
+}
+
+#[test]
+fn filters_carry_route_and_range() {
+    let filters = encode_filters(&hel_bcn_query(), "2026-03-01", "2026-03-31");
+    let text = filters.to_string();
+    assert!(text.contains("\"HEL\""));
+    assert!(text.contains("\"BCN\""));
+    assert_eq!(filters[2], serde_json::json!(["2026-03-01", "2026-03-31"]));
+    assert_eq!(filters.as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn round_trip_filters_include_trip_length() {
+    let mut params = hel_bcn_query();
+    params.trip = TripType::RoundTrip;
+    params.legs.push(FlightLeg {
+        date: "2026-03-08".into(),
+        from_airport: "BCN".into(),
+        to_airport: "HEL".into(),
+        ..FlightLeg::default()
+    });
+
+    let filters = encode_filters(&params, "2026-03-01", "2026-03-31");
+    assert_eq!(filters[3], serde_json::json!([7, 7]));
+}
+
+#[test]
+fn request_is_form_encoded() {
+    let body = encode_request(&hel_bcn_query(), "2026-03-01", "2026-03-31");
+    assert!(body.starts_with("f.req="));
+    assert!(body.contains("HEL"));
+    assert!(!body.contains(' '));
+
+    let url = request_url(&hel_bcn_query());
+    assert!(url.ends_with("?hl=en&curr=EUR"));
+}
+
+#[test]
+fn parses_single_json_body() {
+    let body = format!(")]}}'\n\n{}", wrapped(INNER));
+    let days = parse_response(&body).unwrap();
+
+    assert_eq!(days.len(), 3);
+    assert_eq!(days[0].date, "2026-03-01");
+    assert_eq!(days[0].price, Some(142));
+    assert_eq!(days[1].price, Some(188));
+    assert_eq!(days[2].price, None);
+}
+
+#[test]
+fn parses_length_prefixed_body() {
+    let chunk = wrapped(INNER);
+    let body = format!(")]}}'\n\n{}\n{chunk}\n25\n[[\"e\",4,null,null,151]]\n", chunk.len());
+    let days = parse_response(&body).unwrap();
+    assert_eq!(days.len(), 3);
+}
+
+#[test]
+fn missing_payload_is_an_error() {
+    assert!(parse_response(")]}'\n[[\"e\",4]]").is_err());
+}
+
+#[test]
+fn date_arithmetic_round_trips() {
+    assert_eq!(query::add_days("2026-03-01", -1).as_deref(), Some("2026-02-28"));
+    assert_eq!(query::add_days("2028-02-28", 1).as_deref(), Some("2028-02-29"));
+    assert_eq!(query::add_days("2026-12-31", 1).as_deref(), Some("2027-01-01"));
+    let days = query::date_to_days("2026-03-01").unwrap();
+    assert_eq!(query::days_to_date(days), "2026-03-01");
+    assert_eq!(query::add_days("2026-13-01", 1), None);
+}
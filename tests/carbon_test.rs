@@ -0,0 +1,45 @@
+use flyr::carbon::{delta_percent, report};
+use flyr::model::CarbonEmission;
+use flyr::parse::parse_html;
+use flyr::testing::fixtures;
+
+fn emission(emitted: Option<i64>, typical: Option<i64>) -> CarbonEmission {
+    CarbonEmission::new(emitted, typical)
+}
+
+#[test]
+fn delta_is_relative_to_typical() {
+    assert_eq!(delta_percent(&emission(Some(160_000), Some(181_000))), Some(-12));
+    assert_eq!(delta_percent(&emission(Some(214_000), Some(181_000))), Some(18));
+    assert_eq!(delta_percent(&emission(Some(100), Some(100))), Some(0));
+    assert_eq!(delta_percent(&emission(None, Some(100))), None);
+    assert_eq!(delta_percent(&emission(Some(100), Some(0))), None);
+    assert_eq!(emission(Some(160_000), Some(181_000)).delta_percent, Some(-12));
+}
+
+#[test]
+fn report_sorts_greenest_first() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let report = report(&result);
+
+    let grams: Vec<_> = report.itineraries.iter().map(|r| r.emission_grams).collect();
+    assert_eq!(grams, vec![Some(160_000), Some(168_000), Some(214_000)]);
+    assert_eq!(report.grams_saved, Some(0));
+}
+
+#[test]
+fn report_compares_greenest_with_cheapest() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    for flight in &mut result.flights {
+        if flight.carbon.emission_grams == Some(160_000) {
+            flight.price = Some(250);
+        }
+    }
+    let report = report(&result);
+
+    assert_eq!(report.greenest.as_ref().unwrap().price, Some(250));
+    assert_eq!(report.cheapest.as_ref().unwrap().price, Some(142));
+    assert_eq!(report.grams_saved, Some(54_000));
+    assert_eq!(report.extra_cost, Some(108));
+    assert_eq!(report.cheapest.unwrap().route, vec!["HEL", "FRA", "BCN"]);
+}
@@ -50,10 +50,13 @@ fn search_help_shows_all_sections() {
         .stdout(predicate::str::contains("--lang <CODE>"))
         .stdout(predicate::str::contains("--currency <CODE>"))
         .stdout(predicate::str::contains("--json"))
+        .stdout(predicate::str::contains("--strict"))
         .stdout(predicate::str::contains("--pretty"))
         .stdout(predicate::str::contains("--proxy <URL>"))
         .stdout(predicate::str::contains("--timeout <SECS>"))
+        .stdout(predicate::str::contains("--no-auto-consent"))
         .stdout(predicate::str::contains("--top <N>"))
+        .stdout(predicate::str::contains("--sort <KEY>"))
         .stdout(predicate::str::contains("--compact"))
         .stdout(predicate::str::contains("Examples:"))
         .stdout(predicate::str::contains("One-way:"))
@@ -200,6 +203,25 @@ fn invalid_trip_type() {
         .stderr(predicate::str::contains("invalid trip type"));
 }
 
+#[test]
+fn invalid_sort_key() {
+    cmd()
+        .args([
+            "search",
+            "-f",
+            "HEL",
+            "-t",
+            "BCN",
+            "-d",
+            "2026-03-01",
+            "--sort",
+            "vibes",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid sort key"));
+}
+
 #[test]
 fn invalid_date_format() {
     cmd()
@@ -260,6 +282,35 @@ fn malformed_leg_fails() {
         .stderr(predicate::str::contains("--leg must be"));
 }
 
+#[test]
+fn unknown_leg_option_fails() {
+    cmd()
+        .args(["search", "--leg", "2026-03-01 LAX NRT cabin=first", "--url"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown --leg option"));
+}
+
+#[test]
+fn city_names_resolve_to_codes() {
+    let expected = flyr::generate_browser_url(&flyr::testing::hel_bcn_query());
+    let tfs = expected.split("tfs=").nth(1).unwrap().split('&').next().unwrap();
+    cmd()
+        .args(["search", "-f", "Helsinki", "-t", "barcelona", "-d", "2026-03-01", "--url"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(tfs));
+}
+
+#[test]
+fn ambiguous_city_lists_candidates() {
+    cmd()
+        .args(["search", "-f", "Shanghai", "-t", "HEL", "-d", "2026-03-01", "--url"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("PVG"));
+}
+
 #[test]
 fn json_mode_error_is_structured() {
     let output = cmd()
@@ -277,6 +328,7 @@ fn json_mode_error_is_structured() {
         .failure();
     let stdout = String::from_utf8_lossy(&output.get_output().stdout);
     let parsed: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON error");
+    assert_eq!(parsed["schema_version"], flyr::SCHEMA_VERSION);
     assert_eq!(parsed["error"]["kind"], "invalid_airport");
     assert!(parsed["error"]["message"]
         .as_str()
@@ -485,3 +537,442 @@ fn mcp_help_shows_description() {
         .stdout(predicate::str::contains("MCP"))
         .stdout(predicate::str::contains("stdio"));
 }
+
+fn temp_db(name: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("flyr-{name}-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[test]
+fn history_empty_database() {
+    let db = temp_db("history");
+    cmd()
+        .env("FLYR_DB", &db)
+        .args(["history", "-f", "HEL", "-t", "BCN"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No history recorded for HEL-BCN"));
+
+    cmd()
+        .env("FLYR_DB", &db)
+        .args(["history", "-f", "HEL", "-t", "BCN", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("[]"));
+}
+
+#[test]
+fn cache_stats_and_clear() {
+    let db = temp_db("cache");
+    cmd()
+        .env("FLYR_DB", &db)
+        .args(["cache", "stats", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"searches\":0"));
+
+    cmd()
+        .env("FLYR_DB", &db)
+        .args(["cache", "clear"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 0 cached searches"));
+}
+
+#[test]
+fn invalid_cache_ttl() {
+    cmd()
+        .args([
+            "search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--cache-ttl", "soon",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid duration"));
+}
+
+#[test]
+fn calendar_help() {
+    cmd()
+        .args(["calendar", "--help"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--month"))
+        .stdout(predicate::str::contains("--return-days"));
+}
+
+#[test]
+fn calendar_requires_a_range() {
+    cmd()
+        .args(["calendar", "-f", "HEL", "-t", "BCN"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--month"));
+}
+
+#[test]
+fn book_counts_results_from_one() {
+    cmd()
+        .args(["book", "0", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("<N>"));
+}
+
+#[test]
+fn with_returns_needs_a_return_date() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--with-returns"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--return-date"));
+}
+
+#[test]
+fn raw_rejects_multi_dest() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN,MAD", "-d", "2026-03-01", "--raw"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--raw"));
+}
+
+#[test]
+fn flex_rejects_multi_dest() {
+    cmd()
+        .args([
+            "search", "-f", "HEL", "-t", "BCN,MAD", "-d", "2026-03-01", "--flex", "3",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("--flex"));
+}
+
+#[test]
+fn invalid_hedge_delay() {
+    cmd()
+        .args([
+            "search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--hedge", "later",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid delay"));
+}
+
+#[test]
+fn invalid_resolve_override() {
+    cmd()
+        .args([
+            "search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--resolve", "google",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("HOST=IP"));
+}
+
+#[test]
+fn invalid_phase_timeout() {
+    cmd()
+        .args([
+            "search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--ttfb-timeout", "fast",
+        ])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid delay"));
+}
+
+#[test]
+fn unknown_schema_version_rejected() {
+    cmd()
+        .args([
+            "search", "-f", "HEL", "-t", "BCN,MAD", "-d", "2026-03-01", "--json", "--schema", "4",
+        ])
+        .assert()
+        .code(2);
+}
+
+fn unreachable_multi_dest() -> Command {
+    let mut c = cmd();
+    c.args([
+        "search", "-f", "HEL", "-t", "BCN,MAD", "-d", "2026-03-01", "--json", "--timeout", "5",
+        "--resolve", "www.google.com=127.0.0.1",
+    ]);
+    c
+}
+
+#[test]
+fn multi_dest_all_failed_reports_each_error() {
+    unreachable_multi_dest()
+        .assert()
+        .code(3)
+        .stdout(predicate::str::starts_with("{\"schema_version\":3,\"destinations\":{"))
+        .stdout(predicate::str::contains("\"BCN\":{\"error\""))
+        .stdout(predicate::str::contains("\"MAD\":{\"error\""));
+}
+
+#[test]
+fn multi_origin_keys_results_by_route() {
+    let mut c = cmd();
+    c.args([
+        "search", "-f", "HEL,ARN", "-t", "BCN", "-d", "2026-03-01", "--json", "--timeout", "5",
+        "--resolve", "www.google.com=127.0.0.1",
+    ])
+    .assert()
+    .code(3)
+    .stdout(predicate::str::contains("\"ARN-BCN\":{\"error\""))
+    .stdout(predicate::str::contains("\"HEL-BCN\":{\"error\""));
+}
+
+#[test]
+fn multi_date_keys_results_by_date() {
+    let mut c = cmd();
+    c.args([
+        "search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01,2026-03-02", "--json",
+        "--timeout", "5", "--resolve", "www.google.com=127.0.0.1",
+    ])
+    .assert()
+    .code(3)
+    .stdout(predicate::str::contains("\"2026-03-01\":{\"error\""))
+    .stdout(predicate::str::contains("\"2026-03-02\":{\"error\""));
+}
+
+#[test]
+fn multi_dest_schema_2_is_a_bare_destination_map() {
+    unreachable_multi_dest()
+        .args(["--schema", "2"])
+        .assert()
+        .code(3)
+        .stdout(predicate::str::starts_with("{\"BCN\":{\"error\""));
+}
+
+#[test]
+fn multi_dest_fail_fast_stops_at_first_error() {
+    unreachable_multi_dest()
+        .arg("--fail-fast")
+        .assert()
+        .code(3)
+        .stdout(predicate::str::contains("\"error\"").count(1));
+}
+
+#[test]
+fn fail_fast_conflicts_with_allow_partial() {
+    unreachable_multi_dest()
+        .args(["--fail-fast", "--allow-partial"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn fail_on_empty_keeps_error_exit_codes() {
+    unreachable_multi_dest()
+        .arg("--fail-on-empty")
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn exit_code_map_overrides_defaults() {
+    cmd()
+        .args(["search", "-f", "X1", "-t", "BCN", "-d", "2026-03-01"])
+        .args(["--exit-code-map", r#"{"invalid_airport":64,"timeout":75}"#])
+        .assert()
+        .code(64);
+}
+
+#[test]
+fn exit_code_map_keeps_unlisted_kinds() {
+    unreachable_multi_dest()
+        .args(["--exit-code-map", r#"{"rate_limited":75}"#])
+        .assert()
+        .code(3);
+}
+
+#[test]
+fn exit_code_map_reads_file() {
+    let path = std::env::temp_dir().join(format!("flyr-exit-codes-{}.json", std::process::id()));
+    std::fs::write(&path, r#"{"connection_failed":69,"timeout":69,"dns_error":69}"#).unwrap();
+    unreachable_multi_dest()
+        .arg("--exit-code-map")
+        .arg(format!("@{}", path.display()))
+        .assert()
+        .code(69);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn exit_code_map_accepts_outcome_kinds() {
+    cmd()
+        .args(["search", "-f", "X1", "-t", "BCN", "-d", "2026-03-01"])
+        .args(["--exit-code-map", r#"{"empty":4,"partial":75,"alert":1}"#])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown kind").not());
+}
+
+#[test]
+fn exit_code_map_rejects_unknown_kinds() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01"])
+        .args(["--exit-code-map", r#"{"rate_limitd":75}"#])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown kind \"rate_limitd\""));
+}
+
+#[test]
+fn unknown_compact_field_rejected() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--compact"])
+        .args(["--compact-fields", "price,legroom"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown compact field \"legroom\""))
+        .stderr(predicate::str::contains("flight_numbers"));
+}
+
+#[test]
+fn compact_fields_requires_compact() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01"])
+        .args(["--compact-fields", "price,route"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn invalid_value_weights_rejected() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01"])
+        .args(["--sort", "value", "--value-weights", "legroom=1"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid value weight"));
+}
+
+#[test]
+fn rank_conflicts_with_sort() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01"])
+        .args(["--rank", "price=0.6,duration=0.3,stops=0.1", "--sort", "price"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn query_conflicts_with_route() {
+    cmd()
+        .args(["search", "--query", "flights from Helsinki to Tokyo", "-f", "HEL"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn nights_conflicts_with_return_date() {
+    cmd()
+        .args([
+            "search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--return-date",
+            "2026-03-08", "--nights", "5-7",
+        ])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn invalid_config_file_rejected() {
+    let path = std::env::temp_dir().join(format!("flyr-bad-config-{}.json", std::process::id()));
+    std::fs::write(&path, r#"{"rank": "price=fast"}"#).unwrap();
+    cmd()
+        .env("FLYR_CONFIG", &path)
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid config file"));
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn pareto_co2_requires_pareto() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--pareto-co2"])
+        .assert()
+        .code(2);
+}
+
+#[test]
+fn invalid_bag_rejected() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--with-bag", "duffel"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("carry-on or checked"));
+}
+
+#[test]
+fn compare_seats_needs_two_distinct_cabins() {
+    for list in ["business", "economy,economy", "economy,coach"] {
+        cmd()
+            .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01"])
+            .args(["--compare-seats", list])
+            .assert()
+            .code(2);
+    }
+}
+
+#[test]
+fn compare_seats_rejects_multiple_destinations() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN,ATH", "-d", "2026-03-01"])
+        .args(["--compare-seats", "economy,business", "--json"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("single destination"));
+}
+
+#[test]
+fn snapshot_validates_routes() {
+    cmd()
+        .args(["snapshot", "-f", "HEL", "-t", "BCN,xx", "-d", "2026-03-01", "--json"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("invalid_airport"));
+}
+
+#[test]
+fn bench_runs_over_fixtures() {
+    cmd()
+        .args(["bench", "--fixture", "tests/fixtures", "-n", "2", "--json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"fixture\":\"one_way_hel_bcn.html\""))
+        .stdout(predicate::function(|out: &str| {
+            out.contains("allocations_per_iter") == cfg!(feature = "count-allocations")
+        }));
+}
+
+#[test]
+fn jq_rejects_invalid_filters() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "BCN", "-d", "2026-03-01", "--jq", ".flights["])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("invalid --jq filter"));
+}
+
+#[test]
+fn jq_leaves_error_documents_unfiltered() {
+    cmd()
+        .args(["search", "-f", "HEL", "-t", "xx", "-d", "2026-03-01", "--jq", ".flights"])
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("invalid_airport"));
+}
+
+#[test]
+fn schema_describes_versioned_search_results() {
+    cmd()
+        .arg("schema")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\"schema_version\""))
+        .stdout(predicate::str::contains("\"FlightResult\""));
+}
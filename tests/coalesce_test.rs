@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use flyr::coalesce::Coalescer;
+use flyr::error::FlightError;
+use flyr::fetch::Fetcher;
+use flyr::parse::ParseOptions;
+use flyr::query::SearchQuery;
+use flyr::testing::{fixtures, hel_bcn_query};
+
+#[derive(Default)]
+struct CountingFetcher {
+    calls: AtomicUsize,
+}
+
+impl Fetcher for CountingFetcher {
+    async fn fetch_html(&self, _params: &[(String, String)]) -> Result<String, FlightError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        tokio::task::yield_now().await;
+        Ok(fixtures::ONE_WAY_HEL_BCN.to_string())
+    }
+}
+
+fn query(currency: &str) -> SearchQuery {
+    let mut params = hel_bcn_query();
+    params.currency = currency.into();
+    SearchQuery::Structured(params)
+}
+
+#[tokio::test]
+async fn identical_concurrent_searches_share_one_fetch() {
+    let fetcher = CountingFetcher::default();
+    let coalescer = Coalescer::new();
+
+    let (a, b) = tokio::join!(
+        coalescer.search(&fetcher, query("EUR"), ParseOptions::default()),
+        coalescer.search(&fetcher, query("EUR"), ParseOptions::default()),
+    );
+
+    assert_eq!(fetcher.calls.load(Ordering::SeqCst), 1);
+    assert_eq!(a.unwrap().flights.len(), b.unwrap().flights.len());
+    assert_eq!(coalescer.in_flight(), 0);
+}
+
+#[tokio::test]
+async fn different_searches_are_not_coalesced() {
+    let fetcher = CountingFetcher::default();
+    let coalescer = Coalescer::new();
+
+    let (a, b) = tokio::join!(
+        coalescer.search(&fetcher, query("EUR"), ParseOptions::default()),
+        coalescer.search(&fetcher, query("USD"), ParseOptions::default()),
+    );
+
+    assert_eq!(fetcher.calls.load(Ordering::SeqCst), 2);
+    assert!(a.is_ok() && b.is_ok());
+}
+
+#[tokio::test]
+async fn sequential_searches_fetch_again() {
+    let fetcher = CountingFetcher::default();
+    let coalescer = Coalescer::new();
+
+    coalescer
+        .search(&fetcher, query("EUR"), ParseOptions::default())
+        .await
+        .unwrap();
+    coalescer
+        .search(&fetcher, query("EUR"), ParseOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(fetcher.calls.load(Ordering::SeqCst), 2);
+}
@@ -0,0 +1,47 @@
+use flyr::config::Config;
+use flyr::error::FlightError;
+use flyr::value::ValueWeights;
+
+fn temp_config(name: &str, contents: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("flyr-config-{name}-{}.json", std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn missing_config_is_empty() {
+    let path = std::env::temp_dir().join("flyr-config-does-not-exist.json");
+    let config = Config::load_from(&path).unwrap();
+    assert!(config.rank.is_none());
+    assert!(config.ranker().unwrap().is_none());
+}
+
+#[test]
+fn config_rank_becomes_a_ranker() {
+    let path = temp_config("rank", r#"{"rank": "price=0.6,duration=0.3,stops=0.1"}"#);
+    let ranker = Config::load_from(&path).unwrap().ranker().unwrap().unwrap();
+    assert_eq!(
+        ranker.weights,
+        ValueWeights {
+            price: 0.6,
+            duration: 0.3,
+            stops: 0.1
+        }
+    );
+    std::fs::remove_file(path).ok();
+}
+
+#[test]
+fn invalid_config_is_a_validation_error() {
+    for (name, contents) in [
+        ("weights", r#"{"rank": "legroom=1"}"#),
+        ("unknown", r#"{"ranking": "price=1"}"#),
+        ("syntax", "{"),
+    ] {
+        let path = temp_config(name, contents);
+        let err = Config::load_from(&path).unwrap_err();
+        assert!(matches!(err, FlightError::Validation(_)), "{name}: {err}");
+        assert!(err.to_string().contains("invalid config file"));
+        std::fs::remove_file(path).ok();
+    }
+}
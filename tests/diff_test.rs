@@ -0,0 +1,58 @@
+use flyr::diff::{annotate, change, format_age, Change};
+use flyr::locale::Locale;
+use flyr::model::{Baseline, SearchResult};
+use flyr::parse::parse_html;
+use flyr::table::format_change;
+use flyr::testing::fixtures;
+
+fn sample() -> SearchResult {
+    parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap()
+}
+
+fn baseline() -> Baseline {
+    Baseline {
+        searched_at: "2027-01-15T02:00:00Z".into(),
+        age_secs: 6 * 3600,
+    }
+}
+
+#[test]
+fn matches_itineraries_across_searches() {
+    let mut previous = sample();
+    previous.flights[0].price = previous.flights[0].price.map(|p| p + 31);
+    previous.flights[1].price = previous.flights[1].price.map(|p| p - 10);
+    previous.flights.truncate(2);
+
+    let mut result = sample();
+    annotate(&mut result, &previous, baseline());
+
+    assert_eq!(change(&result.flights[0]), Change::Down(31));
+    assert_eq!(change(&result.flights[1]), Change::Up(10));
+    assert_eq!(change(&result.flights[2]), Change::New);
+    assert_eq!(result.flights[0].delta, Some(-31));
+    assert_eq!(result.flights[2].previous_price, None);
+    assert_eq!(result.baseline, Some(baseline()));
+}
+
+#[test]
+fn unchanged_prices_have_zero_delta() {
+    let mut result = sample();
+    annotate(&mut result, &sample(), baseline());
+
+    assert!(result.flights.iter().all(|f| f.delta == Some(0)));
+    assert_eq!(change(&result.flights[0]), Change::Unchanged);
+}
+
+#[test]
+fn changes_render_with_age() {
+    let mut previous = sample();
+    previous.flights[0].price = previous.flights[0].price.map(|p| p + 31);
+    let mut result = sample();
+    annotate(&mut result, &previous, baseline());
+
+    let text = format_change(&result.flights[0], &baseline(), "EUR", &Locale::default());
+    assert!(text.starts_with('▼'), "{text}");
+    assert!(text.ends_with("since 6h ago"), "{text}");
+    assert_eq!(format_age(30), "1m ago");
+    assert_eq!(format_age(2 * 86_400), "2d ago");
+}
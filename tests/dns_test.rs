@@ -0,0 +1,46 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+use flyr::dns::{parse_override, DnsCache};
+use flyr::error::FlightError;
+
+#[test]
+fn parses_host_overrides() {
+    let (host, ip) = parse_override("WWW.Google.com=142.250.74.36").unwrap();
+    assert_eq!(host, "www.google.com");
+    assert_eq!(ip, IpAddr::V4(Ipv4Addr::new(142, 250, 74, 36)));
+
+    assert!(parse_override("www.google.com").is_err());
+    assert!(parse_override("=1.2.3.4").is_err());
+    assert!(parse_override("www.google.com=not-an-ip").is_err());
+}
+
+#[tokio::test]
+async fn pinned_hosts_skip_resolution() {
+    let ip = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 7));
+    let cache = DnsCache::with_overrides(Duration::from_secs(60), &[("pinned.invalid".into(), ip)]);
+
+    let addrs = cache.lookup("PINNED.invalid").await.unwrap();
+    assert_eq!(addrs.len(), 1);
+    assert_eq!(addrs[0].ip(), ip);
+}
+
+#[tokio::test]
+async fn lookups_are_cached_until_ttl() {
+    let cache = DnsCache::new(Duration::from_secs(60));
+    assert!(cache.cached("localhost").is_none());
+
+    let first = cache.lookup("localhost").await.unwrap();
+    assert_eq!(cache.cached("localhost"), Some(first));
+
+    let expired = DnsCache::new(Duration::ZERO);
+    expired.lookup("localhost").await.unwrap();
+    assert!(expired.cached("localhost").is_none());
+}
+
+#[tokio::test]
+async fn failures_are_reported_clearly() {
+    let err = DnsCache::default().lookup("no-such-host.invalid").await.unwrap_err();
+    assert!(matches!(err, FlightError::DnsResolution(_)));
+    assert!(err.to_string().contains("no-such-host.invalid"));
+}
@@ -0,0 +1,71 @@
+use flyr::export::{itinerary_key, Export};
+use flyr::model::SearchResult;
+use flyr::parse::parse_html;
+use flyr::testing::{fixtures, hel_bcn_query};
+
+fn sample(searched_at: &str) -> SearchResult {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.searched_at = Some(searched_at.into());
+    result
+}
+
+fn count(export: &Export, table: &str) -> i64 {
+    export
+        .connection()
+        .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn write_normalizes_flights_segments_and_fares() {
+    let mut export = Export::in_memory().unwrap();
+    let counts = export
+        .write(&hel_bcn_query(), &sample("2026-01-01T10:00:00Z"))
+        .unwrap();
+
+    assert_eq!(counts.flights, 3);
+    assert_eq!(counts.skipped, 0);
+    assert_eq!(count(&export, "searches"), 1);
+    assert_eq!(count(&export, "flights"), 3);
+    assert_eq!(count(&export, "segments"), 4);
+    assert_eq!(count(&export, "fares"), 3);
+    assert_eq!(count(&export, "airlines"), 3);
+
+    let (stops, via): (i64, String) = export
+        .connection()
+        .query_row(
+            "SELECT f.stops, s.to_airport FROM flights f
+             JOIN segments s ON s.flight_id = f.id AND s.position = 0
+             JOIN fares p ON p.flight_id = f.id WHERE p.price = 142",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    assert_eq!((stops, via.as_str()), (1, "FRA"));
+}
+
+#[test]
+fn repeated_searches_reuse_flights() {
+    let mut export = Export::in_memory().unwrap();
+    let params = hel_bcn_query();
+    export.write(&params, &sample("2026-01-01T10:00:00Z")).unwrap();
+    export.write(&params, &sample("2026-01-02T10:00:00Z")).unwrap();
+    export.write(&params, &sample("2026-01-02T10:00:00Z")).unwrap();
+
+    assert_eq!(count(&export, "searches"), 2);
+    assert_eq!(count(&export, "flights"), 3);
+    assert_eq!(count(&export, "segments"), 4);
+    assert_eq!(count(&export, "fares"), 6);
+}
+
+#[test]
+fn flights_without_segments_are_skipped() {
+    let mut result = sample("2026-01-01T10:00:00Z");
+    result.flights[0].segments.clear();
+    assert!(itinerary_key(&result.flights[0]).is_none());
+
+    let mut export = Export::in_memory().unwrap();
+    let counts = export.write(&hel_bcn_query(), &result).unwrap();
+    assert_eq!(counts.flights, 2);
+    assert_eq!(counts.skipped, 1);
+}
@@ -0,0 +1,155 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use flyr::breaker::DEFAULT_RETRY_BUDGET;
+use flyr::error::FlightError;
+use flyr::fetch::{
+    backoff_delay, interstitial_error, parse_retry_after, with_retries, FetchOptions, FlyrClient,
+    SessionPool, MAX_BACKOFF,
+};
+use flyr::testing::fixtures;
+
+#[test]
+fn retry_after_delta_seconds() {
+    assert_eq!(parse_retry_after("120", 0), Some(120));
+    assert_eq!(parse_retry_after(" 5 ", 0), Some(5));
+}
+
+#[test]
+fn retry_after_http_date() {
+    let at = 1_445_412_480;
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", at - 90),
+        Some(90)
+    );
+}
+
+#[test]
+fn retry_after_date_in_past_is_zero() {
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", 1_445_412_480 + 60),
+        Some(0)
+    );
+}
+
+#[test]
+fn retry_after_garbage() {
+    assert_eq!(parse_retry_after("soon", 0), None);
+    assert_eq!(parse_retry_after("Wed, 21 Foo 2015 07:28:00 GMT", 0), None);
+}
+
+#[test]
+fn rate_limited_exposes_retry_after() {
+    let err = FlightError::RateLimited {
+        retry_after: Some(30),
+    };
+    assert_eq!(err.retry_after(), Some(Duration::from_secs(30)));
+    assert!(err.to_string().contains("30s"));
+    assert_eq!(FlightError::Timeout.retry_after(), None);
+}
+
+#[test]
+fn backoff_doubles_with_bounded_jitter() {
+    let base = Duration::from_millis(500);
+    assert_eq!(backoff_delay(base, 1, 0.0), Duration::from_millis(500));
+    assert_eq!(backoff_delay(base, 2, 0.0), Duration::from_millis(1000));
+    assert_eq!(backoff_delay(base, 3, 0.0), Duration::from_millis(2000));
+    assert_eq!(backoff_delay(base, 3, 1.0), Duration::from_millis(3000));
+    assert_eq!(backoff_delay(base, 30, 0.5), MAX_BACKOFF);
+}
+
+#[test]
+fn only_transient_failures_are_retried() {
+    assert!(FlightError::Timeout.is_retryable());
+    assert!(FlightError::ConnectionFailed("reset".into()).is_retryable());
+    assert!(FlightError::RateLimited { retry_after: None }.is_retryable());
+    assert!(FlightError::Blocked(503).is_retryable());
+    assert!(FlightError::HttpStatus(502).is_retryable());
+    assert!(!FlightError::Blocked(403).is_retryable());
+    assert!(!FlightError::HttpStatus(404).is_retryable());
+    assert!(!FlightError::CaptchaRequired("https://www.google.com/sorry/".into()).is_retryable());
+    assert!(!FlightError::ScriptTagNotFound.is_retryable());
+}
+
+fn no_backoff(retries: u32) -> FetchOptions {
+    FetchOptions {
+        retries,
+        backoff: Duration::ZERO,
+        ..FetchOptions::default()
+    }
+}
+
+async fn attempts(options: &FetchOptions, error: FlightError) -> u32 {
+    let calls = AtomicU32::new(0);
+    let result: Result<(), _> = with_retries(options, || async {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Err(error.clone())
+    })
+    .await;
+    assert!(result.is_err());
+    calls.load(Ordering::SeqCst)
+}
+
+#[tokio::test]
+async fn zero_retries_means_one_attempt() {
+    assert_eq!(attempts(&no_backoff(0), FlightError::Timeout).await, 1);
+    assert_eq!(attempts(&no_backoff(2), FlightError::Timeout).await, 3);
+}
+
+#[tokio::test]
+async fn fan_out_requests_share_one_retry_budget() {
+    let options = no_backoff(5).for_fan_out();
+    assert_eq!(attempts(&options, FlightError::Timeout).await, DEFAULT_RETRY_BUDGET + 1);
+    assert_eq!(attempts(&options, FlightError::Timeout).await, 1);
+    assert_eq!(options.retry_budget.as_ref().unwrap().remaining(), 0);
+}
+
+#[tokio::test]
+async fn fan_out_leaves_rate_limits_to_the_breaker() {
+    let limited = FlightError::RateLimited { retry_after: None };
+    assert_eq!(attempts(&no_backoff(2), limited.clone()).await, 3);
+
+    let options = no_backoff(2).for_fan_out();
+    assert_eq!(attempts(&options, limited).await, 1);
+    assert_eq!(
+        options.retry_budget.as_ref().unwrap().remaining(),
+        DEFAULT_RETRY_BUDGET
+    );
+}
+
+#[test]
+fn flyr_client_keeps_its_options() {
+    let options = FetchOptions {
+        timeout: 5,
+        auto_consent: false,
+        ..FetchOptions::default()
+    };
+    let client = FlyrClient::new(options).unwrap();
+    assert_eq!(client.options().timeout, 5);
+    assert!(!client.options().auto_consent);
+}
+
+#[test]
+fn session_pool_has_at_least_one_session() {
+    assert_eq!(SessionPool::new(0, FetchOptions::default()).len(), 1);
+
+    let pool = SessionPool::new(3, FetchOptions::default());
+    assert_eq!(pool.len(), 3);
+    assert!(!pool.is_empty());
+    assert_eq!(pool.session().unwrap().options().timeout, 30);
+    assert!(pool.for_fan_out().session().unwrap().options().retry_budget.is_some());
+}
+
+#[test]
+fn interstitial_pages_are_named_not_parsed() {
+    let url = "https://www.google.com/_/FlightsFrontendUi/data/batchexecute";
+    assert!(matches!(
+        interstitial_error(fixtures::CAPTCHA, url),
+        Some(FlightError::CaptchaRequired(u)) if u == url
+    ));
+    assert!(matches!(
+        interstitial_error(fixtures::CONSENT, url),
+        Some(FlightError::ConsentRequired)
+    ));
+    assert!(interstitial_error(")]}'\n[[\"wrb.fr\",null,\"[]\"]]", url).is_none());
+}
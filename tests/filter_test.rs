@@ -0,0 +1,223 @@
+use flyr::filter::{
+    apply, apply_best_only, apply_exclude_basic, apply_low_emissions, dedupe_codeshares, apply_max_price, is_low_emission, is_redeye,
+    matches,
+};
+use flyr::model::{Category, FareBrand};
+use flyr::parse::parse_html;
+use flyr::query::FlightLeg;
+use flyr::testing::fixtures;
+
+fn window(depart_after: Option<u32>, arrive_before: Option<u32>) -> FlightLeg {
+    FlightLeg {
+        depart_after,
+        arrive_before,
+        ..FlightLeg::default()
+    }
+}
+
+#[test]
+fn leg_without_windows_keeps_everything() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    assert_eq!(apply(&mut result, &FlightLeg::default()), 0);
+    assert_eq!(result.flights.len(), 3);
+}
+
+#[test]
+fn departure_and_arrival_bounds_are_inclusive() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let flight = &result.flights[0];
+    let dep = &flight.segments[0].departure;
+    let arr = &flight.segments.last().unwrap().arrival;
+    let dep_min = dep.hour * 60 + dep.minute;
+    let arr_min = arr.hour * 60 + arr.minute;
+
+    assert!(matches(flight, &window(Some(dep_min), Some(arr_min))));
+    assert!(!matches(flight, &window(Some(dep_min + 1), None)));
+    assert!(!matches(flight, &window(None, Some(arr_min - 1))));
+}
+
+#[test]
+fn apply_drops_flights_outside_the_window() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let earliest = result
+        .flights
+        .iter()
+        .map(|f| f.segments[0].departure.hour * 60 + f.segments[0].departure.minute)
+        .min()
+        .unwrap();
+    let removed = apply(&mut result, &window(Some(earliest + 1), None));
+    assert!(removed >= 1);
+    assert!(result.flights.iter().all(|f| {
+        let d = &f.segments[0].departure;
+        d.hour * 60 + d.minute > earliest
+    }));
+}
+
+#[test]
+fn max_duration_drops_long_itineraries() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let shortest = result.flights.iter().map(|f| f.elapsed_minutes).min().unwrap();
+    let leg = FlightLeg {
+        max_duration_minutes: Some(shortest),
+        ..FlightLeg::default()
+    };
+    apply(&mut result, &leg);
+    assert!(!result.flights.is_empty());
+    assert!(result.flights.iter().all(|f| f.elapsed_minutes == shortest));
+}
+
+#[test]
+fn layover_bounds_apply_to_every_connection() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let connecting = result
+        .flights
+        .iter()
+        .find(|f| !f.layovers.is_empty())
+        .unwrap();
+    let layover = connecting.layovers[0].minutes;
+    let bounds = |min, max| FlightLeg {
+        min_layover_minutes: min,
+        max_layover_minutes: max,
+        ..FlightLeg::default()
+    };
+
+    assert!(matches(connecting, &bounds(Some(layover), Some(layover))));
+    assert!(!matches(connecting, &bounds(Some(layover + 1), None)));
+    assert!(!matches(connecting, &bounds(None, Some(layover - 1))));
+
+    let nonstop = result.flights.iter().find(|f| f.layovers.is_empty()).unwrap();
+    assert!(matches(nonstop, &bounds(Some(600), Some(601))));
+}
+
+#[test]
+fn excluded_airlines_drop_any_itinerary_they_fly() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let carrier = result.flights[0].segments[0].airline.clone().unwrap();
+    let leg = FlightLeg {
+        exclude_airlines: Some(vec![carrier.to_lowercase()]),
+        ..FlightLeg::default()
+    };
+    let removed = apply(&mut result, &leg);
+    assert!(removed >= 1);
+    assert!(result
+        .flights
+        .iter()
+        .flat_map(|f| &f.segments)
+        .all(|s| s.airline.as_deref() != Some(carrier.as_str())));
+}
+
+#[test]
+fn max_price_drops_dearer_and_unpriced_flights() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.flights[0].price = None;
+    let max = result.flights[1].price.unwrap();
+    apply_max_price(&mut result, max);
+    assert!(result.flights.iter().all(|f| f.price.is_some_and(|p| p <= max)));
+    assert!(!result.flights.is_empty());
+}
+
+#[test]
+fn best_only_keeps_the_best_flights_bucket() {
+    let mut result = parse_html(fixtures::BEST_FLIGHTS_HEL_BCN).unwrap();
+    assert_eq!(result.flights.len(), 3);
+
+    assert_eq!(apply_best_only(&mut result), 2);
+    assert_eq!(result.flights.len(), 1);
+    assert_eq!(result.flights[0].category, Some(Category::Best));
+    assert_eq!(result.flights[0].airlines, vec!["AY"]);
+}
+
+#[test]
+fn low_emissions_keeps_flights_at_or_below_typical() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.flights[0].carbon.emission_grams = Some(90_000);
+    result.flights[0].carbon.typical_grams = Some(100_000);
+    result.flights[1].carbon.emission_grams = Some(100_000);
+    result.flights[1].carbon.typical_grams = Some(100_000);
+    result.flights[2].carbon.emission_grams = Some(120_000);
+    result.flights[2].carbon.typical_grams = Some(100_000);
+    assert!(!is_low_emission(&result.flights[2]));
+
+    assert_eq!(apply_low_emissions(&mut result), 1);
+    assert_eq!(result.flights.len(), 2);
+}
+
+#[test]
+fn low_emissions_drops_flights_without_carbon_data() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    for flight in &mut result.flights {
+        flight.carbon.typical_grams = None;
+    }
+    apply_low_emissions(&mut result);
+    assert!(result.flights.is_empty());
+}
+
+#[test]
+fn redeye_window_spans_midnight() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let flight = &mut result.flights[0];
+    let cases = [
+        (21, 59, false),
+        (22, 0, true),
+        (0, 30, true),
+        (4, 59, true),
+        (5, 0, false),
+    ];
+    for (hour, minute, redeye) in cases {
+        flight.segments[0].departure.hour = hour;
+        flight.segments[0].departure.minute = minute;
+        assert_eq!(is_redeye(flight), redeye, "{hour:02}:{minute:02}");
+    }
+}
+
+#[test]
+fn no_redeye_drops_night_departures() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.flights[0].segments[0].departure.hour = 23;
+    result.flights[1].segments[0].departure.hour = 9;
+    let leg = FlightLeg {
+        no_redeye: true,
+        ..FlightLeg::default()
+    };
+    assert!(!matches(&result.flights[0], &leg));
+    assert!(matches(&result.flights[1], &leg));
+    assert!(matches(&result.flights[0], &FlightLeg::default()));
+}
+
+#[test]
+fn codeshare_listings_collapse_into_the_cheapest() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let mut codeshare = result.flights[0].clone();
+    codeshare.price = result.flights[0].price.map(|p| p - 10);
+    codeshare.segments[0].airline = Some("IB".into());
+    codeshare.segments[0].flight_number = Some("5541".into());
+    let original = result.flights[0].segments[0].clone();
+    result.flights.push(codeshare);
+
+    assert_eq!(dedupe_codeshares(&mut result), 1);
+    assert_eq!(result.flights.len(), 3);
+    let kept = &result.flights[0];
+    assert_eq!(kept.segments[0].airline.as_deref(), Some("IB"));
+    let partners = &kept.segments[0].codeshares;
+    assert_eq!(partners.len(), 1);
+    assert_eq!(Some(&partners[0].airline), original.airline.as_ref());
+    assert_eq!(Some(&partners[0].flight_number), original.flight_number.as_ref());
+}
+
+#[test]
+fn distinct_flights_are_not_deduped() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    assert_eq!(dedupe_codeshares(&mut result), 0);
+    assert_eq!(result.flights.len(), 3);
+}
+
+#[test]
+fn exclude_basic_keeps_hand_baggage_fares() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.flights[0].fare_brand = Some(FareBrand::Basic);
+    result.flights[1].fare_brand = Some(FareBrand::HandBaggageOnly);
+
+    assert_eq!(apply_exclude_basic(&mut result), 1);
+    assert_eq!(result.flights.len(), 2);
+    assert_eq!(result.flights[0].fare_brand, Some(FareBrand::HandBaggageOnly));
+}
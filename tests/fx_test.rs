@@ -0,0 +1,81 @@
+use flyr::fx::{convert, RateSource, Rates};
+use flyr::parse::parse_html;
+use flyr::testing::fixtures;
+
+const ECB_DAILY: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+	<gesmes:subject>Reference rates</gesmes:subject>
+	<Cube>
+		<Cube time='2026-10-14'>
+			<Cube currency='USD' rate='1.2000'/>
+			<Cube currency='JPY' rate='180.00'/>
+			<Cube currency='GBP' rate='0.8000'/>
+		</Cube>
+	</Cube>
+</gesmes:Envelope>"#;
+
+#[test]
+fn bundled_rates_cross_through_the_euro() {
+    let rates = Rates::bundled();
+    assert_eq!(rates.per_eur("EUR"), Some(1.0));
+    assert_eq!(rates.per_eur("usd"), rates.per_eur("USD"));
+    assert_eq!(rates.per_eur("XXX"), None);
+    let round = rates.rate("USD", "GBP").unwrap() * rates.rate("GBP", "USD").unwrap();
+    assert!((round - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn parses_ecb_daily_feed() {
+    let rates = Rates::parse_ecb(ECB_DAILY).unwrap();
+    assert_eq!(rates.date.as_deref(), Some("2026-10-14"));
+    assert_eq!(rates.currencies(), vec!["EUR", "USD", "JPY", "GBP"]);
+    assert_eq!(rates.rate("EUR", "USD"), Some(1.2));
+    assert_eq!(rates.rate("USD", "JPY"), Some(150.0));
+}
+
+#[test]
+fn ecb_feed_without_rates_is_an_error() {
+    assert!(Rates::parse_ecb("<html>maintenance</html>").is_err());
+    assert!(Rates::parse_ecb("<Cube currency='USD' rate='n/a'/>").is_err());
+}
+
+#[test]
+fn convert_keeps_the_original_prices() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.metadata.currency = Some("EUR".into());
+    let quoted: Vec<Option<i64>> = result.flights.iter().map(|f| f.price).collect();
+
+    convert(&mut result, &Rates::parse_ecb(ECB_DAILY).unwrap(), "EUR", "usd");
+
+    assert_eq!(result.metadata.currency.as_deref(), Some("USD"));
+    assert_eq!(result.metadata.original_currency.as_deref(), Some("EUR"));
+    for (flight, quoted) in result.flights.iter().zip(quoted) {
+        assert_eq!(flight.original_price, quoted);
+        assert_eq!(flight.price, quoted.map(|p| (p as f64 * 1.2).round() as i64));
+        let fare = flight.fare.as_ref().unwrap();
+        assert_eq!(fare.currency, "USD");
+        assert_eq!(Some(fare.amount_minor), quoted.map(|p| p * 120));
+    }
+}
+
+#[test]
+fn convert_to_the_same_currency_is_a_no_op() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.metadata.currency = Some("EUR".into());
+    convert(&mut result, &Rates::bundled(), "EUR", "EUR");
+    assert!(result.metadata.original_currency.is_none());
+    assert!(result.flights.iter().all(|f| f.original_price.is_none()));
+}
+
+#[test]
+fn unknown_currency_warns_and_leaves_prices() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.metadata.currency = Some("EUR".into());
+    let before: Vec<Option<i64>> = result.flights.iter().map(|f| f.price).collect();
+    convert(&mut result, &Rates::bundled(), "EUR", "XXX");
+
+    let after: Vec<Option<i64>> = result.flights.iter().map(|f| f.price).collect();
+    assert_eq!(before, after);
+    assert_eq!(result.metadata.currency.as_deref(), Some("EUR"));
+    assert_eq!(result.warnings.last().unwrap().kind, "currency_conversion");
+}
@@ -0,0 +1,87 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+use flyr::error::FlightError;
+use flyr::fetch::Fetcher;
+use flyr::hedge::Hedged;
+
+struct Delayed {
+    delay: Duration,
+    response: Result<&'static str, FlightError>,
+    calls: AtomicU32,
+}
+
+impl Delayed {
+    fn new(millis: u64, response: Result<&'static str, FlightError>) -> Self {
+        Self {
+            delay: Duration::from_millis(millis),
+            response,
+            calls: AtomicU32::new(0),
+        }
+    }
+}
+
+impl Fetcher for &Delayed {
+    async fn fetch_html(&self, _params: &[(String, String)]) -> Result<String, FlightError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(self.delay).await;
+        self.response.clone().map(String::from)
+    }
+}
+
+#[test]
+fn primary_is_reachable_for_other_requests() {
+    let primary = Delayed::new(0, Ok("primary"));
+    let hedged = Hedged::disabled(&primary);
+    assert!(std::ptr::eq(*hedged.primary(), &primary));
+    assert_eq!(hedged.after(), None);
+}
+
+#[tokio::test]
+async fn fast_primary_skips_the_backup() {
+    let primary = Delayed::new(0, Ok("primary"));
+    let backup = Delayed::new(0, Ok("backup"));
+    let hedged = Hedged::new(&primary, &backup, Duration::from_millis(200));
+
+    assert_eq!(hedged.fetch_html(&[]).await.unwrap(), "primary");
+    assert_eq!(backup.calls.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn slow_primary_is_overtaken() {
+    let primary = Delayed::new(5_000, Ok("primary"));
+    let backup = Delayed::new(0, Ok("backup"));
+    let hedged = Hedged::new(&primary, &backup, Duration::from_millis(20));
+
+    let started = Instant::now();
+    assert_eq!(hedged.fetch_html(&[]).await.unwrap(), "backup");
+    assert!(started.elapsed() < Duration::from_secs(2));
+    assert_eq!(backup.calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn failed_backup_waits_for_primary() {
+    let primary = Delayed::new(100, Ok("primary"));
+    let backup = Delayed::new(0, Err(FlightError::Timeout));
+    let hedged = Hedged::new(&primary, &backup, Duration::from_millis(10));
+
+    assert_eq!(hedged.fetch_html(&[]).await.unwrap(), "primary");
+}
+
+#[tokio::test]
+async fn both_failing_reports_the_first_error() {
+    let primary = Delayed::new(100, Err(FlightError::Blocked(403)));
+    let backup = Delayed::new(0, Err(FlightError::Timeout));
+    let hedged = Hedged::new(&primary, &backup, Duration::from_millis(10));
+
+    assert!(matches!(hedged.fetch_html(&[]).await, Err(FlightError::Timeout)));
+}
+
+#[tokio::test]
+async fn disabled_uses_only_the_primary() {
+    let primary = Delayed::new(20, Ok("primary"));
+    let hedged = Hedged::disabled(&primary);
+
+    assert_eq!(hedged.after(), None);
+    assert_eq!(hedged.fetch_html(&[]).await.unwrap(), "primary");
+}
@@ -0,0 +1,83 @@
+use flyr::error::FlightError;
+use flyr::jq::Filter;
+use flyr::parse::parse_html;
+use flyr::testing::fixtures;
+use serde_json::{json, Value};
+
+fn run(filter: &str, input: &Value) -> Vec<Value> {
+    Filter::parse(filter).unwrap().run(input).unwrap()
+}
+
+fn document() -> Value {
+    serde_json::to_value(parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap()).unwrap()
+}
+
+#[test]
+fn selects_flights_from_a_result() {
+    let flights = run(".flights[] | select(.price < 150) | .price", &document());
+    assert_eq!(flights, vec![json!(142), json!(121)]);
+
+    let cheapest = run(".flights | min_by(.price) | .airlines[0]", &document());
+    assert_eq!(cheapest, vec![json!("Norwegian")]);
+
+    let count = run("[.flights[] | select(.segments | length == 1)] | length", &document());
+    assert_eq!(count, vec![json!(2)]);
+}
+
+#[test]
+fn paths_and_construction() {
+    let input = json!({"a": {"b": [1, 2, 3]}, "name": "x"});
+    assert_eq!(run(".a.b[1]", &input), vec![json!(2)]);
+    assert_eq!(run(".a.b[-1]", &input), vec![json!(3)]);
+    assert_eq!(run(".[\"name\"]", &input), vec![json!("x")]);
+    assert_eq!(run(".missing.deeper", &input), vec![Value::Null]);
+    assert_eq!(run("[.a.b[] * 2]", &input), vec![json!([2, 4, 6])]);
+    assert_eq!(run("{name, n: (.a.b | length)}", &input), vec![json!({"name": "x", "n": 3})]);
+    assert_eq!(run(".a.b[0], .name", &input), vec![json!(1), json!("x")]);
+    assert_eq!(run("keys", &input), vec![json!(["a", "name"])]);
+}
+
+#[test]
+fn operators_and_builtins() {
+    let input = json!([3, 1, null, 2]);
+    assert_eq!(run("map(. // 0) | add", &input), vec![json!(6)]);
+    assert_eq!(run("map(values) | sort", &input), vec![json!([1, 2, 3])]);
+    assert_eq!(run(".[0] > 2 and .[1] == 1", &input), vec![json!(true)]);
+    assert_eq!(run("limit(2; .[])", &input), vec![json!(3), json!(1)]);
+    assert_eq!(
+        run("if .[0] > 2 then \"big\" else \"small\" end", &input),
+        vec![json!("big")]
+    );
+    assert_eq!(run("[\"a\", \"b\"] | join(\"-\")", &input), vec![json!("a-b")]);
+}
+
+#[test]
+fn invalid_filters_are_validation_errors() {
+    for filter in [".flights[", "select(", "foo", "$missing", "1 +"] {
+        let err = Filter::parse(filter).unwrap_err();
+        assert!(matches!(err, FlightError::Validation(_)), "{filter}: {err}");
+    }
+}
+
+#[test]
+fn runtime_errors_surface() {
+    let err = Filter::parse(".a + \"x\"").unwrap().run(&json!({"a": 1})).unwrap_err();
+    assert!(err.to_string().starts_with("--jq: "), "{err}");
+    assert_eq!(run("(.a + \"x\")?", &json!({"a": 1})), Vec::<Value>::new());
+}
+
+#[test]
+fn full_jq_language() {
+    let input = json!({"a": 1, "b": 2});
+    assert_eq!(run(". as {a: $x} | $x + .b", &input), vec![json!(3)]);
+    assert_eq!(run("reduce .[] as $n (0; . + $n)", &input), vec![json!(3)]);
+    assert_eq!(run("\"a is \\(.a)\"", &input), vec![json!("a is 1")]);
+    assert_eq!(run("with_entries(.value += 1)", &input), vec![json!({"a": 2, "b": 3})]);
+    assert_eq!(run("keys | map(test(\"^a\"))", &input), vec![json!([true, false])]);
+}
+
+#[test]
+fn generators_are_lazy_and_arithmetic_is_total() {
+    assert_eq!(run("limit(2; range(1e15))", &Value::Null), vec![json!(0), json!(1)]);
+    assert_eq!(run("-9223372036854775808 % -1", &Value::Null).len(), 1);
+}
@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use flyr::error::FlightError;
+use flyr::fetch::Fetcher;
+use flyr::limit::{AdaptiveLimit, Limited};
+
+fn pushback() -> Result<(), FlightError> {
+    Err(FlightError::RateLimited { retry_after: None })
+}
+
+#[test]
+fn pushback_halves_the_limit() {
+    let limit = AdaptiveLimit::new(8, Duration::ZERO);
+    limit.record(&pushback());
+    assert_eq!(limit.limit(), 4);
+    limit.record(&Err::<(), _>(FlightError::Blocked(403)));
+    assert_eq!(limit.limit(), 2);
+    limit.record(&pushback());
+    limit.record(&pushback());
+    assert_eq!(limit.limit(), 1);
+}
+
+#[test]
+fn other_errors_leave_the_limit_alone() {
+    let limit = AdaptiveLimit::new(4, Duration::ZERO);
+    limit.record(&Err::<(), _>(FlightError::Timeout));
+    assert_eq!(limit.limit(), 4);
+}
+
+#[test]
+fn successes_recover_slowly() {
+    let limit = AdaptiveLimit::new(4, Duration::ZERO);
+    limit.record(&pushback());
+    assert_eq!(limit.limit(), 2);
+
+    limit.record(&Ok(()));
+    assert_eq!(limit.limit(), 2);
+    limit.record(&Ok(()));
+    assert_eq!(limit.limit(), 3);
+
+    for _ in 0..10 {
+        limit.record(&Ok(()));
+    }
+    assert_eq!(limit.limit(), 4);
+}
+
+#[derive(Clone, Default)]
+struct Tracker {
+    active: Arc<AtomicU32>,
+    peak: Arc<AtomicU32>,
+}
+
+impl Fetcher for Tracker {
+    async fn fetch_html(&self, _params: &[(String, String)]) -> Result<String, FlightError> {
+        let now = self.active.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(now, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        self.active.fetch_sub(1, Ordering::SeqCst);
+        Ok(String::new())
+    }
+}
+
+#[tokio::test]
+async fn limited_caps_requests_in_flight() {
+    let tracker = Tracker::default();
+    let fetcher = Limited::new(tracker.clone(), AdaptiveLimit::new(2, Duration::ZERO));
+
+    let mut tasks = tokio::task::JoinSet::new();
+    for _ in 0..6 {
+        let fetcher = fetcher.clone();
+        tasks.spawn(async move { fetcher.fetch_html(&[]).await });
+    }
+    while let Some(result) = tasks.join_next().await {
+        result.unwrap().unwrap();
+    }
+
+    assert_eq!(tracker.peak.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn delay_spaces_request_starts() {
+    let fetcher = Limited::new(Tracker::default(), AdaptiveLimit::new(4, Duration::from_millis(30)));
+
+    let started = Instant::now();
+    for _ in 0..3 {
+        fetcher.fetch_html(&[]).await.unwrap();
+    }
+    assert!(started.elapsed() >= Duration::from_millis(60));
+}
@@ -0,0 +1,64 @@
+use flyr::locale::{Clock, Locale};
+use flyr::model::{FlightDateTime, Money};
+
+fn at(hour: u32, minute: u32) -> FlightDateTime {
+    FlightDateTime {
+        year: 2026,
+        month: 3,
+        day: 1,
+        hour,
+        minute,
+    }
+}
+
+#[test]
+fn default_keeps_iso_output() {
+    let locale = Locale::default();
+    assert_eq!(locale.price(Some(1234), "EUR"), "€1234");
+    assert_eq!(locale.price(Some(99), "CHF"), "99 CHF");
+    assert_eq!(locale.price(None, "EUR"), "—");
+    assert_eq!(locale.datetime(&at(13, 5)), "2026-03-01 13:05");
+}
+
+#[test]
+fn en_us_uses_twelve_hour_clock_and_commas() {
+    let locale = Locale::parse("en-US").unwrap();
+    assert_eq!(locale.clock, Clock::H12);
+    assert_eq!(locale.price(Some(1234567), "USD"), "$1,234,567");
+    assert_eq!(locale.datetime(&at(13, 5)), "03/01/2026 1:05 PM");
+    assert_eq!(locale.time(&at(0, 30)), "12:30 AM");
+    assert_eq!(locale.time(&at(12, 0)), "12:00 PM");
+}
+
+#[test]
+fn de_de_uses_decimal_commas_and_trailing_symbol() {
+    let locale = Locale::parse("de_DE").unwrap();
+    assert_eq!(locale.price(Some(1234), "EUR"), "1.234\u{a0}€");
+    assert_eq!(locale.decimal(1234.5, 1), "1.234,5");
+    assert_eq!(locale.decimal(-0.25, 2), "-0,25");
+    assert_eq!(locale.datetime(&at(7, 45)), "01.03.2026 07:45");
+}
+
+#[test]
+fn money_keeps_minor_digits_only_when_fractional() {
+    let locale = Locale::default();
+    assert_eq!(locale.money(Some(&Money::from_major_f64(12.345, "BHD"))), "12.345 BHD");
+    assert_eq!(locale.money(Some(&Money::from_major(120, "BHD"))), "120 BHD");
+    assert_eq!(locale.money(None), "—");
+    let de = Locale::parse("de-DE").unwrap();
+    assert_eq!(de.money(Some(&Money::from_major_f64(1234.5, "EUR"))), "1.234,50\u{a0}€");
+}
+
+#[test]
+fn regions_override_language_defaults() {
+    assert_eq!(Locale::parse("en-GB").unwrap().datetime(&at(18, 0)), "01/03/2026 18:00");
+    assert_eq!(Locale::parse("sv-SE").unwrap().iso_date("2026-03-01"), "2026-03-01");
+    assert_eq!(Locale::parse("pt-BR").unwrap().price(Some(1500), "EUR"), "€1.500");
+    assert_eq!(Locale::parse("de-CH").unwrap().number(1500), "1'500");
+}
+
+#[test]
+fn unknown_locale_is_rejected() {
+    let err = Locale::parse("xx-YY").unwrap_err();
+    assert!(err.to_string().contains("xx-YY"));
+}
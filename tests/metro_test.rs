@@ -0,0 +1,48 @@
+use flyr::calendar::encode_filters;
+use flyr::metro::{airports, expand, is_metro};
+use flyr::proto;
+use flyr::query::{FlightLeg, Passengers, Seat, TripType};
+use flyr::testing::hel_bcn_query;
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn metro_codes_expand_to_their_airports() {
+    assert_eq!(airports("NYC"), Some(&["JFK", "EWR", "LGA"][..]));
+    assert!(is_metro("lon"));
+    assert_eq!(expand("TYO"), vec!["HND", "NRT"]);
+    assert_eq!(expand("HEL"), vec!["HEL"]);
+    assert!(!is_metro("JFK"));
+}
+
+#[test]
+fn proto_lists_every_metro_airport() {
+    let legs = vec![FlightLeg {
+        date: "2026-03-01".into(),
+        from_airport: "NYC".into(),
+        to_airport: "LON".into(),
+        ..FlightLeg::default()
+    }];
+    let pax = Passengers::default();
+    let bytes = proto::encode(&legs, &pax, &Seat::Economy, &TripType::OneWay);
+
+    for code in ["JFK", "EWR", "LGA", "LHR", "LGW", "STN", "LTN", "LCY", "SEN"] {
+        assert!(contains(&bytes, code.as_bytes()), "{code} missing");
+    }
+    assert!(!contains(&bytes, b"NYC"));
+    assert_eq!(
+        proto::encoded_len(&legs, &pax, &Seat::Economy, &TripType::OneWay),
+        bytes.len()
+    );
+}
+
+#[test]
+fn calendar_filters_list_every_metro_airport() {
+    let mut params = hel_bcn_query();
+    params.legs[0].to_airport = "TYO".into();
+    let filters = encode_filters(&params, "2026-03-01", "2026-03-31");
+    let text = filters.to_string();
+    assert!(text.contains(r#"[["HND",0],["NRT",0]]"#), "{text}");
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use flyr::error::{ErrorInfo, FlightError, ERROR_KINDS};
+use flyr::model::{DestinationOutcome, FlightDateTime, Money, MultiSearch, SearchResult, Versioned};
+
+#[test]
+fn destination_outcomes_are_tagged() {
+    let ok = serde_json::to_value(DestinationOutcome::Ok(Box::default())).unwrap();
+    assert!(ok["ok"]["flights"].is_array());
+
+    let err = FlightError::RateLimited {
+        retry_after: Some(30),
+    };
+    let failed = serde_json::to_value(DestinationOutcome::Error(ErrorInfo::from(&err))).unwrap();
+    assert_eq!(failed["error"]["kind"], "rate_limited");
+    assert_eq!(failed["error"]["retry_after_secs"], 30);
+    assert!(failed.get("ok").is_none());
+}
+
+#[test]
+fn error_info_omits_missing_retry_after() {
+    let info = serde_json::to_value(ErrorInfo::from(&FlightError::Timeout)).unwrap();
+    assert_eq!(info["kind"], "timeout");
+    assert!(info.get("retry_after_secs").is_none());
+}
+
+#[test]
+fn error_kinds_are_listed_once() {
+    let mut kinds = ERROR_KINDS.to_vec();
+    kinds.sort_unstable();
+    kinds.dedup();
+    assert_eq!(kinds.len(), ERROR_KINDS.len());
+    assert!(ERROR_KINDS.contains(&FlightError::JsParse(String::new()).kind()));
+    assert!(ERROR_KINDS.contains(&FlightError::NoResults.kind()));
+}
+
+#[test]
+fn legacy_outcome_flattens_errors() {
+    let failed = DestinationOutcome::Error(ErrorInfo::from(&FlightError::Blocked(403)));
+    assert!(failed.legacy().flights.is_empty());
+}
+
+#[test]
+fn versioned_documents_lead_with_schema_version() {
+    let result = flyr::parse::parse_html(flyr::testing::fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let json = serde_json::to_string(&Versioned::new(&result)).unwrap();
+    assert!(json.starts_with(&format!("{{\"schema_version\":{},", flyr::SCHEMA_VERSION)));
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["flights"].as_array().unwrap().len(), 3);
+}
+
+#[test]
+fn multi_search_nests_destinations() {
+    let mut results = BTreeMap::new();
+    results.insert(
+        "BCN".to_string(),
+        DestinationOutcome::Error(ErrorInfo::from(&FlightError::Timeout)),
+    );
+    let doc = Versioned::new(MultiSearch {
+        destinations: &results,
+        warnings: &[],
+    });
+    let value = serde_json::to_value(&doc).unwrap();
+    assert_eq!(value["schema_version"], flyr::SCHEMA_VERSION);
+    assert_eq!(value["destinations"]["BCN"]["error"]["kind"], "timeout");
+}
+
+#[test]
+fn datetimes_round_trip_through_epoch_minutes() {
+    let dt = FlightDateTime {
+        year: 2028,
+        month: 2,
+        day: 29,
+        hour: 23,
+        minute: 59,
+    };
+    let back = FlightDateTime::from_minutes_since_epoch(dt.minutes_since_epoch());
+    assert_eq!(back.to_string(), "2028-02-29 23:59");
+    assert_eq!(
+        FlightDateTime::from_minutes_since_epoch(-1).to_string(),
+        "1969-12-31 23:59"
+    );
+}
+
+#[test]
+fn money_uses_each_currencys_minor_unit() {
+    assert_eq!(Money::from_major(12, "bhd").amount_minor, 12_000);
+    assert_eq!(Money::from_major(12, "EUR").amount_minor, 1_200);
+    assert_eq!(Money::from_major(12, "JPY").amount_minor, 12);
+
+    let fare = Money::from_major_f64(12.3456, "KWD");
+    assert_eq!(fare.amount_minor, 12_346);
+    assert_eq!(fare.whole(), 12);
+    assert!(!fare.is_whole());
+    assert_eq!(Money::from_major_f64(99.5, "USD").whole(), 100);
+}
+
+#[test]
+fn results_saved_without_fares_are_completed_from_price() {
+    let mut result = flyr::parse::parse_html(flyr::testing::fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.metadata.currency = None;
+    let mut json = serde_json::to_value(&result).unwrap();
+    for flight in json["flights"].as_array_mut().unwrap() {
+        flight.as_object_mut().unwrap().remove("fare");
+    }
+
+    let mut legacy: SearchResult = serde_json::from_value(json).unwrap();
+    assert!(legacy.flights.iter().all(|f| f.fare.is_none()));
+    legacy.complete_fares("EUR");
+    for flight in &legacy.flights {
+        let fare = flight.fare.as_ref().unwrap();
+        assert_eq!(fare.currency, "EUR");
+        assert_eq!(Some(fare.amount_minor), flight.price.map(|p| p * 100));
+    }
+}
+
+#[test]
+fn unset_optional_flight_fields_are_omitted() {
+    let mut result = flyr::parse::parse_html(flyr::testing::fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let segment = &mut result.flights[0].segments[0];
+    segment.departure_utc_offset_minutes = None;
+    segment.arrival_utc_offset_minutes = None;
+    segment.departure_iso = None;
+    segment.arrival_iso = None;
+    let json = serde_json::to_value(&result).unwrap();
+    let flight = json["flights"][0].as_object().unwrap();
+    for key in ["price_with_bag", "previous_price", "delta"] {
+        assert!(!flight.contains_key(key), "{key} serialized while unset");
+    }
+    let segment = flight["segments"][0].as_object().unwrap();
+    for key in [
+        "departure_utc_offset_minutes",
+        "arrival_utc_offset_minutes",
+        "departure_iso",
+        "arrival_iso",
+    ] {
+        assert!(!segment.contains_key(key), "{key} serialized while unset");
+    }
+}
+
+#[test]
+fn schema_covers_every_field_of_search_output() {
+    let schema = flyr::model::search_result_schema();
+    assert_eq!(schema["properties"]["schema_version"]["const"], flyr::SCHEMA_VERSION);
+
+    let result = flyr::parse::parse_html(flyr::testing::fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let doc = serde_json::to_value(Versioned::new(&result)).unwrap();
+    let defined = |name: &str, value: &serde_json::Value| {
+        let properties = &schema["$defs"][name]["properties"];
+        for key in value.as_object().unwrap().keys() {
+            assert!(properties.get(key).is_some(), "{name}.{key} missing from schema");
+        }
+    };
+    for key in doc.as_object().unwrap().keys() {
+        assert!(schema["properties"].get(key).is_some(), "{key} missing from schema");
+    }
+    defined("FlightResult", &doc["flights"][0]);
+    defined("Segment", &doc["flights"][0]["segments"][0]);
+    defined("SearchMetadata", &doc["metadata"]);
+}
@@ -0,0 +1,49 @@
+use flyr::parse::parse_html;
+use flyr::pareto::{dominates, frontier, retain};
+use flyr::testing::fixtures;
+
+fn airlines(result: &flyr::model::SearchResult) -> Vec<String> {
+    result.flights.iter().map(|f| f.airlines.join(",")).collect()
+}
+
+#[test]
+fn cheaper_and_as_fast_dominates() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let by_airline = |a: &str| result.flights.iter().find(|f| f.airlines == vec![a]).unwrap();
+
+    assert!(dominates(by_airline("Norwegian"), by_airline("AY"), false));
+    assert!(dominates(by_airline("Norwegian"), by_airline("Lufthansa"), false));
+    assert!(!dominates(by_airline("AY"), by_airline("AY"), false));
+    assert!(!dominates(by_airline("Lufthansa"), by_airline("AY"), false));
+}
+
+#[test]
+fn frontier_keeps_trade_offs() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    assert_eq!(frontier(&result.flights, false).len(), 1);
+
+    for flight in &mut result.flights {
+        if flight.airlines == vec!["Norwegian"] {
+            flight.elapsed_minutes = 400;
+        }
+    }
+    retain(&mut result, false);
+    assert_eq!(airlines(&result), vec!["AY", "Lufthansa", "Norwegian"]);
+}
+
+#[test]
+fn emissions_add_a_third_criterion() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    for flight in &mut result.flights {
+        if flight.airlines == vec!["Norwegian"] {
+            flight.carbon.emission_grams = Some(170_000);
+        }
+    }
+
+    let mut without = result.clone();
+    retain(&mut without, false);
+    assert_eq!(airlines(&without), vec!["Norwegian"]);
+
+    retain(&mut result, true);
+    assert_eq!(airlines(&result), vec!["AY", "Norwegian"]);
+}
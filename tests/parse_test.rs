@@ -1,4 +1,14 @@
-use flyr::parse::{extract_script, parse_html, parse_js, parse_payload};
+use flyr::error::FlightError;
+use flyr::model::{
+    BagAllowance, Category, FareBrand, Layover, PriceLevel, PriceScope, Risk, SegmentKind,
+    Ticketing,
+};
+use flyr::parse::{
+    extract_payload, extract_script, is_captcha_page, is_consent_page, parse_free_text_html, parse_html,
+    parse_html_owned, parse_js, parse_js_sparse, parse_payload, parse_payload_owned, parse_payload_with, price_scope_hint,
+    GooglePayloadParser, ParseOptions, ResultParser,
+};
+use flyr::query::SearchQuery;
 use serde_json::json;
 
 #[test]
@@ -14,6 +24,27 @@ fn extract_script_finds_ds1() {
     assert!(result.contains("data:"));
 }
 
+#[test]
+fn extract_script_ignores_similar_classes() {
+    let html = r#"
+    <script class="ds:10">data:[10],x</script>
+    <script nonce="abc" class="ds:1" async>data:[1],x</script>
+    "#;
+    assert_eq!(extract_script(html).unwrap(), "data:[1],x");
+}
+
+#[test]
+fn extract_script_single_quoted_class() {
+    let html = "<script class='ds:1'>data:[7],x</script>";
+    assert_eq!(extract_script(html).unwrap(), "data:[7],x");
+}
+
+#[test]
+fn extract_script_falls_back_to_dom() {
+    let html = r#"<SCRIPT CLASS="ds:1">data:[3],x</SCRIPT>"#;
+    assert_eq!(extract_script(html).unwrap(), "data:[3],x");
+}
+
 #[test]
 fn extract_script_missing_ds1() {
     let html = r#"<html><head><script class="ds:0">x</script></head></html>"#;
@@ -73,6 +104,16 @@ fn make_segment() -> serde_json::Value {
     json!(seg)
 }
 
+// `make_segment` with times that agree with its duration across the HEL and BCN time
+// zones, departing on the given day of March 2026.
+fn make_timed_segment(day: u32) -> serde_json::Value {
+    let mut seg = make_segment();
+    seg[10] = json!([13, 45]);
+    seg[20] = json!([2026, 3, day]);
+    seg[21] = json!([2026, 3, day]);
+    seg
+}
+
 fn make_flight_entry(segments: Vec<serde_json::Value>) -> serde_json::Value {
     let mut flight = vec![serde_json::Value::Null; 23];
     flight[0] = json!("Regular");
@@ -119,6 +160,10 @@ fn parse_payload_extracts_flights() {
     assert_eq!(s.duration_minutes, 255);
     assert_eq!(s.aircraft.as_deref(), Some("Airbus A350"));
     assert_eq!(s.departure.year, 2026);
+    assert_eq!(s.departure_utc_offset_minutes, Some(120));
+    assert_eq!(s.arrival_utc_offset_minutes, Some(60));
+    assert_eq!(s.departure_iso.as_deref(), Some("2026-03-01T10:30:00+02:00"));
+    assert_eq!(s.arrival_iso.as_deref(), Some("2026-03-01T14:45:00+01:00"));
 }
 
 #[test]
@@ -255,3 +300,831 @@ fn parse_segment_missing_airport_name() {
     assert_eq!(result.flights[0].segments[0].from_airport.code, "JFK");
     assert_eq!(result.flights[0].segments[0].from_airport.name, "");
 }
+
+#[test]
+fn parse_payload_detects_currency() {
+    let mut flight = vec![serde_json::Value::Null; 23];
+    flight[0] = json!("Regular");
+    flight[1] = json!(["AY"]);
+    flight[2] = json!([make_segment()]);
+
+    let entry = json!([flight, [["EUR", 279]]]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    assert_eq!(result.metadata.currency.as_deref(), Some("EUR"));
+    assert_eq!(result.flights[0].price, Some(279));
+}
+
+#[test]
+fn parse_payload_currency_absent() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    assert_eq!(result.metadata.currency, None);
+}
+
+#[test]
+fn parse_payload_same_day_arrival_offset() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    assert_eq!(result.flights[0].arrival_day_offset, 0);
+}
+
+#[test]
+fn parse_payload_overnight_arrival_offset() {
+    let mut seg = vec![serde_json::Value::Null; 22];
+    seg[3] = json!("HEL");
+    seg[6] = json!("BKK");
+    seg[8] = json!([23, 50]);
+    seg[10] = json!([14, 5]);
+    seg[11] = json!(615);
+    seg[20] = json!([2026, 2, 28]);
+    seg[21] = json!([2026, 3, 1]);
+
+    let entry = make_flight_entry(vec![json!(seg)]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    assert_eq!(result.flights[0].arrival_day_offset, 1);
+}
+
+#[test]
+fn parse_payload_elapsed_includes_layover() {
+    let seg1 = make_segment();
+    let mut seg2 = vec![serde_json::Value::Null; 22];
+    seg2[3] = json!("BCN");
+    seg2[6] = json!("LIS");
+    seg2[8] = json!([16, 15]);
+    seg2[10] = json!([17, 20]);
+    seg2[11] = json!(125);
+    seg2[20] = json!([2026, 3, 1]);
+    seg2[21] = json!([2026, 3, 1]);
+
+    let entry = make_flight_entry(vec![seg1, json!(seg2)]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    assert_eq!(result.flights[0].air_minutes, 255 + 125);
+    assert_eq!(result.flights[0].elapsed_minutes, 255 + 90 + 125);
+    assert_eq!(
+        result.flights[0].layovers,
+        vec![Layover {
+            airport: "BCN".into(),
+            minutes: 90
+        }]
+    );
+}
+
+#[test]
+fn parse_payload_nonstop_air_equals_elapsed() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    assert_eq!(result.flights[0].air_minutes, 255);
+    assert_eq!(result.flights[0].elapsed_minutes, 255);
+    assert!(result.flights[0].layovers.is_empty());
+}
+
+#[test]
+fn parse_payload_train_leg_is_ground_segment() {
+    let seg1 = make_segment();
+    let mut seg2 = vec![serde_json::Value::Null; 22];
+    seg2[3] = json!("BCN");
+    seg2[6] = json!("QGN");
+    seg2[8] = json!([16, 15]);
+    seg2[10] = json!([17, 20]);
+    seg2[11] = json!(65);
+    seg2[17] = json!("Train");
+    seg2[20] = json!([2026, 3, 1]);
+    seg2[21] = json!([2026, 3, 1]);
+
+    let entry = make_flight_entry(vec![seg1, json!(seg2)]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let flight = &result.flights[0];
+    assert_eq!(flight.segments.len(), 2);
+    assert_eq!(flight.segments[0].kind, SegmentKind::Flight);
+    assert_eq!(flight.segments[1].kind, SegmentKind::Train);
+    assert_eq!(flight.segments[1].aircraft, None);
+    assert_eq!(flight.air_minutes, 255);
+    assert_eq!(flight.elapsed_minutes, 255 + 90 + 65);
+}
+
+#[test]
+fn parse_segment_flight_number() {
+    let mut seg = vec![serde_json::Value::Null; 23];
+    seg[3] = json!("HEL");
+    seg[6] = json!("BCN");
+    seg[8] = json!([10, 30]);
+    seg[10] = json!([14, 45]);
+    seg[11] = json!(255);
+    seg[20] = json!([2026, 3, 1]);
+    seg[21] = json!([2026, 3, 1]);
+    seg[22] = json!(["AY", "1661", null, "Finnair"]);
+
+    let entry = make_flight_entry(vec![json!(seg)]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let s = &result.flights[0].segments[0];
+    assert_eq!(s.airline.as_deref(), Some("AY"));
+    assert_eq!(s.flight_number.as_deref(), Some("1661"));
+}
+
+#[test]
+fn price_scope_hint_detects_round_trip_total() {
+    let html = r#"<div aria-label="From 412 US dollars round trip total.">"#;
+    assert_eq!(price_scope_hint(html), Some(PriceScope::Total));
+}
+
+#[test]
+fn price_scope_hint_absent() {
+    let html = r#"<div aria-label="From 199 US dollars.">"#;
+    assert_eq!(price_scope_hint(html), None);
+}
+
+#[test]
+fn parse_payload_leaves_price_scope_unset() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    assert_eq!(result.flights[0].price_scope, None);
+}
+
+#[test]
+fn google_parser_scopes_prices_by_trip() {
+    let html = flyr::testing::fixtures::ONE_WAY_HEL_BCN;
+    let options = ParseOptions::default();
+    let mut round_trip = flyr::testing::hel_bcn_query();
+    round_trip.trip = flyr::query::TripType::RoundTrip;
+
+    let scoped = |query: &SearchQuery, html: &str| {
+        let result = GooglePayloadParser.parse(html.to_string(), query, &options).unwrap();
+        result.flights[0].price_scope
+    };
+    let round_trip = SearchQuery::Structured(round_trip);
+    assert_eq!(scoped(&round_trip, html), None);
+    let total = html.replace("</html>", "<div>Round trip total</div></html>");
+    assert_eq!(scoped(&round_trip, &total), Some(PriceScope::Total));
+    assert_eq!(scoped(&SearchQuery::Structured(flyr::testing::hel_bcn_query()), &total), None);
+
+    let text = SearchQuery::NaturalLanguage("Helsinki to Barcelona".into());
+    assert_eq!(scoped(&text, html), None);
+    let outbound = html.replace("</html>", "<div>Price per direction</div></html>");
+    assert_eq!(scoped(&text, &outbound), Some(PriceScope::Outbound));
+}
+
+fn payload_with_broken_segment() -> serde_json::Value {
+    let mut broken = vec![serde_json::Value::Null; 22];
+    broken[3] = json!("CDG");
+    let entry = make_flight_entry(vec![make_segment(), json!(broken)]);
+    json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ])
+}
+
+#[test]
+fn lenient_parse_drops_broken_segment() {
+    let result = parse_payload(&payload_with_broken_segment()).unwrap();
+    assert_eq!(result.flights[0].segments.len(), 1);
+}
+
+#[test]
+fn strict_parse_rejects_broken_segment() {
+    let options = ParseOptions {
+        strict: true,
+        ..ParseOptions::default()
+    };
+    let err = parse_payload_with(&payload_with_broken_segment(), &options).unwrap_err();
+    assert!(err.to_string().contains("1 of 2 segments"));
+}
+
+#[test]
+fn strict_parse_rejects_unparseable_entry() {
+    let payload = json!([
+        null, null, null, [[null]], null, null, null,
+        [null, [[], []]]
+    ]);
+    let options = ParseOptions {
+        strict: true,
+        ..ParseOptions::default()
+    };
+    assert!(parse_payload_with(&payload, &options).is_err());
+    assert!(parse_payload(&payload).unwrap().flights.is_empty());
+}
+
+#[test]
+fn lenient_parse_warns_about_incomplete_flights() {
+    let result = parse_payload(&payload_with_broken_segment()).unwrap();
+    assert_eq!(result.warnings.len(), 1);
+    assert_eq!(result.warnings[0].kind, "incomplete_flights");
+    assert!(result.warnings[0].message.starts_with("1 of 1 flight entries"));
+
+    let owned = parse_payload_owned(payload_with_broken_segment(), &ParseOptions::default());
+    assert_eq!(owned.unwrap().warnings, result.warnings);
+}
+
+#[test]
+fn clean_payload_has_no_warnings() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+    assert!(parse_payload(&payload).unwrap().warnings.is_empty());
+}
+
+#[test]
+fn strict_parse_accepts_clean_payload() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+    let options = ParseOptions {
+        strict: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(parse_payload_with(&payload, &options).unwrap().flights.len(), 1);
+}
+
+#[test]
+fn parse_html_zero_itineraries_is_ok() {
+    let html = r#"<script class="ds:1">AF_initDataCallback({data:[null,null,null,[null],null,null,null,[null,[[],[]]]],sideChannel: {}});</script>"#;
+    assert!(parse_html(html).unwrap().flights.is_empty());
+}
+
+#[test]
+fn parse_html_empty_shell_is_error() {
+    let html = r#"<script class="ds:1">AF_initDataCallback({data:[null,null,null],sideChannel: {}});</script>"#;
+    let err = parse_html(html).unwrap_err();
+    assert!(err.to_string().contains("no results section"));
+}
+
+#[test]
+fn parse_html_shell_with_block_markers_is_blocked() {
+    let html = r#"<form id="captcha-form" action="index"><div class="g-recaptcha"></div></form>
+    <script class="ds:1">AF_initDataCallback({data:[null,null,null],sideChannel: {}});</script>"#;
+    let err = parse_html(html).unwrap_err();
+    assert!(matches!(err, FlightError::CaptchaRequired(_)));
+}
+
+#[test]
+fn parse_html_sorry_page_is_captcha() {
+    let html = r#"<html><body>Our systems have detected unusual traffic from your computer network.
+    <form id="captcha-form" action="index" method="post"></form></body></html>"#;
+    match parse_html(html).unwrap_err() {
+        FlightError::CaptchaRequired(url) => assert!(url.contains("/sorry/")),
+        other => panic!("expected CaptchaRequired, got {other:?}"),
+    }
+}
+
+#[test]
+fn parse_html_consent_page_without_script() {
+    let html = r#"<html><head><title>Before you continue to Google</title></head>
+    <body><form action="https://consent.google.com/save" method="POST"></form></body></html>"#;
+    assert!(matches!(parse_html(html).unwrap_err(), FlightError::ConsentRequired));
+}
+
+#[test]
+fn parse_html_missing_script_is_not_consent() {
+    let html = r#"<html><head><script class="ds:0">x</script></head></html>"#;
+    assert!(matches!(parse_html(html).unwrap_err(), FlightError::ScriptTagNotFound));
+}
+
+#[test]
+fn is_consent_page_detects_form() {
+    assert!(is_consent_page(r#"<form action="https://consent.google.com/save">"#));
+    assert!(!is_consent_page("<html><body>flights</body></html>"));
+}
+
+#[test]
+fn is_captcha_page_detects_sorry_form() {
+    assert!(is_captcha_page(r#"<form id="captcha-form" action="index">"#));
+    assert!(!is_captcha_page("<html><body>flights</body></html>"));
+}
+
+#[test]
+fn captcha_wording_in_a_results_page_is_not_a_captcha() {
+    let page = r#"<html><body><div>flights</div><script>
+        var hint = "unusual traffic from your computer network";
+        var widget = "g-recaptcha", id = "captcha-form";
+        </script></body></html>"#;
+    assert!(!is_captcha_page(page));
+}
+
+#[test]
+fn parse_js_sparse_keeps_only_read_indices() {
+    let js = r#"data:[{"big":[1,2,3]},"skip",null,[null],4,5,6,[null,[[],[]]],"tail"],sideChannel"#;
+    let payload = parse_js_sparse(js).unwrap();
+    let arr = payload.as_array().unwrap();
+    assert_eq!(arr.len(), 9);
+    assert!(arr[0].is_null());
+    assert!(arr[1].is_null());
+    assert_eq!(arr[3], json!([null]));
+    assert_eq!(arr[7], json!([null, [[], []]]));
+}
+
+#[test]
+fn parse_js_sparse_matches_full_parse() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let full = json!([null, null, null, [[entry]], null, null, null, [null, [[], []]]]);
+    let js = format!("AF_initDataCallback({{data:{full},sideChannel: {{}}}});");
+
+    let sparse = parse_payload(&parse_js_sparse(&js).unwrap()).unwrap();
+    let dense = parse_payload(&parse_js(&js).unwrap()).unwrap();
+    assert_eq!(sparse.flights.len(), dense.flights.len());
+    assert_eq!(sparse.flights[0].price, dense.flights[0].price);
+}
+
+#[test]
+fn parse_html_low_memory_matches_default() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let payload = json!([null, null, null, [[entry]], null, null, null, [null, [[], []]]]);
+    let html = format!(
+        r#"<script class="ds:1">AF_initDataCallback({{data:{payload},sideChannel: {{}}}});</script>"#
+    );
+    let low = ParseOptions {
+        low_memory: true,
+        ..ParseOptions::default()
+    };
+
+    let lean = parse_html_owned(html.clone(), &low).unwrap();
+    let full = parse_html(&html).unwrap();
+    assert_eq!(lean.flights.len(), full.flights.len());
+    assert_eq!(lean.flights[0].price, full.flights[0].price);
+    assert_eq!(lean.flights[0].segments.len(), full.flights[0].segments.len());
+}
+
+#[test]
+fn parse_html_low_memory_keeps_block_detection() {
+    let html = r#"<form id="captcha-form" action="index"><div class="g-recaptcha"></div></form>
+    <script class="ds:1">AF_initDataCallback({data:[null,null,null],sideChannel: {}});</script>"#;
+    let low = ParseOptions {
+        low_memory: true,
+        ..ParseOptions::default()
+    };
+    let err = parse_html_owned(html.to_string(), &low).unwrap_err();
+    assert!(matches!(err, FlightError::CaptchaRequired(_)));
+}
+
+fn top_flights_page(class: &str) -> String {
+    let html = flyr::testing::fixtures::ONE_WAY_HEL_BCN;
+    let mut payload = parse_js(&extract_script(html).unwrap()).unwrap();
+    payload[2] = payload[3].take();
+    format!(
+        "<html><script class=\"{class}\">AF_initDataCallback({{key: '{class}', data:{payload}, \
+         sideChannel: {{}}}});</script></html>"
+    )
+}
+
+fn renumbered_page(scripts: &[(&str, &str)]) -> String {
+    let html = flyr::testing::fixtures::ONE_WAY_HEL_BCN;
+    let payload = parse_js(&extract_script(html).unwrap()).unwrap();
+    let empty = json!([null, null, null, [null], null, null, null, [null, [[], []]]]);
+    let mut page = String::from("<html>");
+    for (tag, key) in scripts {
+        let data = if key.ends_with('!') { &payload } else { &empty };
+        let key = key.trim_end_matches('!');
+        page.push_str(&format!(
+            "<script{tag}>AF_initDataCallback({{key: '{key}', data:{data}, sideChannel: {{}}}});</script>"
+        ));
+    }
+    page + "</html>"
+}
+
+#[test]
+fn renumbered_data_script_is_found_with_a_warning() {
+    let expected = parse_html(flyr::testing::fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let html = renumbered_page(&[(" class=\"ds:0\"", "ds:0"), (" class=\"ds:4\"", "ds:4!")]);
+
+    let result = parse_html(&html).unwrap();
+    assert_eq!(result.flights.len(), expected.flights.len());
+    let warning = result.warnings.iter().find(|w| w.kind == "script_fallback").unwrap();
+    assert!(warning.message.contains("ds:4"));
+
+    let low = ParseOptions {
+        low_memory: true,
+        ..ParseOptions::default()
+    };
+    assert_eq!(parse_html_owned(html.clone(), &low).unwrap().flights.len(), expected.flights.len());
+
+    let free_text = parse_free_text_html(&html, &ParseOptions::default()).unwrap();
+    assert!(free_text.warnings.iter().any(|w| w.kind == "script_fallback"));
+}
+
+#[test]
+fn fallback_prefers_the_script_with_the_most_flights() {
+    let html = renumbered_page(&[
+        (" class=\"ds:1\"", "ds:1"),
+        ("", "ds:7!"),
+        (" class=\"ds:2\"", "ds:2"),
+    ]);
+    let result = parse_html(&html).unwrap();
+    assert!(!result.flights.is_empty());
+    assert!(result.warnings.iter().any(|w| w.message.contains("ds:7")));
+}
+
+#[test]
+fn regular_page_needs_no_fallback() {
+    let result = parse_html(flyr::testing::fixtures::ONE_WAY_HEL_BCN).unwrap();
+    assert!(result.warnings.iter().all(|w| w.kind != "script_fallback"));
+}
+
+#[test]
+fn free_text_page_reads_top_flights() {
+    let expected = parse_html(flyr::testing::fixtures::ONE_WAY_HEL_BCN).unwrap();
+    for class in ["ds:1", "ds:0"] {
+        let result = parse_free_text_html(&top_flights_page(class), &ParseOptions::default())
+            .unwrap();
+        assert_eq!(result.flights.len(), expected.flights.len(), "{class}");
+    }
+}
+
+#[test]
+fn free_text_page_accepts_regular_results() {
+    let html = flyr::testing::fixtures::ONE_WAY_HEL_BCN;
+    let result = parse_free_text_html(html, &ParseOptions::default()).unwrap();
+    assert_eq!(result.flights.len(), parse_html(html).unwrap().flights.len());
+}
+
+#[test]
+fn free_text_page_reports_consent() {
+    let err = parse_free_text_html(flyr::testing::fixtures::CONSENT, &ParseOptions::default())
+        .unwrap_err();
+    assert!(matches!(err, FlightError::ConsentRequired));
+}
+
+#[test]
+fn parse_segment_operating_carrier() {
+    let mut seg = make_segment().as_array().unwrap().clone();
+    seg.push(json!(["IB", "3844", null, "Iberia"]));
+    seg[2] = json!("Operated by Air Nostrum as Iberia Regional");
+    let mut own = seg.clone();
+    own[2] = json!("Operated by Iberia");
+
+    let entry = make_flight_entry(vec![json!(seg), json!(own)]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let segments = &result.flights[0].segments;
+    assert_eq!(segments[0].airline.as_deref(), Some("IB"));
+    assert_eq!(segments[0].airline_name.as_deref(), Some("Iberia"));
+    assert_eq!(segments[0].operated_by.as_deref(), Some("Air Nostrum"));
+    assert_eq!(segments[1].operated_by, None);
+}
+
+#[test]
+fn parse_payload_keeps_best_and_other_buckets() {
+    let best = make_flight_entry(vec![make_segment()]);
+    let other = make_flight_entry(vec![make_segment(), make_segment()]);
+    let payload = json!([
+        null, null, [[best]], [[other]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    for result in [
+        parse_payload(&payload).unwrap(),
+        parse_payload_owned(payload.clone(), &ParseOptions::default()).unwrap(),
+    ] {
+        let categories: Vec<_> = result.flights.iter().map(|f| f.category).collect();
+        assert_eq!(categories, vec![Some(Category::Best), Some(Category::Other)]);
+        assert_eq!(result.flights[1].segments.len(), 2);
+    }
+}
+
+#[test]
+fn borrowed_and_owned_payloads_reject_the_same_bucket() {
+    let payload = json!([null, null, null, ["flights"], null, null, null, [null, [[], []]]]);
+    let borrowed = parse_payload(&payload).unwrap_err().to_string();
+    let owned = parse_payload_owned(payload, &ParseOptions::default())
+        .unwrap_err()
+        .to_string();
+    assert_eq!(borrowed, owned);
+    assert!(borrowed.contains("payload[3][0] is not an array"), "{borrowed}");
+}
+
+#[test]
+fn parse_payload_reads_price_insights() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null,
+        [1, [["EUR", 320], ["EUR", 480]]],
+        null, [null, [[], []]]
+    ]);
+
+    let insights = parse_payload(&payload).unwrap().metadata.price_insights.unwrap();
+    assert_eq!(insights.current_level, Some(PriceLevel::Low));
+    assert_eq!(insights.typical_low, Some(320));
+    assert_eq!(insights.typical_high, Some(480));
+}
+
+#[test]
+fn price_level_falls_back_to_the_typical_range() {
+    let entry = make_flight_entry(vec![make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null,
+        [null, [["EUR", 200], ["EUR", 280]]],
+        null, [null, [[], []]]
+    ]);
+
+    let insights = parse_payload(&payload).unwrap().metadata.price_insights.unwrap();
+    assert_eq!(insights.current_level, Some(PriceLevel::High));
+}
+
+#[test]
+fn price_insights_absent_without_payload_section() {
+    let result = parse_html(flyr::testing::fixtures::ONE_WAY_HEL_BCN).unwrap();
+    assert!(result.metadata.price_insights.is_none());
+}
+
+#[test]
+fn parse_payload_reads_included_bags() {
+    let mut entry = make_flight_entry(vec![make_segment()]);
+    entry[0][19] = json!([1, 0]);
+    let plain = make_flight_entry(vec![make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry, plain]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    assert_eq!(
+        result.flights[0].baggage,
+        Some(BagAllowance {
+            carry_on: Some(1),
+            checked: Some(0),
+        })
+    );
+    assert_eq!(result.flights[1].baggage, None);
+}
+
+#[test]
+fn parse_segment_legroom() {
+    let mut seg = make_segment();
+    seg[14] = json!("Below average legroom (29 in)");
+    let entry = make_flight_entry(vec![seg, make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let segments = &result.flights[0].segments;
+    assert_eq!(segments[0].legroom.as_deref(), Some("Below average legroom (29 in)"));
+    assert_eq!(segments[1].legroom, None);
+}
+
+#[test]
+fn parse_segment_amenities() {
+    let mut seg = make_segment();
+    seg[12] = json!([["Wi-Fi for a fee", 1], "In-seat power & USB outlets", null, ""]);
+    let entry = make_flight_entry(vec![seg, make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let segments = &result.flights[0].segments;
+    assert_eq!(
+        segments[0].amenities,
+        vec!["Wi-Fi for a fee", "In-seat power & USB outlets"]
+    );
+    assert!(segments[1].amenities.is_empty());
+}
+
+#[test]
+fn parse_flight_often_delayed_warning() {
+    let mut late = make_timed_segment(2);
+    late[13] = json!("Often delayed by 30+ min");
+    late.as_array_mut().unwrap().push(json!(["AY", "1661", null, "Finnair"]));
+    let entry = make_flight_entry(vec![make_timed_segment(1), late]);
+    let punctual = make_flight_entry(vec![make_timed_segment(1)]);
+    let payload = json!([
+        null, null, null, [[entry, punctual]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let flight = &result.flights[0];
+    assert!(flight.often_delayed());
+    assert_eq!(flight.warnings.len(), 1);
+    assert_eq!(flight.warnings[0].message, "AY1661: Often delayed by 30+ min");
+    assert!(!result.flights[1].often_delayed());
+    assert!(result.flights[1].warnings.is_empty());
+}
+
+#[test]
+fn parse_segment_terminals() {
+    let mut seg = vec![serde_json::Value::Null; 26];
+    for (i, v) in make_segment().as_array().unwrap().iter().enumerate() {
+        seg[i] = v.clone();
+    }
+    seg[24] = json!("Terminal 2");
+    seg[25] = json!("T1");
+    let entry = make_flight_entry(vec![json!(seg), make_segment()]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let segments = &result.flights[0].segments;
+    assert_eq!(segments[0].departure_terminal.as_deref(), Some("2"));
+    assert_eq!(segments[0].arrival_terminal.as_deref(), Some("T1"));
+    assert_eq!(segments[1].departure_terminal, None);
+}
+
+#[test]
+fn parse_flight_ticketing_notice() {
+    let with_notice = |notice: &str| {
+        let mut entry = make_flight_entry(vec![make_timed_segment(1), make_timed_segment(2)]);
+        let flight = entry[0].as_array_mut().unwrap();
+        flight.resize(25, serde_json::Value::Null);
+        flight[24] = json!(notice);
+        entry
+    };
+    let payload = json!([
+        null, null, null,
+        [[
+            with_notice("Self transfer"),
+            with_notice("Separate tickets booked together"),
+            make_flight_entry(vec![make_timed_segment(1)])
+        ]],
+        null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let flights = &result.flights;
+    assert_eq!(flights[0].ticketing, Some(Ticketing::SelfTransfer));
+    assert_eq!(flights[0].warnings[0].kind, "self_transfer");
+    assert!(flights[0].warnings[0].message.starts_with("Self-transfer at "));
+    assert_eq!(flights[1].ticketing, Some(Ticketing::SeparateTickets));
+    assert_eq!(flights[1].warnings[0].kind, "separate_tickets");
+    assert!(flights[0].risk >= Risk::Medium && flights[1].risk >= Risk::Medium);
+    assert_eq!(flights[2].ticketing, None);
+    assert_eq!(flights[2].risk, Risk::Low);
+    assert!(flights[2].warnings.is_empty());
+}
+
+#[test]
+fn parse_flight_fare_brand() {
+    let branded = |label: Option<&str>, bags: serde_json::Value| {
+        let mut entry = make_flight_entry(vec![make_timed_segment(1)]);
+        let flight = entry[0].as_array_mut().unwrap();
+        flight.resize(24, serde_json::Value::Null);
+        flight[19] = bags;
+        flight[23] = json!(label);
+        entry
+    };
+    let payload = json!([
+        null, null, null,
+        [[
+            branded(Some("Basic Economy"), json!(null)),
+            branded(None, json!([0, 0])),
+            branded(Some("Economy Light"), json!([1, 0])),
+            branded(Some("Main Cabin"), json!([1, 1])),
+            make_flight_entry(vec![make_timed_segment(1)])
+        ]],
+        null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let brands: Vec<_> = parse_payload(&payload)
+        .unwrap()
+        .flights
+        .iter()
+        .map(|f| f.fare_brand)
+        .collect();
+    assert_eq!(
+        brands,
+        vec![
+            Some(FareBrand::Basic),
+            Some(FareBrand::Basic),
+            Some(FareBrand::HandBaggageOnly),
+            None,
+            None
+        ]
+    );
+}
+
+#[test]
+fn parse_segment_codeshares() {
+    let mut seg = make_segment();
+    seg[15] = json!([["IB", "5541", null, "Iberia"], ["AA", "8610"], [null, "1"]]);
+    let entry = make_flight_entry(vec![seg]);
+    let payload = json!([
+        null, null, null, [[entry]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let codeshares = &result.flights[0].segments[0].codeshares;
+    assert_eq!(codeshares.len(), 2);
+    assert_eq!(codeshares[0].airline, "IB");
+    assert_eq!(codeshares[0].flight_number, "5541");
+    assert_eq!(codeshares[0].airline_name.as_deref(), Some("Iberia"));
+    assert_eq!(codeshares[1].airline_name, None);
+}
+
+#[test]
+fn parse_flight_flags_inconsistent_elapsed_time() {
+    let consistent = make_flight_entry(vec![make_timed_segment(1), make_timed_segment(2)]);
+    let overlapping = make_flight_entry(vec![make_timed_segment(1), make_timed_segment(1)]);
+    let payload = json!([
+        null, null, null, [[consistent, overlapping]], null, null, null,
+        [null, [[], []]]
+    ]);
+
+    let result = parse_payload(&payload).unwrap();
+    let flights = &result.flights;
+    assert!(flights[0].warnings.is_empty());
+    assert_eq!(flights[0].elapsed_minutes, 28 * 60 + 15);
+    assert_eq!(flights[1].warnings.len(), 1);
+    assert_eq!(flights[1].warnings[0].kind, "elapsed_mismatch");
+    assert!(flights[1].warnings[0].message.starts_with("HEL→BCN: "));
+}
+
+#[test]
+fn extract_payload_reports_blocked_pages() {
+    use flyr::testing::fixtures;
+    assert!(extract_payload(fixtures::ONE_WAY_HEL_BCN).unwrap().is_array());
+    assert!(matches!(
+        extract_payload(fixtures::CONSENT),
+        Err(FlightError::ConsentRequired)
+    ));
+}
+
+#[test]
+fn best_flights_bucket_survives_sparse_parsing() {
+    let html = flyr::testing::fixtures::BEST_FLIGHTS_HEL_BCN;
+    let low = ParseOptions {
+        low_memory: true,
+        ..ParseOptions::default()
+    };
+    for result in [parse_html(html).unwrap(), parse_html_owned(html.to_string(), &low).unwrap()] {
+        let best: Vec<_> = result
+            .flights
+            .iter()
+            .filter(|f| f.category == Some(Category::Best))
+            .collect();
+        assert_eq!(best.len(), 1);
+        assert_eq!(best[0].segments[0].flight_number.as_deref(), Some("1657"));
+        assert_eq!(result.flights.len(), 3);
+    }
+}
+
+#[test]
+fn price_insights_survive_sparse_parsing() {
+    let html = flyr::testing::fixtures::BEST_FLIGHTS_HEL_BCN;
+    let low = ParseOptions {
+        low_memory: true,
+        ..ParseOptions::default()
+    };
+    for result in [parse_html(html).unwrap(), parse_html_owned(html.to_string(), &low).unwrap()] {
+        let insights = result.metadata.price_insights.unwrap();
+        assert_eq!(insights.current_level, Some(PriceLevel::Low));
+        assert_eq!(insights.typical_low, Some(150));
+        assert_eq!(insights.typical_high, Some(260));
+    }
+}
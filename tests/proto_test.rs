@@ -2,7 +2,7 @@ use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
 
 use flyr::proto;
-use flyr::query::{FlightLeg, Passengers, Seat, TripType};
+use flyr::query::{FlightLeg, Passengers, QueryParams, Seat, SelectedFlight, TripType};
 
 fn encode_b64(
     legs: &[FlightLeg],
@@ -19,8 +19,7 @@ fn basic_one_way_economy() {
         date: "2026-03-01".into(),
         from_airport: "LAX".into(),
         to_airport: "NRT".into(),
-        max_stops: None,
-        airlines: None,
+        ..FlightLeg::default()
     }];
     let pax = Passengers {
         adults: 1,
@@ -43,15 +42,13 @@ fn round_trip_with_return_leg() {
             date: "2026-03-01".into(),
             from_airport: "LAX".into(),
             to_airport: "NRT".into(),
-            max_stops: None,
-            airlines: None,
+            ..FlightLeg::default()
         },
         FlightLeg {
             date: "2026-03-10".into(),
             from_airport: "NRT".into(),
             to_airport: "LAX".into(),
-            max_stops: None,
-            airlines: None,
+            ..FlightLeg::default()
         },
     ];
     let pax = Passengers {
@@ -74,8 +71,7 @@ fn multiple_passengers() {
         date: "2026-03-01".into(),
         from_airport: "HEL".into(),
         to_airport: "BCN".into(),
-        max_stops: None,
-        airlines: None,
+        ..FlightLeg::default()
     }];
     let pax = Passengers {
         adults: 2,
@@ -99,6 +95,7 @@ fn with_max_stops() {
         to_airport: "BKK".into(),
         max_stops: Some(1),
         airlines: None,
+        ..FlightLeg::default()
     }];
     let pax = Passengers {
         adults: 1,
@@ -122,6 +119,7 @@ fn with_airline_filter() {
         to_airport: "BCN".into(),
         max_stops: None,
         airlines: Some(vec!["AY".into(), "IB".into()]),
+        ..FlightLeg::default()
     }];
     let pax = Passengers {
         adults: 1,
@@ -144,22 +142,19 @@ fn multi_city_three_legs() {
             date: "2026-03-01".into(),
             from_airport: "LAX".into(),
             to_airport: "NRT".into(),
-            max_stops: None,
-            airlines: None,
+            ..FlightLeg::default()
         },
         FlightLeg {
             date: "2026-03-05".into(),
             from_airport: "NRT".into(),
             to_airport: "ICN".into(),
-            max_stops: None,
-            airlines: None,
+            ..FlightLeg::default()
         },
         FlightLeg {
             date: "2026-03-10".into(),
             from_airport: "ICN".into(),
             to_airport: "LAX".into(),
-            max_stops: None,
-            airlines: None,
+            ..FlightLeg::default()
         },
     ];
     let pax = Passengers {
@@ -175,3 +170,222 @@ fn multi_city_three_legs() {
         "GhoSCjIwMjYtMDMtMDFqBRIDTEFYcgUSA05SVBoaEgoyMDI2LTAzLTA1agUSA05SVHIFEgNJQ04aGhIKMjAyNi0wMy0xMGoFEgNJQ05yBRIDTEFYQgIBAUgCmAED"
     );
 }
+
+// Only fields seen in real Google Flights URLs are encoded; these bounds are
+// enforced by `filter::apply` after parsing.
+#[test]
+fn post_filter_bounds_are_not_encoded() {
+    let leg = FlightLeg {
+        date: "2026-03-01".into(),
+        from_airport: "HEL".into(),
+        to_airport: "BCN".into(),
+        ..FlightLeg::default()
+    };
+    let bounded = FlightLeg {
+        depart_after: Some(8 * 60 + 30),
+        depart_before: Some(11 * 60 + 15),
+        arrive_after: Some(10 * 60),
+        arrive_before: Some(22 * 60),
+        max_duration_minutes: Some(14 * 60),
+        min_layover_minutes: Some(90),
+        max_layover_minutes: Some(240),
+        seat: Some(Seat::Business),
+        ..leg.clone()
+    };
+    let pax = Passengers::default();
+    assert_eq!(
+        proto::encode(&[bounded], &pax, &Seat::Economy, &TripType::OneWay),
+        proto::encode(&[leg], &pax, &Seat::Economy, &TripType::OneWay)
+    );
+}
+
+fn sample_legs() -> Vec<FlightLeg> {
+    vec![
+        FlightLeg {
+            date: "2026-03-01".into(),
+            from_airport: "HEL".into(),
+            to_airport: "BCN".into(),
+            max_stops: Some(200),
+            airlines: Some(vec!["AY".into(), "IB".into()]),
+            ..FlightLeg::default()
+        },
+        FlightLeg {
+            date: "2026-03-08".into(),
+            from_airport: "BCN".into(),
+            to_airport: "HEL".into(),
+            seat: Some(Seat::First),
+            depart_after: Some(8 * 60 + 30),
+            arrive_before: Some(22 * 60),
+            max_duration_minutes: Some(14 * 60),
+            min_layover_minutes: Some(90),
+            max_layover_minutes: Some(240),
+            ..FlightLeg::default()
+        },
+    ]
+}
+
+#[test]
+fn encoded_len_is_exact() {
+    let legs = sample_legs();
+    let pax = Passengers {
+        adults: 2,
+        children: 1,
+        infants_in_seat: 0,
+        infants_on_lap: 1,
+    };
+    for trip in [TripType::RoundTrip, TripType::OneWay] {
+        let bytes = proto::encode(&legs, &pax, &Seat::Business, &trip);
+        assert_eq!(proto::encoded_len(&legs, &pax, &Seat::Business, &trip), bytes.len());
+    }
+
+    let none = Passengers {
+        adults: 0,
+        ..Passengers::default()
+    };
+    let bytes = proto::encode(&legs[..1], &none, &Seat::Economy, &TripType::OneWay);
+    assert_eq!(
+        proto::encoded_len(&legs[..1], &none, &Seat::Economy, &TripType::OneWay),
+        bytes.len()
+    );
+}
+
+#[test]
+fn encode_into_reuses_the_buffer() {
+    let legs = sample_legs();
+    let pax = Passengers::default();
+    let expected = proto::encode(&legs, &pax, &Seat::Economy, &TripType::RoundTrip);
+
+    let mut buf = Vec::new();
+    proto::encode_into(&mut buf, &legs, &pax, &Seat::Economy, &TripType::RoundTrip);
+    assert_eq!(buf, expected);
+    let capacity = buf.capacity();
+
+    buf.clear();
+    proto::encode_into(&mut buf, &legs, &pax, &Seat::Economy, &TripType::RoundTrip);
+    assert_eq!(buf, expected);
+    assert_eq!(buf.capacity(), capacity);
+}
+
+fn round_trip(params: &QueryParams) -> QueryParams {
+    QueryParams::from_tfs(&params.tfs()).unwrap()
+}
+
+fn json(params: &QueryParams) -> serde_json::Value {
+    serde_json::to_value(params).unwrap()
+}
+
+fn tfs_query(legs: Vec<FlightLeg>, trip: TripType) -> QueryParams {
+    QueryParams {
+        legs,
+        passengers: Passengers::default(),
+        seat: Seat::Economy,
+        trip,
+        language: String::new(),
+        currency: String::new(),
+        region: String::new(),
+        max_price: None,
+        low_emissions: false,
+    }
+}
+
+#[test]
+fn decode_known_url_blob() {
+    let params = QueryParams::from_tfs("GhoSCjIwMjYtMDMtMDFqBRIDTEFYcgUSA05SVEIBAUgBmAEC").unwrap();
+    assert_eq!(params.legs.len(), 1);
+    assert_eq!(params.legs[0].date, "2026-03-01");
+    assert_eq!(params.legs[0].from_airport, "LAX");
+    assert_eq!(params.legs[0].to_airport, "NRT");
+    assert_eq!(params.passengers.adults, 1);
+    assert_eq!(params.seat, Seat::Economy);
+    assert!(matches!(params.trip, TripType::OneWay));
+}
+
+#[test]
+fn decode_round_trips_every_field() {
+    let outbound = FlightLeg {
+        date: "2026-03-01".into(),
+        from_airport: "HEL".into(),
+        to_airport: "NYC".into(),
+        max_stops: Some(1),
+        airlines: Some(vec!["AY".into(), "AA".into()]),
+        ..FlightLeg::default()
+    };
+    let inbound = FlightLeg {
+        date: "2026-03-10".into(),
+        from_airport: "NYC".into(),
+        to_airport: "HEL".into(),
+        ..FlightLeg::default()
+    };
+    let mut params = tfs_query(vec![outbound, inbound], TripType::RoundTrip);
+    params.passengers = Passengers {
+        adults: 2,
+        children: 1,
+        infants_on_lap: 1,
+        ..Passengers::default()
+    };
+    params.seat = Seat::PremiumEconomy;
+
+    assert_eq!(json(&round_trip(&params)), json(&params));
+}
+
+#[test]
+fn selected_outbound_round_trips_and_sizes_exactly() {
+    let outbound = FlightLeg {
+        date: "2026-03-01".into(),
+        from_airport: "HEL".into(),
+        to_airport: "BCN".into(),
+        selected: vec![SelectedFlight {
+            from_airport: "HEL".into(),
+            date: "2026-03-01".into(),
+            to_airport: "BCN".into(),
+            airline: "AY".into(),
+            flight_number: "1661".into(),
+        }],
+        ..FlightLeg::default()
+    };
+    let inbound = FlightLeg {
+        date: "2026-03-08".into(),
+        from_airport: "BCN".into(),
+        to_airport: "HEL".into(),
+        ..FlightLeg::default()
+    };
+    let params = tfs_query(vec![outbound, inbound], TripType::RoundTrip);
+    assert_eq!(json(&round_trip(&params)), json(&params));
+
+    let bytes = proto::encode(&params.legs, &params.passengers, &params.seat, &params.trip);
+    assert_eq!(
+        proto::encoded_len(&params.legs, &params.passengers, &params.seat, &params.trip),
+        bytes.len()
+    );
+    assert!(bytes.windows(4).any(|w| w == b"1661"));
+}
+
+#[test]
+fn decode_rejects_garbage() {
+    assert!(QueryParams::from_tfs("not base64!").is_err());
+    assert!(QueryParams::from_tfs("").is_err());
+    assert!(proto::decode(&[0x1a, 0x10, 0x12]).is_err());
+}
+
+#[test]
+fn decode_rejects_unrelated_airport_sets() {
+    let mut buf = Vec::new();
+    let leg = FlightLeg {
+        date: "2026-03-01".into(),
+        from_airport: "HEL".into(),
+        to_airport: "BCN".into(),
+        ..FlightLeg::default()
+    };
+    proto::encode_into(&mut buf, &[leg], &Passengers::default(), &Seat::Economy, &TripType::OneWay);
+    let plain = proto::decode(&buf).unwrap();
+    assert_eq!(plain.legs[0].to_airport, "BCN");
+
+    // A second departure airport that doesn't complete a metro area.
+    let extra = [0x6a, 0x05, 0x12, 0x03, b'T', b'L', b'L'];
+    let mut bytes = buf;
+    let leg_len = bytes[1] as usize;
+    bytes[1] += extra.len() as u8;
+    bytes.splice(2 + leg_len..2 + leg_len, extra);
+    let err = proto::decode(&bytes).unwrap_err().to_string();
+    assert!(err.contains("HEL, TLL"), "{err}");
+}
@@ -1,4 +1,6 @@
-use flyr::query::{to_google_flights_url, FlightLeg, Passengers, QueryParams, Seat, TripType};
+use flyr::query::{
+    format_timestamp, parse_delay, parse_time_of_day, parse_timestamp, parse_minutes, to_google_flights_url, FlightLeg, Passengers, QueryParams, Seat, TripType,
+};
 
 fn make_valid_query() -> QueryParams {
     QueryParams {
@@ -6,14 +8,16 @@ fn make_valid_query() -> QueryParams {
             date: "2026-03-01".into(),
             from_airport: "HEL".into(),
             to_airport: "BCN".into(),
-            max_stops: None,
-            airlines: None,
+            ..FlightLeg::default()
         }],
         passengers: Passengers::default(),
         seat: Seat::Economy,
         trip: TripType::OneWay,
         language: "en".into(),
         currency: "USD".into(),
+        region: String::new(),
+        max_price: None,
+        low_emissions: false,
     }
 }
 
@@ -101,6 +105,46 @@ fn rejects_infants_exceeding_adults() {
     assert!(q.validate().is_err());
 }
 
+#[test]
+fn rejects_seated_infants_exceeding_adults() {
+    let mut q = make_valid_query();
+    q.passengers = Passengers {
+        adults: 1,
+        children: 0,
+        infants_in_seat: 2,
+        infants_on_lap: 0,
+    };
+    let err = q.validate().unwrap_err().to_string();
+    assert!(err.contains("infants in seat cannot exceed number of adults"));
+    assert!(err.contains("2 infants, 1 adults"));
+}
+
+#[test]
+fn accepts_one_lap_and_one_seated_infant_per_adult() {
+    let mut q = make_valid_query();
+    q.passengers = Passengers {
+        adults: 1,
+        children: 0,
+        infants_in_seat: 1,
+        infants_on_lap: 1,
+    };
+    assert!(q.validate().is_ok());
+}
+
+#[test]
+fn too_many_passengers_message_breaks_down_seats() {
+    let mut q = make_valid_query();
+    q.passengers = Passengers {
+        adults: 5,
+        children: 3,
+        infants_in_seat: 0,
+        infants_on_lap: 2,
+    };
+    let err = q.validate().unwrap_err().to_string();
+    assert!(err.contains("exceeds maximum of 9"));
+    assert!(err.contains("8 needing seats, 2 on lap"));
+}
+
 #[test]
 fn accepts_nine_passengers() {
     let mut q = make_valid_query();
@@ -150,6 +194,26 @@ fn rejects_apr_31() {
     assert!(q.validate().is_err());
 }
 
+#[test]
+fn leg_in_another_cabin_is_searched_on_its_own() {
+    let mut q = make_valid_query();
+    q.legs[0].seat = Some(Seat::Economy);
+    assert!(!q.has_mixed_cabins());
+
+    q.legs[0].seat = Some(Seat::Business);
+    q.max_price = Some(500);
+    assert!(q.validate().is_ok());
+    assert!(q.has_mixed_cabins());
+
+    let leg = q.leg_search(0).unwrap();
+    assert_eq!(leg.seat, Seat::Business);
+    assert_eq!(leg.legs.len(), 1);
+    assert!(leg.legs[0].seat.is_none());
+    assert!(matches!(leg.trip, TripType::OneWay));
+    assert_eq!(leg.max_price, None);
+    assert!(q.leg_search(1).is_none());
+}
+
 #[test]
 fn accepts_feb_28_non_leap() {
     let mut q = make_valid_query();
@@ -179,6 +243,25 @@ fn empty_lang_omitted_from_params() {
     assert!(!params.iter().any(|(k, _)| k == "hl"));
 }
 
+#[test]
+fn region_adds_gl_param() {
+    let mut q = make_valid_query();
+    assert!(!q.to_url_params().iter().any(|(k, _)| k == "gl"));
+    q.region = "DE".into();
+    assert!(q.validate().is_ok());
+    assert!(q.to_url_params().contains(&("gl".to_string(), "DE".to_string())));
+    assert!(to_google_flights_url(&q).ends_with("&gl=DE"));
+}
+
+#[test]
+fn region_must_be_a_country_code() {
+    let mut q = make_valid_query();
+    for bad in ["DEU", "D", "1E"] {
+        q.region = bad.into();
+        assert!(q.validate().is_err(), "{bad}");
+    }
+}
+
 #[test]
 fn browser_url_uses_tfs_path() {
     let q = make_valid_query();
@@ -204,3 +287,150 @@ fn browser_url_tfs_is_url_safe_base64() {
     assert!(!tfs_value.contains('/'), "tfs contains '/' (not URL-safe)");
     assert!(!tfs_value.contains('='), "tfs contains '=' (has padding)");
 }
+
+#[test]
+fn browser_url_parses_back_into_params() {
+    let mut q = make_valid_query();
+    q.currency = "EUR".into();
+    let back = QueryParams::from_google_flights_url(&to_google_flights_url(&q)).unwrap();
+    assert_eq!(back.legs[0].from_airport, "HEL");
+    assert_eq!(back.legs[0].to_airport, "BCN");
+    assert_eq!(back.legs[0].date, "2026-03-01");
+    assert_eq!(back.currency, "EUR");
+    assert_eq!(back.language, "en");
+    assert!(back.region.is_empty());
+    assert!(back.validate().is_ok());
+}
+
+#[test]
+fn url_without_tfs_is_rejected() {
+    let err = QueryParams::from_google_flights_url("https://www.google.com/travel/flights?q=x")
+        .unwrap_err();
+    assert!(err.to_string().contains("no tfs"), "{err}");
+}
+
+#[test]
+fn parse_minutes_accepts_common_forms() {
+    assert_eq!(parse_minutes("90").unwrap(), 90);
+    assert_eq!(parse_minutes("90m").unwrap(), 90);
+    assert_eq!(parse_minutes("2h").unwrap(), 120);
+    assert_eq!(parse_minutes("1h30m").unwrap(), 90);
+    assert_eq!(parse_minutes("1h30").unwrap(), 90);
+    assert_eq!(parse_minutes(" 14H ").unwrap(), 840);
+}
+
+#[test]
+fn parse_minutes_rejects_garbage() {
+    assert!(parse_minutes("").is_err());
+    assert!(parse_minutes("m").is_err());
+    assert!(parse_minutes("soon").is_err());
+    assert!(parse_minutes("1x").is_err());
+}
+
+#[test]
+fn parse_minutes_rejects_overflow() {
+    let err = parse_minutes("71582789h").unwrap_err().to_string();
+    assert!(err.contains("invalid duration"), "{err}");
+    assert!(parse_minutes("71582788h59m").is_err());
+    assert_eq!(parse_minutes("71582788h").unwrap(), 71582788 * 60);
+}
+
+#[test]
+fn parse_delay_accepts_seconds_and_millis() {
+    use std::time::Duration;
+    assert_eq!(parse_delay("2s").unwrap(), Duration::from_secs(2));
+    assert_eq!(parse_delay("1500ms").unwrap(), Duration::from_millis(1500));
+    assert_eq!(parse_delay("3").unwrap(), Duration::from_secs(3));
+    assert!(parse_delay("soon").is_err());
+    assert!(parse_delay("1.5s").is_err());
+}
+
+#[test]
+fn parse_delay_rejects_overflow() {
+    let err = parse_delay("99999999999999999s").unwrap_err();
+    assert!(matches!(err, flyr::error::FlightError::Validation(_)));
+    assert!(parse_delay("99999999999999999").is_err());
+    assert!(parse_delay("99999999999999999ms").is_ok());
+}
+
+#[test]
+fn timestamps_are_rfc3339_utc() {
+    assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+    assert_eq!(format_timestamp(1_800_000_000), "2027-01-15T08:00:00Z");
+    assert_eq!(parse_timestamp("2027-01-15T08:00:00Z"), Some(1_800_000_000));
+    assert_eq!(parse_timestamp("2027-01-15 08:00"), None);
+}
+
+#[test]
+fn shift_days_moves_every_leg() {
+    let mut q = make_valid_query();
+    q.legs.push(FlightLeg {
+        date: "2026-03-08".into(),
+        from_airport: "BCN".into(),
+        to_airport: "HEL".into(),
+        ..FlightLeg::default()
+    });
+    let earlier = q.shift_days(-3).unwrap();
+    assert_eq!(earlier.legs[0].date, "2026-02-26");
+    assert_eq!(earlier.legs[1].date, "2026-03-05");
+    assert_eq!(q.shift_days(0).unwrap().legs[0].date, "2026-03-01");
+}
+
+#[test]
+fn parses_times_of_day() {
+    assert_eq!(parse_time_of_day("08:00").unwrap(), 480);
+    assert_eq!(parse_time_of_day("21:30").unwrap(), 1290);
+    assert_eq!(parse_time_of_day("24:00").unwrap(), 1440);
+    assert!(parse_time_of_day("8").is_err());
+    assert!(parse_time_of_day("25:00").is_err());
+    assert!(parse_time_of_day("12:5").is_err());
+}
+
+#[test]
+fn rejects_empty_time_windows() {
+    let mut q = make_valid_query();
+    q.legs[0].depart_after = Some(12 * 60);
+    q.legs[0].depart_before = Some(8 * 60);
+    assert!(q.validate().is_err());
+    q.legs[0].depart_before = Some(14 * 60);
+    assert!(q.validate().is_ok());
+}
+
+#[test]
+fn rejects_zero_max_duration() {
+    let mut q = make_valid_query();
+    q.legs[0].max_duration_minutes = Some(0);
+    assert!(q.validate().is_err());
+    q.legs[0].max_duration_minutes = Some(parse_minutes("14h").unwrap());
+    assert!(q.validate().is_ok());
+}
+
+#[test]
+fn rejects_airline_both_required_and_excluded() {
+    let mut q = make_valid_query();
+    q.legs[0].airlines = Some(vec!["AY".into(), "IB".into()]);
+    q.legs[0].exclude_airlines = Some(vec!["FR".into()]);
+    assert!(q.validate().is_ok());
+    q.legs[0].exclude_airlines = Some(vec!["IB".into()]);
+    assert!(q.validate().is_err());
+}
+
+#[test]
+fn max_price_is_filtered_locally_not_sent_in_tfs() {
+    let mut q = make_valid_query();
+    let plain = q.tfs();
+    q.max_price = Some(400);
+    assert!(q.validate().is_ok());
+    assert_eq!(q.tfs(), plain);
+
+    q.max_price = Some(0);
+    assert!(q.validate().is_err());
+}
+
+#[test]
+fn low_emissions_is_filtered_locally_not_sent_in_tfs() {
+    let mut q = make_valid_query();
+    let plain = q.tfs();
+    q.low_emissions = true;
+    assert_eq!(q.tfs(), plain);
+}
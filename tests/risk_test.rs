@@ -0,0 +1,116 @@
+use flyr::model::{Airport, FlightDateTime, Risk, Segment, SegmentKind, Ticketing};
+use flyr::risk::assess;
+
+fn segment(from: &str, to: &str, airline: &str, dep: (u32, u32), arr: (u32, u32)) -> Segment {
+    Segment {
+        from_airport: Airport {
+            code: from.into(),
+            name: String::new(),
+        },
+        to_airport: Airport {
+            code: to.into(),
+            name: String::new(),
+        },
+        departure: FlightDateTime {
+            year: 2026,
+            month: 3,
+            day: 1,
+            hour: dep.0,
+            minute: dep.1,
+        },
+        arrival: FlightDateTime {
+            year: 2026,
+            month: 3,
+            day: 1,
+            hour: arr.0,
+            minute: arr.1,
+        },
+        duration_minutes: 120,
+        kind: SegmentKind::Flight,
+        aircraft: None,
+        airline: Some(airline.into()),
+        flight_number: None,
+        airline_name: None,
+        operated_by: None,
+        legroom: None,
+        amenities: Vec::new(),
+        departure_terminal: None,
+        arrival_terminal: None,
+        codeshares: Vec::new(),
+        departure_utc_offset_minutes: None,
+        arrival_utc_offset_minutes: None,
+        departure_iso: None,
+        arrival_iso: None,
+    }
+}
+
+#[test]
+fn nonstop_is_low_risk() {
+    let segs = vec![segment("HEL", "BCN", "AY", (8, 0), (11, 20))];
+    assert_eq!(assess(&segs, None, 90), Risk::Low);
+}
+
+#[test]
+fn long_connection_same_carrier_is_low() {
+    let segs = vec![
+        segment("HEL", "CDG", "AF", (8, 0), (10, 0)),
+        segment("CDG", "BCN", "AF", (12, 0), (14, 0)),
+    ];
+    assert_eq!(assess(&segs, None, 90), Risk::Low);
+}
+
+#[test]
+fn short_connection_same_carrier_is_medium() {
+    let segs = vec![
+        segment("HEL", "CDG", "AF", (8, 0), (10, 0)),
+        segment("CDG", "BCN", "AF", (10, 45), (12, 45)),
+    ];
+    assert_eq!(assess(&segs, None, 90), Risk::Medium);
+}
+
+#[test]
+fn carrier_change_with_long_connection_is_low() {
+    let segs = vec![
+        segment("HEL", "CDG", "AY", (8, 0), (10, 0)),
+        segment("CDG", "BCN", "VY", (13, 0), (15, 0)),
+    ];
+    assert_eq!(assess(&segs, None, 90), Risk::Low);
+}
+
+#[test]
+fn carrier_change_with_short_connection_is_high() {
+    let segs = vec![
+        segment("HEL", "CDG", "AY", (8, 0), (10, 0)),
+        segment("CDG", "BCN", "VY", (11, 0), (13, 0)),
+    ];
+    assert_eq!(assess(&segs, None, 90), Risk::High);
+}
+
+#[test]
+fn min_connection_threshold_is_configurable() {
+    let segs = vec![
+        segment("HEL", "CDG", "AF", (8, 0), (10, 0)),
+        segment("CDG", "BCN", "AF", (11, 0), (13, 0)),
+    ];
+    assert_eq!(assess(&segs, None, 45), Risk::Low);
+    assert_eq!(assess(&segs, None, 90), Risk::Medium);
+}
+
+#[test]
+fn separate_tickets_are_at_least_medium() {
+    let segs = vec![
+        segment("HEL", "CDG", "AY", (8, 0), (10, 0)),
+        segment("CDG", "BCN", "AY", (13, 0), (15, 0)),
+    ];
+    assert_eq!(assess(&segs, Some(Ticketing::SeparateTickets), 90), Risk::Medium);
+    assert_eq!(assess(&segs, Some(Ticketing::SelfTransfer), 90), Risk::Medium);
+}
+
+#[test]
+fn separate_tickets_with_short_connection_are_high() {
+    let segs = vec![
+        segment("HEL", "CDG", "AY", (8, 0), (10, 0)),
+        segment("CDG", "BCN", "AY", (11, 0), (13, 0)),
+    ];
+    assert_eq!(assess(&segs, Some(Ticketing::SelfTransfer), 90), Risk::High);
+}
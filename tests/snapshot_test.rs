@@ -0,0 +1,71 @@
+use flyr::parse::parse_html;
+use flyr::query::{FlightLeg, Passengers, QueryParams, Seat, TripType};
+use flyr::snapshot::{append, SnapshotRow, CSV_HEADER};
+use flyr::testing::fixtures;
+
+fn params(return_date: Option<&str>) -> QueryParams {
+    let leg = |date: &str, from: &str, to: &str| FlightLeg {
+        date: date.into(),
+        from_airport: from.into(),
+        to_airport: to.into(),
+        ..FlightLeg::default()
+    };
+    let mut legs = vec![leg("2026-03-01", "HEL", "BCN")];
+    legs.extend(return_date.map(|d| leg(d, "BCN", "HEL")));
+    QueryParams {
+        legs,
+        passengers: Passengers::default(),
+        seat: Seat::Economy,
+        trip: TripType::OneWay,
+        language: "en".into(),
+        currency: "USD".into(),
+        region: String::new(),
+        max_price: None,
+        low_emissions: false,
+    }
+}
+
+fn row() -> SnapshotRow {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.searched_at = Some("2026-02-01T07:00:00Z".into());
+    SnapshotRow::new(&params(None), &result)
+}
+
+#[test]
+fn row_records_lowest_and_nonstop_prices() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.searched_at = Some("2026-02-01T07:00:00Z".into());
+    for flight in &mut result.flights {
+        if flight.airlines == vec!["Norwegian"] {
+            flight.price = Some(300);
+        }
+    }
+    let row = SnapshotRow::new(&params(Some("2026-03-08")), &result);
+
+    assert_eq!(row.lowest_price, Some(142));
+    assert_eq!(row.nonstop_lowest, Some(189));
+    assert_eq!(row.return_date.as_deref(), Some("2026-03-08"));
+    assert_eq!(row.currency, "EUR");
+    assert_eq!(
+        row.csv_line(),
+        "2026-02-01T07:00:00Z,HEL,BCN,2026-03-01,2026-03-08,142,189,EUR,3"
+    );
+}
+
+#[test]
+fn append_writes_the_header_once() {
+    let dir = std::env::temp_dir().join(format!("flyr-snapshots-{}", std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+
+    let path = append(&dir, &row()).unwrap();
+    append(&dir, &row()).unwrap();
+
+    assert_eq!(path.file_name().unwrap(), "HEL-BCN.csv");
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0], CSV_HEADER);
+    assert_eq!(lines[1], "2026-02-01T07:00:00Z,HEL,BCN,2026-03-01,,121,121,EUR,3");
+    assert_eq!(lines[1], lines[2]);
+    std::fs::remove_dir_all(&dir).ok();
+}
@@ -0,0 +1,59 @@
+use flyr::query::TripType;
+use flyr::stay::{pairs, parse_nights, round_trip};
+use flyr::testing::hel_bcn_query;
+
+#[test]
+fn parses_single_and_ranged_nights() {
+    assert_eq!(parse_nights("7").unwrap(), 7..=7);
+    assert_eq!(parse_nights("5-7").unwrap(), 5..=7);
+    assert_eq!(parse_nights(" 3 - 4 ").unwrap(), 3..=4);
+}
+
+#[test]
+fn rejects_empty_reversed_and_zero_ranges() {
+    for bad in ["", "0", "7-5", "five", "5-", "0-3"] {
+        assert!(parse_nights(bad).is_err(), "{bad:?} should be rejected");
+    }
+}
+
+#[test]
+fn pairs_cover_every_departure_and_stay() {
+    let found = pairs("2026-03-01", 1, &(5..=6)).unwrap();
+    let summary: Vec<(&str, &str, u32)> = found
+        .iter()
+        .map(|(d, r, n)| (d.as_str(), r.as_str(), *n))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("2026-02-28", "2026-03-05", 5),
+            ("2026-02-28", "2026-03-06", 6),
+            ("2026-03-01", "2026-03-06", 5),
+            ("2026-03-01", "2026-03-07", 6),
+            ("2026-03-02", "2026-03-07", 5),
+            ("2026-03-02", "2026-03-08", 6),
+        ]
+    );
+}
+
+#[test]
+fn too_many_pairs_is_an_error() {
+    assert!(pairs("2026-03-01", 10, &(1..=3)).is_err());
+    assert_eq!(pairs("2026-03-01", 9, &(1..=3)).unwrap().len(), 57);
+}
+
+#[test]
+fn round_trip_mirrors_the_outbound_leg() {
+    let mut params = hel_bcn_query();
+    params.legs[0].max_stops = Some(0);
+    let stay = round_trip(&params, "2026-03-02", "2026-03-09");
+
+    assert!(matches!(stay.trip, TripType::RoundTrip));
+    assert_eq!(stay.legs.len(), 2);
+    assert_eq!(stay.legs[0].date, "2026-03-02");
+    assert_eq!(stay.legs[0].from_airport, "HEL");
+    assert_eq!(stay.legs[1].date, "2026-03-09");
+    assert_eq!(stay.legs[1].from_airport, "BCN");
+    assert_eq!(stay.legs[1].to_airport, "HEL");
+    assert_eq!(stay.legs[1].max_stops, Some(0));
+}
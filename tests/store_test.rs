@@ -0,0 +1,86 @@
+use flyr::model::SearchResult;
+use flyr::parse::parse_html;
+use flyr::store::{cache_key, route_key, Store};
+use flyr::testing::{fixtures, hel_bcn_query};
+
+const NOW: u64 = 1_800_000_000;
+
+fn sample() -> SearchResult {
+    parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap()
+}
+
+#[test]
+fn lookup_returns_fresh_result() {
+    let store = Store::in_memory().unwrap();
+    let params = hel_bcn_query();
+    store.record(&params, &sample(), NOW).unwrap();
+
+    let hit = store.lookup(&params, 900, NOW + 60).unwrap().unwrap();
+    assert_eq!(hit.flights.len(), 3);
+    assert_eq!(hit.flights[1].price, Some(142));
+    assert_eq!(hit.metadata.currency.as_deref(), Some("EUR"));
+}
+
+#[test]
+fn lookup_ignores_stale_and_different_queries() {
+    let store = Store::in_memory().unwrap();
+    let params = hel_bcn_query();
+    store.record(&params, &sample(), NOW).unwrap();
+
+    assert!(store.lookup(&params, 900, NOW + 901).unwrap().is_none());
+
+    let mut other = hel_bcn_query();
+    other.currency = "USD".into();
+    assert_ne!(cache_key(&params), cache_key(&other));
+    assert!(store.lookup(&other, 900, NOW).unwrap().is_none());
+}
+
+#[test]
+fn previous_returns_the_latest_older_search() {
+    let store = Store::in_memory().unwrap();
+    let params = hel_bcn_query();
+    store.record(&params, &SearchResult::default(), NOW).unwrap();
+    store.record(&params, &sample(), NOW + 3600).unwrap();
+
+    let (at, previous) = store.previous(&params, NOW + 7200).unwrap().unwrap();
+    assert_eq!(at, NOW + 3600);
+    assert_eq!(previous.flights.len(), 3);
+
+    let (at, _) = store.previous(&params, NOW + 3600).unwrap().unwrap();
+    assert_eq!(at, NOW);
+    assert!(store.previous(&params, NOW).unwrap().is_none());
+}
+
+#[test]
+fn recorded_searches_form_history() {
+    let store = Store::in_memory().unwrap();
+    let params = hel_bcn_query();
+    store.record(&params, &sample(), NOW).unwrap();
+    store.record(&params, &SearchResult::default(), NOW + 3600).unwrap();
+
+    let points = store.history(&route_key("hel", "bcn"), None).unwrap();
+    assert_eq!(points.len(), 2);
+    assert_eq!(points[0].min_price, Some(121));
+    assert_eq!(points[0].flight_count, 3);
+    assert_eq!(points[0].seat, "economy");
+    assert_eq!(points[1].min_price, None);
+    assert_eq!(points[0].searched_at, "2027-01-15T08:00:00Z");
+
+    assert!(store.history("HEL-BCN", Some("2026-03-02")).unwrap().is_empty());
+}
+
+#[test]
+fn prune_respects_retention() {
+    let store = Store::in_memory().unwrap();
+    let params = hel_bcn_query();
+    store.record(&params, &sample(), NOW).unwrap();
+    store.record(&params, &sample(), NOW + 10 * 86_400).unwrap();
+
+    assert_eq!(store.prune(5, NOW + 11 * 86_400).unwrap(), 1);
+    let stats = store.stats().unwrap();
+    assert_eq!(stats.searches, 1);
+    assert_eq!(stats.routes, 1);
+
+    assert_eq!(store.clear().unwrap(), 1);
+    assert_eq!(store.stats().unwrap().searches, 0);
+}
@@ -0,0 +1,187 @@
+use flyr::locale::Locale;
+use flyr::parse::parse_html;
+use flyr::model::{
+    CarbonEmission, PriceInsights, PriceLevel, PriceScope, SegmentKind, Ticketing, Warning,
+};
+use flyr::table::{
+    render, render_alert_summary, render_plain, render_price_insights, scoped_price, Highlights,
+};
+use flyr::testing::fixtures;
+
+#[test]
+fn plain_output_has_one_labeled_line_per_flight() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let plain = render_plain(&result, "EUR", &Locale::default(), &Highlights::default());
+
+    let lines: Vec<&str> = plain.lines().collect();
+    assert_eq!(lines.len(), result.flights.len());
+    assert!(lines[0].starts_with("Option 1 of 3. Price: €"));
+    assert!(lines.iter().all(|l| l.contains("Route: HEL to BCN")));
+    assert!(lines.iter().any(|l| l.contains("Route: HEL to BCN via FRA.")));
+    assert!(lines.iter().any(|l| l.contains("Route: HEL to BCN, nonstop.")));
+    assert!(lines.iter().all(|l| l.contains(". Departs 2026-")));
+}
+
+#[test]
+fn plain_output_has_no_box_drawing() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let plain = render_plain(&result, "EUR", &Locale::default(), &Highlights::default());
+
+    assert!(!plain.chars().any(|c| ('\u{2500}'..='\u{257F}').contains(&c)));
+    assert!(plain.contains("Duration: "));
+    assert!(plain.contains(" hours "));
+}
+
+#[test]
+fn alert_marks_flights_below_the_limit() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let highlights = Highlights {
+        alert_below: Some(150),
+        ..Highlights::default()
+    };
+
+    let alerted: Vec<_> = result.flights.iter().filter(|f| highlights.is_alert(f)).collect();
+    assert_eq!(alerted.len(), 2);
+    assert!(!highlights.is_alert(&result.flights[0]));
+
+    let plain = render_plain(&result, "EUR", &Locale::default(), &highlights);
+    assert_eq!(plain.matches("Below alert price").count(), 2);
+}
+
+#[test]
+fn alert_summary_counts_qualifying_flights() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let locale = Locale::default();
+
+    assert_eq!(
+        render_alert_summary(&result, 150, "EUR", &locale),
+        "Alert: 2 of 3 flights below €150; cheapest is €121 (Norwegian)."
+    );
+    assert_eq!(
+        render_alert_summary(&result, 121, "EUR", &locale),
+        "No flights below €121; cheapest is €121 (Norwegian)."
+    );
+}
+
+#[test]
+fn operated_by_column_is_opt_in() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.flights[0].segments[0].operated_by = Some("Nordic Regional Airlines".into());
+
+    let default = render(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(!default.contains("Operated by"));
+
+    let highlights = Highlights {
+        operated_by: true,
+        ..Highlights::default()
+    };
+    let table = render(&result, "EUR", &Locale::default(), &highlights);
+    assert!(table.contains("Operated by"));
+    assert!(table.contains("Nordic Regional Airlines"));
+}
+
+#[test]
+fn price_insights_summary_names_level_and_range() {
+    let insights = PriceInsights {
+        current_level: Some(PriceLevel::Low),
+        typical_low: Some(320),
+        typical_high: Some(480),
+    };
+    let summary = render_price_insights(&insights, "EUR", &Locale::default()).unwrap();
+    assert!(summary.starts_with("Prices are currently LOW"));
+    assert!(summary.contains("320"));
+    assert!(summary.contains("480"));
+
+    let unknown = PriceInsights {
+        current_level: None,
+        ..insights
+    };
+    assert!(render_price_insights(&unknown, "EUR", &Locale::default()).is_none());
+}
+
+#[test]
+fn legroom_column_appears_only_with_data() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let without = render(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(!without.contains("Legroom"));
+
+    result.flights[1].segments[0].legroom = Some("30 in".into());
+    let with = render(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(with.contains("Legroom"));
+    assert!(with.contains("30 in"));
+}
+
+#[test]
+fn emissions_show_relative_to_typical() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    for flight in &mut result.flights {
+        flight.carbon = CarbonEmission::new(None, None);
+    }
+    let without = render(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(!without.contains("CO2"));
+
+    result.flights[0].carbon = CarbonEmission::new(Some(148_000), Some(181_000));
+    let with = render(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(with.contains("CO2"));
+    assert!(with.contains("−18% emissions"));
+
+    let plain = render_plain(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(plain.contains("Emissions: 148 kg, 18% below typical"));
+}
+
+#[test]
+fn terminals_show_in_route_and_plain_connections() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let segments = &mut result.flights[1].segments;
+    segments[0].departure_terminal = Some("2".into());
+    segments[0].arrival_terminal = Some("1".into());
+    segments[1].departure_terminal = Some("2".into());
+
+    let table = render(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(table.contains("HEL T2 → FRA T1"));
+
+    let plain = render_plain(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(plain.contains("Terminal change at FRA: T1 to T2"));
+}
+
+#[test]
+fn ground_segments_are_named_in_route_and_plain() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.flights[1].segments[1].kind = SegmentKind::Train;
+
+    let table = render(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(table.contains("FRA → BCN (train)"));
+
+    let plain = render_plain(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(plain.contains("Train from FRA to BCN"));
+}
+
+#[test]
+fn separate_tickets_are_flagged_in_table_and_plain() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.flights[1].ticketing = Some(Ticketing::SelfTransfer);
+    result.flights[1].warnings.push(Warning::new(
+        "self_transfer",
+        "Self-transfer at FRA: collect and re-check bags; a missed connection is not covered",
+    ));
+
+    let table = render(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(table.contains("⚠ self-transfer"));
+
+    let plain = render_plain(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert!(plain.contains("Ticketing: Self-transfer at FRA"));
+}
+
+#[test]
+fn scoped_price_says_what_the_price_covers() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let locale = Locale::default();
+    let flight = &mut result.flights[0];
+    let bare = scoped_price(flight, "EUR", &locale);
+    assert!(!bare.contains(' '));
+
+    flight.price_scope = Some(PriceScope::Total);
+    assert_eq!(scoped_price(flight, "EUR", &locale), format!("{bare} round trip"));
+    flight.price_scope = Some(PriceScope::Outbound);
+    assert_eq!(scoped_price(flight, "EUR", &locale), format!("{bare} outbound"));
+}
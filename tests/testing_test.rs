@@ -0,0 +1,369 @@
+use std::sync::Arc;
+
+use flyr::error::FlightError;
+use flyr::model::{SearchResult, Warning};
+use flyr::parse::{ParseOptions, ResultParser};
+use flyr::query::SearchQuery;
+use flyr::testing::{fixtures, hel_bcn_query, MockFetcher};
+
+async fn search(fetcher: &MockFetcher) -> Result<flyr::model::SearchResult, FlightError> {
+    flyr::search_with_fetcher(
+        fetcher,
+        SearchQuery::Structured(hel_bcn_query()),
+        ParseOptions::default(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn one_way_fixture_parses_end_to_end() {
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let result = search(&fetcher).await.unwrap();
+
+    assert_eq!(result.flights.len(), 3);
+    assert_eq!(result.metadata.currency.as_deref(), Some("EUR"));
+    assert_eq!(result.metadata.airlines.len(), 3);
+    assert_eq!(result.flights[1].segments.len(), 2);
+    assert_eq!(result.flights[2].arrival_day_offset, 1);
+    assert_eq!(result.flights[0].segments[0].flight_number.as_deref(), Some("1657"));
+}
+
+#[tokio::test]
+async fn mock_records_request_params() {
+    let fetcher = MockFetcher::new(fixtures::NO_RESULTS);
+    let result = search(&fetcher).await.unwrap();
+    assert!(result.flights.is_empty());
+
+    let requests = fetcher.requests();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].iter().any(|(k, _)| k == "tfs"));
+    assert!(requests[0].iter().any(|(k, v)| k == "curr" && v == "EUR"));
+}
+
+#[tokio::test]
+async fn blocked_fixtures_map_to_errors() {
+    let consent = MockFetcher::new(fixtures::CONSENT);
+    assert!(matches!(search(&consent).await, Err(FlightError::ConsentRequired)));
+
+    let captcha = MockFetcher::new(fixtures::CAPTCHA);
+    assert!(matches!(search(&captcha).await, Err(FlightError::CaptchaRequired(_))));
+}
+
+#[tokio::test]
+async fn queued_responses_are_served_in_order() {
+    let fetcher = MockFetcher::queued()
+        .push_error(FlightError::Timeout)
+        .push_html(fixtures::ONE_WAY_HEL_BCN);
+
+    assert!(matches!(search(&fetcher).await, Err(FlightError::Timeout)));
+    assert_eq!(search(&fetcher).await.unwrap().flights.len(), 3);
+    assert!(matches!(
+        search(&fetcher).await,
+        Err(FlightError::ConnectionFailed(_))
+    ));
+}
+
+#[tokio::test]
+async fn results_carry_phase_timings() {
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let result = search(&fetcher).await.unwrap();
+
+    let timings = result.timings.expect("timings recorded");
+    assert!(timings.total() >= timings.fetch);
+    assert_eq!(
+        timings.total(),
+        timings.fetch + timings.extract + timings.json + timings.build
+    );
+
+    let json = serde_json::to_string(&result).unwrap();
+    assert!(!json.contains("timings"));
+}
+
+#[tokio::test]
+async fn result_echoes_query_and_timestamp() {
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let result = search(&fetcher).await.unwrap();
+
+    let searched_at = result.searched_at.as_deref().unwrap();
+    assert_eq!(searched_at.len(), "2026-03-01T10:30:00Z".len());
+    assert!(searched_at.ends_with('Z'));
+
+    let json = serde_json::to_value(&result).unwrap();
+    let query = &json["query"];
+    assert_eq!(query["trip"], "one-way");
+    assert_eq!(query["seat"], "economy");
+    assert_eq!(query["passengers"]["adults"], 1);
+    assert_eq!(query["legs"][0]["from_airport"], "HEL");
+    assert_eq!(query["legs"][0]["to_airport"], "BCN");
+
+    let back: flyr::model::SearchResult = serde_json::from_value(json).unwrap();
+    assert!(matches!(back.query, Some(SearchQuery::Structured(_))));
+}
+
+#[tokio::test]
+async fn natural_language_query_echoes_text() {
+    let fetcher = MockFetcher::new(fixtures::NO_RESULTS);
+    let query = SearchQuery::NaturalLanguage("flights from HEL to BCN".into());
+    let result = flyr::search_with_fetcher(&fetcher, query, ParseOptions::default())
+        .await
+        .unwrap();
+
+    let json = serde_json::to_value(&result).unwrap();
+    assert_eq!(json["query"], "flights from HEL to BCN");
+}
+
+#[tokio::test]
+async fn currency_mismatch_is_reported_as_warning() {
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let mut params = hel_bcn_query();
+    params.currency = "USD".into();
+    let query = SearchQuery::Structured(params);
+    let result = flyr::search_with_fetcher(&fetcher, query, ParseOptions::default())
+        .await
+        .unwrap();
+
+    assert_eq!(result.warnings.len(), 1);
+    assert_eq!(result.warnings[0].kind, "currency_mismatch");
+    assert!(result.warnings[0].message.contains("Google returned EUR"));
+
+    let json = serde_json::to_value(&result).unwrap();
+    assert_eq!(json["warnings"][0]["kind"], "currency_mismatch");
+}
+
+#[tokio::test]
+async fn matching_currency_has_no_warnings() {
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let result = search(&fetcher).await.unwrap();
+    assert!(result.warnings.is_empty());
+}
+
+#[tokio::test]
+async fn flexible_search_covers_every_date() {
+    let fetcher = std::sync::Arc::new(
+        MockFetcher::queued()
+            .push_html(fixtures::ONE_WAY_HEL_BCN)
+            .push_html(fixtures::ONE_WAY_HEL_BCN)
+            .push_html(fixtures::NO_RESULTS)
+            .push_html(fixtures::NO_RESULTS)
+            .push_error(FlightError::Timeout),
+    );
+    let mut params = hel_bcn_query();
+    params.legs[0].date = "2099-03-01".into();
+    let by_date = flyr::search_flexible(&fetcher, &params, 2, ParseOptions::default())
+        .await
+        .unwrap();
+
+    let dates: Vec<&str> = by_date.keys().map(String::as_str).collect();
+    assert_eq!(
+        dates,
+        vec!["2099-02-27", "2099-02-28", "2099-03-01", "2099-03-02", "2099-03-03"]
+    );
+    assert_eq!(fetcher.requests().len(), 5);
+    assert_eq!(by_date.values().filter(|r| r.is_err()).count(), 1);
+    let flights: usize = by_date.values().flatten().map(|r| r.flights.len()).sum();
+    assert_eq!(flights, 6);
+}
+
+#[test]
+fn flexible_search_skips_past_dates_and_is_capped() {
+    let params = hel_bcn_query();
+    let today = flyr::query::date_to_days("2026-03-01").unwrap();
+    assert_eq!(flyr::flex_offsets(&params, 2, today).unwrap(), vec![0, 1, 2]);
+    assert_eq!(flyr::flex_offsets(&params, 2, today - 5).unwrap().len(), 5);
+    assert!(flyr::flex_offsets(&params, 2, today + 3).is_err());
+
+    let err = flyr::flex_offsets(&params, flyr::MAX_FLEX_DAYS + 1, today - 100).unwrap_err();
+    assert!(err.to_string().contains("narrow the range"), "{err}");
+}
+
+#[tokio::test]
+async fn max_price_filters_parsed_results() {
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let all = search(&fetcher).await.unwrap();
+    let cheapest = all.flights.iter().filter_map(|f| f.price).min().unwrap();
+
+    let mut params = hel_bcn_query();
+    params.max_price = Some(cheapest);
+    let result = flyr::search_with_fetcher(
+        &fetcher,
+        SearchQuery::Structured(params),
+        ParseOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert!(!result.flights.is_empty());
+    assert!(result.flights.iter().all(|f| f.price == Some(cheapest)));
+}
+
+#[tokio::test]
+async fn stay_search_ranks_pairs_by_cheapest_fare() {
+    let fetcher = std::sync::Arc::new(
+        MockFetcher::queued()
+            .push_html(fixtures::ONE_WAY_HEL_BCN)
+            .push_html(fixtures::NO_RESULTS)
+            .push_error(FlightError::Timeout),
+    );
+    let by_pair = flyr::search_stays(
+        &fetcher,
+        &hel_bcn_query(),
+        0,
+        5..=7,
+        ParseOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(fetcher.requests().len(), 3);
+    assert_eq!(by_pair.len(), 3);
+    assert!(by_pair.keys().all(|(date, _)| date == "2026-03-01"));
+    assert_eq!(by_pair.values().filter(|r| r.is_err()).count(), 1);
+
+    let stays = flyr::stay::cheapest(&by_pair);
+    assert_eq!(stays.len(), 2);
+    assert!(stays[0].price.is_some());
+    assert_eq!(stays[1].price, None);
+    assert!(stays.iter().all(|s| (5..=7).contains(&s.nights)));
+}
+
+#[tokio::test]
+async fn returns_search_selects_the_outbound_flight() {
+    let outbound = search(&MockFetcher::new(fixtures::ONE_WAY_HEL_BCN))
+        .await
+        .unwrap()
+        .flights
+        .remove(0);
+    let mut params = hel_bcn_query();
+    params.trip = flyr::query::TripType::RoundTrip;
+    let mut inbound = params.legs[0].clone();
+    inbound.date = "2026-03-08".into();
+    std::mem::swap(&mut inbound.from_airport, &mut inbound.to_airport);
+    params.legs.push(inbound);
+
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let returns = flyr::search_returns(&fetcher, &params, &outbound, ParseOptions::default())
+        .await
+        .unwrap();
+    assert!(returns
+        .flights
+        .iter()
+        .all(|f| f.price_scope == Some(flyr::model::PriceScope::Total)));
+
+    let requests = fetcher.requests();
+    let tfs = &requests[0].iter().find(|(k, _)| k == "tfs").unwrap().1;
+    let sent = flyr::query::QueryParams::from_tfs(tfs).unwrap();
+    assert_eq!(
+        sent.legs[0].selected,
+        flyr::query::SelectedFlight::from_flight(&outbound).unwrap()
+    );
+    assert!(sent.legs[1].selected.is_empty());
+}
+
+#[tokio::test]
+async fn mixed_cabins_search_each_leg_and_add_the_cheapest_later_fare() {
+    let one_way = search(&MockFetcher::new(fixtures::ONE_WAY_HEL_BCN)).await.unwrap();
+    let cheapest = one_way.flights.iter().filter_map(|f| f.price).min().unwrap();
+
+    let mut params = hel_bcn_query();
+    params.trip = flyr::query::TripType::RoundTrip;
+    let mut inbound = params.legs[0].clone();
+    inbound.date = "2026-03-08".into();
+    inbound.seat = Some(flyr::query::Seat::Business);
+    std::mem::swap(&mut inbound.from_airport, &mut inbound.to_airport);
+    params.legs.push(inbound);
+
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let result = flyr::search_with_fetcher(
+        &fetcher,
+        SearchQuery::Structured(params),
+        ParseOptions::default(),
+    )
+    .await
+    .unwrap();
+
+    let sent: Vec<_> = fetcher
+        .requests()
+        .iter()
+        .map(|r| {
+            let tfs = &r.iter().find(|(k, _)| k == "tfs").unwrap().1;
+            flyr::query::QueryParams::from_tfs(tfs).unwrap()
+        })
+        .collect();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[0].seat, flyr::query::Seat::Economy);
+    assert_eq!(sent[1].seat, flyr::query::Seat::Business);
+    assert_eq!(sent[1].legs[0].from_airport, "BCN");
+    assert!(sent.iter().all(|q| q.legs.len() == 1));
+
+    for (flight, leg) in result.flights.iter().zip(&one_way.flights) {
+        assert_eq!(flight.price, leg.price.map(|p| p + cheapest));
+        assert_eq!(flight.price_scope, Some(flyr::model::PriceScope::Total));
+    }
+    assert!(result.warnings.iter().any(|w| w.kind == "mixed_cabins"
+        && w.message.contains("BCN→HEL on 2026-03-08 in business")));
+}
+
+#[tokio::test]
+async fn returns_search_needs_a_round_trip() {
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let outbound = search(&fetcher).await.unwrap().flights.remove(0);
+    let err = flyr::search_returns(&fetcher, &hel_bcn_query(), &outbound, ParseOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, FlightError::Validation(_)));
+}
+
+#[tokio::test]
+async fn raw_payload_is_what_the_parser_reads() {
+    let fetcher = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let query = SearchQuery::Structured(hel_bcn_query());
+    let payload = flyr::fetch_raw_payload(&fetcher, &query).await.unwrap();
+
+    let parsed = flyr::parse::parse_payload(&payload).unwrap();
+    assert_eq!(parsed.flights.len(), 3);
+    assert_eq!(fetcher.requests().len(), 1);
+}
+
+#[derive(Debug)]
+struct MobileParser;
+
+impl ResultParser for MobileParser {
+    fn name(&self) -> &str {
+        "mobile"
+    }
+
+    fn accepts(&self, html: &str, _query: &SearchQuery) -> bool {
+        html.contains("data-mobile-results")
+    }
+
+    fn parse(
+        &self,
+        _html: String,
+        _query: &SearchQuery,
+        _options: &ParseOptions,
+    ) -> Result<SearchResult, FlightError> {
+        Ok(SearchResult {
+            warnings: vec![Warning::new("parser", "mobile")],
+            ..SearchResult::default()
+        })
+    }
+}
+
+#[tokio::test]
+async fn registered_parsers_take_the_pages_they_accept() {
+    let options = ParseOptions {
+        parsers: vec![Arc::new(MobileParser)],
+        ..ParseOptions::default()
+    };
+    let query = SearchQuery::Structured(hel_bcn_query());
+
+    let mobile = MockFetcher::new("<html><div data-mobile-results></div></html>");
+    let result = flyr::search_with_fetcher(&mobile, query.clone(), options.clone())
+        .await
+        .unwrap();
+    assert!(result.warnings.iter().any(|w| w.kind == "parser"));
+
+    let regular = MockFetcher::new(fixtures::ONE_WAY_HEL_BCN);
+    let result = flyr::search_with_fetcher(&regular, query, options).await.unwrap();
+    assert_eq!(result.flights.len(), 3);
+    assert!(result.warnings.iter().all(|w| w.kind != "parser"));
+}
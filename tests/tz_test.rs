@@ -0,0 +1,82 @@
+use flyr::model::FlightDateTime;
+use flyr::tz::{
+    convert, format_offset, iso8601, offset_at, segment_offsets, to_utc_minutes, utc_offset, Times,
+};
+
+fn at(year: u32, month: u32, day: u32, hour: u32, minute: u32) -> FlightDateTime {
+    FlightDateTime {
+        year,
+        month,
+        day,
+        hour,
+        minute,
+    }
+}
+
+#[test]
+fn offsets_follow_daylight_saving() {
+    assert_eq!(utc_offset("HEL", &at(2026, 3, 1, 7, 45)), Some(120));
+    assert_eq!(utc_offset("HEL", &at(2026, 7, 1, 7, 45)), Some(180));
+    assert_eq!(utc_offset("JFK", &at(2026, 3, 7, 12, 0)), Some(-300));
+    assert_eq!(utc_offset("JFK", &at(2026, 3, 9, 12, 0)), Some(-240));
+    assert_eq!(utc_offset("SYD", &at(2026, 1, 15, 9, 0)), Some(660));
+    assert_eq!(utc_offset("SYD", &at(2026, 7, 15, 9, 0)), Some(600));
+    assert_eq!(utc_offset("DEL", &at(2026, 7, 15, 9, 0)), Some(330));
+    assert_eq!(utc_offset("XXX", &at(2026, 7, 15, 9, 0)), None);
+}
+
+#[test]
+fn southern_and_reinstated_daylight_saving() {
+    assert_eq!(utc_offset("SCL", &at(2026, 1, 15, 9, 0)), Some(-180));
+    assert_eq!(utc_offset("SCL", &at(2026, 7, 15, 9, 0)), Some(-240));
+    assert_eq!(utc_offset("CAI", &at(2026, 1, 15, 9, 0)), Some(120));
+    assert_eq!(utc_offset("CAI", &at(2026, 7, 15, 9, 0)), Some(180));
+}
+
+#[test]
+fn clock_changes_resolve_to_one_offset() {
+    // 03:30 never happens in Helsinki on 2026-03-29 and happens twice on 2026-10-25.
+    assert_eq!(utc_offset("HEL", &at(2026, 3, 29, 3, 30)), Some(120));
+    assert_eq!(utc_offset("HEL", &at(2026, 10, 25, 3, 30)), Some(180));
+}
+
+#[test]
+fn converts_between_zones() {
+    let departure = at(2026, 3, 1, 7, 45);
+    let (utc, offset) = convert(&departure, "HEL", "HEL", Times::Utc).unwrap();
+    assert_eq!((utc.hour, utc.minute, offset), (5, 45, 0));
+
+    let arrival = at(2026, 3, 1, 1, 30);
+    let (origin, offset) = convert(&arrival, "BCN", "NRT", Times::Origin).unwrap();
+    assert_eq!((origin.day, origin.hour, offset), (1, 9, 540));
+
+    assert_eq!(
+        to_utc_minutes("BCN", &arrival),
+        Some(at(2026, 3, 1, 0, 30).minutes_since_epoch())
+    );
+    assert_eq!(offset_at("HEL", at(2026, 7, 1, 0, 0).minutes_since_epoch()), Some(180));
+}
+
+#[test]
+fn unknown_airport_offsets_are_inferred_from_duration() {
+    let departure = at(2026, 3, 1, 10, 0);
+    let arrival = at(2026, 3, 1, 16, 30);
+    assert_eq!(
+        segment_offsets(("HEL", &departure), ("XXX", &arrival), 300),
+        (Some(120), Some(210))
+    );
+    assert_eq!(
+        segment_offsets(("XXX", &departure), ("XXY", &arrival), 300),
+        (None, None)
+    );
+    assert_eq!(iso8601(&arrival, -570), "2026-03-01T16:30:00-09:30");
+}
+
+#[test]
+fn offsets_render_as_utc_labels() {
+    assert_eq!(format_offset(0), "UTC");
+    assert_eq!(format_offset(120), "UTC+2");
+    assert_eq!(format_offset(-300), "UTC-5");
+    assert_eq!(format_offset(330), "UTC+5:30");
+    assert!(Times::from_str_loose("zulu").is_err());
+}
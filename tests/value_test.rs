@@ -0,0 +1,63 @@
+use flyr::locale::Locale;
+use flyr::parse::parse_html;
+use flyr::table::{render, render_plain, Highlights};
+use flyr::testing::fixtures;
+use flyr::value::{annotate, best, ValueWeights};
+
+fn score_of(result: &flyr::model::SearchResult, airline: &str) -> Option<u32> {
+    result
+        .flights
+        .iter()
+        .find(|f| f.airlines == vec![airline])
+        .and_then(|f| f.value_score)
+}
+
+#[test]
+fn parsed_flights_carry_a_value_score() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+
+    assert_eq!(score_of(&result, "Norwegian"), Some(100));
+    assert_eq!(score_of(&result, "AY"), Some(64));
+    assert_eq!(score_of(&result, "Lufthansa"), Some(48));
+}
+
+#[test]
+fn weights_change_the_ranking() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    annotate(&mut result, &ValueWeights::parse("price=1,duration=0,stops=0").unwrap());
+
+    assert_eq!(score_of(&result, "Lufthansa"), Some(85));
+    assert!(score_of(&result, "Lufthansa") > score_of(&result, "AY"));
+}
+
+#[test]
+fn value_weights_are_validated() {
+    let weights = ValueWeights::parse("stops=2").unwrap();
+    assert_eq!(weights.stops, 2.0);
+    assert_eq!((weights.price, weights.duration), (0.0, 0.0));
+
+    assert!(ValueWeights::parse("legroom=1").is_err());
+    assert!(ValueWeights::parse("price=-1").is_err());
+    assert!(ValueWeights::parse("price").is_err());
+    assert!(ValueWeights::parse("price=0,duration=0,stops=0").is_err());
+}
+
+#[test]
+fn best_value_is_marked_once() {
+    let result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    let best = best(&result.flights).unwrap();
+    assert_eq!(result.flights[best].airlines, vec!["Norwegian"]);
+
+    let table = render(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert_eq!(table.matches("best value").count(), 1);
+    let plain = render_plain(&result, "EUR", &Locale::default(), &Highlights::default());
+    assert_eq!(plain.matches("Best value").count(), 1);
+    assert!(plain.contains("Price: €121. Best value. Airlines: Norwegian."));
+}
+
+#[test]
+fn single_flight_has_no_best_value_marker() {
+    let mut result = parse_html(fixtures::ONE_WAY_HEL_BCN).unwrap();
+    result.flights.truncate(1);
+    assert_eq!(best(&result.flights), None);
+}